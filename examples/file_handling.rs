@@ -91,7 +91,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // For demo purposes, we'll just show the API
     println!("   File output can be downloaded with:");
     println!("   - output.download().await  // Gets bytes");
-    println!("   - output.save_to_path(path).await  // Saves to file");
+    println!("   - output.save_to_path(path).await  // Streams to file, returns bytes written");
 
     // Clean up
     temp_dir.close()?;
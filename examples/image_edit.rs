@@ -0,0 +1,57 @@
+//! Image editing example.
+//!
+//! This example demonstrates how to:
+//! - Pass an input image to an image-to-image model
+//! - Add model-specific inputs to the edit
+//! - Save the edited images with the right extension
+//!
+//! Run with: cargo run --example image_edit -- <image path or URL> [prompt]
+
+use replicate_client::{Client, FileInput, ImageEditRequest};
+use std::time::Duration;
+
+/// Image-to-image capable model used by this example.
+const MODEL: &str =
+    "stability-ai/sdxl:7762fd07cf82c948538e41f63f77d685e02b063e37e496e96eefd46c929f9bdc";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("🖼️ Image Edit Demo");
+
+    let client = match Client::from_env() {
+        Ok(client) => client,
+        Err(_) => {
+            println!("❌ Please set the REPLICATE_API_TOKEN environment variable");
+            return Ok(());
+        }
+    };
+
+    let mut args = std::env::args().skip(1);
+    let image = args.next().unwrap_or_else(|| {
+        "https://replicate.delivery/pbxt/IJjJHBfbGfNT4gmhLkmHvV6XDKQDV3LJXhXH4C0WMPBozWqTwg1/view.jpeg"
+            .to_string()
+    });
+    let prompt = args
+        .next()
+        .unwrap_or_else(|| "the same scene as a watercolor painting".to_string());
+
+    // URLs are passed through, local files are inlined or uploaded by size
+    let request = ImageEditRequest::new(FileInput::from(image.as_str()), prompt)
+        .input("prompt_strength", 0.7)
+        .input("num_inference_steps", 30)
+        .output_dir("image_edit_output")
+        .timeout(Duration::from_secs(5 * 60));
+
+    println!("\n🎨 Editing {}...", image);
+    match client.run_image_edit(MODEL, request).await {
+        Ok(paths) => {
+            println!("✅ Saved {} image(s):", paths.len());
+            for path in paths {
+                println!("   {}", path.display());
+            }
+        }
+        Err(e) => println!("❌ Image edit failed: {}", e),
+    }
+
+    Ok(())
+}
@@ -95,6 +95,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         min_delay: Duration::from_millis(200),
         max_delay: Duration::from_secs(10),
         base_multiplier: 3,
+        ..RetryConfig::default()
     };
 
     println!("   Custom configuration:");
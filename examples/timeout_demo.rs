@@ -88,6 +88,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let custom_timeout_config = TimeoutConfig {
         connect_timeout: Some(Duration::from_secs(15)),
         request_timeout: Some(Duration::from_secs(90)),
+        ..TimeoutConfig::default()
     };
 
     let custom_retry_config = RetryConfig {
@@ -95,12 +96,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         min_delay: Duration::from_millis(200),
         max_delay: Duration::from_secs(10),
         base_multiplier: 3,
+        ..RetryConfig::default()
     };
 
-    let custom_http_config = HttpConfig {
-        retry: custom_retry_config,
-        timeout: custom_timeout_config,
-    };
+    let custom_http_config = HttpConfig::default()
+        .retry(custom_retry_config)
+        .timeout(custom_timeout_config);
 
     let custom_client = Client::with_http_config(&api_token, custom_http_config)?;
 
@@ -0,0 +1,227 @@
+//! Webhook-only prediction example.
+//!
+//! This example demonstrates how to:
+//! - Receive prediction updates on an endpoint that verifies their signatures
+//! - Create predictions from a request handler without waiting on them
+//! - Collect the outcome of creates running on background tasks
+//!
+//! `receive_webhook` only uses `http` types, so it drops into any framework:
+//! in axum it is the body of a `POST` route taking `State<Receiver>`,
+//! `HeaderMap` and `Bytes`. Here it is served with hyper on port 3000.
+//!
+//! Set `WEBHOOK_URL` to a public URL that forwards to the receiver (e.g.
+//! through a tunnel) so that Replicate can deliver to it.
+//!
+//! Run with: cargo run --example webhook_only
+
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Empty, Limited};
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{HeaderMap, Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use replicate_client::{
+    Client, DeliveryDeduplicator, Prediction, Result, WebhookCheck, WebhookEvent, WebhookHeaders,
+    WebhookSecret, parse_event,
+};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+/// Where the receiver listens.
+const LISTEN_ADDR: &str = "127.0.0.1:3000";
+
+/// Largest delivery body the receiver accepts.
+const MAX_BODY_SIZE: usize = 1 << 20;
+
+/// Shared state of the webhook receiver.
+#[derive(Clone)]
+struct Receiver {
+    secret: Arc<WebhookSecret>,
+    deduplicator: Arc<DeliveryDeduplicator>,
+}
+
+/// Handle one webhook delivery and pick the status to answer with.
+///
+/// Replicate retries deliveries answered with an error, so a delivery is
+/// only acknowledged once it has been handled.
+fn receive_webhook(receiver: &Receiver, headers: &HeaderMap, body: &[u8]) -> StatusCode {
+    let headers = match WebhookHeaders::from_headers(headers) {
+        Ok(headers) => headers,
+        Err(e) => {
+            println!("⚠️  Rejected delivery: {}", e);
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+    if let Err(e) = receiver.secret.verify(&headers, body) {
+        println!("⚠️  Rejected delivery {}: {}", headers.id, e);
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    match parse_event(&headers, body, &receiver.deduplicator) {
+        Ok(WebhookEvent::Prediction(prediction)) => {
+            if let Err(e) = handle_prediction(&prediction) {
+                println!("❌ Handling {} failed: {}", prediction.id, e);
+                // Let Replicate's redelivery through
+                receiver.deduplicator.forget(&headers.id);
+                return StatusCode::INTERNAL_SERVER_ERROR;
+            }
+            StatusCode::OK
+        }
+        Ok(WebhookEvent::Duplicate { id }) => {
+            println!("🔁 Skipped repeated delivery {}", id);
+            StatusCode::OK
+        }
+        Err(e) => {
+            println!("⚠️  Rejected delivery {}: {}", headers.id, e);
+            StatusCode::BAD_REQUEST
+        }
+    }
+}
+
+/// Act on a prediction update, e.g. store its outputs.
+fn handle_prediction(prediction: &Prediction) -> Result<()> {
+    println!(
+        "📬 {} is {:?}, output: {}",
+        prediction.id,
+        prediction.status,
+        prediction.output.clone().unwrap_or_default()
+    );
+    Ok(())
+}
+
+/// Serve `receive_webhook` over HTTP/1.
+async fn serve(listener: TcpListener, receiver: Receiver) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                println!("⚠️  Accept failed: {}", e);
+                continue;
+            }
+        };
+        let receiver = receiver.clone();
+        let service = service_fn(move |request: Request<Incoming>| {
+            let receiver = receiver.clone();
+            async move {
+                let (parts, body) = request.into_parts();
+                let status = if parts.method != Method::POST {
+                    StatusCode::METHOD_NOT_ALLOWED
+                } else {
+                    match Limited::new(body, MAX_BODY_SIZE).collect().await {
+                        Ok(body) => receive_webhook(&receiver, &parts.headers, &body.to_bytes()),
+                        Err(_) => StatusCode::PAYLOAD_TOO_LARGE,
+                    }
+                };
+                let mut response = Response::new(Empty::<Bytes>::new());
+                *response.status_mut() = status;
+                Ok::<_, Infallible>(response)
+            }
+        });
+        tokio::spawn(async move {
+            if let Err(e) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                println!("⚠️  Connection failed: {}", e);
+            }
+        });
+    }
+}
+
+/// Start a prediction for `prompt` without waiting for the create call.
+fn handle_request(
+    client: &Client,
+    webhook_url: &str,
+    prompt: &str,
+) -> JoinHandle<Result<Prediction>> {
+    client
+        .create_prediction(
+            "stability-ai/sdxl:7762fd07cf82c948538e41f63f77d685e02b063e37e496e96eefd46c929f9bdc",
+        )
+        .input("prompt", prompt)
+        .webhook(webhook_url)
+        .webhook_check(WebhookCheck::new().preflight(true))
+        .send_detached(&tokio::runtime::Handle::current())
+}
+
+#[tokio::main]
+async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    println!("🪝 Webhook-only Prediction Demo");
+
+    let client = match Client::from_env() {
+        Ok(client) => client,
+        Err(_) => {
+            println!("❌ Please set the REPLICATE_API_TOKEN environment variable");
+            println!("   You can get your token from: https://replicate.com/account");
+            return Ok(());
+        }
+    };
+
+    // Deliveries are signed with the account's secret
+    let secret = client.webhooks().default_secret().await?;
+    let receiver = Receiver {
+        secret: Arc::new(secret.clone()),
+        deduplicator: Arc::new(DeliveryDeduplicator::new(10_000, Duration::from_secs(3600))),
+    };
+    let listener = TcpListener::bind(LISTEN_ADDR).await?;
+    tokio::spawn(serve(listener, receiver));
+    println!("👂 Receiving webhooks on http://{}/", LISTEN_ADDR);
+
+    // A signed test event is accepted; an unsigned one is turned away
+    let local_url = format!("http://{}/hooks/replicate", LISTEN_ADDR);
+    let delivery = client
+        .webhooks()
+        .test_delivery_signed(&local_url, &secret)
+        .await?;
+    println!("✅ Signed test event answered {}", delivery.status);
+    if let Err(e) = client.test_webhook(&local_url).await {
+        println!("✅ Unsigned test event refused: {}", e);
+    }
+
+    let Ok(webhook_url) = std::env::var("WEBHOOK_URL") else {
+        println!("💡 Set WEBHOOK_URL to a public URL of this receiver to run predictions");
+        return Ok(());
+    };
+
+    // Each "request" returns at once; the creates run in the background
+    let pending: Vec<_> = ["a lighthouse at dusk", "a fox in the snow"]
+        .into_iter()
+        .map(|prompt| (prompt, handle_request(&client, &webhook_url, prompt)))
+        .collect();
+    println!("📨 Accepted {} requests", pending.len());
+
+    // Errors are kept in the handles, so a supervisor can still report them
+    for (prompt, handle) in pending {
+        match handle.await? {
+            Ok(prediction) => println!(
+                "✅ '{}' created as {} ({:?}), updates go to {}",
+                prompt, prediction.id, prediction.status, webhook_url
+            ),
+            Err(e) => println!("❌ '{}' could not be created: {}", prompt, e),
+        }
+    }
+
+    // In a handler that must confirm creation, await the create directly
+    match client
+        .create_prediction(
+            "stability-ai/sdxl:7762fd07cf82c948538e41f63f77d685e02b063e37e496e96eefd46c929f9bdc",
+        )
+        .input("prompt", "a paper boat")
+        .webhook(&webhook_url)
+        .webhook_check(WebhookCheck::new().preflight(true))
+        .send_nowait()
+        .await
+    {
+        Ok(prediction) => println!("✅ Created {} before responding", prediction.id),
+        Err(e) => println!("❌ Creation failed: {}", e),
+    }
+
+    println!("⏳ Waiting for deliveries, press Ctrl-C to stop");
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
@@ -0,0 +1,219 @@
+//! Collections API implementation.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::error::Result;
+use crate::http::HttpClient;
+use crate::models::{collection::Collection, common::PaginatedResponse};
+
+/// Cached responses, keyed by request path.
+#[derive(Debug, Default)]
+struct CollectionCache {
+    lists: HashMap<String, (Instant, PaginatedResponse<Collection>)>,
+    collections: HashMap<String, (Instant, Collection)>,
+}
+
+impl CollectionCache {
+    /// Drop the entries older than `ttl`.
+    fn prune(&mut self, ttl: Duration) {
+        self.lists
+            .retain(|_, (stored_at, _)| stored_at.elapsed() < ttl);
+        self.collections
+            .retain(|_, (stored_at, _)| stored_at.elapsed() < ttl);
+    }
+}
+
+/// API for browsing model collections.
+///
+/// Collections change slowly, so an optional TTL cache can serve repeated
+/// `list` and `get` calls without hitting the API. The cache is shared by all
+/// clones of this API, and expired entries are dropped whenever a response is
+/// stored.
+#[derive(Debug, Clone)]
+pub struct CollectionsApi {
+    http: HttpClient,
+    cache_ttl: Option<Duration>,
+    cache: Arc<Mutex<CollectionCache>>,
+}
+
+impl CollectionsApi {
+    /// Create a new collections API instance without caching.
+    pub fn new(http: HttpClient) -> Self {
+        Self {
+            http,
+            cache_ttl: None,
+            cache: Arc::new(Mutex::new(CollectionCache::default())),
+        }
+    }
+
+    /// Create a new collections API instance that caches responses for `ttl`.
+    pub fn with_cache(http: HttpClient, ttl: Duration) -> Self {
+        Self {
+            cache_ttl: Some(ttl),
+            ..Self::new(http)
+        }
+    }
+
+    /// Create a copy of this API that sends requests through another HTTP client.
+    ///
+    /// The cache is shared with the original.
+    pub(crate) fn with_http(&self, http: HttpClient) -> Self {
+        Self {
+            http,
+            ..self.clone()
+        }
+    }
+
+    /// Get the cache TTL, if caching is enabled.
+    pub fn cache_ttl(&self) -> Option<Duration> {
+        self.cache_ttl
+    }
+
+    /// Drop all cached responses.
+    pub fn clear_cache(&self) {
+        let mut cache = self.cache.lock().unwrap();
+        cache.lists.clear();
+        cache.collections.clear();
+    }
+
+    /// List collections with optional pagination.
+    pub async fn list(&self, cursor: Option<&str>) -> Result<PaginatedResponse<Collection>> {
+        let path = match cursor {
+            Some(cursor) => cursor.to_string(),
+            None => "/v1/collections".to_string(),
+        };
+
+        if let Some(cached) = self.cached(|cache| cache.lists.get(&path).cloned()) {
+            return Ok(cached);
+        }
+
+        let response: PaginatedResponse<Collection> = self.http.get_json(&path).await?;
+        self.store(|cache| {
+            cache.lists.insert(path, (Instant::now(), response.clone()));
+        });
+        Ok(response)
+    }
+
+    /// Get a collection, including its models, by slug.
+    pub async fn get(&self, slug: &str) -> Result<Collection> {
+        if let Some(cached) = self.cached(|cache| cache.collections.get(slug).cloned()) {
+            return Ok(cached);
+        }
+
+        let path = format!("/v1/collections/{}", slug);
+        let collection: Collection = self.http.get_json(&path).await?;
+        self.store(|cache| {
+            cache
+                .collections
+                .insert(slug.to_string(), (Instant::now(), collection.clone()));
+        });
+        Ok(collection)
+    }
+
+    /// Look up a cache entry, returning it only if it is still fresh.
+    fn cached<T>(
+        &self,
+        lookup: impl FnOnce(&CollectionCache) -> Option<(Instant, T)>,
+    ) -> Option<T> {
+        let ttl = self.cache_ttl?;
+        let cache = self.cache.lock().unwrap();
+        lookup(&cache)
+            .filter(|(stored_at, _)| stored_at.elapsed() < ttl)
+            .map(|(_, value)| value)
+    }
+
+    /// Add a cache entry if caching is enabled, first dropping expired ones
+    /// so that the cache only holds what is still fresh.
+    fn store(&self, insert: impl FnOnce(&mut CollectionCache)) {
+        let Some(ttl) = self.cache_ttl else {
+            return;
+        };
+        let mut cache = self.cache.lock().unwrap();
+        cache.prune(ttl);
+        insert(&mut cache);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn start_server() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/collections"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "results": [{"name": "Text to image", "slug": "text-to-image", "description": null}],
+                "next": null,
+                "previous": null,
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/collections/text-to-image"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "name": "Text to image",
+                "slug": "text-to-image",
+                "description": "Models that generate images",
+                "models": [],
+            })))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn test_collection_cache_hits() {
+        let server = start_server().await;
+        let http = HttpClient::with_base_url("test-token", server.uri()).unwrap();
+        let api = CollectionsApi::with_cache(http, Duration::from_secs(60));
+
+        for _ in 0..3 {
+            assert_eq!(api.list(None).await.unwrap().len(), 1);
+            assert_eq!(
+                api.get("text-to-image").await.unwrap().slug,
+                "text-to-image"
+            );
+        }
+
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_collection_cache_expires() {
+        let server = start_server().await;
+        let http = HttpClient::with_base_url("test-token", server.uri()).unwrap();
+        let api = CollectionsApi::with_cache(http, Duration::from_millis(50));
+
+        api.list(None).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(80)).await;
+        api.list(None).await.unwrap();
+
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+
+        // Expired entries are dropped when a new one is stored
+        api.get("text-to-image").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(80)).await;
+        api.list(None).await.unwrap();
+        let cache = api.cache.lock().unwrap();
+        assert_eq!(cache.lists.len(), 1);
+        assert!(cache.collections.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_collections_uncached_by_default() {
+        let server = start_server().await;
+        let http = HttpClient::with_base_url("test-token", server.uri()).unwrap();
+        let api = CollectionsApi::new(http);
+
+        api.get("text-to-image").await.unwrap();
+        api.get("text-to-image").await.unwrap();
+
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+}
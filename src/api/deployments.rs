@@ -0,0 +1,138 @@
+//! Deployments API implementation.
+
+use crate::error::{Error, Result};
+use crate::http::HttpClient;
+use crate::models::{common::PaginatedResponse, prediction::Prediction};
+
+/// API for working with deployments.
+#[derive(Debug, Clone)]
+pub struct DeploymentsApi {
+    http: HttpClient,
+}
+
+impl DeploymentsApi {
+    /// Create a new deployments API instance.
+    pub fn new(http: HttpClient) -> Self {
+        Self { http }
+    }
+
+    /// Create a copy of this API that sends requests through another HTTP client.
+    pub(crate) fn with_http(&self, http: HttpClient) -> Self {
+        Self { http }
+    }
+
+    /// List the predictions created through a deployment, newest first.
+    ///
+    /// Pass the `next` URL of a page as `cursor` to fetch the following page,
+    /// or use [`PaginatedResponse::into_stream`] with
+    /// [`Client::http_client`](crate::Client::http_client) to follow every
+    /// page. A deployment without predictions yet gives an empty page.
+    pub async fn list_predictions(
+        &self,
+        owner: &str,
+        name: &str,
+        cursor: Option<&str>,
+    ) -> Result<PaginatedResponse<Prediction>> {
+        let path = match cursor {
+            Some(cursor) => cursor.to_string(),
+            None => {
+                if owner.is_empty() || name.is_empty() || owner.contains('/') || name.contains('/')
+                {
+                    return Err(Error::invalid_input(format!(
+                        "Invalid deployment '{}/{}'",
+                        owner, name
+                    )));
+                }
+                format!("/v1/deployments/{}/{}/predictions", owner, name)
+            }
+        };
+
+        self.http.get_json(&path).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::TryStreamExt;
+    use serde_json::json;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn prediction(id: &str, status: &str) -> serde_json::Value {
+        json!({
+            "id": id,
+            "model": "acme/model",
+            "version": "v1",
+            "status": status,
+            "deployment": {"owner": "acme", "name": "prod"},
+        })
+    }
+
+    #[tokio::test]
+    async fn test_list_predictions_follows_cursor() {
+        let server = MockServer::start().await;
+        let next = format!(
+            "{}/v1/deployments/acme/prod/predictions?cursor=abc",
+            server.uri()
+        );
+        Mock::given(method("GET"))
+            .and(path("/v1/deployments/acme/prod/predictions"))
+            .and(query_param("cursor", "abc"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "results": [prediction("p3", "failed")],
+                "next": null,
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/deployments/acme/prod/predictions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "results": [prediction("p1", "succeeded"), prediction("p2", "processing")],
+                "next": next,
+            })))
+            .mount(&server)
+            .await;
+
+        let http = HttpClient::with_base_url("test-token", server.uri()).unwrap();
+        let api = DeploymentsApi::new(http.clone());
+        let page = api.list_predictions("acme", "prod", None).await.unwrap();
+        assert_eq!(page.len(), 2);
+        assert_eq!(page.next.as_deref(), Some(next.as_str()));
+
+        let second = api
+            .list_predictions("acme", "prod", page.next.as_deref())
+            .await
+            .unwrap();
+        assert_eq!(second.results[0].id, "p3");
+        assert!(!second.has_next());
+
+        let all: Vec<Prediction> = page.into_stream(&http).try_collect().await.unwrap();
+        let ids: Vec<_> = all
+            .iter()
+            .map(|prediction| prediction.id.as_str())
+            .collect();
+        assert_eq!(ids, ["p1", "p2", "p3"]);
+    }
+
+    #[tokio::test]
+    async fn test_list_predictions_empty_deployment() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/deployments/acme/new/predictions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"next": null})))
+            .mount(&server)
+            .await;
+
+        let api =
+            DeploymentsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        let page = api.list_predictions("acme", "new", None).await.unwrap();
+        assert!(page.is_empty());
+        assert!(!page.has_next());
+
+        assert!(matches!(
+            api.list_predictions("acme/prod", "x", None).await,
+            Err(Error::InvalidInput(_))
+        ));
+    }
+}
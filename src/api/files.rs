@@ -1,12 +1,24 @@
 //! Files API for uploading and managing files.
 
 use crate::error::{Error, Result};
-use crate::http::HttpClient;
+use crate::http::client::size_changed_error;
+use crate::http::{HttpClient, RequestOptions};
+use crate::models::common::parse_timestamp;
 use crate::models::file::{FileEncodingStrategy, FileInput};
+use crate::models::id::FileId;
 use base64::{Engine as _, engine::general_purpose};
+use bytes::Bytes;
+use futures::channel::mpsc;
+use futures::future::{AbortHandle, Abortable};
+use futures::stream::{self, Stream};
+use reqwest::multipart::{Form, Part};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::future::Future;
 use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::io::AsyncReadExt;
 
 /// Represents a file uploaded to Replicate.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,17 +34,366 @@ pub struct File {
     /// The ETag of the file.
     pub etag: String,
     /// File checksums.
-    pub checksums: HashMap<String, String>,
+    #[serde(default)]
+    pub checksums: FileChecksums,
     /// File metadata.
     pub metadata: HashMap<String, serde_json::Value>,
     /// When the file was created.
     pub created_at: String,
     /// When the file expires (optional).
     pub expires_at: Option<String>,
-    /// File URLs.
+    /// File URLs, keyed by purpose. Prefer [`download_url`](Self::download_url)
+    /// and [`upload_url`](Self::upload_url) over reading keys directly.
     pub urls: HashMap<String, String>,
+    /// Fields not modelled by this crate, kept as returned by the API.
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
+/// Checksums of an uploaded file, as hex digests.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileChecksums {
+    /// SHA-256 digest
+    #[serde(default, alias = "sha-256", skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// MD5 digest
+    #[serde(default, alias = "md-5", skip_serializing_if = "Option::is_none")]
+    pub md5: Option<String>,
+    /// Any other checksums, keyed by algorithm name
+    #[serde(flatten)]
+    pub other: HashMap<String, String>,
+}
+
+impl FileChecksums {
+    /// Check if no checksum is present.
+    pub fn is_empty(&self) -> bool {
+        self.sha256.is_none() && self.md5.is_none() && self.other.is_empty()
+    }
+}
+
+impl From<&File> for FileId {
+    fn from(file: &File) -> Self {
+        Self::new(file.id.as_str())
+    }
+}
+
+/// Key of the download URL in [`File::urls`].
+const DOWNLOAD_URL_KEY: &str = "get";
+
+/// Key of the upload URL in [`File::urls`].
+const UPLOAD_URL_KEY: &str = "upload";
+
+impl File {
+    /// Get the URL the file's content is downloaded from, which is also the
+    /// URL to pass as a prediction input.
+    pub fn download_url(&self) -> Option<&str> {
+        self.urls.get(DOWNLOAD_URL_KEY).map(String::as_str)
+    }
+
+    /// Get the URL the file's content is uploaded to, if the API returned one.
+    pub fn upload_url(&self) -> Option<&str> {
+        self.urls.get(UPLOAD_URL_KEY).map(String::as_str)
+    }
+
+    /// Get the time left before the file expires.
+    ///
+    /// Returns `None` if the file has no (parseable) `expires_at`, and zero
+    /// once it has expired. Use [`FilesApi::refresh`] to re-check a file
+    /// before reusing its URL.
+    pub fn expires_in(&self) -> Option<Duration> {
+        let expires_at = parse_timestamp(self.expires_at.as_deref()?)?;
+        Some(
+            expires_at
+                .duration_since(SystemTime::now())
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Check if the file has passed its `expires_at`.
+    pub fn is_expired(&self) -> bool {
+        self.expires_in().is_some_and(|left| left.is_zero())
+    }
+
+    /// Check that a local file matches this upload's checksum.
+    ///
+    /// SHA-256 is preferred over MD5 when both are available. The file is
+    /// hashed in chunks rather than read into memory. Fails with
+    /// [`Error::InvalidInput`] if there is no SHA-256 or MD5 checksum to
+    /// compare against.
+    pub fn verify_local(&self, path: &Path) -> Result<bool> {
+        use md5::Md5;
+        use sha2::{Digest, Sha256};
+
+        fn digest_file<D: Digest>(path: &Path) -> Result<String> {
+            let mut file = std::fs::File::open(path)?;
+            let mut hasher = D::new();
+            let mut buffer = vec![0u8; 64 * 1024];
+            loop {
+                let read = std::io::Read::read(&mut file, &mut buffer)?;
+                if read == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..read]);
+            }
+            Ok(hasher
+                .finalize()
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect())
+        }
+
+        let (expected, actual) = match (&self.checksums.sha256, &self.checksums.md5) {
+            (Some(sha256), _) => (sha256, digest_file::<Sha256>(path)?),
+            (None, Some(md5)) => (md5, digest_file::<Md5>(path)?),
+            (None, None) => {
+                return Err(Error::invalid_input(format!(
+                    "File {} has no SHA-256 or MD5 checksum",
+                    self.id
+                )));
+            }
+        };
+        Ok(expected.trim().eq_ignore_ascii_case(&actual))
+    }
+}
+
+/// Progress of a streaming file upload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UploadProgress {
+    /// Bytes of the file handed to the connection so far
+    pub bytes_sent: u64,
+    /// Size of the file in bytes
+    pub total_bytes: u64,
+}
+
+impl UploadProgress {
+    /// Fraction of the file sent, from 0.0 to 1.0
+    pub fn fraction(&self) -> f64 {
+        if self.total_bytes == 0 {
+            1.0
+        } else {
+            self.bytes_sent as f64 / self.total_bytes as f64
+        }
+    }
+}
+
+/// Options for [`FilesApi::create_with_options`].
+#[derive(Debug, Clone, Default)]
+pub struct UploadOptions {
+    /// Metadata stored with the file
+    pub metadata: Option<HashMap<String, serde_json::Value>>,
+    /// Requested lifetime of the file
+    pub expires_in: Option<Duration>,
+    /// Check that a path does not change size while it is read; see
+    /// [`check_size`](Self::check_size)
+    pub check_size: bool,
+}
+
+impl UploadOptions {
+    /// Create default upload options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store metadata with the file.
+    pub fn metadata(mut self, metadata: HashMap<String, serde_json::Value>) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Ask for the file to expire after `duration`.
+    ///
+    /// Sent as the `expires_in` form field, in seconds. Servers that do not
+    /// support it keep their default expiry; the `expires_at` of the
+    /// returned file tells which applies.
+    pub fn expires_in(mut self, duration: Duration) -> Self {
+        self.expires_in = Some(duration);
+        self
+    }
+
+    /// Check that a file uploaded from a path does not change size while it
+    /// is read, as happens for files that are still being written.
+    ///
+    /// The file is read again once if its size changes; a second mismatch
+    /// fails with [`Error::InvalidInput`] instead of sending a body that
+    /// does not match its length. Off by default, as it costs an extra stat
+    /// of the file.
+    pub fn check_size(mut self, check: bool) -> Self {
+        self.check_size = check;
+        self
+    }
+}
+
+/// Aborts an in-progress upload, e.g. on user request.
+///
+/// Aborting drops the upload request and with it the connection, so the
+/// server discards the partial file; the upload then fails with
+/// [`Error::UploadAborted`]. Uploads are sent as a single request, so there
+/// is no server-side session left to clean up. An upload that already
+/// finished is not affected. Clones abort the same upload.
+#[derive(Debug, Clone)]
+pub struct UploadAbortHandle(AbortHandle);
+
+impl UploadAbortHandle {
+    /// Make an upload abortable, returning its handle and the wrapped upload.
+    ///
+    /// Works with any upload of [`FilesApi`], e.g. the one returned by
+    /// [`FilesApi::create_from_path_with_progress`].
+    pub fn wrap<'a>(
+        upload: impl Future<Output = Result<File>> + Send + 'a,
+    ) -> (Self, impl Future<Output = Result<File>> + Send + 'a) {
+        let (handle, registration) = AbortHandle::new_pair();
+        let upload = Abortable::new(upload, registration);
+        (Self(handle), async move {
+            upload.await.unwrap_or(Err(Error::UploadAborted))
+        })
+    }
+
+    /// Abort the upload. Has no effect once it has finished.
+    pub fn abort(&self) {
+        self.0.abort();
+    }
+
+    /// Check if [`abort`](Self::abort) has been called.
+    pub fn is_aborted(&self) -> bool {
+        self.0.is_aborted()
+    }
+}
+
+/// A file uploaded once to be reused as the input of many predictions.
+///
+/// Returned by [`FilesApi::upload`]. Pass it to
+/// [`PredictionBuilder::uploaded_file`](crate::api::predictions::PredictionBuilder::uploaded_file)
+/// to send its URL instead of uploading the source again. Before each use the
+/// builder checks `expires_at` and uploads the source again once the file
+/// has expired, or is about to; see
+/// [`reupload_when_expired`](Self::reupload_when_expired). Clones share the
+/// upload, so an expired file is uploaded again once for all of them.
+///
+/// It also converts into a [`FileInput::Url`] of the current upload, which
+/// skips the expiry check.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use replicate_client::{Client, FileInput};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = Client::new("your-api-token")?;
+/// let reference = client
+///     .files()
+///     .upload(FileInput::from_path("reference.png"))
+///     .await?;
+/// for seed in 0..50 {
+///     client
+///         .create_prediction("black-forest-labs/flux-redux-dev")
+///         .uploaded_file("redux_image", &reference)
+///         .input("seed", seed)
+///         .send()
+///         .await?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct UploadedFile {
+    shared: Arc<UploadedShared>,
+    reupload: bool,
+}
+
+#[derive(Debug)]
+struct UploadedShared {
+    files_api: FilesApi,
+    source: FileInput,
+    file: Mutex<File>,
+    /// Held while uploading again, so clones wait for one upload
+    reupload_lock: tokio::sync::Mutex<()>,
+}
+
+impl UploadedFile {
+    /// Get the current upload.
+    pub fn file(&self) -> File {
+        self.shared.file.lock().unwrap().clone()
+    }
+
+    /// Get the source the file was uploaded from.
+    pub fn source(&self) -> &FileInput {
+        &self.shared.source
+    }
+
+    /// Set whether an expired file is uploaded again from its source on use;
+    /// when disabled, using it fails with [`Error::InvalidInput`] instead.
+    /// Enabled by default.
+    pub fn reupload_when_expired(mut self, reupload: bool) -> Self {
+        self.reupload = reupload;
+        self
+    }
+
+    /// Get the URL of the upload, uploading the source again first if the
+    /// file has expired or expires within a minute.
+    ///
+    /// Fails with [`Error::InvalidInput`] for an expired file when
+    /// re-uploading is disabled, either on this handle or by `allow_upload`.
+    pub(crate) async fn current_url(&self, allow_upload: bool) -> Result<String> {
+        let is_stale = |file: &File| file.expires_in().is_some_and(|left| left < REUSE_MARGIN);
+        let file = self.file();
+        if !is_stale(&file) {
+            return get_url(&file);
+        }
+        if !self.reupload || !allow_upload {
+            return Err(Error::invalid_input(format!(
+                "Uploaded file {} has expired (expires_at: {})",
+                file.id,
+                file.expires_at.as_deref().unwrap_or_default()
+            )));
+        }
+
+        let _guard = self.shared.reupload_lock.lock().await;
+        let file = self.file();
+        if !is_stale(&file) {
+            // Uploaded again through a clone while waiting
+            return get_url(&file);
+        }
+        tracing::debug!("Uploading expired file {} again", file.id);
+        let fresh = self
+            .shared
+            .files_api
+            .create_from_file_input(&self.shared.source, None)
+            .await?;
+        let url = get_url(&fresh)?;
+        *self.shared.file.lock().unwrap() = fresh;
+        Ok(url)
+    }
+}
+
+impl From<&UploadedFile> for FileInput {
+    fn from(uploaded: &UploadedFile) -> Self {
+        let file = uploaded.file();
+        FileInput::Url(get_url(&file).unwrap_or_default())
+    }
+}
+
+impl From<UploadedFile> for FileInput {
+    fn from(uploaded: UploadedFile) -> Self {
+        Self::from(&uploaded)
+    }
+}
+
+/// Get the URL predictions read an uploaded file from.
+fn get_url(file: &File) -> Result<String> {
+    file.download_url()
+        .map(str::to_string)
+        .ok_or_else(|| Error::InvalidInput("File missing URL".to_string()))
+}
+
+/// Leeway when checking that the server applied a requested expiry.
+const EXPIRY_LEEWAY: Duration = Duration::from_secs(60);
+
+/// Time a reused upload must stay valid for, so the model can still fetch it.
+const REUSE_MARGIN: Duration = Duration::from_secs(60);
+
+/// Size of the chunks read from disk for streaming uploads.
+const UPLOAD_CHUNK_SIZE: usize = 64 * 1024;
+
 /// Files API for managing file uploads.
 #[derive(Debug, Clone)]
 pub struct FilesApi {
@@ -45,7 +406,14 @@ impl FilesApi {
         Self { http }
     }
 
+    /// Create a copy of this API that sends requests through another HTTP client.
+    pub(crate) fn with_http(&self, http: HttpClient) -> Self {
+        Self { http }
+    }
+
     /// Upload a file from bytes with optional metadata.
+    ///
+    /// The content type may include parameters, e.g. `text/plain; charset=utf-8`.
     pub async fn create_from_bytes(
         &self,
         file_content: &[u8],
@@ -60,6 +428,10 @@ impl FilesApi {
     }
 
     /// Upload a file from a local path.
+    ///
+    /// For files that may still be being written, upload with
+    /// [`create_with_options`](Self::create_with_options) and
+    /// [`UploadOptions::check_size`].
     pub async fn create_from_path(
         &self,
         file_path: &Path,
@@ -69,6 +441,153 @@ impl FilesApi {
         self.http.post_multipart_json("/v1/files", form).await
     }
 
+    /// Upload a file from a local path, returning a handle to abort it.
+    ///
+    /// The upload only runs while its future is polled; call
+    /// [`UploadAbortHandle::abort`] from elsewhere, e.g. another task, to
+    /// stop it. The file is streamed in chunks as with
+    /// [`create_from_path_with_progress`](Self::create_from_path_with_progress).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use replicate_client::Client;
+    /// # use std::path::Path;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::new("your-api-token")?;
+    /// let (handle, upload) = client
+    ///     .files()
+    ///     .create_from_path_abortable(Path::new("video.mp4"), None);
+    /// let upload = tokio::spawn(upload);
+    ///
+    /// // Later, e.g. when the user presses cancel
+    /// handle.abort();
+    /// assert!(matches!(upload.await?, Err(replicate_client::Error::UploadAborted)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_from_path_abortable(
+        &self,
+        file_path: &Path,
+        metadata: Option<&HashMap<String, serde_json::Value>>,
+    ) -> (
+        UploadAbortHandle,
+        impl Future<Output = Result<File>> + Send + 'static,
+    ) {
+        let api = self.clone();
+        let file_path = file_path.to_path_buf();
+        let metadata = metadata.cloned();
+        UploadAbortHandle::wrap(async move {
+            let (_, upload) = api.create_from_path_with_progress(&file_path, metadata.as_ref());
+            upload.await
+        })
+    }
+
+    /// Upload a file from a local path, reporting progress as a stream.
+    ///
+    /// Returns a stream of progress events and the upload itself. The upload
+    /// only runs while its future is polled, so drive both, e.g. with
+    /// `tokio::select!` or `futures::join!`. The stream ends when the upload
+    /// finishes or fails. The file is read in chunks rather than all at once.
+    ///
+    /// The upload declares the file's size up front, so a file that changes
+    /// size while being sent fails with [`Error::InvalidInput`]. Wrap the
+    /// upload with [`UploadAbortHandle::wrap`] to be able to abort it.
+    pub fn create_from_path_with_progress<'a>(
+        &'a self,
+        file_path: &'a Path,
+        metadata: Option<&'a HashMap<String, serde_json::Value>>,
+    ) -> (
+        impl Stream<Item = UploadProgress> + Send + 'static,
+        impl Future<Output = Result<File>> + Send + 'a,
+    ) {
+        let (progress_tx, progress_rx) = mpsc::unbounded();
+
+        let upload = async move {
+            let file = tokio::fs::File::open(file_path).await?;
+            let total_bytes = file.metadata().await?.len();
+            // Set when the file's size no longer matches the declared length
+            let size_mismatch = Arc::new(Mutex::new(None));
+            let chunk_mismatch = size_mismatch.clone();
+
+            let chunks = stream::unfold(
+                (file, 0u64, progress_tx),
+                move |(mut file, bytes_sent, progress_tx)| {
+                    let size_mismatch = chunk_mismatch.clone();
+                    async move {
+                        let mut buffer = vec![0u8; UPLOAD_CHUNK_SIZE];
+                        let read = match file.read(&mut buffer).await {
+                            Ok(read) => read,
+                            Err(error) => {
+                                return Some((Err(error), (file, bytes_sent, progress_tx)));
+                            }
+                        };
+                        let bytes_sent = bytes_sent + read as u64;
+                        if (read == 0 && bytes_sent != total_bytes) || bytes_sent > total_bytes {
+                            *size_mismatch.lock().unwrap() = Some(bytes_sent);
+                            let error = std::io::Error::other("file changed size during upload");
+                            return Some((Err(error), (file, bytes_sent, progress_tx)));
+                        }
+                        if read == 0 {
+                            return None;
+                        }
+                        buffer.truncate(read);
+                        let _ = progress_tx.unbounded_send(UploadProgress {
+                            bytes_sent,
+                            total_bytes,
+                        });
+                        Some((Ok(Bytes::from(buffer)), (file, bytes_sent, progress_tx)))
+                    }
+                },
+            );
+
+            let filename = file_path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or("file")
+                .to_string();
+            let content_type = mime_guess::from_path(file_path)
+                .first_or_octet_stream()
+                .to_string();
+
+            let part = Part::stream_with_length(reqwest::Body::wrap_stream(chunks), total_bytes)
+                .file_name(filename)
+                .mime_str(&content_type)
+                .map_err(|e| Error::InvalidInput(format!("Invalid content type: {}", e)))?;
+            let mut form = Form::new().part("content", part);
+            if let Some(metadata) = metadata {
+                form = form.text("metadata", serde_json::to_string(metadata)?);
+            }
+
+            let result = self.http.post_multipart_json("/v1/files", form).await;
+            match *size_mismatch.lock().unwrap() {
+                Some(actual) => Err(size_changed_error(file_path, total_bytes, actual)),
+                None => result,
+            }
+        };
+
+        (progress_rx, upload)
+    }
+
+    /// Upload a file once, to reuse it as the input of many predictions.
+    ///
+    /// The source is kept to upload it again when the file expires; see
+    /// [`UploadedFile`]. Fails for URL inputs, which need no upload.
+    pub async fn upload(&self, file_input: FileInput) -> Result<UploadedFile> {
+        let file = self.create_from_file_input(&file_input, None).await?;
+        get_url(&file)?;
+        Ok(UploadedFile {
+            shared: Arc::new(UploadedShared {
+                files_api: self.clone(),
+                source: file_input,
+                file: Mutex::new(file),
+                reupload_lock: tokio::sync::Mutex::new(()),
+            }),
+            reupload: true,
+        })
+    }
+
     /// Upload a file from FileInput.
     pub async fn create_from_file_input(
         &self,
@@ -91,45 +610,134 @@ impl FilesApi {
         }
     }
 
+    /// Upload a file from FileInput with upload options.
+    ///
+    /// When an expiry is requested but the returned file expires later, or
+    /// not at all, the server did not apply it and a warning is logged.
+    pub async fn create_with_options(
+        &self,
+        file_input: &FileInput,
+        options: &UploadOptions,
+    ) -> Result<File> {
+        let metadata = options.metadata.as_ref();
+        let mut form = match file_input {
+            FileInput::Path(path) => {
+                HttpClient::file_form_from_path(path, metadata, options.check_size).await?
+            }
+            FileInput::Bytes {
+                data,
+                filename,
+                content_type,
+            } => {
+                HttpClient::create_file_form(
+                    data,
+                    filename.as_deref(),
+                    content_type.as_deref(),
+                    metadata,
+                )
+                .await?
+            }
+            FileInput::Url(_) => {
+                return Err(Error::InvalidInput(
+                    "Cannot upload from URL - file must be local or bytes".to_string(),
+                ));
+            }
+        };
+        if let Some(expires_in) = options.expires_in {
+            form = form.text("expires_in", expires_in.as_secs().to_string());
+        }
+
+        let file: File = self.http.post_multipart_json("/v1/files", form).await?;
+        if let Some(requested) = options.expires_in
+            && file
+                .expires_in()
+                .is_none_or(|left| left > requested + EXPIRY_LEEWAY)
+        {
+            tracing::warn!(
+                "File {} did not get the requested expiry of {:?}; expires_at is {:?}",
+                file.id,
+                requested,
+                file.expires_at
+            );
+        }
+        Ok(file)
+    }
+
+    /// Fetch the current metadata of an uploaded file.
+    ///
+    /// Long-lived pipelines can call this before reusing a file URL in a
+    /// prediction: a deleted or expired file fails with an API error, and
+    /// [`File::expires_in`] of the result tells how long it stays valid.
+    pub async fn refresh(&self, file: &File) -> Result<File> {
+        self.get(file).await
+    }
+
     /// Get a file by ID.
-    pub async fn get(&self, file_id: &str) -> Result<File> {
-        self.http.get_json(&format!("/v1/files/{}", file_id)).await
+    pub async fn get(&self, file_id: impl Into<FileId>) -> Result<File> {
+        self.get_with_options(file_id, &RequestOptions::default())
+            .await
+    }
+
+    /// Get a file by ID with per-request options, e.g. conditional GETs.
+    pub async fn get_with_options(
+        &self,
+        file_id: impl Into<FileId>,
+        options: &RequestOptions,
+    ) -> Result<File> {
+        self.http
+            .get_json_with_options(&format!("/v1/files/{}", file_id.into()), options)
+            .await
     }
 
     /// List all uploaded files.
     pub async fn list(&self) -> Result<Vec<File>> {
+        self.list_with_options(&RequestOptions::default()).await
+    }
+
+    /// List all uploaded files with per-request options, e.g. conditional GETs.
+    pub async fn list_with_options(&self, options: &RequestOptions) -> Result<Vec<File>> {
         #[derive(Deserialize)]
         struct ListResponse {
             results: Vec<File>,
         }
 
-        let response: ListResponse = self.http.get_json("/v1/files").await?;
+        let response: ListResponse = self
+            .http
+            .get_json_with_options("/v1/files", options)
+            .await?;
         Ok(response.results)
     }
 
     /// Delete a file by ID.
-    pub async fn delete(&self, file_id: &str) -> Result<bool> {
-        let response = self.http.delete(&format!("/v1/files/{}", file_id)).await?;
+    pub async fn delete(&self, file_id: impl Into<FileId>) -> Result<bool> {
+        let path = format!("/v1/files/{}", file_id.into());
+        let response = self.http.delete(&path).await?;
         Ok(response.status() == 204)
     }
 }
 
 /// Helper to process file inputs based on encoding strategy.
+///
+/// URLs, including data URLs, are passed through as is for the API to fetch,
+/// whatever the strategy. Other inputs are uploaded with `files_api` under
+/// [`FileEncodingStrategy::Multipart`], and fail without one.
 pub async fn process_file_input(
     file_input: &FileInput,
     encoding_strategy: &FileEncodingStrategy,
     files_api: Option<&FilesApi>,
 ) -> Result<String> {
+    if let FileInput::Url(url) = file_input {
+        return Ok(url.clone());
+    }
     match encoding_strategy {
-        FileEncodingStrategy::Base64DataUrl => encode_file_as_data_url(file_input).await,
+        FileEncodingStrategy::Base64DataUrl | FileEncodingStrategy::Auto => {
+            encode_file_as_data_url(file_input).await
+        }
         FileEncodingStrategy::Multipart => {
             if let Some(api) = files_api {
                 let file = api.create_from_file_input(file_input, None).await?;
                 // Return the file URL for use in predictions
-                file.urls
-                    .get("get")
-                    .cloned()
-                    .ok_or_else(|| Error::InvalidInput("File missing URL".to_string()))
+                get_url(&file)
             } else {
                 Err(Error::InvalidInput(
                     "Files API required for multipart upload".to_string(),
@@ -139,6 +747,93 @@ pub async fn process_file_input(
     }
 }
 
+/// Process a set of file inputs into the input values that replace them, in order.
+pub async fn process_file_inputs<'a>(
+    file_inputs: impl IntoIterator<Item = (&'a String, &'a FileInput)>,
+    encoding_strategy: &FileEncodingStrategy,
+    files_api: Option<&FilesApi>,
+) -> Result<Vec<(String, serde_json::Value)>> {
+    let mut processed = Vec::new();
+    for (key, file_input) in file_inputs {
+        let processed_value = process_file_input(file_input, encoding_strategy, files_api).await?;
+        processed.push((key.clone(), serde_json::Value::String(processed_value)));
+    }
+    Ok(processed)
+}
+
+/// Check that file inputs can be sent before any of them is processed.
+///
+/// Paths must name an existing, readable regular file. Empty files and byte
+/// inputs fail unless `allow_empty` is set, as the API rejects them with a
+/// confusing error. Failures are [`Error::InvalidInput`] naming the input key.
+pub async fn check_file_inputs<'a>(
+    file_inputs: impl IntoIterator<Item = (&'a String, &'a FileInput)>,
+    allow_empty: bool,
+) -> Result<()> {
+    for (key, file_input) in file_inputs {
+        let (size, empty_error) = match file_input {
+            FileInput::Url(_) => continue,
+            FileInput::Bytes { data, .. } => {
+                (data.len() as u64, format!("file input '{}' is empty", key))
+            }
+            FileInput::Path(path) => {
+                let describe =
+                    |problem: &str| format!("file input '{}': {} {}", key, path.display(), problem);
+                let metadata = match tokio::fs::metadata(path).await {
+                    Ok(metadata) => metadata,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                        return Err(Error::invalid_input(describe("does not exist")));
+                    }
+                    Err(e) => {
+                        return Err(Error::invalid_input(describe(&format!(
+                            "cannot be read: {}",
+                            e
+                        ))));
+                    }
+                };
+                if !metadata.is_file() {
+                    return Err(Error::invalid_input(describe("is not a regular file")));
+                }
+                if let Err(e) = tokio::fs::File::open(path).await {
+                    return Err(Error::invalid_input(describe(&format!(
+                        "cannot be read: {}",
+                        e
+                    ))));
+                }
+                (metadata.len(), describe("is empty"))
+            }
+        };
+        if size == 0 && !allow_empty {
+            return Err(Error::invalid_input(empty_error));
+        }
+    }
+    Ok(())
+}
+
+/// Get the length in bytes a file input would have as an input value when
+/// sent inline, without reading or encoding the file.
+pub(crate) async fn inline_size(file_input: &FileInput) -> Result<usize> {
+    let (content_type, len) = match file_input {
+        FileInput::Url(url) => return Ok(url.len()),
+        FileInput::Path(path) => (
+            mime_guess::from_path(path)
+                .first_or_octet_stream()
+                .to_string(),
+            tokio::fs::metadata(path).await?.len() as usize,
+        ),
+        FileInput::Bytes {
+            data, content_type, ..
+        } => (
+            content_type
+                .as_deref()
+                .map(data_url_media_type)
+                .unwrap_or_else(|| "application/octet-stream".to_string()),
+            data.len(),
+        ),
+    };
+    Ok("data:;base64,".len() + content_type.len() + len.div_ceil(3) * 4)
+}
+
 /// Encode a file input as a base64 data URL.
 async fn encode_file_as_data_url(file_input: &FileInput) -> Result<String> {
     match file_input {
@@ -162,7 +857,8 @@ async fn encode_file_as_data_url(file_input: &FileInput) -> Result<String> {
         } => {
             let content_type = content_type
                 .as_deref()
-                .unwrap_or("application/octet-stream");
+                .map(data_url_media_type)
+                .unwrap_or_else(|| "application/octet-stream".to_string());
 
             let encoded = general_purpose::STANDARD.encode(data);
             Ok(format!("data:{};base64,{}", content_type, encoded))
@@ -170,6 +866,19 @@ async fn encode_file_as_data_url(file_input: &FileInput) -> Result<String> {
     }
 }
 
+/// Format a content type for a data URL, keeping parameters such as `charset`.
+///
+/// Data URLs don't allow whitespace in the media type, so
+/// `text/plain; charset=utf-8` becomes `text/plain;charset=utf-8`.
+fn data_url_media_type(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -185,6 +894,255 @@ mod tests {
 
         let data_url = encode_file_as_data_url(&file_input).await.unwrap();
         assert_eq!(data_url, "data:text/plain;base64,SGVsbG8sIFdvcmxkIQ==");
+        assert_eq!(inline_size(&file_input).await.unwrap(), data_url.len());
+    }
+
+    #[tokio::test]
+    async fn test_check_file_inputs() {
+        let temp_dir = tempdir().unwrap();
+        let empty = temp_dir.path().join("empty.png");
+        std::fs::write(&empty, b"").unwrap();
+        let image = temp_dir.path().join("image.png");
+        std::fs::write(&image, b"png").unwrap();
+        let missing = temp_dir.path().join("missing.png");
+
+        let check = |file: FileInput, allow_empty: bool| async move {
+            let inputs = HashMap::from([("image".to_string(), file)]);
+            match check_file_inputs(&inputs, allow_empty).await {
+                Ok(()) => None,
+                Err(Error::InvalidInput(message)) => Some(message),
+                Err(other) => panic!("Expected invalid input, got {:?}", other),
+            }
+        };
+
+        assert_eq!(check(FileInput::from_path(&image), false).await, None);
+        assert_eq!(
+            check(FileInput::from_path(&missing), false).await,
+            Some(format!(
+                "file input 'image': {} does not exist",
+                missing.display()
+            ))
+        );
+        assert_eq!(
+            check(FileInput::from_path(temp_dir.path()), false).await,
+            Some(format!(
+                "file input 'image': {} is not a regular file",
+                temp_dir.path().display()
+            ))
+        );
+        assert_eq!(
+            check(FileInput::from_path(&empty), false).await,
+            Some(format!("file input 'image': {} is empty", empty.display()))
+        );
+        assert_eq!(check(FileInput::from_path(&empty), true).await, None);
+        assert_eq!(
+            check(FileInput::from_bytes(Vec::new()), false).await,
+            Some("file input 'image' is empty".to_string())
+        );
+        assert_eq!(
+            check(FileInput::from_url("https://example.com/a.png"), false).await,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_data_url_keeps_charset() {
+        let file_input = FileInput::from_bytes_with_metadata(
+            &b"Hello"[..],
+            None,
+            Some("text/plain; charset=utf-8".to_string()),
+        );
+
+        let data_url = encode_file_as_data_url(&file_input).await.unwrap();
+        assert_eq!(data_url, "data:text/plain;charset=utf-8;base64,SGVsbG8=");
+    }
+
+    #[tokio::test]
+    async fn test_multipart_upload_keeps_charset() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/files"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(serde_json::json!({
+                "id": "file-1",
+                "name": "notes.txt",
+                "content_type": "text/plain; charset=utf-8",
+                "size": 5,
+                "etag": "etag",
+                "checksums": {},
+                "metadata": {},
+                "created_at": "2024-01-01T00:00:00Z",
+                "expires_at": null,
+                "urls": {"get": "https://api.replicate.com/v1/files/file-1"},
+            })))
+            .mount(&server)
+            .await;
+
+        let http = HttpClient::with_base_url("test-token", server.uri()).unwrap();
+        let file = FilesApi::new(http)
+            .create_from_bytes(
+                b"Hello",
+                Some("notes.txt"),
+                Some("text/plain; charset=utf-8"),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(file.content_type, "text/plain; charset=utf-8");
+
+        let requests = server.received_requests().await.unwrap();
+        let body = String::from_utf8_lossy(&requests[0].body);
+        assert!(
+            body.contains("Content-Type: text/plain; charset=utf-8"),
+            "multipart body lost the charset: {}",
+            body
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upload_progress_stream() {
+        use futures::StreamExt;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/files"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(serde_json::json!({
+                "id": "file-1",
+                "name": "weights.bin",
+                "content_type": "application/octet-stream",
+                "size": 300_000,
+                "etag": "etag",
+                "checksums": {},
+                "metadata": {},
+                "created_at": "2024-01-01T00:00:00Z",
+                "urls": {},
+            })))
+            .mount(&server)
+            .await;
+
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("weights.bin");
+        let content: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&file_path, &content).unwrap();
+
+        let api = FilesApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        let (progress, upload) = api.create_from_path_with_progress(&file_path, None);
+        let (events, file) = futures::join!(progress.collect::<Vec<_>>(), upload);
+
+        assert_eq!(file.unwrap().id, "file-1");
+        assert!(events.len() > 1, "expected several progress events");
+        assert!(
+            events
+                .windows(2)
+                .all(|pair| pair[0].bytes_sent < pair[1].bytes_sent)
+        );
+        let last = events.last().unwrap();
+        assert_eq!(last.bytes_sent, content.len() as u64);
+        assert_eq!(last.total_bytes, content.len() as u64);
+        assert_eq!(last.fraction(), 1.0);
+
+        let requests = server.received_requests().await.unwrap();
+        let body = &requests[0].body;
+        assert!(
+            body.windows(content.len()).any(|window| window == content),
+            "multipart body is missing the file content"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_abort_upload_drops_connection() {
+        use tokio::io::AsyncReadExt;
+
+        // Accept one upload, stop reading after its first bytes so it stalls,
+        // then report when the client closes the connection.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let (started_tx, started_rx) = tokio::sync::oneshot::channel();
+        let (closed_tx, closed_rx) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut chunk = [0u8; 1024];
+            socket.read_exact(&mut chunk).await.unwrap();
+            started_tx.send(()).unwrap();
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            // Drain until the client hangs up
+            while !matches!(socket.read(&mut chunk).await, Ok(0) | Err(_)) {}
+            closed_tx.send(()).unwrap();
+        });
+
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("large.bin");
+        std::fs::write(&file_path, vec![7u8; 32 * 1024 * 1024]).unwrap();
+
+        let api = FilesApi::new(HttpClient::with_base_url("test-token", base_url).unwrap());
+        let (handle, upload) = api.create_from_path_abortable(&file_path, None);
+        let upload = tokio::spawn(upload);
+        started_rx.await.unwrap();
+        handle.abort();
+
+        assert!(handle.is_aborted());
+        assert!(matches!(upload.await.unwrap(), Err(Error::UploadAborted)));
+        tokio::time::timeout(Duration::from_secs(5), closed_rx)
+            .await
+            .expect("connection was not closed")
+            .unwrap();
+    }
+
+    /// The server reads nothing until the file was truncated, so the upload
+    /// stalls with most of the file unread and reads the rest after the
+    /// change.
+    #[tokio::test]
+    async fn test_upload_rejects_file_that_changes_size() {
+        use futures::StreamExt;
+        use tokio::io::AsyncReadExt;
+
+        let temp_dir = tempdir().unwrap();
+        let shrinking = temp_dir.path().join("shrinking.bin");
+        std::fs::write(&shrinking, vec![0u8; 32 * 1024 * 1024]).unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (truncated_tx, truncated_rx) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let _ = truncated_rx.await;
+            let mut buffer = vec![0u8; UPLOAD_CHUNK_SIZE];
+            while matches!(socket.read(&mut buffer).await, Ok(read) if read > 0) {}
+        });
+
+        let api = FilesApi::new(
+            HttpClient::with_base_url("test-token", format!("http://{}", addr)).unwrap(),
+        );
+        let (mut progress, upload) = api.create_from_path_with_progress(&shrinking, None);
+        let truncate = async {
+            progress.next().await.unwrap();
+            std::fs::OpenOptions::new()
+                .write(true)
+                .open(&shrinking)
+                .unwrap()
+                .set_len(0)
+                .unwrap();
+            truncated_tx.send(()).unwrap();
+            progress.collect::<Vec<_>>().await
+        };
+        let (_, result) = futures::join!(truncate, upload);
+        match result {
+            Err(Error::InvalidInput(message)) => {
+                assert!(message.contains("changed size"), "{}", message)
+            }
+            other => panic!("Expected invalid input, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_content_type_rejected() {
+        let result =
+            HttpClient::create_file_form(b"Hello", Some("a.txt"), Some("not a mime"), None).await;
+        assert!(matches!(result, Err(Error::InvalidInput(_))));
     }
 
     #[tokio::test]
@@ -198,5 +1156,148 @@ mod tests {
 
         assert!(data_url.starts_with("data:text/plain;base64,"));
         assert!(data_url.contains("VGVzdCBjb250ZW50")); // "Test content" in base64
+        assert_eq!(inline_size(&file_input).await.unwrap(), data_url.len());
+    }
+
+    #[test]
+    fn test_file_unknown_fields_round_trip() {
+        let payload = serde_json::json!({
+            "id": "f1",
+            "name": "a.png",
+            "content_type": "image/png",
+            "size": 3,
+            "etag": "abc",
+            "checksums": {"sha256": "deadbeef"},
+            "metadata": {},
+            "created_at": "2024-01-01T00:00:00Z",
+            "expires_at": null,
+            "urls": {"get": "https://api.replicate.com/v1/files/f1"},
+            "owner": "acme",
+        });
+
+        let file: File = serde_json::from_value(payload.clone()).unwrap();
+        assert_eq!(file.extra["owner"], "acme");
+        assert_eq!(file.extra.len(), 1);
+        assert_eq!(
+            file.download_url(),
+            Some("https://api.replicate.com/v1/files/f1")
+        );
+        assert_eq!(file.upload_url(), None);
+        assert_eq!(serde_json::to_value(&file).unwrap(), payload);
+    }
+
+    fn uploaded_file(checksums: serde_json::Value) -> File {
+        serde_json::from_value(serde_json::json!({
+            "id": "f1",
+            "name": "hello.txt",
+            "content_type": "text/plain",
+            "size": 13,
+            "etag": "abc",
+            "checksums": checksums,
+            "metadata": {},
+            "created_at": "2024-01-01T00:00:00Z",
+            "urls": {},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_verify_local() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("hello.txt");
+        std::fs::write(&path, b"Hello, World!").unwrap();
+
+        let sha256 = "dffd6021bb2bd5b0af676290809ec3a53191dd81c7f70a4b28688a362182986f";
+        let md5 = "65a8e27d8879283831b664bd8b7f0ad4";
+
+        let file = uploaded_file(serde_json::json!({"sha256": sha256, "md5": md5}));
+        assert!(file.verify_local(&path).unwrap());
+
+        let md5_only = uploaded_file(serde_json::json!({"md5": md5.to_uppercase()}));
+        assert!(md5_only.verify_local(&path).unwrap());
+
+        // SHA-256 wins over a matching MD5
+        let mismatch = uploaded_file(serde_json::json!({"sha256": "00".repeat(32), "md5": md5}));
+        assert!(!mismatch.verify_local(&path).unwrap());
+
+        let none = uploaded_file(serde_json::json!({"crc32c": "abcd"}));
+        assert!(none.checksums.sha256.is_none());
+        assert_eq!(none.checksums.other["crc32c"], "abcd");
+        assert!(matches!(
+            none.verify_local(&path),
+            Err(Error::InvalidInput(_))
+        ));
+
+        let aliased = uploaded_file(serde_json::json!({"sha-256": sha256}));
+        assert_eq!(aliased.checksums.sha256.as_deref(), Some(sha256));
+    }
+
+    #[test]
+    fn test_expires_in() {
+        let file = |expires_at: serde_json::Value| -> File {
+            let mut file = uploaded_file(serde_json::json!({}));
+            file.expires_at = serde_json::from_value(expires_at).unwrap();
+            file
+        };
+
+        let future = file(serde_json::json!("2999-01-01T00:00:00.000Z"));
+        assert!(future.expires_in().unwrap() > Duration::from_secs(365 * 24 * 3600));
+        assert!(!future.is_expired());
+
+        let past = file(serde_json::json!("2000-01-01T00:00:00+01:00"));
+        assert_eq!(past.expires_in(), Some(Duration::ZERO));
+        assert!(past.is_expired());
+
+        assert_eq!(file(serde_json::Value::Null).expires_in(), None);
+        assert_eq!(file(serde_json::json!("tomorrow")).expires_in(), None);
+        assert!(!file(serde_json::Value::Null).is_expired());
+    }
+
+    #[tokio::test]
+    async fn test_upload_expiry_and_refresh() {
+        use wiremock::matchers::{body_string_contains, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let file = |expires_at: &str| {
+            serde_json::json!({
+                "id": "file-1",
+                "name": "notes.txt",
+                "content_type": "text/plain",
+                "size": 5,
+                "etag": "etag",
+                "checksums": {},
+                "metadata": {},
+                "created_at": "2024-01-01T00:00:00Z",
+                "expires_at": expires_at,
+                "urls": {"get": "https://api.replicate.com/v1/files/file-1"},
+            })
+        };
+        Mock::given(method("POST"))
+            .and(path("/v1/files"))
+            .and(body_string_contains("name=\"expires_in\"\r\n\r\n3600\r\n"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(file("2999-01-01T00:00:00Z")))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/files/file-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(file("2000-01-01T00:00:00Z")))
+            .mount(&server)
+            .await;
+
+        let api = FilesApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        let uploaded = api
+            .create_with_options(
+                &FileInput::from_bytes(&b"Hello"[..]),
+                &UploadOptions::new().expires_in(Duration::from_secs(3600)),
+            )
+            .await
+            .unwrap();
+        assert!(!uploaded.is_expired());
+
+        let refreshed = api.refresh(&uploaded).await.unwrap();
+        assert_eq!(refreshed.id, "file-1");
+        assert!(refreshed.is_expired());
     }
 }
@@ -0,0 +1,63 @@
+//! Hardware API implementation.
+
+use crate::error::Result;
+use crate::http::HttpClient;
+use crate::models::common::Hardware;
+
+/// API for listing the hardware models can run on.
+#[derive(Debug, Clone)]
+pub struct HardwareApi {
+    http: HttpClient,
+}
+
+impl HardwareApi {
+    /// Create a new hardware API instance.
+    pub fn new(http: HttpClient) -> Self {
+        Self { http }
+    }
+
+    /// Create a copy of this API that sends requests through another HTTP client.
+    pub(crate) fn with_http(&self, http: HttpClient) -> Self {
+        Self { http }
+    }
+
+    /// List the available hardware.
+    pub async fn list(&self) -> Result<Vec<Hardware>> {
+        self.http.get_json("/v1/hardware").await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::common::HardwareSku;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_list_returns_typed_skus() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/hardware"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {"name": "CPU", "sku": "cpu"},
+                {"name": "Nvidia T4 GPU", "sku": "gpu-t4"},
+                {"name": "Nvidia B200 GPU", "sku": "gpu-b200"},
+            ])))
+            .mount(&server)
+            .await;
+
+        let api = HardwareApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        let hardware = api.list().await.unwrap();
+        let skus: Vec<_> = hardware.into_iter().map(|hardware| hardware.sku).collect();
+        assert_eq!(
+            skus,
+            [
+                HardwareSku::CPU,
+                HardwareSku::NVIDIA_T4,
+                HardwareSku::from("gpu-b200")
+            ]
+        );
+    }
+}
@@ -1,8 +1,34 @@
 //! API operation implementations.
 
+pub mod collections;
+pub mod deployments;
 pub mod files;
+pub mod hardware;
+pub mod models;
+pub mod polling;
 pub mod predictions;
+pub mod stream;
+pub mod trainings;
+pub mod watcher;
+pub mod webhooks;
 
 // Re-export main API components
-pub use files::{File, FilesApi};
-pub use predictions::PredictionsApi;
+pub use collections::CollectionsApi;
+pub use deployments::DeploymentsApi;
+pub use files::{
+    File, FileChecksums, FilesApi, UploadAbortHandle, UploadOptions, UploadProgress, UploadedFile,
+};
+pub use hardware::HardwareApi;
+pub use models::ModelsApi;
+pub use polling::{PollOptions, Pollable, poll_until_terminal};
+pub use predictions::{
+    CancelOutcome, CancelReport, DEFAULT_FALLBACK_STATUSES, PollUpdate, PredictionFilter,
+    PredictionsApi, RunMode, RunOptions,
+};
+pub use stream::{JsonObjects, PredictionStream};
+pub use trainings::{TrainingBuilder, TrainingsApi};
+pub use watcher::{PredictionWatcher, WatcherConfig};
+pub use webhooks::{
+    DeliveryDeduplicator, WebhookCheck, WebhookDelivery, WebhookEvent, WebhookHeaders,
+    WebhookSecret, WebhooksApi, parse_event,
+};
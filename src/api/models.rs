@@ -0,0 +1,237 @@
+//! Models API implementation.
+
+use crate::error::{Error, Result};
+use crate::http::HttpClient;
+use crate::models::common::{CreateModelRequest, Model, PaginatedResponse};
+use crate::models::training::split_model_identifier;
+
+/// Path of the collection of official models.
+const OFFICIAL_MODELS_PATH: &str = "/v1/collections/official";
+
+/// API for creating, listing and deleting models.
+#[derive(Debug, Clone)]
+pub struct ModelsApi {
+    http: HttpClient,
+}
+
+impl ModelsApi {
+    /// Create a new models API instance.
+    pub fn new(http: HttpClient) -> Self {
+        Self { http }
+    }
+
+    /// Create a copy of this API that sends requests through another HTTP client.
+    pub(crate) fn with_http(&self, http: HttpClient) -> Self {
+        Self { http }
+    }
+
+    /// Create a model, e.g. as the destination of a training.
+    ///
+    /// Fails with [`Error::AlreadyExists`] if the owner already has a model
+    /// of that name.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use replicate_client::{Client, CreateModelRequest, HardwareSku, Visibility};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::new("your-api-token")?;
+    /// let model = client
+    ///     .models()
+    ///     .create(&CreateModelRequest::new(
+    ///         "acme",
+    ///         "flux-lora",
+    ///         Visibility::Private,
+    ///         HardwareSku::NVIDIA_H100,
+    ///     ))
+    ///     .await?;
+    /// println!("Created {}", model.identifier());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn create(&self, request: &CreateModelRequest) -> Result<Model> {
+        let identifier = format!("{}/{}", request.owner, request.name);
+        split_model_identifier(&identifier)?;
+        self.http
+            .post_json("/v1/models", request)
+            .await
+            .map_err(|e| match e {
+                Error::Api { status: 409, .. } => {
+                    Error::already_exists(format!("Model {} already exists", identifier))
+                }
+                e => e,
+            })
+    }
+
+    /// List the official models, which Replicate maintains and keeps stable.
+    ///
+    /// The models come from the `official` collection. Pass the `next` URL of
+    /// a page as `cursor` to fetch the following page, or use
+    /// [`PaginatedResponse::into_stream`] with
+    /// [`Client::http_client`](crate::Client::http_client) to follow every
+    /// page. A cursor that does not point at the API host fails with
+    /// [`Error::InvalidInput`].
+    pub async fn list_official(&self, cursor: Option<&str>) -> Result<PaginatedResponse<Model>> {
+        match cursor {
+            Some(cursor) => {
+                let url = self.http.validate_api_url(cursor)?;
+                self.http.get_json(url.as_str()).await
+            }
+            None => self.http.get_json(OFFICIAL_MODELS_PATH).await,
+        }
+    }
+
+    /// Delete a model.
+    ///
+    /// The API only deletes private models without versions; others fail
+    /// with an API error.
+    pub async fn delete(&self, owner: &str, name: &str) -> Result<bool> {
+        let identifier = format!("{}/{}", owner, name);
+        split_model_identifier(&identifier)?;
+        let response = self
+            .http
+            .delete(&format!("/v1/models/{}", identifier))
+            .await?;
+        Ok(response.status() == 204)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::common::{HardwareSku, Visibility};
+    use futures::TryStreamExt;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_create_and_delete_model() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/models"))
+            .and(body_json(json!({
+                "owner": "acme",
+                "name": "flux-lora",
+                "visibility": "private",
+                "hardware": "gpu-t4",
+                "description": "Fine-tuned FLUX",
+            })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "owner": "acme",
+                "name": "flux-lora",
+                "description": "Fine-tuned FLUX",
+                "visibility": "private",
+                "github_url": null,
+                "paper_url": null,
+                "license_url": null,
+                "cover_image_url": null,
+                "latest_version": null,
+            })))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/models"))
+            .respond_with(
+                ResponseTemplate::new(409)
+                    .set_body_json(json!({"detail": "A model with that name already exists"})),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/v1/models/acme/flux-lora"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let api = ModelsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        let request = CreateModelRequest::new(
+            "acme",
+            "flux-lora",
+            Visibility::Private,
+            HardwareSku::NVIDIA_T4,
+        )
+        .with_description("Fine-tuned FLUX");
+        let model = api.create(&request).await.unwrap();
+        assert_eq!(model.identifier(), "acme/flux-lora");
+
+        match api.create(&request).await {
+            Err(Error::AlreadyExists(message)) => {
+                assert!(message.contains("acme/flux-lora"), "{}", message)
+            }
+            other => panic!("Expected already exists, got {:?}", other),
+        }
+        assert!(api.delete("acme", "flux-lora").await.unwrap());
+        assert!(matches!(
+            api.delete("acme", "").await,
+            Err(Error::InvalidInput(_))
+        ));
+    }
+
+    fn model(owner: &str, name: &str) -> serde_json::Value {
+        json!({
+            "owner": owner,
+            "name": name,
+            "description": null,
+            "visibility": "public",
+            "github_url": null,
+            "paper_url": null,
+            "license_url": null,
+            "cover_image_url": null,
+            "latest_version": null,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_list_official_models() {
+        let server = MockServer::start().await;
+        let next = format!("{}/v1/collections/official?cursor=abc", server.uri());
+        Mock::given(method("GET"))
+            .and(path("/v1/collections/official"))
+            .and(query_param("cursor", "abc"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "name": "Official models",
+                "slug": "official",
+                "models": [model("meta", "llama")],
+                "next": null,
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/collections/official"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "name": "Official models",
+                "slug": "official",
+                "description": "Models maintained by Replicate",
+                "models": [model("black-forest-labs", "flux-schnell")],
+                "next": next,
+            })))
+            .mount(&server)
+            .await;
+
+        let http = HttpClient::with_base_url("test-token", server.uri()).unwrap();
+        let api = ModelsApi::new(http.clone());
+        let page = api.list_official(None).await.unwrap();
+        assert_eq!(
+            page.results[0].identifier(),
+            "black-forest-labs/flux-schnell"
+        );
+
+        let second = api.list_official(page.next.as_deref()).await.unwrap();
+        assert_eq!(second.results[0].identifier(), "meta/llama");
+        assert!(!second.has_next());
+
+        let all: Vec<Model> = page.into_stream(&http).try_collect().await.unwrap();
+        assert_eq!(all.len(), 2);
+
+        let foreign = "https://example.com/v1/collections/official?cursor=abc";
+        assert!(matches!(
+            api.list_official(Some(foreign)).await,
+            Err(Error::InvalidInput(_))
+        ));
+        assert_eq!(server.received_requests().await.unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,386 @@
+//! Shared polling for long-running resources such as predictions and trainings.
+
+use std::future::Future;
+use std::time::Duration;
+
+use tokio::time::{Instant, interval, timeout_at};
+
+use crate::error::{Error, Result, TimeoutKind};
+use crate::http::client::no_cache_headers;
+use crate::http::{Deadline, HttpClient};
+use crate::models::prediction::{Prediction, PredictionStatus};
+use crate::models::training::Training;
+
+/// A remote resource that moves through [`PredictionStatus`] states and can
+/// be re-fetched until it reaches a terminal one.
+pub trait Pollable: Send {
+    /// Resource name used in error messages, e.g. `Prediction`
+    const KIND: &'static str;
+
+    /// ID of the resource
+    fn id(&self) -> &str;
+
+    /// Current status of the resource
+    fn status(&self) -> &PredictionStatus;
+
+    /// Error message reported for a failed resource
+    fn error(&self) -> Option<&str> {
+        None
+    }
+
+    /// Logs reported by the resource
+    fn logs(&self) -> Option<&str> {
+        None
+    }
+
+    /// Re-fetch the resource, replacing `self` with its latest state
+    fn refresh(&mut self, http: &HttpClient) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// Options controlling how a [`Pollable`] is polled.
+#[derive(Debug, Clone)]
+pub struct PollOptions {
+    /// Time between two polls
+    pub interval: Duration,
+    /// Maximum time to wait for a terminal state
+    pub max_duration: Option<Duration>,
+    /// Time by which polling must be finished, whichever comes first with `max_duration`
+    pub deadline: Option<Deadline>,
+}
+
+impl Default for PollOptions {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_millis(500),
+            max_duration: None,
+            deadline: None,
+        }
+    }
+}
+
+impl PollOptions {
+    /// Create default poll options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the time between two polls.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Set the maximum time to wait for a terminal state.
+    pub fn max_duration(mut self, max_duration: Duration) -> Self {
+        self.max_duration = Some(max_duration);
+        self
+    }
+
+    /// Set the time by which polling must be finished.
+    ///
+    /// Unlike [`max_duration`](Self::max_duration), the deadline is usually
+    /// shared with the requests made before polling started.
+    pub fn deadline(mut self, deadline: Deadline) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+}
+
+/// Refresh a resource until it reaches a terminal state.
+///
+/// Failed resources are returned as [`Error::ModelExecution`], and exceeding
+/// [`PollOptions::max_duration`] or [`PollOptions::deadline`] as
+/// [`Error::Timeout`] with the last fetched status. Canceled resources are
+/// returned as-is.
+pub async fn poll_until_terminal<P: Pollable>(
+    item: P,
+    http: &HttpClient,
+    options: &PollOptions,
+) -> Result<P> {
+    if item.status().is_terminal() {
+        return terminal_result(item);
+    }
+
+    let id = item.id().to_string();
+    let mut initial = Some(item);
+    poll_loop(P::KIND, &id, options, move |previous| {
+        let item = previous.or_else(|| initial.take());
+        async move {
+            let mut item = item.expect("polling starts from the given resource");
+            item.refresh(http).await?;
+            Ok(item)
+        }
+    })
+    .await
+}
+
+/// Fetch the latest state on every tick until it is terminal or the wait
+/// times out. The first fetch happens immediately and receives `None`; later
+/// fetches receive the previous state.
+pub(crate) async fn poll_loop<P, F, Fut>(
+    kind: &str,
+    id: &str,
+    options: &PollOptions,
+    mut fetch: F,
+) -> Result<P>
+where
+    P: Pollable,
+    F: FnMut(Option<P>) -> Fut,
+    Fut: Future<Output = Result<P>>,
+{
+    let mut ticker = interval(options.interval);
+    let mut last_status = None;
+    let wait_future = async {
+        let mut latest = None;
+        loop {
+            ticker.tick().await;
+            let item = match fetch(latest.take()).await {
+                Ok(item) => item,
+                Err(Error::Timeout {
+                    kind: TimeoutKind::Deadline,
+                    ..
+                }) => return Err(deadline_error(kind, id, last_status.clone())),
+                Err(error) => return Err(error),
+            };
+            if item.status().is_terminal() {
+                return terminal_result(item);
+            }
+            last_status = Some(item.status().clone());
+            latest = Some(item);
+        }
+    };
+
+    let wait_end = options
+        .max_duration
+        .map(|duration| Instant::now() + duration);
+    let deadline = options.deadline.map(|deadline| deadline.instant());
+    let end = match (wait_end, deadline) {
+        (Some(wait_end), Some(deadline)) => Some(wait_end.min(deadline)),
+        (wait_end, deadline) => wait_end.or(deadline),
+    };
+    let Some(end) = end else {
+        return wait_future.await;
+    };
+    let outcome = timeout_at(end, wait_future).await;
+    outcome.unwrap_or_else(|_| match (wait_end, options.max_duration) {
+        (Some(wait_end), Some(duration)) if wait_end <= end => Err(Error::wait_timeout(
+            id,
+            last_status,
+            format!("{} {} did not complete within {:?}", kind, id, duration),
+        )),
+        _ => Err(deadline_error(kind, id, last_status)),
+    })
+}
+
+/// Error for a wait cut short by its deadline.
+fn deadline_error(kind: &str, id: &str, last_status: Option<PredictionStatus>) -> Error {
+    Error::Timeout {
+        message: format!("{} {} did not complete before the deadline", kind, id),
+        prediction_id: Some(id.to_string()),
+        last_status,
+        kind: TimeoutKind::Deadline,
+    }
+}
+
+/// Translate a terminal resource into the caller-facing result.
+pub(crate) fn terminal_result<P: Pollable>(item: P) -> Result<P> {
+    if *item.status() == PredictionStatus::Failed {
+        return Err(Error::model_execution(
+            item.id(),
+            item.error().map(str::to_string),
+            item.logs().map(str::to_string),
+        ));
+    }
+    Ok(item)
+}
+
+impl Pollable for Prediction {
+    const KIND: &'static str = "Prediction";
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn status(&self) -> &PredictionStatus {
+        &self.status
+    }
+
+    fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    fn logs(&self) -> Option<&str> {
+        self.logs.as_deref()
+    }
+
+    async fn refresh(&mut self, http: &HttpClient) -> Result<()> {
+        *self = http
+            .get_json_with_headers(&format!("/v1/predictions/{}", self.id), &no_cache_headers())
+            .await?;
+        Ok(())
+    }
+}
+
+impl Pollable for Training {
+    const KIND: &'static str = "Training";
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn status(&self) -> &PredictionStatus {
+        &self.status
+    }
+
+    fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    fn logs(&self) -> Option<&str> {
+        self.logs.as_deref()
+    }
+
+    async fn refresh(&mut self, http: &HttpClient) -> Result<()> {
+        *self = http.get_json(&format!("/v1/trainings/{}", self.id)).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Walks through a fixed list of statuses, one per refresh.
+    struct FakeJob {
+        statuses: Vec<PredictionStatus>,
+        refreshes: Arc<AtomicUsize>,
+    }
+
+    impl FakeJob {
+        fn new(statuses: Vec<PredictionStatus>) -> Self {
+            Self {
+                statuses,
+                refreshes: Arc::new(AtomicUsize::new(0)),
+            }
+        }
+    }
+
+    impl Pollable for FakeJob {
+        const KIND: &'static str = "Job";
+
+        fn id(&self) -> &str {
+            "job-1"
+        }
+
+        fn status(&self) -> &PredictionStatus {
+            let index = self.refreshes.load(Ordering::SeqCst);
+            &self.statuses[index.min(self.statuses.len() - 1)]
+        }
+
+        fn error(&self) -> Option<&str> {
+            Some("boom")
+        }
+
+        async fn refresh(&mut self, _http: &HttpClient) -> Result<()> {
+            self.refreshes.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    fn options() -> PollOptions {
+        PollOptions::new().interval(Duration::from_millis(5))
+    }
+
+    fn http() -> HttpClient {
+        HttpClient::new("test-token").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_polls_until_terminal() {
+        use PredictionStatus::*;
+        let job = FakeJob::new(vec![Starting, Processing, Processing, Succeeded]);
+        let refreshes = job.refreshes.clone();
+
+        let job = poll_until_terminal(job, &http(), &options()).await.unwrap();
+        assert_eq!(*job.status(), Succeeded);
+        assert_eq!(refreshes.load(Ordering::SeqCst), 3);
+
+        // Already terminal resources are not refreshed
+        let done = FakeJob::new(vec![Canceled]);
+        let refreshes = done.refreshes.clone();
+        let done = poll_until_terminal(done, &http(), &options())
+            .await
+            .unwrap();
+        assert_eq!(*done.status(), Canceled);
+        assert_eq!(refreshes.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_failure_and_timeout() {
+        use PredictionStatus::*;
+        let failed = FakeJob::new(vec![Processing, Failed]);
+        match poll_until_terminal(failed, &http(), &options()).await {
+            Err(Error::ModelExecution {
+                prediction_id,
+                error_message,
+                ..
+            }) => {
+                assert_eq!(prediction_id, "job-1");
+                assert_eq!(error_message.as_deref(), Some("boom"));
+            }
+            other => panic!("Expected a model execution error, got {:?}", other.err()),
+        }
+
+        let stuck = FakeJob::new(vec![Processing]);
+        let options = options().max_duration(Duration::from_millis(30));
+        match poll_until_terminal(stuck, &http(), &options).await {
+            Err(Error::Timeout {
+                message,
+                prediction_id,
+                last_status,
+                kind,
+            }) => {
+                assert!(message.starts_with("Job job-1"));
+                assert_eq!(prediction_id.as_deref(), Some("job-1"));
+                assert_eq!(last_status, Some(Processing));
+                assert_eq!(kind, TimeoutKind::Wait);
+            }
+            other => panic!("Expected a timeout, got {:?}", other.err()),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_deadline_bounds_polling() {
+        use PredictionStatus::*;
+        let start = Instant::now();
+        let options = PollOptions::new()
+            .interval(Duration::from_secs(1))
+            .max_duration(Duration::from_secs(60))
+            .deadline(Deadline::after(Duration::from_secs(10)));
+        match poll_until_terminal(FakeJob::new(vec![Processing]), &http(), &options).await {
+            Err(Error::Timeout {
+                prediction_id,
+                last_status,
+                kind,
+                ..
+            }) => {
+                assert_eq!(kind, TimeoutKind::Deadline);
+                assert_eq!(prediction_id.as_deref(), Some("job-1"));
+                assert_eq!(last_status, Some(Processing));
+            }
+            other => panic!("Expected a deadline timeout, got {:?}", other.err()),
+        }
+        assert_eq!(start.elapsed(), Duration::from_secs(10));
+
+        // The shorter limit wins
+        let options = options
+            .max_duration(Duration::from_secs(2))
+            .deadline(Deadline::after(Duration::from_secs(10)));
+        match poll_until_terminal(FakeJob::new(vec![Processing]), &http(), &options).await {
+            Err(Error::Timeout { kind, .. }) => assert_eq!(kind, TimeoutKind::Wait),
+            other => panic!("Expected a timeout, got {:?}", other.err()),
+        }
+    }
+}
@@ -1,65 +1,699 @@
 //! Predictions API implementation.
 
+use futures::stream::{self, StreamExt};
+use reqwest::header::{HeaderMap, HeaderValue};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
 use serde_json::Value;
 use std::collections::HashMap;
-use std::time::Duration;
-use tokio::time::{interval, timeout};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::task::JoinHandle;
 
-use crate::api::files::{FilesApi, process_file_input};
-use crate::error::{Error, Result};
-use crate::http::HttpClient;
+use crate::api::files::{
+    FilesApi, UploadedFile, check_file_inputs, inline_size, process_file_inputs,
+};
+use crate::api::polling::{PollOptions, Pollable, poll_loop, terminal_result};
+use crate::api::stream::PredictionStream;
+use crate::api::watcher::{PredictionWatcher, WatcherConfig};
+use crate::api::webhooks::WebhookCheck;
+use crate::cost::CostTracker;
+use crate::error::{Error, Result, TimeoutKind};
+use crate::http::client::no_cache_headers;
+use crate::http::{Deadline, HttpClient};
 use crate::models::{
-    common::PaginatedResponse,
+    common::{ModelRef, PaginatedResponse, parse_timestamp},
     file::{FileEncodingStrategy, FileInput},
-    prediction::{CreatePredictionRequest, Prediction},
+    id::PredictionId,
+    output::OutputSelector,
+    prediction::{
+        CreatePredictionRequest, FallbackTarget, Prediction, PredictionHandle, PredictionStatus,
+        TypedPrediction,
+    },
+    schema::DynamicInput,
 };
+use crate::sanitize::InputSanitizer;
 
 /// API for managing predictions.
 #[derive(Debug, Clone)]
 pub struct PredictionsApi {
     http: HttpClient,
     files_api: Option<FilesApi>,
+    watcher: Arc<OnceLock<PredictionWatcher>>,
+    raw_output: bool,
+    nonce_key: String,
+    cost_tracker: Option<CostTracker>,
+    input_sanitizer: Option<InputSanitizer>,
+    max_body_size: Option<usize>,
+    default_webhook: Option<String>,
+    default_file_encoding: FileEncodingStrategy,
+    default_inputs: HashMap<String, Value>,
+    default_run_mode: RunMode,
 }
 
+/// Default limit of [`PredictionsApi::max_body_size`].
+pub const DEFAULT_MAX_BODY_SIZE: usize = 10 * 1024 * 1024;
+
+/// Default statuses of [`PredictionBuilder::fallback_statuses`]: the target
+/// is missing or being re-created (404, 409) or saturated (429, 503).
+pub const DEFAULT_FALLBACK_STATUSES: &[u16] = &[404, 409, 429, 503];
+
+/// Number of predictions [`PredictionsApi::cancel_matching`] checks or
+/// cancels at once.
+const CANCEL_CONCURRENCY: usize = 8;
+
+/// Default input key holding the nonce used by [`PredictionsApi::create_exactly_once`].
+pub const DEFAULT_NONCE_KEY: &str = "_replicate_rs_nonce";
+
+/// How long before the first create attempt predictions are searched for
+/// its nonce, to allow for the API's clock being behind.
+const NONCE_SEARCH_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+/// Most pages of predictions searched for a nonce.
+const MAX_NONCE_SEARCH_PAGES: usize = 20;
+
 impl PredictionsApi {
     /// Create a new predictions API instance.
     pub fn new(http: HttpClient) -> Self {
         Self {
             http: http.clone(),
             files_api: Some(FilesApi::new(http)),
+            watcher: Arc::new(OnceLock::new()),
+            raw_output: false,
+            nonce_key: DEFAULT_NONCE_KEY.to_string(),
+            cost_tracker: None,
+            input_sanitizer: None,
+            max_body_size: Some(DEFAULT_MAX_BODY_SIZE),
+            default_webhook: None,
+            default_file_encoding: FileEncodingStrategy::default(),
+            default_inputs: HashMap::new(),
+            default_run_mode: RunMode::default(),
+        }
+    }
+
+    /// Create a copy of this API that sends requests through another HTTP client.
+    ///
+    /// Settings are kept, but the copy gets its own watcher so that polling
+    /// also goes through the new client.
+    pub(crate) fn with_http(&self, http: HttpClient) -> Self {
+        Self {
+            files_api: self
+                .files_api
+                .as_ref()
+                .map(|api| api.with_http(http.clone())),
+            http,
+            watcher: Arc::new(OnceLock::new()),
+            raw_output: self.raw_output,
+            nonce_key: self.nonce_key.clone(),
+            cost_tracker: self.cost_tracker.clone(),
+            input_sanitizer: self.input_sanitizer.clone(),
+            max_body_size: self.max_body_size,
+            default_webhook: self.default_webhook.clone(),
+            default_file_encoding: self.default_file_encoding.clone(),
+            default_inputs: self.default_inputs.clone(),
+            default_run_mode: self.default_run_mode,
+        }
+    }
+
+    /// Create a copy of this API whose requests must all finish by `deadline`.
+    ///
+    /// Covers file uploads, creates and polls; see [`HttpClient::with_deadline`].
+    pub fn with_deadline(&self, deadline: Deadline) -> Self {
+        self.with_http(self.http.with_deadline(deadline))
+    }
+
+    /// Enable or disable raw output mode.
+    ///
+    /// In raw output mode, [`get`](Self::get) streams the response and keeps the
+    /// prediction output as unparsed JSON in [`Prediction::output_raw`] instead
+    /// of building a `Value` tree. Use this for models with very large outputs.
+    pub fn set_raw_output(&mut self, enabled: bool) {
+        self.raw_output = enabled;
+    }
+
+    /// Check if raw output mode is enabled.
+    pub fn raw_output(&self) -> bool {
+        self.raw_output
+    }
+
+    /// Set the input key holding the nonce used by
+    /// [`create_exactly_once`](Self::create_exactly_once).
+    pub fn set_nonce_key(&mut self, key: impl Into<String>) {
+        self.nonce_key = key.into();
+    }
+
+    /// Get the input key holding the nonce used by
+    /// [`create_exactly_once`](Self::create_exactly_once).
+    pub fn nonce_key(&self) -> &str {
+        &self.nonce_key
+    }
+
+    /// Record the estimated cost of predictions completed through this API.
+    ///
+    /// See [`CostTracker`] for which waits are recorded.
+    pub fn set_cost_tracker(&mut self, tracker: Option<CostTracker>) {
+        self.cost_tracker = tracker;
+    }
+
+    /// Get the cost tracker, if one is set.
+    pub fn cost_tracker(&self) -> Option<&CostTracker> {
+        self.cost_tracker.as_ref()
+    }
+
+    /// Check the input of every created prediction with `sanitizer`.
+    ///
+    /// The check runs after file inputs are processed, so uploaded file URLs
+    /// and data URLs are checked too.
+    pub fn set_input_sanitizer(&mut self, sanitizer: Option<InputSanitizer>) {
+        self.input_sanitizer = sanitizer;
+    }
+
+    /// Get the input sanitizer, if one is set.
+    pub fn input_sanitizer(&self) -> Option<&InputSanitizer> {
+        self.input_sanitizer.as_ref()
+    }
+
+    /// Set the largest create request body to send, or `None` for no limit.
+    ///
+    /// Bodies over the limit fail with [`Error::InvalidInput`] before they
+    /// are sent, except that file inputs using [`FileEncodingStrategy::Auto`]
+    /// are uploaded through the Files API instead of inlined. Defaults to
+    /// [`DEFAULT_MAX_BODY_SIZE`].
+    pub fn set_max_body_size(&mut self, limit: Option<usize>) {
+        self.max_body_size = limit;
+    }
+
+    /// Get the largest create request body to send, if limited.
+    pub fn max_body_size(&self) -> Option<usize> {
+        self.max_body_size
+    }
+
+    /// Set the webhook of predictions built with [`PredictionBuilder`]
+    /// unless they set their own.
+    pub fn set_default_webhook(&mut self, webhook: Option<String>) {
+        self.default_webhook = webhook;
+    }
+
+    /// Get the default webhook, if one is set.
+    pub fn default_webhook(&self) -> Option<&str> {
+        self.default_webhook.as_deref()
+    }
+
+    /// Set how predictions built with [`PredictionBuilder`] encode file
+    /// inputs unless they choose a strategy with
+    /// [`file_input_with_strategy`](PredictionBuilder::file_input_with_strategy).
+    pub fn set_default_file_encoding(&mut self, strategy: FileEncodingStrategy) {
+        self.default_file_encoding = strategy;
+    }
+
+    /// Get the default file encoding strategy.
+    pub fn default_file_encoding(&self) -> &FileEncodingStrategy {
+        &self.default_file_encoding
+    }
+
+    /// Set how predictions built with [`PredictionBuilder`] are waited on
+    /// unless they choose a mode with [`mode`](PredictionBuilder::mode).
+    ///
+    /// Also applies to helpers that run a model and wait for it, such as
+    /// [`Client::run_and_get_output`](crate::Client::run_and_get_output).
+    pub fn set_default_run_mode(&mut self, mode: RunMode) {
+        self.default_run_mode = mode;
+    }
+
+    /// Get the default run mode.
+    pub fn default_run_mode(&self) -> RunMode {
+        self.default_run_mode
+    }
+
+    /// Set inputs added to every created prediction, e.g. a fixed `seed`.
+    ///
+    /// An input or file input of the same name set on the request wins over
+    /// its default.
+    pub fn set_default_inputs(&mut self, inputs: HashMap<String, Value>) {
+        self.default_inputs = inputs;
+    }
+
+    /// Get the inputs added to every created prediction.
+    pub fn default_inputs(&self) -> &HashMap<String, Value> {
+        &self.default_inputs
+    }
+
+    /// Add the default inputs the request does not set, in key order.
+    fn apply_default_inputs(&self, request: &mut CreatePredictionRequest) {
+        let mut defaults: Vec<_> = self
+            .default_inputs
+            .iter()
+            .filter(|(key, _)| {
+                !request.input.contains_key(*key) && !request.file_inputs.contains_key(*key)
+            })
+            .collect();
+        defaults.sort_by_key(|(key, _)| *key);
+        for (key, value) in defaults {
+            request.insert_input(key.clone(), value.clone());
+        }
+    }
+
+    /// Add default inputs, check and process file inputs, check the body
+    /// size and apply the input sanitizer, if any.
+    async fn prepare_input(&self, request: &mut CreatePredictionRequest) -> Result<()> {
+        self.apply_default_inputs(request);
+        check_file_inputs(
+            request.ordered(&request.file_inputs),
+            request.allow_empty_files,
+        )
+        .await?;
+        if request.no_auto_upload
+            && matches!(
+                request.file_encoding_strategy,
+                FileEncodingStrategy::Multipart
+            )
+            && let Some(key) = request
+                .file_inputs
+                .iter()
+                .find(|(_, file)| !matches!(file, FileInput::Url(_)))
+                .map(|(key, _)| key)
+        {
+            return Err(Error::invalid_input(format!(
+                "file input '{}' would be uploaded, but uploads are disabled; pass a URL \
+                 or use FileEncodingStrategy::Base64DataUrl",
+                key
+            )));
+        }
+        let files_api = self.files_api.as_ref().filter(|_| !request.no_auto_upload);
+        let strategy = match request.file_encoding_strategy {
+            FileEncodingStrategy::Auto
+                if files_api.is_some() && self.inline_exceeds_limit(request).await? =>
+            {
+                FileEncodingStrategy::Multipart
+            }
+            ref strategy => strategy.clone(),
+        };
+        let processed =
+            process_file_inputs(request.ordered(&request.file_inputs), &strategy, files_api)
+                .await?;
+        request.input.extend(processed);
+        self.fit_body_size(request).await?;
+        if let Some(sanitizer) = &self.input_sanitizer {
+            sanitizer.sanitize(&mut request.input)?;
+        }
+        Ok(())
+    }
+
+    /// Check whether sending the file inputs inline would put the request
+    /// over the body size limit, from their sizes alone.
+    async fn inline_exceeds_limit(&self, request: &CreatePredictionRequest) -> Result<bool> {
+        let Some(limit) = self.max_body_size else {
+            return Ok(false);
+        };
+        let mut size = serde_json::to_vec(request)?.len();
+        for (key, file_input) in &request.file_inputs {
+            // The quoted key and value, a colon and a comma
+            size += key.len() + inline_size(file_input).await? + 6;
+        }
+        Ok(size > limit)
+    }
+
+    /// Make sure the serialized request fits in the body size limit.
+    async fn fit_body_size(&self, request: &mut CreatePredictionRequest) -> Result<()> {
+        let Some(limit) = self.max_body_size else {
+            return Ok(());
+        };
+        let mut size = serde_json::to_vec(request)?.len();
+        if size <= limit {
+            return Ok(());
+        }
+
+        if matches!(request.file_encoding_strategy, FileEncodingStrategy::Auto)
+            && !request.file_inputs.is_empty()
+            && !request.no_auto_upload
+        {
+            tracing::debug!(
+                "Request body of {} bytes is over the {} byte limit, uploading file inputs",
+                size,
+                limit
+            );
+            let processed = process_file_inputs(
+                request.ordered(&request.file_inputs),
+                &FileEncodingStrategy::Multipart,
+                self.files_api.as_ref(),
+            )
+            .await?;
+            request.input.extend(processed);
+            size = serde_json::to_vec(request)?.len();
+            if size <= limit {
+                return Ok(());
+            }
+        }
+
+        Err(Error::invalid_input(format!(
+            "Request body of {} bytes is over the {} byte limit; inputs too large to send \
+             inline: {}. Upload files with FileEncodingStrategy::Multipart instead",
+            size,
+            limit,
+            oversized_inputs(&request.input, size - limit)?.join(", ")
+        )))
+    }
+
+    /// Create a copy of this API whose requests make up one operation, which
+    /// [`Client::shutdown`](crate::Client::shutdown) lets finish.
+    fn begin_operation(&self) -> Result<Self> {
+        let http = self.http.begin_operation()?;
+        Ok(Self {
+            files_api: self
+                .files_api
+                .as_ref()
+                .map(|api| api.with_http(http.clone())),
+            http,
+            ..self.clone()
+        })
+    }
+
+    /// Report a created prediction to the lifecycle observer, if any.
+    fn notify_created(&self, prediction: &Prediction) {
+        if let Some(observer) = self.http.observer() {
+            observer.created(prediction);
+        }
+    }
+
+    /// Fetch a prediction for a polling wait, reporting it to the lifecycle
+    /// observer, if any.
+    async fn poll(&self, id: &str) -> Result<Prediction> {
+        let prediction = self.get_fresh(id).await?;
+        if let Some(observer) = self.http.observer() {
+            observer.polled(&prediction);
+        }
+        Ok(prediction)
+    }
+
+    /// Record a completed prediction with the cost tracker, if any.
+    fn record_cost(&self, prediction: Result<Prediction>) -> Result<Prediction> {
+        if let (Some(tracker), Ok(prediction)) = (&self.cost_tracker, &prediction) {
+            tracker.record(prediction);
         }
+        prediction
+    }
+
+    /// Get the shared prediction watcher for this API.
+    ///
+    /// The watcher is created on first use with the default [`WatcherConfig`]
+    /// and shared by all clones of this API.
+    pub fn watcher(&self) -> &PredictionWatcher {
+        self.watcher.get_or_init(|| {
+            let mut api = Self::new(self.http.clone());
+            api.raw_output = self.raw_output;
+            PredictionWatcher::new(api, WatcherConfig::default())
+        })
     }
 
     /// Create a new prediction.
-    pub async fn create(&self, mut request: CreatePredictionRequest) -> Result<Prediction> {
-        // Process file inputs if any
-        if !request.file_inputs.is_empty() {
-            for (key, file_input) in request.file_inputs.iter() {
-                let processed_value = process_file_input(
-                    file_input,
-                    &request.file_encoding_strategy,
-                    self.files_api.as_ref(),
+    pub async fn create(&self, request: CreatePredictionRequest) -> Result<Prediction> {
+        self.create_routed(request, &CreateRoute::default()).await
+    }
+
+    /// Create a prediction on a deployment.
+    ///
+    /// The deployment picks the model version, so `request.version` is ignored.
+    pub async fn create_on_deployment(
+        &self,
+        owner: &str,
+        name: &str,
+        request: CreatePredictionRequest,
+    ) -> Result<Prediction> {
+        let route = CreateRoute {
+            target: Some(FallbackTarget::Deployment {
+                owner: owner.to_string(),
+                name: name.to_string(),
+            }),
+            ..CreateRoute::default()
+        };
+        self.create_routed(request, &route).await
+    }
+
+    /// Create a prediction on a model.
+    ///
+    /// Runs the latest version unless `model` pins one; `request.version` is
+    /// ignored.
+    pub async fn create_on_model(
+        &self,
+        model: &ModelRef,
+        request: CreatePredictionRequest,
+    ) -> Result<Prediction> {
+        let route = CreateRoute {
+            target: Some(FallbackTarget::Model(model.clone())),
+            ..CreateRoute::default()
+        };
+        self.create_routed(request, &route).await
+    }
+
+    /// Create a prediction on the route's target, failing over if it says so.
+    async fn create_routed(
+        &self,
+        mut request: CreatePredictionRequest,
+        route: &CreateRoute,
+    ) -> Result<Prediction> {
+        let api = self.begin_operation()?;
+        api.prepare_input(&mut request).await?;
+
+        let (target, outcome) = match (
+            api.post_to(route.target.as_ref(), &request, route.prefer_wait)
+                .await,
+            &route.fallback,
+        ) {
+            (Err(Error::Api { status, .. }), Some(fallback))
+                if route.fallback_statuses.contains(&status) =>
+            {
+                tracing::warn!(
+                    "Prediction create failed with status {}, falling back to {}",
+                    status,
+                    fallback
+                );
+                let outcome = api
+                    .post_to(Some(fallback), &request, route.prefer_wait)
+                    .await;
+                (
+                    Some(fallback),
+                    outcome.map(|mut prediction| {
+                        prediction.failed_over_to = Some(fallback.clone());
+                        prediction
+                    }),
                 )
-                .await?;
+            }
+            (outcome, _) => (route.target.as_ref(), outcome),
+        };
+        let prediction = outcome.map_err(|error| match target_version(target, &request) {
+            Some(version) => create_error(version, error),
+            None => error,
+        })?;
+        api.notify_created(&prediction);
+        Ok(prediction)
+    }
 
-                request
+    /// Post a prepared create request to a target, or to `request.version` without one.
+    ///
+    /// With `prefer_wait`, the API holds the response for up to that long
+    /// while the prediction runs.
+    async fn post_to(
+        &self,
+        target: Option<&FallbackTarget>,
+        request: &CreatePredictionRequest,
+        prefer_wait: Option<Duration>,
+    ) -> Result<Prediction> {
+        let path = match target {
+            None
+            | Some(FallbackTarget::Model(ModelRef {
+                version: Some(_), ..
+            })) => "/v1/predictions".to_string(),
+            Some(FallbackTarget::Model(model)) => {
+                format!("/v1/models/{}/{}/predictions", model.owner, model.name)
+            }
+            Some(FallbackTarget::Deployment { owner, name }) => {
+                format!("/v1/deployments/{}/{}/predictions", owner, name)
+            }
+        };
+        let version = target_version(target, request).unwrap_or_default();
+        let renamed;
+        let request = if version == request.version {
+            request
+        } else {
+            let mut copy = request.clone();
+            copy.version = version.to_string();
+            renamed = copy;
+            &renamed
+        };
+        match prefer_wait {
+            Some(wait) => {
+                let seconds = wait
+                    .as_secs_f64()
+                    .ceil()
+                    .clamp(1.0, MAX_SYNC_CUTOFF_SECS as f64);
+                let mut headers = HeaderMap::new();
+                headers.insert(
+                    "prefer",
+                    HeaderValue::from_str(&format!("wait={}", seconds as u64))
+                        .expect("wait preference is a valid header value"),
+                );
+                self.http
+                    .post_json_with_headers(&path, request, &headers)
+                    .await
+            }
+            None => self.http.post_json(&path, request).await,
+        }
+    }
+
+    /// Create a new prediction, making sure a retried request never creates
+    /// it twice.
+    ///
+    /// The request is tagged with a random nonce under
+    /// [`nonce_key`](Self::nonce_key) in its input, and each attempt is sent
+    /// without the transport-level retries. When an attempt fails in a way
+    /// that may still have reached the server, the predictions created since
+    /// the first attempt are checked for the nonce, page by page, and a match
+    /// is returned instead of posting again. The
+    /// nonce is removed from the input of the returned prediction. The model
+    /// must accept the extra input.
+    pub async fn create_exactly_once(
+        &self,
+        mut request: CreatePredictionRequest,
+    ) -> Result<Prediction> {
+        let api = self.begin_operation()?;
+        api.prepare_input(&mut request).await?;
+
+        let nonce = uuid::Uuid::new_v4().simple().to_string();
+        request
+            .input
+            .insert(api.nonce_key.clone(), Value::String(nonce.clone()));
+
+        let since = SystemTime::now() - NONCE_SEARCH_MARGIN;
+        let retry = api.http.current_retry_config();
+        let mut delay = retry.min_delay;
+        let mut attempt = 0;
+        loop {
+            let error = match api.http.post_json_once("/v1/predictions", &request).await {
+                Ok(prediction) => {
+                    api.notify_created(&prediction);
+                    return Ok(api.strip_nonce(prediction));
+                }
+                Err(error) if is_transient(&error) => error,
+                Err(error) => return Err(create_error(&request.version, error)),
+            };
+
+            if let Some(prediction) = api.find_by_nonce(&nonce, since).await? {
+                api.notify_created(&prediction);
+                return Ok(api.strip_nonce(prediction));
+            }
+            if attempt >= retry.max_retries {
+                return Err(error);
+            }
+            attempt += 1;
+            tracing::debug!(
+                "Prediction create attempt {} failed, retrying: {}",
+                attempt,
+                error
+            );
+            tokio::time::sleep(delay).await;
+            delay = (delay * retry.base_multiplier.max(1)).min(retry.max_delay);
+        }
+    }
+
+    /// Look for a prediction created since `since` carrying `nonce` in its
+    /// input.
+    ///
+    /// Predictions are listed newest first, so pages are read until one
+    /// reaches past `since`, up to [`MAX_NONCE_SEARCH_PAGES`]. Listed records
+    /// carry no input, so each one inside the window is fetched in full
+    /// before its nonce is checked.
+    async fn find_by_nonce(&self, nonce: &str, since: SystemTime) -> Result<Option<Prediction>> {
+        let mut cursor = None;
+        for _ in 0..MAX_NONCE_SEARCH_PAGES {
+            let page = self.list(cursor.as_deref()).await?;
+            let mut past_window = false;
+            for mut prediction in page.results {
+                let before_window = prediction
+                    .created_at
+                    .as_deref()
+                    .and_then(parse_timestamp)
+                    .is_some_and(|created_at| created_at < since);
+                if before_window {
+                    past_window = true;
+                    continue;
+                }
+                if prediction.input.is_none() {
+                    self.hydrate(&mut prediction).await?;
+                }
+                let carries_nonce = prediction
                     .input
-                    .insert(key.clone(), serde_json::Value::String(processed_value));
+                    .as_ref()
+                    .and_then(|input| input.get(&self.nonce_key))
+                    .and_then(Value::as_str)
+                    == Some(nonce);
+                if carries_nonce {
+                    return Ok(Some(prediction));
+                }
+            }
+            match page.next {
+                Some(next) if !past_window => {
+                    cursor = Some(self.http.validate_api_url(&next)?.to_string())
+                }
+                _ => break,
             }
         }
+        Ok(None)
+    }
 
-        let prediction: Prediction = self.http.post_json("/v1/predictions", &request).await?;
-        Ok(prediction)
+    fn strip_nonce(&self, mut prediction: Prediction) -> Prediction {
+        if let Some(input) = prediction.input.as_mut() {
+            input.remove(&self.nonce_key);
+        }
+        prediction
     }
 
     /// Get a prediction by ID.
-    pub async fn get(&self, id: &str) -> Result<Prediction> {
-        let path = format!("/v1/predictions/{}", id);
+    pub async fn get(&self, id: impl Into<PredictionId>) -> Result<Prediction> {
+        let path = format!("/v1/predictions/{}", id.into());
+        if self.raw_output {
+            let fields = self.http.get_json_streaming(&path).await?;
+            return Prediction::from_raw_fields(fields);
+        }
         let prediction: Prediction = self.http.get_json(&path).await?;
         Ok(prediction)
     }
 
+    /// Get a prediction by ID, bypassing any cache in front of the API.
+    ///
+    /// Sends `Cache-Control: no-cache`, so a CDN or proxy revalidates rather
+    /// than serving a stored response with a stale status. Polling waits such
+    /// as [`wait_for_completion`](Self::wait_for_completion) fetch through this.
+    pub async fn get_fresh(&self, id: impl Into<PredictionId>) -> Result<Prediction> {
+        let path = format!("/v1/predictions/{}", id.into());
+        let headers = no_cache_headers();
+        if self.raw_output {
+            let fields = self
+                .http
+                .get_json_streaming_with_headers(&path, &headers)
+                .await?;
+            return Prediction::from_raw_fields(fields);
+        }
+        self.http.get_json_with_headers(&path, &headers).await
+    }
+
+    /// Get a prediction through a URL returned by the API, such as `urls.get`.
+    ///
+    /// The URL must point at the configured API host; anything else is
+    /// rejected with [`Error::InvalidInput`] so the API token is never sent
+    /// elsewhere.
+    pub async fn get_from_url(&self, url: &str) -> Result<Prediction> {
+        let url = self.http.validate_api_url(url)?;
+        if self.raw_output {
+            let fields = self.http.get_json_streaming(url.as_str()).await?;
+            return Prediction::from_raw_fields(fields);
+        }
+        self.http.get_json(url.as_str()).await
+    }
+
     /// List predictions with optional pagination.
+    ///
+    /// The API returns trimmed records without input and logs; see
+    /// [`hydrate`](Self::hydrate) to fetch the full ones.
     pub async fn list(&self, cursor: Option<&str>) -> Result<PaginatedResponse<Prediction>> {
         let path = match cursor {
             Some(cursor) => cursor.to_string(),
@@ -70,144 +704,1135 @@ impl PredictionsApi {
         Ok(response)
     }
 
-    /// Cancel a prediction.
-    pub async fn cancel(&self, id: &str) -> Result<Prediction> {
-        let path = format!("/v1/predictions/{}/cancel", id);
+    /// Replace a [partial](Prediction::is_partial) record with the full one.
+    ///
+    /// Does nothing for complete records, so it is cheap to call on every
+    /// item of a list before reading inputs, logs or outputs.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use replicate_client::Client;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::new("your-api-token")?;
+    /// let page = client.predictions().list(None).await?;
+    /// for mut prediction in page.results {
+    ///     client.predictions().hydrate(&mut prediction).await?;
+    ///     println!("{}: {:?}", prediction.id, prediction.logs);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn hydrate(&self, prediction: &mut Prediction) -> Result<()> {
+        if prediction.is_partial {
+            *prediction = self.get(&prediction.id).await?;
+        }
+        Ok(())
+    }
+
+    /// Cancel a prediction, through its cancel URL when known.
+    ///
+    /// Takes a prediction ID, or a [`Prediction`] or [`PredictionHandle`] to
+    /// use the cancel URL the API returned for it, validated like in
+    /// [`get_from_url`](Self::get_from_url). Without a cancel URL the
+    /// prediction is canceled by ID.
+    pub async fn cancel(&self, prediction: impl Into<PredictionHandle>) -> Result<Prediction> {
+        let handle = prediction.into();
+        if let Some(url) = handle.urls.cancel.as_deref() {
+            return self.cancel_by_url(url).await;
+        }
+        let path = format!("/v1/predictions/{}/cancel", handle.id);
         let prediction: Prediction = self.http.post_empty_json(&path).await?;
         Ok(prediction)
     }
 
+    /// Cancel a prediction through a cancel URL returned by the API.
+    ///
+    /// The URL is validated like in [`get_from_url`](Self::get_from_url).
+    pub async fn cancel_by_url(&self, cancel_url: &str) -> Result<Prediction> {
+        let url = self.http.validate_api_url(cancel_url)?;
+        self.http.post_empty_json(url.as_str()).await
+    }
+
+    /// Cancel a prediction and report whether the cancel had any effect.
+    ///
+    /// A prediction that had already succeeded or failed is returned
+    /// unchanged by the API; this is reported as
+    /// [`was_already_terminal`](CancelOutcome::was_already_terminal).
+    pub async fn cancel_checked(&self, id: &str) -> Result<CancelOutcome> {
+        self.cancel(id).await.map(CancelOutcome::from)
+    }
+
+    /// Cancel every running prediction that matches `filter`.
+    ///
+    /// Pages through the listed predictions, newest first, and stops paging
+    /// once they were created before the filter's
+    /// [`created_after`](PredictionFilter::created_after), which is required
+    /// so a call never walks the account's whole history; without it, this
+    /// fails with [`Error::InvalidInput`]. Only `starting` and `processing`
+    /// predictions are considered. Listed records carry no input, so with
+    /// input filters each candidate is fetched in full first. Each page is
+    /// checked and canceled before the next one is listed, a few predictions
+    /// at a time.
+    ///
+    /// With `dry_run`, nothing is canceled and the report lists what would
+    /// be. Failing to list predictions fails the whole call; failures for
+    /// single predictions are collected in the report instead.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use replicate_client::{Client, PredictionFilter};
+    /// # use std::time::{Duration, SystemTime};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::new("your-api-token")?;
+    /// let filter = PredictionFilter::new()
+    ///     .model("acme/image-tagger")
+    ///     .created_after(SystemTime::now() - Duration::from_secs(3600))
+    ///     .input("feature", "auto-tagging");
+    /// let report = client.predictions().cancel_matching(filter, false).await?;
+    /// println!("{}", report);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn cancel_matching(
+        &self,
+        filter: PredictionFilter,
+        dry_run: bool,
+    ) -> Result<CancelReport> {
+        enum Outcome {
+            Canceled,
+            Skipped,
+            Failed(Error),
+        }
+
+        if filter.created_after.is_none() {
+            return Err(Error::invalid_input(
+                "cancel_matching needs a created_after window",
+            ));
+        }
+
+        let filter = &filter;
+        let mut report = CancelReport {
+            dry_run,
+            ..CancelReport::default()
+        };
+        let mut cursor: Option<String> = None;
+        loop {
+            let page = self.list(cursor.as_deref()).await?;
+            let mut past_window = false;
+            let mut candidates = Vec::new();
+            for prediction in page.results {
+                if filter.is_before_window(&prediction) {
+                    past_window = true;
+                    continue;
+                }
+                if !matches!(
+                    prediction.status,
+                    PredictionStatus::Starting | PredictionStatus::Processing
+                ) {
+                    continue;
+                }
+                if filter.matches_listed(&prediction) {
+                    candidates.push(PredictionId::from(prediction.id));
+                } else {
+                    report.skipped += 1;
+                }
+            }
+
+            let outcomes: Vec<(PredictionId, Outcome)> = stream::iter(candidates)
+                .map(|id| async move {
+                    if !filter.input.is_empty() {
+                        match self.get(&id).await {
+                            Ok(full) if filter.matches_input(&full) => {}
+                            Ok(_) => return (id, Outcome::Skipped),
+                            Err(e) => return (id, Outcome::Failed(e)),
+                        }
+                    }
+                    if dry_run {
+                        return (id, Outcome::Canceled);
+                    }
+                    let outcome = match self.cancel(&id).await.map(CancelOutcome::from) {
+                        Ok(outcome) if outcome.was_already_terminal => Outcome::Skipped,
+                        Ok(_) => Outcome::Canceled,
+                        Err(e) => Outcome::Failed(e),
+                    };
+                    (id, outcome)
+                })
+                .buffered(CANCEL_CONCURRENCY)
+                .collect()
+                .await;
+            for (id, outcome) in outcomes {
+                match outcome {
+                    Outcome::Canceled => report.canceled.push(id),
+                    Outcome::Skipped => report.skipped += 1,
+                    Outcome::Failed(e) => report.failed.push((id, e)),
+                }
+            }
+
+            match page.next {
+                Some(next) if !past_window => {
+                    cursor = Some(self.http.validate_api_url(&next)?.to_string())
+                }
+                _ => break,
+            }
+        }
+        Ok(report)
+    }
+
+    /// Get the prediction behind a handle, preferring its `get` URL.
+    pub async fn get_handle(&self, handle: &PredictionHandle) -> Result<Prediction> {
+        match handle.urls.get.as_deref() {
+            Some(url) => self.get_from_url(url).await,
+            None => self.get(&handle.id).await,
+        }
+    }
+
+    /// Cancel the prediction behind a handle, preferring its `cancel` URL;
+    /// see [`cancel`](Self::cancel).
+    pub async fn cancel_handle(&self, handle: &PredictionHandle) -> Result<Prediction> {
+        self.cancel(handle).await
+    }
+
+    /// Wait for the prediction behind a handle to complete.
+    pub async fn wait_handle(
+        &self,
+        handle: &PredictionHandle,
+        options: &RunOptions,
+    ) -> Result<Prediction> {
+        self.wait_with_options(&handle.id, options).await
+    }
+
+    /// Open the output stream of a prediction created with streaming enabled.
+    ///
+    /// Fails with [`Error::InvalidInput`] if the prediction has no stream
+    /// URL. The stream fails with [`Error::Timeout`] if no event arrives within
+    /// [`TimeoutConfig::stream_idle_timeout`](crate::TimeoutConfig::stream_idle_timeout).
+    pub async fn stream(&self, prediction: &Prediction) -> Result<PredictionStream> {
+        let url = prediction.stream_url().ok_or_else(|| {
+            Error::invalid_input(format!(
+                "Prediction {} has no stream URL; create it with streaming enabled",
+                prediction.id
+            ))
+        })?;
+
+        let response = self.http.get_event_stream(url).await?;
+        let idle_timeout = self.http.current_timeout_config().stream_idle_timeout;
+        Ok(PredictionStream::from_response(
+            &prediction.id,
+            response,
+            idle_timeout,
+        ))
+    }
+
     /// Wait for a prediction to complete with polling.
+    ///
+    /// If `max_duration` elapses first, the prediction keeps running and the
+    /// returned [`Error::Timeout`] carries its ID and last fetched status.
     pub async fn wait_for_completion(
         &self,
         id: &str,
         max_duration: Option<Duration>,
         poll_interval: Option<Duration>,
     ) -> Result<Prediction> {
-        let poll_interval = poll_interval.unwrap_or(Duration::from_millis(500));
-        let mut interval = interval(poll_interval);
+        let mut options = PollOptions::new();
+        options.max_duration = max_duration;
+        options.deadline = self.http.deadline();
+        if let Some(poll_interval) = poll_interval {
+            options.interval = poll_interval;
+        }
 
-        let wait_future = async {
-            loop {
-                interval.tick().await;
-                let prediction = self.get(id).await?;
-
-                if prediction.status.is_terminal() {
-                    if prediction.is_failed() {
-                        return Err(Error::model_execution(
-                            id,
-                            prediction.error.clone(),
-                            prediction.logs.clone(),
-                        ));
+        let api = self.begin_operation()?;
+        let prediction = poll_loop(Prediction::KIND, id, &options, |_| api.poll(id)).await;
+        api.record_cost(prediction)
+    }
+
+    /// Wait for a prediction to complete by an absolute deadline.
+    ///
+    /// Unlike [`wait_for_completion`](Self::wait_for_completion), several
+    /// waits can share one deadline without recomputing the time left. The
+    /// earlier of `deadline` and the deadline of this API, if any, applies.
+    /// Fails with [`Error::Timeout`] of kind [`TimeoutKind::Deadline`] when
+    /// it passes, without sending a request if it already has.
+    pub async fn wait_for_completion_until(
+        &self,
+        id: &str,
+        deadline: impl Into<Deadline>,
+        poll_interval: Option<Duration>,
+    ) -> Result<Prediction> {
+        let deadline = deadline.into();
+        let deadline = self
+            .http
+            .deadline()
+            .map_or(deadline, |current| current.min(deadline));
+        if deadline.is_expired() {
+            return Err(Error::Timeout {
+                message: format!("Deadline for prediction {} has already passed", id),
+                prediction_id: Some(id.to_string()),
+                last_status: None,
+                kind: TimeoutKind::Deadline,
+            });
+        }
+
+        self.with_deadline(deadline)
+            .wait_for_completion(id, None, poll_interval)
+            .await
+    }
+
+    /// Wait for a prediction to complete, calling `on_poll` after every poll.
+    ///
+    /// Each [`PollUpdate`] carries the text added to the output since the
+    /// previous poll, which gives pseudo-streaming for models without SSE.
+    pub async fn wait_with_progress<F>(
+        &self,
+        id: &str,
+        options: &PollOptions,
+        on_poll: F,
+    ) -> Result<Prediction>
+    where
+        F: FnMut(PollUpdate<'_>) + Send,
+    {
+        let api = self.begin_operation()?;
+        let started = Instant::now();
+        let on_poll = std::sync::Mutex::new(on_poll);
+        // Status last seen and when this wait first saw it
+        let seen = std::sync::Mutex::new(None::<(PredictionStatus, Instant)>);
+        poll_loop(Prediction::KIND, id, options, |previous| {
+            let (api, on_poll, seen) = (&api, &on_poll, &seen);
+            async move {
+                let prediction = api.poll(id).await?;
+                let output_delta = prediction.output_delta_since(previous.as_ref());
+                let first_seen = {
+                    let mut seen = seen.lock().unwrap();
+                    match &*seen {
+                        Some((status, at)) if *status == prediction.status => *at,
+                        _ => seen.insert((prediction.status.clone(), Instant::now())).1,
                     }
-                    return Ok(prediction);
-                }
+                };
+                let phase_elapsed = match prediction.status_since() {
+                    Some(since) => SystemTime::now().duration_since(since).unwrap_or_default(),
+                    None => first_seen.elapsed(),
+                };
+                (on_poll.lock().unwrap())(PollUpdate {
+                    prediction: &prediction,
+                    output_delta,
+                    phase: prediction.status.clone(),
+                    phase_elapsed,
+                    waited: started.elapsed(),
+                });
+                Ok(prediction)
             }
-        };
+        })
+        .await
+    }
 
-        match max_duration {
-            Some(duration) => timeout(duration, wait_future).await.map_err(|_| {
-                Error::Timeout(format!(
-                    "Prediction {} did not complete within {:?}",
-                    id, duration
-                ))
-            })?,
-            None => wait_future.await,
+    /// Wait for a prediction to complete according to the given run options.
+    pub async fn wait_with_options(&self, id: &str, options: &RunOptions) -> Result<Prediction> {
+        let deadline = options.deadline.or(self.http.deadline());
+        if options.use_shared_watcher {
+            let max_duration = match deadline {
+                Some(deadline) => Some(deadline.cap(options.max_duration)),
+                None => options.max_duration,
+            };
+            let mut prediction = self.watcher().wait(id, max_duration).await;
+            if let Err(Error::Timeout { kind, .. }) = &mut prediction
+                && deadline.is_some_and(|deadline| deadline.is_expired())
+            {
+                *kind = TimeoutKind::Deadline;
+            }
+            self.record_cost(prediction)
+        } else if let Some(deadline) = options.deadline {
+            self.with_deadline(deadline)
+                .wait_for_completion(id, options.max_duration, options.poll_interval)
+                .await
+        } else {
+            self.wait_for_completion(id, options.max_duration, options.poll_interval)
+                .await
         }
     }
 }
 
-/// Builder for creating predictions with a fluent API.
+/// State of a prediction after one poll of [`PredictionsApi::wait_with_progress`].
 #[derive(Debug)]
-pub struct PredictionBuilder {
-    api: PredictionsApi,
-    request: CreatePredictionRequest,
+pub struct PollUpdate<'a> {
+    /// The prediction as just fetched
+    pub prediction: &'a Prediction,
+    /// Output text added since the previous poll, if any
+    pub output_delta: Option<String>,
+    /// Current phase: queued while `Starting`, running while `Processing`
+    pub phase: PredictionStatus,
+    /// Time spent in the current phase so far
+    ///
+    /// Measured from the API timestamp that starts the phase, see
+    /// [`Prediction::status_since`], so a prediction queued before the wait
+    /// began counts in full. Falls back to when this wait first saw the
+    /// phase if the timestamp is missing.
+    pub phase_elapsed: Duration,
+    /// Time since the wait began
+    pub waited: Duration,
 }
 
-impl PredictionBuilder {
-    /// Create a new prediction builder.
-    pub fn new(api: PredictionsApi, version: impl Into<String>) -> Self {
+/// Result of [`PredictionsApi::cancel_checked`].
+#[derive(Debug, Clone)]
+pub struct CancelOutcome {
+    /// The prediction as returned by the cancel request
+    pub prediction: Prediction,
+    /// Whether the prediction had already succeeded or failed, so the cancel did nothing
+    ///
+    /// A prediction that was canceled earlier cannot be told apart from one
+    /// canceled by this request and counts as canceled now.
+    pub was_already_terminal: bool,
+}
+
+impl From<Prediction> for CancelOutcome {
+    fn from(prediction: Prediction) -> Self {
+        let was_already_terminal = matches!(
+            prediction.status,
+            PredictionStatus::Succeeded | PredictionStatus::Failed
+        );
         Self {
-            api,
-            request: CreatePredictionRequest::new(version),
+            prediction,
+            was_already_terminal,
         }
     }
+}
 
-    /// Add an input parameter.
-    pub fn input<K, V>(mut self, key: K, value: V) -> Self
-    where
-        K: Into<String>,
-        V: Into<Value>,
-    {
-        self.request = self.request.with_input(key, value);
-        self
+/// Selects predictions for [`PredictionsApi::cancel_matching`].
+///
+/// An empty filter matches everything; each criterion set narrows it down.
+/// [`cancel_matching`](PredictionsApi::cancel_matching) requires
+/// [`created_after`](Self::created_after).
+#[derive(Debug, Clone, Default)]
+pub struct PredictionFilter {
+    /// Model the predictions run, as `owner/name`
+    pub model: Option<String>,
+    /// Only predictions created after this time
+    pub created_after: Option<SystemTime>,
+    /// Input values the predictions must all have
+    pub input: Vec<(String, Value)>,
+}
+
+impl PredictionFilter {
+    /// Create a filter matching every prediction.
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    /// Add multiple input parameters from a HashMap.
-    pub fn inputs(mut self, inputs: HashMap<String, Value>) -> Self {
-        for (key, value) in inputs {
-            self.request = self.request.with_input(key, value);
-        }
+    /// Only match predictions of a model, given as `owner/name`.
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
         self
     }
 
-    /// Add a file input parameter.
-    pub fn file_input<K>(mut self, key: K, file: FileInput) -> Self
-    where
-        K: Into<String>,
-    {
-        // Store the file input for later processing
-        self.request.file_inputs.insert(key.into(), file);
+    /// Only match predictions created after `time`.
+    pub fn created_after(mut self, time: SystemTime) -> Self {
+        self.created_after = Some(time);
         self
     }
 
-    /// Add a file input with specific encoding strategy.
-    pub fn file_input_with_strategy<K>(
-        mut self,
-        key: K,
-        file: FileInput,
-        strategy: FileEncodingStrategy,
-    ) -> Self
-    where
-        K: Into<String>,
-    {
-        // Store the file input and strategy for later processing
-        self.request.file_inputs.insert(key.into(), file);
-        self.request.file_encoding_strategy = strategy;
+    /// Only match predictions whose input `key` equals `value`.
+    pub fn input(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.input.push((key.into(), value.into()));
         self
     }
 
-    /// Set a webhook URL.
-    pub fn webhook(mut self, webhook: impl Into<String>) -> Self {
-        self.request = self.request.with_webhook(webhook);
-        self
+    /// Check the criteria a listed record carries besides the window, i.e.
+    /// the model.
+    fn matches_listed(&self, prediction: &Prediction) -> bool {
+        self.model
+            .as_ref()
+            .is_none_or(|model| *model == prediction.model)
     }
 
-    /// Enable streaming output.
-    pub fn stream(mut self) -> Self {
+    /// Check the input criteria against a full record.
+    fn matches_input(&self, prediction: &Prediction) -> bool {
+        self.input.iter().all(|(key, value)| {
+            prediction.input.as_ref().and_then(|input| input.get(key)) == Some(value)
+        })
+    }
+
+    /// Check if a prediction was created before `created_after`. Predictions
+    /// without a readable creation time count as inside the window.
+    fn is_before_window(&self, prediction: &Prediction) -> bool {
+        let created_at = prediction.created_at.as_deref().and_then(parse_timestamp);
+        matches!(
+            (self.created_after, created_at),
+            (Some(after), Some(created_at)) if created_at <= after
+        )
+    }
+}
+
+/// Result of [`PredictionsApi::cancel_matching`].
+#[derive(Debug, Default)]
+pub struct CancelReport {
+    /// Whether this was a dry run, so nothing was canceled
+    pub dry_run: bool,
+    /// Predictions canceled, or that would be in a dry run
+    pub canceled: Vec<PredictionId>,
+    /// Predictions that could not be checked or canceled, with the error
+    pub failed: Vec<(PredictionId, Error)>,
+    /// Running predictions left alone: they did not match the filter, or
+    /// finished before the cancel reached them
+    pub skipped: usize,
+}
+
+impl std::fmt::Display for CancelReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}, {} failed, {} skipped",
+            self.canceled.len(),
+            if self.dry_run {
+                "would be canceled"
+            } else {
+                "canceled"
+            },
+            self.failed.len(),
+            self.skipped
+        )
+    }
+}
+
+/// Longest blocking create the API accepts, in seconds.
+const MAX_SYNC_CUTOFF_SECS: u64 = 60;
+
+/// How [`PredictionBuilder::send_and_wait`] waits for a prediction.
+///
+/// Set per prediction with [`PredictionBuilder::mode`] or for all of them
+/// with [`PredictionsApi::set_default_run_mode`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RunMode {
+    /// Create the prediction, then wait for it with polls or the watcher
+    #[default]
+    Poll,
+    /// Hold the create open with `Prefer: wait` for up to `sync_cutoff`,
+    /// which suits quick models, and keep waiting on the same prediction if
+    /// it is still running after that
+    ///
+    /// The cutoff is rounded up to whole seconds and capped at 60, the most
+    /// the API accepts; the request timeout must be longer. The result is the
+    /// same whichever way the prediction finishes.
+    Auto { sync_cutoff: Duration },
+}
+
+/// Options controlling how a prediction is waited on.
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Maximum time to wait for completion
+    pub max_duration: Option<Duration>,
+    /// Polling interval for a dedicated polling loop
+    pub poll_interval: Option<Duration>,
+    /// Wait through the shared [`PredictionWatcher`] instead of a dedicated loop
+    pub use_shared_watcher: bool,
+    /// Time by which the whole run, from file uploads to the last poll, must finish
+    pub deadline: Option<Deadline>,
+}
+
+impl RunOptions {
+    /// Create default run options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum time to wait for completion.
+    pub fn max_duration(mut self, max_duration: Duration) -> Self {
+        self.max_duration = Some(max_duration);
+        self
+    }
+
+    /// Set the polling interval for a dedicated polling loop.
+    ///
+    /// Ignored when waiting through the shared watcher, which uses its own
+    /// [`WatcherConfig::poll_interval`].
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = Some(poll_interval);
+        self
+    }
+
+    /// Wait through the API's shared [`PredictionWatcher`].
+    ///
+    /// Recommended when many predictions are awaited concurrently, since all
+    /// waits share one rate-limited polling loop.
+    pub fn use_shared_watcher(mut self, enabled: bool) -> Self {
+        self.use_shared_watcher = enabled;
+        self
+    }
+
+    /// Set the time by which the whole run must finish.
+    ///
+    /// Unlike [`max_duration`](Self::max_duration), which only bounds the
+    /// wait, the deadline also covers file uploads, the create request and
+    /// every poll with their retries. Each of them gets only the time left,
+    /// and the run fails with a [`TimeoutKind::Deadline`] timeout once it
+    /// has passed.
+    pub fn deadline(mut self, deadline: Deadline) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+}
+
+/// Builder for creating predictions with a fluent API.
+#[derive(Debug)]
+pub struct PredictionBuilder {
+    api: PredictionsApi,
+    request: CreatePredictionRequest,
+    run_options: RunOptions,
+    mode: RunMode,
+    webhook_check: Option<WebhookCheck>,
+    route: CreateRoute,
+    uploaded_files: Vec<(String, UploadedFile)>,
+}
+
+impl PredictionBuilder {
+    /// Create a new prediction builder.
+    pub fn new(api: PredictionsApi, version: impl Into<String>) -> Self {
+        let mut request = CreatePredictionRequest::new(version);
+        request.webhook = api.default_webhook.clone();
+        request.file_encoding_strategy = api.default_file_encoding.clone();
+        Self {
+            mode: api.default_run_mode,
+            api,
+            request,
+            run_options: RunOptions::default(),
+            webhook_check: None,
+            route: CreateRoute::default(),
+            uploaded_files: Vec::new(),
+        }
+    }
+
+    /// Create a builder that resumes a request, e.g. one persisted with
+    /// [`CreatePredictionRequest::persisted`].
+    ///
+    /// File inputs are processed again when the request is sent.
+    pub fn from_request(api: PredictionsApi, request: CreatePredictionRequest) -> Self {
+        Self {
+            request,
+            ..Self::new(api, "")
+        }
+    }
+
+    /// Get the request built so far, e.g. to persist it with
+    /// [`CreatePredictionRequest::persisted`].
+    pub fn request(&self) -> &CreatePredictionRequest {
+        &self.request
+    }
+
+    /// Set the options used when waiting for completion.
+    pub fn run_options(mut self, options: RunOptions) -> Self {
+        self.run_options = options;
+        self
+    }
+
+    /// Set whether the create blocks for quick predictions before waiting.
+    ///
+    /// Defaults to the API's [`default_run_mode`](PredictionsApi::default_run_mode).
+    pub fn mode(mut self, mode: RunMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Add an input parameter.
+    pub fn input<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<Value>,
+    {
+        self.request = self.request.with_input(key, value);
+        self
+    }
+
+    /// Add multiple input parameters from a HashMap.
+    ///
+    /// Keys are added in sorted order so the serialized request is the same
+    /// on every run, regardless of the map's iteration order.
+    pub fn inputs(mut self, inputs: HashMap<String, Value>) -> Self {
+        self.request = self.request.with_inputs(inputs);
+        self
+    }
+
+    /// Add every field of a serializable struct as an input parameter.
+    ///
+    /// The value must serialize to a JSON object. Fields are added in
+    /// declaration order.
+    pub fn inputs_from<T: Serialize>(mut self, input: &T) -> Result<Self> {
+        match serde_json::to_value(input)? {
+            Value::Object(fields) => {
+                for (key, value) in fields {
+                    self.request = self.request.with_input(key, value);
+                }
+                Ok(self)
+            }
+            _ => Err(Error::invalid_input(
+                "Prediction input must serialize to a JSON object",
+            )),
+        }
+    }
+
+    /// Set an input from the output of a completed prediction.
+    ///
+    /// Use this to chain predictions, e.g. to upscale the first image another
+    /// prediction generated. Fails if the source prediction has not succeeded
+    /// or its output has nothing matching `selector`.
+    pub fn input_from_output<K>(
+        self,
+        key: K,
+        source: &Prediction,
+        selector: impl Into<OutputSelector>,
+    ) -> Result<Self>
+    where
+        K: Into<String>,
+    {
+        let value = source.select_output(&selector.into())?;
+        Ok(self.input(key, value))
+    }
+
+    /// Add inputs built against the model's input schema.
+    ///
+    /// Schema defaults are filled in for unset optional inputs, in the schema's
+    /// input order. Fails if a required input is missing.
+    pub fn dynamic_inputs(mut self, input: DynamicInput) -> Result<Self> {
+        for (key, value) in input.into_ordered()? {
+            self.request = self.request.with_input(key, value);
+        }
+        Ok(self)
+    }
+
+    /// Add a file input parameter.
+    ///
+    /// Files are checked when the prediction is created: a path that does
+    /// not exist, is not a readable regular file or is empty fails with
+    /// [`Error::InvalidInput`] naming the input key.
+    pub fn file_input<K>(mut self, key: K, file: FileInput) -> Self
+    where
+        K: Into<String>,
+    {
+        // Store the file input for later processing
+        let key = key.into();
+        self.uploaded_files.retain(|(existing, _)| *existing != key);
+        self.request.insert_file_input(key, file);
+        self
+    }
+
+    /// Add multiple file inputs from a HashMap.
+    ///
+    /// Files are encoded with the currently set encoding strategy. Keys are
+    /// added in sorted order, like [`inputs`](Self::inputs).
+    pub fn file_inputs(mut self, files: HashMap<String, FileInput>) -> Self {
+        let mut files: Vec<_> = files.into_iter().collect();
+        files.sort_by(|(a, _), (b, _)| a.cmp(b));
+        self.uploaded_files
+            .retain(|(existing, _)| files.iter().all(|(key, _)| key != existing));
+        for (key, file) in files {
+            self.request.insert_file_input(key, file);
+        }
+        self
+    }
+
+    /// Add a file input with specific encoding strategy.
+    pub fn file_input_with_strategy<K>(
+        mut self,
+        key: K,
+        file: FileInput,
+        strategy: FileEncodingStrategy,
+    ) -> Self
+    where
+        K: Into<String>,
+    {
+        // Store the file input and strategy for later processing
+        let key = key.into();
+        self.uploaded_files.retain(|(existing, _)| *existing != key);
+        self.request.insert_file_input(key, file);
+        self.request.file_encoding_strategy = strategy;
+        self
+    }
+
+    /// Add a file uploaded with [`FilesApi::upload`] as an input, sending its
+    /// URL instead of uploading the file again.
+    ///
+    /// The file's expiry is checked when the prediction is created, and an
+    /// expired file is uploaded again as described on [`UploadedFile`]; with
+    /// [`no_auto_upload`](Self::no_auto_upload), it fails instead.
+    pub fn uploaded_file<K>(mut self, key: K, file: &UploadedFile) -> Self
+    where
+        K: Into<String>,
+    {
+        let key = key.into();
+        self.request
+            .insert_file_input(key.clone(), FileInput::from(file));
+        self.uploaded_files.retain(|(existing, _)| *existing != key);
+        self.uploaded_files.push((key, file.clone()));
+        self
+    }
+
+    /// Accept zero-byte file inputs, which are rejected by default.
+    pub fn allow_empty_files(mut self, allow: bool) -> Self {
+        self.request.allow_empty_files = allow;
+        self
+    }
+
+    /// Never upload file inputs through the Files API for this prediction.
+    ///
+    /// URL inputs are sent as is and local inputs can still be inlined as
+    /// data URLs, but an input that would be uploaded fails with
+    /// [`Error::InvalidInput`] before anything is sent. That includes
+    /// [`FileEncodingStrategy::Auto`] inputs over the body size limit.
+    pub fn no_auto_upload(mut self) -> Self {
+        self.request.no_auto_upload = true;
+        self
+    }
+
+    /// Set a webhook URL.
+    pub fn webhook(mut self, webhook: impl Into<String>) -> Self {
+        self.request = self.request.with_webhook(webhook);
+        self
+    }
+
+    /// Check the webhook URL before creating the prediction.
+    ///
+    /// Runs the default [`WebhookCheck`]: the URL must use https and its host
+    /// must resolve. Use [`webhook_check`](Self::webhook_check) to allow http
+    /// or add a preflight request. Has no effect without a webhook.
+    pub fn validate_webhook(mut self, enabled: bool) -> Self {
+        self.webhook_check = enabled.then(WebhookCheck::default);
+        self
+    }
+
+    /// Check the webhook URL with custom checks before creating the prediction.
+    pub fn webhook_check(mut self, check: WebhookCheck) -> Self {
+        self.webhook_check = Some(check);
+        self
+    }
+
+    /// Run the configured webhook check, if any, and refresh the URLs of
+    /// uploaded files.
+    async fn prepare(&mut self) -> Result<()> {
+        if let (Some(check), Some(url)) = (&self.webhook_check, &self.request.webhook) {
+            check.check(url).await?;
+        }
+        for (key, file) in &self.uploaded_files {
+            let url = file.current_url(!self.request.no_auto_upload).await?;
+            self.request
+                .file_inputs
+                .insert(key.clone(), FileInput::Url(url));
+        }
+        Ok(())
+    }
+
+    /// Set a top-level request field that has no dedicated setter, e.g. for
+    /// audit metadata or API fields this crate does not model yet.
+    ///
+    /// Fails if the key clashes with a modelled field such as `input`.
+    pub fn extra_field<K, V>(mut self, key: K, value: V) -> Result<Self>
+    where
+        K: Into<String>,
+        V: Into<Value>,
+    {
+        self.request = self.request.with_extra(key, value)?;
+        Ok(self)
+    }
+
+    /// Create the prediction on a deployment instead of the builder's version.
+    ///
+    /// Applies to every send method except
+    /// [`send_exactly_once`](Self::send_exactly_once), which always creates
+    /// on the version.
+    pub fn on_deployment(mut self, owner: impl Into<String>, name: impl Into<String>) -> Self {
+        self.route.target = Some(FallbackTarget::Deployment {
+            owner: owner.into(),
+            name: name.into(),
+        });
+        self
+    }
+
+    /// Create the prediction on `target` when the primary target rejects it.
+    ///
+    /// Only the create is failed over, on the statuses set with
+    /// [`fallback_statuses`](Self::fallback_statuses); waits and streams
+    /// follow whichever prediction was created. A failed-over prediction has
+    /// [`Prediction::failed_over_to`] set.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use replicate_client::{Client, FallbackTarget, ModelRef};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::new("your-api-token")?;
+    /// let prediction = client
+    ///     .create_prediction("")
+    ///     .on_deployment("acme", "flux-prod")
+    ///     .with_fallback(FallbackTarget::Model(ModelRef::new(
+    ///         "black-forest-labs",
+    ///         "flux-schnell",
+    ///     )))
+    ///     .input("prompt", "a lighthouse at dusk")
+    ///     .send_and_wait()
+    ///     .await?;
+    /// if let Some(target) = &prediction.failed_over_to {
+    ///     println!("Served by {}", target);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_fallback(mut self, target: FallbackTarget) -> Self {
+        self.route.fallback = Some(target);
+        self
+    }
+
+    /// Set the HTTP statuses of the primary create that trigger the fallback.
+    ///
+    /// Defaults to [`DEFAULT_FALLBACK_STATUSES`].
+    pub fn fallback_statuses(mut self, statuses: impl IntoIterator<Item = u16>) -> Self {
+        self.route.fallback_statuses = statuses.into_iter().collect();
+        self
+    }
+
+    /// Enable streaming output.
+    pub fn stream(mut self) -> Self {
         self.request = self.request.with_streaming();
         self
     }
 
     /// Send the prediction request.
-    pub async fn send(self) -> Result<Prediction> {
-        self.api.create(self.request).await
+    pub async fn send(mut self) -> Result<Prediction> {
+        self.prepare().await?;
+        self.api.create_routed(self.request, &self.route).await
+    }
+
+    /// Send the prediction request and return as soon as it is acknowledged.
+    ///
+    /// Behaves exactly like [`send`](Self::send). Use it when results arrive
+    /// only through a [`webhook`](Self::webhook): the returned prediction
+    /// carries the ID to match deliveries against, and nothing is polled.
+    pub async fn send_nowait(self) -> Result<Prediction> {
+        self.send().await
+    }
+
+    /// Send the prediction request on a background task.
+    ///
+    /// Returns immediately; await the handle to get the created prediction or
+    /// the error that prevented it. Failures are also logged, so they are not
+    /// lost when the handle is dropped.
+    pub fn send_detached(self, runtime: &tokio::runtime::Handle) -> JoinHandle<Result<Prediction>> {
+        runtime.spawn(async move {
+            let result = self.send().await;
+            if let Err(error) = &result {
+                tracing::warn!("Detached prediction creation failed: {}", error);
+            }
+            result
+        })
+    }
+
+    /// Send the prediction request, retrying without ever creating it twice.
+    ///
+    /// See [`PredictionsApi::create_exactly_once`].
+    pub async fn send_exactly_once(mut self) -> Result<Prediction> {
+        self.prepare().await?;
+        self.api.create_exactly_once(self.request).await
+    }
+
+    /// Send the prediction request and return it with a persistable handle.
+    pub async fn send_handle(mut self) -> Result<(Prediction, PredictionHandle)> {
+        self.prepare().await?;
+        let prediction = self.api.create_routed(self.request, &self.route).await?;
+        let handle = prediction.handle();
+        Ok((prediction, handle))
+    }
+
+    /// Send the prediction request with streaming enabled and open its output stream.
+    ///
+    /// Fails with [`Error::Unsupported`] if the model does not support
+    /// streaming, i.e. the created prediction has no stream URL. The
+    /// prediction still runs; the error names its ID so it can be waited on.
+    pub async fn send_and_stream(mut self) -> Result<PredictionStream> {
+        self.prepare().await?;
+        let prediction = self
+            .api
+            .create_routed(self.request.with_streaming(), &self.route)
+            .await?;
+        if !prediction.supports_streaming() {
+            return Err(Error::unsupported(format!(
+                "Model {} does not support streaming; prediction {} was created without a \
+                 stream URL",
+                prediction.model, prediction.id
+            )));
+        }
+        self.api.stream(&prediction).await
     }
 
     /// Send the prediction request and wait for completion.
-    pub async fn send_and_wait(self) -> Result<Prediction> {
-        let prediction = self.api.create(self.request).await?;
-        self.api
-            .wait_for_completion(&prediction.id, None, None)
-            .await
+    ///
+    /// With a [`RunOptions::deadline`], the webhook check, file uploads, the
+    /// create request and all polls share the deadline.
+    pub async fn send_and_wait(mut self) -> Result<Prediction> {
+        let api = match self.run_options.deadline {
+            Some(deadline) => {
+                deadline.run(self.prepare()).await?;
+                self.api.with_deadline(deadline)
+            }
+            None => {
+                self.prepare().await?;
+                self.api
+            }
+        };
+        // One operation, so a shutdown between the create and the wait lets it finish
+        let api = api.begin_operation()?;
+        let RunMode::Auto { sync_cutoff } = self.mode else {
+            let prediction = api.create_routed(self.request, &self.route).await?;
+            return api
+                .wait_with_options(&prediction.id, &self.run_options)
+                .await;
+        };
+
+        let route = CreateRoute {
+            prefer_wait: Some(sync_cutoff),
+            ..self.route
+        };
+        let prediction = api.create_routed(self.request, &route).await?;
+        if !prediction.status.is_terminal() {
+            return api
+                .wait_with_options(&prediction.id, &self.run_options)
+                .await;
+        }
+        // Finished within the cutoff; report it as a wait would have
+        if let Some(observer) = api.http.observer() {
+            observer.polled(&prediction);
+        }
+        api.record_cost(terminal_result(prediction))
+    }
+
+    /// Send the prediction request, wait for completion and deserialize the
+    /// input and output into concrete types.
+    pub async fn send_and_wait_typed<I, O>(self) -> Result<TypedPrediction<I, O>>
+    where
+        I: DeserializeOwned,
+        O: DeserializeOwned,
+    {
+        self.send_and_wait().await?.into_typed()
     }
 
     /// Send the prediction request and wait for completion with custom timeout.
+    ///
+    /// `max_duration` only bounds the wait; use [`RunOptions::deadline`] to
+    /// bound the create and uploads as well.
     pub async fn send_and_wait_with_timeout(self, max_duration: Duration) -> Result<Prediction> {
-        let prediction = self.api.create(self.request).await?;
-        self.api
-            .wait_for_completion(&prediction.id, Some(max_duration), None)
-            .await
+        let options = self.run_options.clone().max_duration(max_duration);
+        self.run_options(options).send_and_wait().await
+    }
+}
+
+/// Name the largest inputs, which together account for at least `excess` bytes.
+fn oversized_inputs(input: &HashMap<String, Value>, excess: usize) -> Result<Vec<&str>> {
+    let mut sizes = input
+        .iter()
+        .map(|(key, value)| Ok((key.as_str(), serde_json::to_vec(value)?.len())))
+        .collect::<Result<Vec<_>>>()?;
+    sizes.sort_by_key(|(key, size)| (std::cmp::Reverse(*size), *key));
+
+    let mut removed = 0;
+    Ok(sizes
+        .into_iter()
+        .take_while(|(_, size)| {
+            let needed = removed < excess;
+            removed += size;
+            needed
+        })
+        .map(|(key, _)| key)
+        .collect())
+}
+
+/// Where [`PredictionBuilder`] creates its prediction.
+#[derive(Debug, Clone)]
+struct CreateRoute {
+    /// Target of the create, or `None` for the request's version
+    target: Option<FallbackTarget>,
+    fallback: Option<FallbackTarget>,
+    fallback_statuses: Vec<u16>,
+    /// Time the API may hold the create open while the prediction runs
+    prefer_wait: Option<Duration>,
+}
+
+impl Default for CreateRoute {
+    fn default() -> Self {
+        Self {
+            target: None,
+            fallback: None,
+            fallback_statuses: DEFAULT_FALLBACK_STATUSES.to_vec(),
+            prefer_wait: None,
+        }
+    }
+}
+
+/// Version sent when creating on `target`, if the target takes one.
+fn target_version<'a>(
+    target: Option<&'a FallbackTarget>,
+    request: &'a CreatePredictionRequest,
+) -> Option<&'a str> {
+    match target {
+        None => Some(&request.version),
+        Some(FallbackTarget::Model(model)) => model.version.as_deref(),
+        Some(FallbackTarget::Deployment { .. }) => None,
+    }
+}
+
+/// Translate an error from prediction creation.
+fn create_error(version: &str, error: Error) -> Error {
+    match error {
+        Error::Api {
+            status: 404,
+            detail,
+            ..
+        } => not_found_error(version, detail.as_deref()),
+        other => other,
+    }
+}
+
+/// Check if a failed request may succeed when sent again.
+///
+/// Transport failures may also have reached the server before failing.
+fn is_transient(error: &Error) -> bool {
+    match error {
+        Error::Http(_) | Error::HttpMiddleware(_) => true,
+        Error::Api { status, .. } => *status == 429 || *status >= 500,
+        _ => false,
+    }
+}
+
+/// Explain a 404 from prediction creation as an unknown model or version.
+///
+/// The API's `detail` message decides which one it is when it says so;
+/// otherwise the shape of the requested version is used as a hint.
+fn not_found_error(version: &str, body: Option<&str>) -> Error {
+    let detail = body.map(|body| {
+        serde_json::from_str::<Value>(body)
+            .ok()
+            .and_then(|json| json.get("detail")?.as_str().map(str::to_string))
+            .unwrap_or_else(|| body.trim().to_string())
+    });
+    let lowered = detail.as_deref().unwrap_or_default().to_ascii_lowercase();
+    let (model, version_id) = match version.split_once(':') {
+        Some((model, version_id)) => (Some(model), version_id),
+        None if version.contains('/') => (Some(version), ""),
+        None => (None, version),
+    };
+
+    let says_version = lowered.contains("version");
+    let says_model = lowered.contains("model");
+    let message = match model {
+        _ if says_version && !version_id.is_empty() => {
+            format!("Unknown version '{}'", version_id)
+        }
+        Some(model) if says_model || version_id.is_empty() => {
+            format!("Unknown model '{}'", model)
+        }
+        _ => format!("Unknown model or version '{}'", version),
+    };
+
+    match detail.filter(|detail| !detail.is_empty()) {
+        Some(detail) => Error::invalid_input(format!("{}: {}", message, detail)),
+        None => Error::invalid_input(message),
     }
 }
 
@@ -240,4 +1865,1914 @@ mod tests {
         );
         assert_eq!(builder.request.stream, Some(true));
     }
+
+    #[tokio::test]
+    async fn test_typed_input_round_trip() {
+        use serde::Deserialize;
+        use serde_json::json;
+        use std::sync::Mutex;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct SdxlInput {
+            prompt: String,
+            width: u32,
+            negative_prompt: Option<String>,
+        }
+
+        /// Echoes the most recently created input back as the prediction input.
+        struct Echo(std::sync::Arc<Mutex<Value>>);
+
+        impl Respond for Echo {
+            fn respond(&self, request: &Request) -> ResponseTemplate {
+                if request.method == wiremock::http::Method::POST {
+                    let body: Value = serde_json::from_slice(&request.body).unwrap();
+                    *self.0.lock().unwrap() = body["input"].clone();
+                }
+                ResponseTemplate::new(200).set_body_json(json!({
+                    "id": "p1",
+                    "model": "stability-ai/sdxl",
+                    "version": "v1",
+                    "status": "succeeded",
+                    "input": self.0.lock().unwrap().clone(),
+                    "output": ["https://replicate.delivery/out.png"],
+                }))
+            }
+        }
+
+        let server = MockServer::start().await;
+        let stored = std::sync::Arc::new(Mutex::new(Value::Null));
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions"))
+            .respond_with(Echo(stored.clone()))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions/p1"))
+            .respond_with(Echo(stored))
+            .mount(&server)
+            .await;
+
+        let api =
+            PredictionsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        let input = SdxlInput {
+            prompt: "A lighthouse".to_string(),
+            width: 1024,
+            negative_prompt: None,
+        };
+
+        let created = PredictionBuilder::new(api.clone(), "v1")
+            .inputs_from(&input)
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+        let fetched = api.get(&created.id).await.unwrap();
+        assert_eq!(
+            fetched.input_as::<SdxlInput>().unwrap(),
+            Some(input.clone())
+        );
+
+        let typed = PredictionBuilder::new(api, "v1")
+            .inputs_from(&input)
+            .unwrap()
+            .send_and_wait_typed::<SdxlInput, Vec<String>>()
+            .await
+            .unwrap();
+        assert_eq!(typed.input(), &input);
+        assert_eq!(typed.output().map(|urls| urls.len()), Some(1));
+
+        match fetched.input_as::<Vec<String>>() {
+            Err(Error::Deserialize { .. }) => {}
+            other => panic!("Expected a deserialize error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_dynamic_inputs() {
+        use crate::models::schema::InputSchema;
+        use serde_json::json;
+
+        let schema = InputSchema::from_openapi(&json!({
+            "components": {"schemas": {"Input": {
+                "required": ["prompt"],
+                "properties": {
+                    "prompt": {"type": "string", "x-order": 0},
+                    "steps": {"type": "integer", "default": 50, "x-order": 1}
+                }
+            }}}
+        }))
+        .unwrap();
+
+        let incomplete = DynamicInput::for_schema(&schema);
+        assert!(matches!(
+            PredictionBuilder::new(create_test_api(), "v").dynamic_inputs(incomplete),
+            Err(Error::InvalidInput(_))
+        ));
+
+        let mut input = DynamicInput::for_schema(&schema);
+        input.set("prompt", "hi").unwrap();
+        let request = PredictionBuilder::new(create_test_api(), "v")
+            .dynamic_inputs(input)
+            .unwrap()
+            .request;
+        assert_eq!(
+            serde_json::to_value(&request.input).unwrap(),
+            json!({"prompt": "hi", "steps": 50})
+        );
+    }
+
+    #[test]
+    fn test_input_from_output() {
+        use serde_json::json;
+
+        let prediction = |status: &str| -> Prediction {
+            serde_json::from_value(json!({
+                "id": "sdxl-1",
+                "model": "stability-ai/sdxl",
+                "version": "v1",
+                "status": status,
+                "output": ["https://replicate.delivery/0.png", "https://replicate.delivery/1.png"],
+            }))
+            .unwrap()
+        };
+
+        let source = prediction("succeeded");
+        let request = PredictionBuilder::new(create_test_api(), "upscaler")
+            .input_from_output("image", &source, OutputSelector::First)
+            .unwrap()
+            .input_from_output("reference", &source, 1)
+            .unwrap()
+            .request;
+        assert_eq!(request.input["image"], "https://replicate.delivery/0.png");
+        assert_eq!(
+            request.input["reference"],
+            "https://replicate.delivery/1.png"
+        );
+
+        let missing = PredictionBuilder::new(create_test_api(), "upscaler")
+            .input_from_output("image", &source, 5);
+        assert!(matches!(missing, Err(Error::InvalidInput(_))));
+
+        for status in ["processing", "failed"] {
+            let result = PredictionBuilder::new(create_test_api(), "upscaler").input_from_output(
+                "image",
+                &prediction(status),
+                OutputSelector::First,
+            );
+            assert!(matches!(result, Err(Error::InvalidInput(_))), "{}", status);
+        }
+    }
+
+    #[test]
+    fn test_inputs_from_rejects_non_objects() {
+        let result = PredictionBuilder::new(create_test_api(), "v").inputs_from(&vec![1, 2]);
+        assert!(matches!(result, Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_file_inputs_keep_strategy() {
+        let mut files = HashMap::new();
+        files.insert(
+            "mask".to_string(),
+            FileInput::from_url("https://example.com/mask.png"),
+        );
+        files.insert(
+            "image".to_string(),
+            FileInput::from_url("https://example.com/image.png"),
+        );
+
+        let request = PredictionBuilder::new(create_test_api(), "v")
+            .file_input_with_strategy(
+                "control",
+                FileInput::from_url("https://example.com/control.png"),
+                FileEncodingStrategy::Base64DataUrl,
+            )
+            .file_inputs(files)
+            .request;
+
+        let keys: Vec<_> = request
+            .ordered(&request.file_inputs)
+            .into_iter()
+            .map(|(key, _)| key.as_str())
+            .collect();
+        assert_eq!(keys, ["control", "image", "mask"]);
+        assert!(matches!(
+            request.file_encoding_strategy,
+            FileEncodingStrategy::Base64DataUrl
+        ));
+    }
+
+    #[test]
+    fn test_input_order_preserved() {
+        let build = || {
+            PredictionBuilder::new(create_test_api(), "test-version")
+                .input("zeta", 1)
+                .input("alpha", "two")
+                .input("mid", true)
+                .file_input("z_image", FileInput::from_url("https://example.com/z.png"))
+                .file_input("a_image", FileInput::from_url("https://example.com/a.png"))
+        };
+
+        let first = serde_json::to_vec(&build().request).unwrap();
+        for _ in 0..10 {
+            assert_eq!(serde_json::to_vec(&build().request).unwrap(), first);
+        }
+        assert_eq!(
+            String::from_utf8(first).unwrap(),
+            r#"{"version":"test-version","input":{"zeta":1,"alpha":"two","mid":true}}"#
+        );
+
+        let request = build().request;
+        let keys: Vec<_> = request
+            .ordered(&request.file_inputs)
+            .into_iter()
+            .map(|(key, _)| key.as_str())
+            .collect();
+        assert_eq!(keys, ["z_image", "a_image"]);
+    }
+
+    #[test]
+    fn test_inputs_from_hashmap_deterministic() {
+        let mut inputs = HashMap::new();
+        for key in ["delta", "bravo", "alpha", "charlie"] {
+            inputs.insert(key.to_string(), Value::from(key));
+        }
+
+        let mut request = PredictionBuilder::new(create_test_api(), "v")
+            .inputs(inputs.clone())
+            .input("zulu", 0)
+            .request;
+        // Inputs inserted into the public map directly follow in key order
+        request.input.insert("yankee".to_string(), Value::from(1));
+        request.input.insert("echo".to_string(), Value::from(2));
+        assert_eq!(
+            serde_json::to_string(&request).unwrap(),
+            r#"{"version":"v","input":{"alpha":"alpha","bravo":"bravo","charlie":"charlie","delta":"delta","zulu":0,"echo":2,"yankee":1}}"#
+        );
+
+        let request = CreatePredictionRequest::from(inputs);
+        assert_eq!(
+            serde_json::to_string(&request).unwrap(),
+            r#"{"input":{"alpha":"alpha","bravo":"bravo","charlie":"charlie","delta":"delta"}}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_and_stream_collects_output() {
+        use serde_json::json;
+        use wiremock::matchers::{body_partial_json, header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions"))
+            .and(body_partial_json(json!({"stream": true})))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "id": "p1",
+                "model": "meta/llama",
+                "version": "v1",
+                "status": "starting",
+                "urls": {
+                    "get": format!("{}/v1/predictions/p1", server.uri()),
+                    "cancel": format!("{}/v1/predictions/p1/cancel", server.uri()),
+                    "stream": format!("{}/stream/p1", server.uri()),
+                },
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/stream/p1"))
+            .and(header("accept", "text/event-stream"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                "event: output\ndata: The sky\n\nevent: output\ndata:  is blue\n\nevent: done\ndata: {}\n\n",
+                "text/event-stream",
+            ))
+            .mount(&server)
+            .await;
+
+        let api =
+            PredictionsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        let output = PredictionBuilder::new(api, "v1")
+            .input("prompt", "What colour is the sky?")
+            .send_and_stream()
+            .await
+            .unwrap()
+            .collect_output()
+            .await
+            .unwrap();
+        assert_eq!(output, "The sky is blue");
+    }
+
+    #[tokio::test]
+    async fn test_send_and_stream_unsupported() {
+        use serde_json::json;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "id": "p1",
+                "model": "stability-ai/sdxl",
+                "version": "v1",
+                "status": "starting",
+                "urls": {"get": format!("{}/v1/predictions/p1", server.uri())},
+            })))
+            .mount(&server)
+            .await;
+
+        let api =
+            PredictionsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        match PredictionBuilder::new(api, "v1").send_and_stream().await {
+            Err(Error::Unsupported(message)) => {
+                assert!(message.contains("stability-ai/sdxl"), "{}", message);
+                assert!(message.contains("p1"), "{}", message);
+            }
+            other => panic!("Expected unsupported, got {:?}", other.map(|_| ())),
+        }
+        // Only the create was sent
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_hydrate_partial_record() {
+        use serde_json::json;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions/listed-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "listed-1",
+                "model": "owner/model",
+                "version": "v1",
+                "status": "succeeded",
+                "input": {"prompt": "a cat"},
+                "output": ["https://example.com/cat.png"],
+                "logs": "done",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let api =
+            PredictionsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        let mut prediction: Prediction = serde_json::from_value(json!({
+            "id": "listed-1",
+            "model": "owner/model",
+            "version": "v1",
+            "status": "succeeded",
+        }))
+        .unwrap();
+        assert!(prediction.is_partial);
+
+        api.hydrate(&mut prediction).await.unwrap();
+        assert!(!prediction.is_partial);
+        assert_eq!(prediction.logs.as_deref(), Some("done"));
+        assert_eq!(prediction.file_outputs().unwrap().len(), 1);
+        // Complete records are not fetched again
+        api.hydrate(&mut prediction).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_missing_urls() {
+        use serde_json::json;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let body = json!({
+            "id": "p1",
+            "model": "owner/model",
+            "version": "v1",
+            "status": "canceled",
+        });
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions/p1/cancel"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/cancel/p1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let api =
+            PredictionsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        let mut prediction: Prediction = serde_json::from_value(json!({
+            "id": "p1",
+            "model": "owner/model",
+            "version": "v1",
+            "status": "processing",
+            "urls": {"web": "https://replicate.com/p/p1"},
+        }))
+        .unwrap();
+
+        // Canceled by ID without a cancel URL
+        assert!(api.cancel(&prediction).await.unwrap().is_canceled());
+        match api.stream(&prediction).await {
+            Err(Error::InvalidInput(message)) => {
+                assert!(message.contains("no stream URL"), "{}", message)
+            }
+            other => panic!("Expected invalid input, got {:?}", other.map(|_| ())),
+        }
+
+        prediction.urls.as_mut().unwrap().cancel = Some(format!("{}/cancel/p1", server.uri()));
+        assert!(api.cancel(&prediction).await.unwrap().is_canceled());
+    }
+
+    #[tokio::test]
+    async fn test_create_not_found_explains_model_or_version() {
+        use serde_json::json;
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let not_found = |version: &str, detail: Value| {
+            Mock::given(method("POST"))
+                .and(path("/v1/predictions"))
+                .and(body_partial_json(json!({"version": version})))
+                .respond_with(ResponseTemplate::new(404).set_body_json(detail))
+        };
+        not_found(
+            "acme/missing:abc123",
+            json!({"detail": "Model not found", "status": 404}),
+        )
+        .mount(&server)
+        .await;
+        not_found(
+            "stability-ai/sdxl:deadbeef",
+            json!({"detail": "The specified version does not exist", "status": 404}),
+        )
+        .mount(&server)
+        .await;
+        not_found("cafebabe", json!({"detail": "Not found.", "status": 404}))
+            .mount(&server)
+            .await;
+
+        let api =
+            PredictionsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        let message = |version: &'static str| {
+            let api = api.clone();
+            async move {
+                match PredictionBuilder::new(api, version).send().await {
+                    Err(Error::InvalidInput(message)) => message,
+                    other => panic!("Expected invalid input for {}, got {:?}", version, other),
+                }
+            }
+        };
+
+        assert_eq!(
+            message("acme/missing:abc123").await,
+            "Unknown model 'acme/missing': Model not found"
+        );
+        assert_eq!(
+            message("stability-ai/sdxl:deadbeef").await,
+            "Unknown version 'deadbeef': The specified version does not exist"
+        );
+        assert_eq!(
+            message("cafebabe").await,
+            "Unknown model or version 'cafebabe': Not found."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wait_with_progress_reports_output_deltas() {
+        use serde_json::json;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+        /// Each poll returns one more token; the last one completes the prediction.
+        struct GrowingTokens(AtomicUsize);
+
+        impl Respond for GrowingTokens {
+            fn respond(&self, _request: &Request) -> ResponseTemplate {
+                let polls = [
+                    ("processing", json!(["The"])),
+                    ("processing", json!(["The", " sky"])),
+                    ("succeeded", json!(["The", " sky", " is blue"])),
+                ];
+                let index = self.0.fetch_add(1, Ordering::SeqCst).min(polls.len() - 1);
+                let (status, output) = &polls[index];
+                ResponseTemplate::new(200).set_body_json(json!({
+                    "id": "p1",
+                    "model": "meta/llama",
+                    "version": "v1",
+                    "status": status,
+                    "output": output,
+                }))
+            }
+        }
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions/p1"))
+            .respond_with(GrowingTokens(AtomicUsize::new(0)))
+            .mount(&server)
+            .await;
+
+        let api =
+            PredictionsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        let mut deltas = Vec::new();
+        let options = PollOptions::new().interval(Duration::from_millis(10));
+        let prediction = api
+            .wait_with_progress("p1", &options, |update| {
+                deltas.push(update.output_delta);
+            })
+            .await
+            .unwrap();
+
+        assert!(prediction.is_successful());
+        assert_eq!(
+            deltas,
+            [
+                Some("The".to_string()),
+                Some(" sky".to_string()),
+                Some(" is blue".to_string())
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wait_with_progress_reports_phases() {
+        use serde_json::json;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+        /// Queued since long ago, then running without a `started_at`, then done.
+        struct Phases(AtomicUsize);
+
+        impl Respond for Phases {
+            fn respond(&self, _request: &Request) -> ResponseTemplate {
+                let polls = [
+                    json!({"status": "starting"}),
+                    json!({"status": "processing"}),
+                    json!({"status": "processing"}),
+                    json!({"status": "succeeded", "completed_at": "2024-01-01T00:05:00Z"}),
+                ];
+                let index = self.0.fetch_add(1, Ordering::SeqCst).min(polls.len() - 1);
+                let mut body = json!({
+                    "id": "p1",
+                    "model": "owner/model",
+                    "version": "v1",
+                    "created_at": "2024-01-01T00:00:00Z",
+                });
+                body.as_object_mut()
+                    .unwrap()
+                    .extend(polls[index].as_object().unwrap().clone());
+                ResponseTemplate::new(200).set_body_json(body)
+            }
+        }
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions/p1"))
+            .respond_with(Phases(AtomicUsize::new(0)))
+            .mount(&server)
+            .await;
+
+        let api =
+            PredictionsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        let mut updates = Vec::new();
+        let options = PollOptions::new().interval(Duration::from_millis(20));
+        api.wait_with_progress("p1", &options, |update| {
+            updates.push((update.phase, update.phase_elapsed, update.waited));
+        })
+        .await
+        .unwrap();
+
+        let phases: Vec<_> = updates.iter().map(|(phase, _, _)| phase.clone()).collect();
+        assert_eq!(
+            phases,
+            [
+                PredictionStatus::Starting,
+                PredictionStatus::Processing,
+                PredictionStatus::Processing,
+                PredictionStatus::Succeeded,
+            ]
+        );
+        // Queued since `created_at`, long before the wait began
+        assert!(updates[0].1 > Duration::from_secs(60));
+        // Without `started_at`, running time counts from the first poll that saw it
+        assert!(updates[1].1 < Duration::from_secs(5));
+        assert!(updates[2].1 > updates[1].1);
+        assert!(updates[3].2 >= updates[2].2);
+    }
+
+    #[tokio::test]
+    async fn test_handle_round_trips_and_resumes_wait() {
+        use serde_json::json;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let prediction = |status: &str| {
+            json!({
+                "id": "p1",
+                "model": "owner/model",
+                "version": "v1",
+                "status": status,
+                "output": if status == "succeeded" { json!("done") } else { Value::Null },
+                "urls": {
+                    "get": format!("{}/v1/predictions/p1", server.uri()),
+                    "cancel": format!("{}/v1/predictions/p1/cancel", server.uri()),
+                },
+            })
+        };
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(prediction("starting")))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions/p1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(prediction("succeeded")))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions/p1/cancel"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(prediction("canceled")))
+            .mount(&server)
+            .await;
+
+        let http = HttpClient::with_base_url("test-token", server.uri()).unwrap();
+        let (created, handle) = PredictionBuilder::new(PredictionsApi::new(http.clone()), "v1")
+            .send_handle()
+            .await
+            .unwrap();
+        assert_eq!(handle, created.handle());
+        assert_eq!(handle.id, "p1");
+
+        // A fresh API instance resumes from the persisted handle alone
+        let stored = serde_json::to_string(&handle).unwrap();
+        let restored: PredictionHandle = serde_json::from_str(&stored).unwrap();
+        assert_eq!(restored, handle);
+
+        let api = PredictionsApi::new(http);
+        let options = RunOptions::new().poll_interval(Duration::from_millis(5));
+        let finished = api.wait_handle(&restored, &options).await.unwrap();
+        assert_eq!(finished.output, Some(json!("done")));
+        assert!(api.get_handle(&restored).await.unwrap().is_successful());
+        assert!(api.cancel_handle(&restored).await.unwrap().is_canceled());
+    }
+
+    #[tokio::test]
+    async fn test_urls_must_point_at_api_host() {
+        use wiremock::MockServer;
+
+        let server = MockServer::start().await;
+        let api =
+            PredictionsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+
+        for url in [
+            "https://evil.example.com/v1/predictions/p1/cancel",
+            "not a url",
+        ] {
+            assert!(matches!(
+                api.cancel_by_url(url).await,
+                Err(Error::InvalidInput(_))
+            ));
+            assert!(matches!(
+                api.get_from_url(url).await,
+                Err(Error::InvalidInput(_))
+            ));
+        }
+        assert!(server.received_requests().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_create_exactly_once_reuses_accepted_prediction() {
+        use serde_json::json;
+        use std::sync::Mutex;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+        /// Accepts the prediction but answers after the client has given up.
+        struct SlowAccept(Arc<Mutex<Option<Value>>>);
+
+        impl Respond for SlowAccept {
+            fn respond(&self, request: &Request) -> ResponseTemplate {
+                let body: Value = serde_json::from_slice(&request.body).unwrap();
+                *self.0.lock().unwrap() = Some(body["input"].clone());
+                ResponseTemplate::new(201).set_delay(Duration::from_secs(2))
+            }
+        }
+
+        /// Lists the predictions accepted so far on the second page, after
+        /// a page of other recent predictions.
+        struct Accepted(Arc<Mutex<Option<Value>>>, String);
+
+        impl Respond for Accepted {
+            fn respond(&self, request: &Request) -> ResponseTemplate {
+                if request.url.query().is_none() {
+                    return ResponseTemplate::new(200).set_body_json(json!({
+                        "results": [{
+                            "id": "p0",
+                            "model": "owner/model",
+                            "version": "v1",
+                            "status": "starting",
+                            "created_at": "2100-01-01T00:00:00Z",
+                        }],
+                        "next": format!("{}/v1/predictions?cursor=2", self.1),
+                        "previous": null,
+                    }));
+                }
+                let results: Vec<Value> = self
+                    .0
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .map(|_| {
+                        json!({
+                            "id": "p1",
+                            "model": "owner/model",
+                            "version": "v1",
+                            "status": "starting",
+                        })
+                    })
+                    .collect();
+                ResponseTemplate::new(200).set_body_json(json!({
+                    "results": results,
+                    "next": null,
+                    "previous": null,
+                }))
+            }
+        }
+
+        /// Returns the full record of the accepted prediction.
+        struct AcceptedRecord(Arc<Mutex<Option<Value>>>);
+
+        impl Respond for AcceptedRecord {
+            fn respond(&self, _: &Request) -> ResponseTemplate {
+                ResponseTemplate::new(200).set_body_json(json!({
+                    "id": "p1",
+                    "model": "owner/model",
+                    "version": "v1",
+                    "status": "starting",
+                    "input": self.0.lock().unwrap().clone(),
+                    "logs": "",
+                }))
+            }
+        }
+
+        let server = MockServer::start().await;
+        let accepted = Arc::new(Mutex::new(None));
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions"))
+            .respond_with(SlowAccept(accepted.clone()))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions"))
+            .respond_with(Accepted(accepted.clone(), server.uri()))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions/p0"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "p0",
+                "model": "owner/model",
+                "version": "v1",
+                "status": "starting",
+                "input": {"prompt": "other"},
+                "logs": "",
+                "created_at": "2100-01-01T00:00:00Z",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions/p1"))
+            .respond_with(AcceptedRecord(accepted.clone()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut http = HttpClient::with_base_url("test-token", server.uri()).unwrap();
+        http.configure_timeouts(None, Some(Duration::from_millis(200)))
+            .unwrap();
+        let mut api = PredictionsApi::new(http);
+        api.set_nonce_key("request_nonce");
+
+        let prediction = PredictionBuilder::new(api, "v1")
+            .input("prompt", "hello")
+            .send_exactly_once()
+            .await
+            .unwrap();
+        assert_eq!(prediction.id, "p1");
+        let input = prediction.input.unwrap();
+        assert_eq!(input["prompt"], "hello");
+        assert!(!input.contains_key("request_nonce"));
+
+        let sent = accepted.lock().unwrap().clone().unwrap();
+        assert!(
+            sent["request_nonce"]
+                .as_str()
+                .is_some_and(|nonce| !nonce.is_empty())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_by_nonce_stops_past_the_window() {
+        use serde_json::json;
+        use wiremock::matchers::{method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions"))
+            .and(query_param("cursor", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"results": []})))
+            .expect(0)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "results": [{
+                    "id": "p0",
+                    "model": "owner/model",
+                    "version": "v1",
+                    "status": "succeeded",
+                    "created_at": "2020-01-01T00:00:00Z",
+                }],
+                "next": format!("{}/v1/predictions?cursor=2", server.uri()),
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        // Records before the window are not fetched in full
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions/p0"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let api =
+            PredictionsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        let found = api
+            .find_by_nonce("abc", SystemTime::now() - NONCE_SEARCH_MARGIN)
+            .await
+            .unwrap();
+        assert!(found.is_none());
+    }
+
+    /// HTTP server on the test runtime that answers every request after
+    /// `step`, so its delays follow a paused clock, and records each request
+    /// line.
+    async fn start_slow_server(
+        step: Duration,
+    ) -> (String, std::sync::Arc<std::sync::Mutex<Vec<String>>>) {
+        use serde_json::json;
+        use std::sync::{Arc, Mutex};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let recorded = recorded.clone();
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 4096];
+                    let head_end = loop {
+                        if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                            break end + 4;
+                        }
+                        match socket.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                        }
+                    };
+                    let head = String::from_utf8_lossy(&buf[..head_end]).to_lowercase();
+                    let request_line = head.lines().next().unwrap_or_default().to_string();
+                    recorded.lock().unwrap().push(request_line.clone());
+
+                    // Read the body, sized or chunked, before answering
+                    let length = head
+                        .lines()
+                        .find_map(|line| line.strip_prefix("content-length:"))
+                        .and_then(|value| value.trim().parse::<usize>().ok());
+                    loop {
+                        let body = &buf[head_end..];
+                        let complete = match length {
+                            Some(length) => body.len() >= length,
+                            None if head.contains("transfer-encoding: chunked") => {
+                                body.ends_with(b"0\r\n\r\n")
+                            }
+                            None => true,
+                        };
+                        if complete {
+                            break;
+                        }
+                        match socket.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+
+                    tokio::time::sleep(step).await;
+                    let (status, body) = match request_line.split(' ').take(2).collect::<Vec<_>>()[..]
+                    {
+                        ["post", "/v1/files"] => (
+                            "201 Created",
+                            json!({
+                                "id": "file-1",
+                                "name": "image.png",
+                                "content_type": "image/png",
+                                "size": 3,
+                                "etag": "etag",
+                                "checksums": {},
+                                "metadata": {},
+                                "created_at": "2024-01-01T00:00:00Z",
+                                "urls": {"get": "https://api.replicate.com/v1/files/file-1"},
+                            }),
+                        ),
+                        ["post", "/v1/predictions"] => (
+                            "201 Created",
+                            json!({"id": "p1", "model": "owner/model", "version": "v1", "status": "starting"}),
+                        ),
+                        _ => (
+                            "200 OK",
+                            json!({"id": "p1", "model": "owner/model", "version": "v1", "status": "processing"}),
+                        ),
+                    };
+                    let body = body.to_string();
+                    let response = format!(
+                        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+
+        (format!("http://{}", addr), requests)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_deadline_covers_upload_create_and_polls() {
+        use crate::error::TimeoutKind;
+        use tokio::time::Instant;
+
+        let step = Duration::from_millis(300);
+        let (base_url, requests) = start_slow_server(step).await;
+        // A paused clock jumps to the next timer whenever the runtime waits,
+        // including on socket I/O; a 1ms ticker keeps those jumps short so
+        // the server starts its delay before the deadline is reached
+        let ticker = tokio::spawn(async {
+            loop {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        });
+        let api = PredictionsApi::new(HttpClient::with_base_url("test-token", base_url).unwrap());
+        let budget = Duration::from_millis(1000);
+        let options = RunOptions::new()
+            .max_duration(Duration::from_secs(60))
+            .poll_interval(Duration::from_millis(50))
+            .deadline(Deadline::after(budget));
+        let start = Instant::now();
+        let result = PredictionBuilder::new(api.clone(), "v1")
+            .file_input("image", FileInput::from_bytes(&b"png"[..]))
+            .run_options(options)
+            .send_and_wait()
+            .await;
+        match result {
+            Err(Error::Timeout {
+                kind,
+                prediction_id,
+                last_status,
+                ..
+            }) => {
+                assert_eq!(kind, TimeoutKind::Deadline);
+                assert_eq!(prediction_id.as_deref(), Some("p1"));
+                assert_eq!(last_status, Some(PredictionStatus::Processing));
+            }
+            other => panic!("Expected a deadline timeout, got {:?}", other),
+        }
+        // Upload, create and the first poll took a step each; the deadline
+        // cut the second poll short
+        assert_eq!(start.elapsed(), budget);
+        assert_eq!(
+            *requests.lock().unwrap(),
+            [
+                "post /v1/files http/1.1",
+                "post /v1/predictions http/1.1",
+                "get /v1/predictions/p1 http/1.1",
+                "get /v1/predictions/p1 http/1.1",
+            ]
+        );
+
+        // A deadline shorter than the upload stops before the create is sent
+        requests.lock().unwrap().clear();
+        let start = Instant::now();
+        let result = PredictionBuilder::new(api, "v1")
+            .file_input("image", FileInput::from_bytes(&b"png"[..]))
+            .run_options(RunOptions::new().deadline(Deadline::after(step / 2)))
+            .send_and_wait()
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::Timeout {
+                kind: TimeoutKind::Deadline,
+                prediction_id: None,
+                ..
+            })
+        ));
+        assert_eq!(start.elapsed(), step / 2);
+        assert_eq!(*requests.lock().unwrap(), ["post /v1/files http/1.1"]);
+        ticker.abort();
+    }
+
+    #[tokio::test]
+    async fn test_auto_run_mode() {
+        use serde_json::json;
+        use wiremock::matchers::{body_partial_json, header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let prediction = |id: &str, status: &str| {
+            json!({
+                "id": id, "model": "owner/model", "version": "v1", "status": status,
+                "output": if status == "succeeded" { json!("hello") } else { json!(null) },
+                "error": if status == "failed" { json!("boom") } else { json!(null) },
+            })
+        };
+        let server = MockServer::start().await;
+        for (id, created, polled) in [
+            ("fast", "succeeded", None),
+            ("slow", "processing", Some("succeeded")),
+            ("broken", "starting", Some("failed")),
+        ] {
+            Mock::given(method("POST"))
+                .and(path("/v1/predictions"))
+                .and(header("prefer", "wait=2"))
+                .and(body_partial_json(json!({"input": {"id": id}})))
+                .respond_with(ResponseTemplate::new(201).set_body_json(prediction(id, created)))
+                .mount(&server)
+                .await;
+            Mock::given(method("GET"))
+                .and(path(format!("/v1/predictions/{}", id)))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .set_body_json(prediction(id, polled.unwrap_or(created))),
+                )
+                .expect(u64::from(polled.is_some()))
+                .mount(&server)
+                .await;
+        }
+
+        let api =
+            PredictionsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        let run = |id: &str| {
+            PredictionBuilder::new(api.clone(), "v1")
+                .input("id", id)
+                .mode(RunMode::Auto {
+                    sync_cutoff: Duration::from_millis(1500),
+                })
+                .run_options(RunOptions::new().poll_interval(Duration::from_millis(5)))
+                .send_and_wait()
+        };
+
+        // Finished within the cutoff, so nothing is polled
+        let fast = run("fast").await.unwrap();
+        // Still running after the cutoff, so the same prediction is polled
+        let slow = run("slow").await.unwrap();
+        let as_fast = |prediction: &Prediction| {
+            let mut value = serde_json::to_value(prediction).unwrap();
+            value["id"] = json!("fast");
+            value
+        };
+        assert_eq!(as_fast(&slow), serde_json::to_value(&fast).unwrap());
+
+        match run("broken").await {
+            Err(Error::ModelExecution {
+                prediction_id,
+                error_message,
+                ..
+            }) => {
+                assert_eq!(prediction_id, "broken");
+                assert_eq!(error_message.as_deref(), Some("boom"));
+            }
+            other => panic!("expected a model execution error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_fresh_bypasses_caches() {
+        use serde_json::json;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions/p1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "p1", "model": "owner/model", "version": "v1", "status": "succeeded"
+            })))
+            .mount(&server)
+            .await;
+
+        let api =
+            PredictionsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        api.get("p1").await.unwrap();
+        let fresh = api.get_fresh("p1").await.unwrap();
+        assert_eq!(fresh.status, PredictionStatus::Succeeded);
+        api.wait_for_completion("p1", None, None).await.unwrap();
+        // Raw output mode reads the body as it arrives, with the same headers
+        let mut raw = api.clone();
+        raw.set_raw_output(true);
+        assert_eq!(raw.get_fresh("p1").await.unwrap().id, "p1");
+
+        let requests = server.received_requests().await.unwrap();
+        let cache_control: Vec<_> = requests
+            .iter()
+            .map(|request| {
+                request
+                    .headers
+                    .get("cache-control")
+                    .map(|v| v.to_str().unwrap())
+            })
+            .collect();
+        assert_eq!(
+            cache_control,
+            [None, Some("no-cache"), Some("no-cache"), Some("no-cache")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_completion_until() {
+        use crate::error::TimeoutKind;
+        use serde_json::json;
+        use std::time::Instant;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let prediction = |id: &str, status: &str| json!({"id": id, "model": "owner/model", "version": "v1", "status": status});
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions/done"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(prediction("done", "succeeded")))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions/slow"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(prediction("slow", "processing")),
+            )
+            .mount(&server)
+            .await;
+
+        let api =
+            PredictionsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        let interval = Some(Duration::from_millis(20));
+        let deadline = Instant::now() + Duration::from_millis(300);
+
+        let done = api
+            .wait_for_completion_until("done", deadline, interval)
+            .await
+            .unwrap();
+        assert_eq!(done.status, PredictionStatus::Succeeded);
+
+        // The second wait shares the remaining budget of the same deadline
+        match api
+            .wait_for_completion_until("slow", deadline, interval)
+            .await
+        {
+            Err(Error::Timeout {
+                kind,
+                prediction_id,
+                last_status,
+                ..
+            }) => {
+                assert_eq!(kind, TimeoutKind::Deadline);
+                assert_eq!(prediction_id.as_deref(), Some("slow"));
+                assert_eq!(last_status, Some(PredictionStatus::Processing));
+            }
+            other => panic!("Expected a deadline timeout, got {:?}", other),
+        }
+        assert!(Instant::now() >= deadline);
+
+        let polls = server.received_requests().await.unwrap().len();
+        assert!(matches!(
+            api.wait_for_completion_until("slow", deadline, interval)
+                .await,
+            Err(Error::Timeout {
+                kind: TimeoutKind::Deadline,
+                last_status: None,
+                ..
+            })
+        ));
+        assert_eq!(server.received_requests().await.unwrap().len(), polls);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body() {
+        use serde_json::json;
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let file_url = "https://api.replicate.com/v1/files/file-1";
+        Mock::given(method("POST"))
+            .and(path("/v1/files"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "id": "file-1",
+                "name": "image.png",
+                "content_type": "image/png",
+                "size": 4096,
+                "etag": "etag",
+                "checksums": {},
+                "metadata": {},
+                "created_at": "2024-01-01T00:00:00Z",
+                "urls": {"get": file_url},
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions"))
+            .and(body_partial_json(
+                json!({"input": {"image": file_url, "prompt": "hi"}}),
+            ))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "id": "p1",
+                "model": "owner/model",
+                "version": "v1",
+                "status": "starting",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut api =
+            PredictionsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        api.set_max_body_size(Some(1024));
+        let create = |strategy| {
+            PredictionBuilder::new(api.clone(), "v1")
+                .input("prompt", "hi")
+                .file_input_with_strategy("image", FileInput::from_bytes(vec![0u8; 4096]), strategy)
+                .send()
+        };
+
+        match create(FileEncodingStrategy::Base64DataUrl).await {
+            Err(Error::InvalidInput(message)) => {
+                assert!(message.contains("over the 1024 byte limit"), "{}", message);
+                assert!(message.contains(": image."), "{}", message);
+            }
+            other => panic!("Expected invalid input, got {:?}", other),
+        }
+        assert!(server.received_requests().await.unwrap().is_empty());
+
+        let prediction = create(FileEncodingStrategy::Auto).await.unwrap();
+        assert_eq!(prediction.id, "p1");
+    }
+
+    #[tokio::test]
+    async fn test_cancel_checked() {
+        use serde_json::json;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        for (id, status) in [
+            ("running", "canceled"),
+            ("done", "succeeded"),
+            ("broken", "failed"),
+        ] {
+            Mock::given(method("POST"))
+                .and(path(format!("/v1/predictions/{}/cancel", id)))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "id": id,
+                    "model": "owner/model",
+                    "version": "v1",
+                    "status": status,
+                })))
+                .mount(&server)
+                .await;
+        }
+
+        let api =
+            PredictionsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        let outcome = api.cancel_checked("running").await.unwrap();
+        assert!(!outcome.was_already_terminal);
+        assert!(outcome.prediction.is_canceled());
+        for id in ["done", "broken"] {
+            let outcome = api.cancel_checked(id).await.unwrap();
+            assert!(outcome.was_already_terminal);
+            assert_eq!(outcome.prediction.id, id);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_detached_returns_prediction_or_error() {
+        use serde_json::json;
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions"))
+            .and(body_partial_json(json!({"version": "v1"})))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "id": "p1",
+                "model": "owner/model",
+                "version": "v1",
+                "status": "starting",
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions"))
+            .and(body_partial_json(json!({"version": "bad"})))
+            .respond_with(ResponseTemplate::new(422).set_body_string("invalid input"))
+            .mount(&server)
+            .await;
+
+        let api =
+            PredictionsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        let runtime = tokio::runtime::Handle::current();
+        let created = PredictionBuilder::new(api.clone(), "v1")
+            .webhook("https://example.com/hooks/replicate")
+            .send_detached(&runtime);
+        let rejected = PredictionBuilder::new(api.clone(), "bad").send_detached(&runtime);
+
+        assert_eq!(created.await.unwrap().unwrap().id, "p1");
+        assert!(matches!(
+            rejected.await.unwrap(),
+            Err(Error::Api { status: 422, .. })
+        ));
+
+        let prediction = PredictionBuilder::new(api, "v1")
+            .send_nowait()
+            .await
+            .unwrap();
+        assert_eq!(prediction.id, "p1");
+        // Only the three creates were sent; nothing was polled
+        let requests = server.received_requests().await.unwrap();
+        assert!(requests.iter().all(|r| r.method.as_str() == "POST"));
+    }
+
+    #[tokio::test]
+    async fn test_validate_webhook_before_create() {
+        use serde_json::json;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "id": "p1",
+                "model": "owner/model",
+                "version": "v1",
+                "status": "starting",
+            })))
+            .mount(&server)
+            .await;
+        let api =
+            PredictionsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        let builder = |webhook: &str| PredictionBuilder::new(api.clone(), "v1").webhook(webhook);
+
+        let result = builder("https://no-such-host.invalid/hook")
+            .validate_webhook(true)
+            .send()
+            .await;
+        assert!(
+            matches!(result, Err(Error::InvalidInput(message)) if message.contains("does not resolve"))
+        );
+        let result = builder(&server.uri()).validate_webhook(true).send().await;
+        assert!(matches!(result, Err(Error::InvalidInput(_))));
+        assert!(server.received_requests().await.unwrap().is_empty());
+
+        // Local receivers pass once http is allowed, and unchecked webhooks are sent as is
+        let check = WebhookCheck::new().allow_http(true).preflight(true);
+        builder(&server.uri())
+            .webhook_check(check)
+            .send()
+            .await
+            .unwrap();
+        builder("https://no-such-host.invalid/hook")
+            .validate_webhook(false)
+            .send()
+            .await
+            .unwrap();
+        PredictionBuilder::new(api.clone(), "v1")
+            .validate_webhook(true)
+            .send()
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_accepts_typed_and_plain_ids() {
+        use serde_json::json;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions/p1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "p1",
+                "model": "owner/model",
+                "version": "v1",
+                "status": "processing",
+            })))
+            .expect(4)
+            .mount(&server)
+            .await;
+
+        let api =
+            PredictionsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        let prediction = api.get("p1").await.unwrap();
+        let id: PredictionId = "p1".parse().unwrap();
+        api.get(&id).await.unwrap();
+        api.get(&prediction).await.unwrap();
+        api.get(prediction.id.clone()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_deployment_create_falls_back_to_model() {
+        use serde_json::json;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/deployments/acme/missing/predictions"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({"detail": "Not found"})))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/deployments/acme/invalid/predictions"))
+            .respond_with(ResponseTemplate::new(422).set_body_json(json!({"detail": "Bad input"})))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/models/acme/flux/predictions"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "id": "p1",
+                "model": "acme/flux",
+                "version": "v2",
+                "status": "starting",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let api =
+            PredictionsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        let fallback = FallbackTarget::Model(ModelRef::new("acme", "flux"));
+        let prediction = PredictionBuilder::new(api.clone(), "")
+            .on_deployment("acme", "missing")
+            .with_fallback(fallback.clone())
+            .input("prompt", "hello")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(prediction.id, "p1");
+        assert_eq!(prediction.failed_over_to, Some(fallback.clone()));
+
+        let requests = server.received_requests().await.unwrap();
+        let body: Value = serde_json::from_slice(&requests[1].body).unwrap();
+        assert_eq!(body, json!({"input": {"prompt": "hello"}}));
+
+        // Statuses outside the fallback set are returned as is
+        let result = PredictionBuilder::new(api.clone(), "")
+            .on_deployment("acme", "invalid")
+            .with_fallback(fallback.clone())
+            .send()
+            .await;
+        assert!(matches!(result, Err(Error::Api { status: 422, .. })));
+        let result = PredictionBuilder::new(api, "")
+            .on_deployment("acme", "missing")
+            .with_fallback(fallback)
+            .fallback_statuses([503])
+            .send()
+            .await;
+        assert!(matches!(result, Err(Error::Api { status: 404, .. })));
+    }
+
+    #[tokio::test]
+    async fn test_resume_persisted_request() {
+        use serde_json::json;
+        use wiremock::matchers::{body_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions"))
+            .and(body_json(json!({
+                "version": "v1",
+                "input": {
+                    "prompt": "hi",
+                    "image": "data:image/png;base64,cG5n",
+                },
+                "webhook": "https://example.com/hook",
+            })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "id": "p1",
+                "model": "owner/model",
+                "version": "v1",
+                "status": "starting",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let api =
+            PredictionsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+
+        let builder = PredictionBuilder::new(api.clone(), "v1")
+            .input("prompt", "hi")
+            .file_input_with_strategy(
+                "image",
+                FileInput::from_bytes_with_metadata(
+                    &b"png"[..],
+                    None,
+                    Some("image/png".to_string()),
+                ),
+                FileEncodingStrategy::Base64DataUrl,
+            )
+            .webhook("https://example.com/hook");
+        let stored = serde_json::to_string(&builder.request().persisted()).unwrap();
+
+        // The API body leaves out local settings
+        let body: Value = serde_json::to_value(builder.request()).unwrap();
+        assert!(body.get("file_encoding_strategy").is_none());
+
+        let request: CreatePredictionRequest = serde_json::from_str(&stored).unwrap();
+        assert_eq!(request.file_inputs, builder.request().file_inputs);
+        assert_eq!(
+            request.file_encoding_strategy,
+            FileEncodingStrategy::Base64DataUrl
+        );
+        assert_eq!(serde_json::to_string(&request.persisted()).unwrap(), stored);
+
+        let prediction = PredictionBuilder::from_request(api, request)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(prediction.id, "p1");
+    }
+
+    #[tokio::test]
+    async fn test_no_auto_upload() {
+        use serde_json::json;
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let image_url = "https://example.com/image.png";
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions"))
+            .and(body_partial_json(json!({"input": {"image": image_url}})))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "id": "p1",
+                "model": "owner/model",
+                "version": "v1",
+                "status": "starting",
+            })))
+            .expect(2)
+            .mount(&server)
+            .await;
+        let api =
+            PredictionsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+
+        // URLs are passed through whether or not uploads are allowed
+        for no_auto_upload in [false, true] {
+            let mut builder = PredictionBuilder::new(api.clone(), "v1")
+                .file_input("image", FileInput::from_url(image_url));
+            if no_auto_upload {
+                builder = builder.no_auto_upload();
+            }
+            assert_eq!(builder.send().await.unwrap().id, "p1");
+        }
+
+        let result = PredictionBuilder::new(api.clone(), "v1")
+            .file_input_with_strategy(
+                "mask",
+                FileInput::from_bytes(&b"png"[..]),
+                FileEncodingStrategy::Multipart,
+            )
+            .no_auto_upload()
+            .send()
+            .await;
+        match result {
+            Err(Error::InvalidInput(message)) => assert!(message.contains("'mask'"), "{}", message),
+            other => panic!("Expected invalid input, got {:?}", other),
+        }
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_default_inputs() {
+        use serde_json::json;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "id": "p1",
+                "model": "owner/model",
+                "version": "v1",
+                "status": "starting",
+            })))
+            .mount(&server)
+            .await;
+        let mut api =
+            PredictionsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        api.set_default_inputs(HashMap::from([
+            ("disable_safety_checker".to_string(), json!(false)),
+            ("seed".to_string(), json!(42)),
+            (
+                "image".to_string(),
+                json!("https://example.com/default.png"),
+            ),
+        ]));
+
+        PredictionBuilder::new(api.clone(), "v1")
+            .input("prompt", "a lighthouse")
+            .input("seed", 7)
+            .file_input_with_strategy(
+                "image",
+                FileInput::from_bytes(&b"png"[..]),
+                FileEncodingStrategy::Base64DataUrl,
+            )
+            .send()
+            .await
+            .unwrap();
+        api.create(CreatePredictionRequest::new("v1"))
+            .await
+            .unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        let body =
+            |index: usize| -> Value { serde_json::from_slice(&requests[index].body).unwrap() };
+        let input = &body(0)["input"];
+        assert_eq!(input["prompt"], "a lighthouse");
+        assert_eq!(input["seed"], 7);
+        assert_eq!(input["disable_safety_checker"], false);
+        assert!(input["image"].as_str().unwrap().starts_with("data:"));
+        assert_eq!(
+            body(1)["input"],
+            json!({
+                "disable_safety_checker": false,
+                "image": "https://example.com/default.png",
+                "seed": 42,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_uploaded_file_reused_across_predictions() {
+        use serde_json::json;
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let file = |id: &str, expires_at: &str| {
+            json!({
+                "id": id,
+                "name": "reference.png",
+                "content_type": "image/png",
+                "size": 3,
+                "etag": "etag",
+                "checksums": {},
+                "metadata": {},
+                "created_at": "2024-01-01T00:00:00Z",
+                "expires_at": expires_at,
+                "urls": {"get": format!("https://api.replicate.com/v1/files/{}", id)},
+            })
+        };
+        // The first upload has already expired, the second lasts
+        Mock::given(method("POST"))
+            .and(path("/v1/files"))
+            .respond_with(
+                ResponseTemplate::new(201).set_body_json(file("file-1", "2000-01-01T00:00:00Z")),
+            )
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/files"))
+            .respond_with(
+                ResponseTemplate::new(201).set_body_json(file("file-2", "2999-01-01T00:00:00Z")),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions"))
+            .and(body_partial_json(json!({
+                "input": {"image": "https://api.replicate.com/v1/files/file-2"}
+            })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "id": "p1",
+                "model": "owner/model",
+                "version": "v1",
+                "status": "starting",
+            })))
+            .expect(3)
+            .mount(&server)
+            .await;
+
+        let http = HttpClient::with_base_url("test-token", server.uri()).unwrap();
+        let api = PredictionsApi::new(http.clone());
+        let uploaded = FilesApi::new(http)
+            .upload(FileInput::from_bytes(&b"png"[..]))
+            .await
+            .unwrap();
+
+        let result = PredictionBuilder::new(api.clone(), "v1")
+            .uploaded_file("image", &uploaded.clone().reupload_when_expired(false))
+            .send()
+            .await;
+        match result {
+            Err(Error::InvalidInput(message)) => assert!(message.contains("file-1"), "{}", message),
+            other => panic!("Expected invalid input, got {:?}", other),
+        }
+
+        for seed in 0..3 {
+            PredictionBuilder::new(api.clone(), "v1")
+                .uploaded_file("image", &uploaded)
+                .input("seed", seed)
+                .send()
+                .await
+                .unwrap();
+        }
+        assert_eq!(uploaded.file().id, "file-2");
+    }
+
+    #[tokio::test]
+    async fn test_cancel_matching() {
+        use serde_json::json;
+        use wiremock::matchers::{method, path, query_param, query_param_is_missing};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let record = |id: &str, status: &str, model: &str, day: &str, feature: &str| {
+            json!({
+                "id": id,
+                "model": model,
+                "version": "v1",
+                "status": status,
+                "created_at": format!("2024-{}T12:00:00Z", day),
+                "input": {"feature": feature},
+            })
+        };
+        let dataset = [
+            record("p1", "processing", "acme/tagger", "06-03", "auto"),
+            record("p2", "succeeded", "acme/tagger", "06-03", "auto"),
+            record("p3", "starting", "other/model", "06-02", "auto"),
+            record("p4", "processing", "acme/tagger", "06-02", "auto"),
+            record("p5", "starting", "acme/tagger", "06-02", "manual"),
+            // Created before the window, so paging stops on this page
+            record("p6", "processing", "acme/tagger", "05-01", "auto"),
+        ];
+        let listed = |records: &[Value]| -> Vec<Value> {
+            records
+                .iter()
+                .map(|record| {
+                    let mut record = record.clone();
+                    record.as_object_mut().unwrap().remove("input");
+                    record
+                })
+                .collect()
+        };
+        let page = |cursor: &str, records: &[Value], next: &str| {
+            Mock::given(method("GET"))
+                .and(path("/v1/predictions"))
+                .and(query_param("cursor", cursor))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "results": listed(records),
+                    "next": format!("{}/v1/predictions?cursor={}", server.uri(), next),
+                })))
+        };
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions"))
+            .and(query_param_is_missing("cursor"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "results": listed(&dataset[..4]),
+                "next": format!("{}/v1/predictions?cursor=2", server.uri()),
+            })))
+            .mount(&server)
+            .await;
+        page("2", &dataset[4..], "3").mount(&server).await;
+        page("3", &[], "4").expect(0).mount(&server).await;
+        for record in &dataset {
+            Mock::given(method("GET"))
+                .and(path(format!(
+                    "/v1/predictions/{}",
+                    record["id"].as_str().unwrap()
+                )))
+                .respond_with(ResponseTemplate::new(200).set_body_json(record))
+                .mount(&server)
+                .await;
+        }
+        let mut canceled = dataset[0].clone();
+        canceled["status"] = json!("canceled");
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions/p1/cancel"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(canceled))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions/p4/cancel"))
+            .respond_with(ResponseTemplate::new(422).set_body_json(json!({"detail": "nope"})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let api =
+            PredictionsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        let filter = PredictionFilter::new()
+            .model("acme/tagger")
+            .created_after(parse_timestamp("2024-06-01T00:00:00Z").unwrap())
+            .input("feature", "auto");
+
+        let dry_run = api.cancel_matching(filter.clone(), true).await.unwrap();
+        assert_eq!(dry_run.canceled, ["p1", "p4"]);
+        assert!(dry_run.failed.is_empty());
+        assert_eq!(dry_run.skipped, 2);
+        assert_eq!(
+            dry_run.to_string(),
+            "2 would be canceled, 0 failed, 2 skipped"
+        );
+
+        let report = api.cancel_matching(filter.clone(), false).await.unwrap();
+        assert_eq!(report.canceled, ["p1"]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, "p4");
+        assert!(matches!(report.failed[0].1, Error::Api { status: 422, .. }));
+        assert_eq!(report.skipped, 2);
+
+        // The first page is canceled before the second one is listed
+        let requests = server.received_requests().await.unwrap();
+        let last = |wanted: &str| {
+            requests
+                .iter()
+                .rposition(|request| format!("{} {}", request.method, request.url).contains(wanted))
+                .unwrap()
+        };
+        assert!(last("/p1/cancel") < last("cursor=2"));
+
+        // Without a window the whole history would be paged
+        let unbounded = PredictionFilter {
+            created_after: None,
+            ..filter
+        };
+        assert!(matches!(
+            api.cancel_matching(unbounded, true).await,
+            Err(Error::InvalidInput(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_matching_rejects_foreign_next() {
+        use serde_json::json;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let foreign = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "results": [],
+                "next": format!("{}/v1/predictions?cursor=2", foreign.uri()),
+            })))
+            .mount(&server)
+            .await;
+
+        let api =
+            PredictionsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap());
+        let filter =
+            PredictionFilter::new().created_after(parse_timestamp("2024-06-01T00:00:00Z").unwrap());
+        assert!(matches!(
+            api.cancel_matching(filter, true).await,
+            Err(Error::InvalidInput(_))
+        ));
+        assert!(foreign.received_requests().await.unwrap().is_empty());
+    }
 }
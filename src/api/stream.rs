@@ -0,0 +1,592 @@
+//! Server-sent event streams for prediction output.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use std::time::Duration;
+
+use bytes::{Buf, Bytes, BytesMut};
+use futures::stream::{self, Stream, StreamExt};
+use reqwest::Response;
+use serde::de::DeserializeOwned;
+use tokio::time::{Instant, timeout_at};
+
+use crate::error::{Error, Result};
+use crate::models::stream::StreamEvent;
+
+type EventStream = Pin<Box<dyn Stream<Item = Result<StreamEvent>> + Send>>;
+
+/// Stream of events for a single prediction.
+///
+/// The stream ends after [`StreamEvent::Done`]. If the connection closes before
+/// that, the last item is [`Error::StreamClosed`]; if no event arrives within
+/// the idle timeout, it is [`Error::Timeout`].
+pub struct PredictionStream {
+    prediction_id: String,
+    inner: EventStream,
+}
+
+impl PredictionStream {
+    /// Wrap an open `text/event-stream` response.
+    pub(crate) fn from_response(
+        prediction_id: impl Into<String>,
+        response: Response,
+        idle_timeout: Option<Duration>,
+    ) -> Self {
+        Self::from_bytes(
+            prediction_id,
+            response
+                .bytes_stream()
+                .map(|chunk| chunk.map_err(Error::from)),
+            idle_timeout,
+        )
+    }
+
+    /// Parse events from a stream of raw body chunks.
+    ///
+    /// With an `idle_timeout`, the stream fails with [`Error::Timeout`] when no
+    /// event arrives in time. Keep-alive comments do not count as events.
+    pub(crate) fn from_bytes<S>(
+        prediction_id: impl Into<String>,
+        body: S,
+        idle_timeout: Option<Duration>,
+    ) -> Self
+    where
+        S: Stream<Item = Result<Bytes>> + Send + 'static,
+    {
+        let prediction_id = prediction_id.into();
+        let state = ParseState {
+            prediction_id: prediction_id.clone(),
+            body: Box::pin(body),
+            parser: SseParser::default(),
+            pending: VecDeque::new(),
+            finished: false,
+        };
+
+        let inner = stream::unfold(state, move |mut state| async move {
+            let deadline = idle_timeout.map(|idle| Instant::now() + idle);
+            loop {
+                if state.finished {
+                    return None;
+                }
+                if let Some(event) = state.pending.pop_front() {
+                    state.finished = event.is_done();
+                    return Some((Ok(event), state));
+                }
+                let next = match deadline {
+                    Some(deadline) => match timeout_at(deadline, state.body.next()).await {
+                        Ok(next) => next,
+                        Err(_) => {
+                            state.finished = true;
+                            let idle = idle_timeout.unwrap_or_default();
+                            return Some((
+                                Err(Error::timeout(format!(
+                                    "No stream event received within {:?}",
+                                    idle
+                                ))),
+                                state,
+                            ));
+                        }
+                    },
+                    None => state.body.next().await,
+                };
+                match next {
+                    Some(Ok(chunk)) => state.pending.extend(state.parser.feed(&chunk)),
+                    Some(Err(error)) => {
+                        state.finished = true;
+                        return Some((Err(error), state));
+                    }
+                    None => {
+                        state.finished = true;
+                        return Some((
+                            Err(Error::StreamClosed {
+                                prediction_id: state.prediction_id.clone(),
+                            }),
+                            state,
+                        ));
+                    }
+                }
+            }
+        });
+
+        Self {
+            prediction_id,
+            inner: Box::pin(inner),
+        }
+    }
+
+    /// ID of the prediction being streamed.
+    pub fn prediction_id(&self) -> &str {
+        &self.prediction_id
+    }
+
+    /// Consume the stream and concatenate all output events.
+    ///
+    /// The first transport error is returned as-is. An error event, or a stream
+    /// that finishes early (e.g. because the prediction was canceled), becomes
+    /// [`Error::ModelExecution`] carrying the logs received so far.
+    pub async fn collect_output(mut self) -> Result<String> {
+        let mut output = String::new();
+        let mut logs = String::new();
+
+        while let Some(event) = self.next().await {
+            match event? {
+                StreamEvent::Output(chunk) => output.push_str(&chunk),
+                StreamEvent::Logs(chunk) => {
+                    logs.push_str(&chunk);
+                    logs.push('\n');
+                }
+                StreamEvent::Error(message) => {
+                    return Err(self.failure(message, logs));
+                }
+                StreamEvent::Done {
+                    reason: Some(reason),
+                } => {
+                    return Err(self.failure(format!("Prediction {}", reason), logs));
+                }
+                StreamEvent::Done { reason: None } => break,
+            }
+        }
+
+        Ok(output)
+    }
+
+    /// Parse the output as JSON lines, yielding one value per document.
+    ///
+    /// For models that stream progress as newline-delimited JSON. Output
+    /// events are joined and split on newlines, so a document may span
+    /// several events; one that ends an event without a newline is parsed
+    /// as soon as it is a valid document, so consecutive scalars are never
+    /// joined. Lines that fail to parse yield an [`Error::Deserialize`] and
+    /// the stream goes on; see [`JsonObjects::skip_invalid`]. Error and early done
+    /// events end the stream like in [`collect_output`](Self::collect_output).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use futures::StreamExt;
+    /// # use replicate_client::Client;
+    /// #[derive(serde::Deserialize)]
+    /// struct Progress {
+    ///     percent: f64,
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::new("your-api-token")?;
+    /// let mut progress = client
+    ///     .create_prediction("acme/video-upscaler")
+    ///     .input("video", "https://example.com/clip.mp4")
+    ///     .send_and_stream()
+    ///     .await?
+    ///     .json_objects::<Progress>();
+    /// while let Some(update) = progress.next().await {
+    ///     println!("{:.0}%", update?.percent);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn json_objects<T: DeserializeOwned>(self) -> JsonObjects<T> {
+        JsonObjects {
+            events: self,
+            buffer: String::new(),
+            logs: String::new(),
+            pending: VecDeque::new(),
+            finished: false,
+            skip_invalid: false,
+            _marker: PhantomData,
+        }
+    }
+
+    fn failure(&self, message: String, logs: String) -> Error {
+        let logs = (!logs.is_empty()).then_some(logs);
+        Error::model_execution(&self.prediction_id, Some(message), logs)
+    }
+}
+
+/// Stream of JSON documents parsed from a prediction's output, returned by
+/// [`PredictionStream::json_objects`].
+pub struct JsonObjects<T> {
+    events: PredictionStream,
+    buffer: String,
+    logs: String,
+    pending: VecDeque<Result<T>>,
+    finished: bool,
+    skip_invalid: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+/// Longest excerpt of a skipped line quoted in the log, in characters.
+const LINE_EXCERPT_LEN: usize = 80;
+
+impl<T: DeserializeOwned> JsonObjects<T> {
+    /// Skip lines that are not valid documents instead of yielding errors;
+    /// they are logged at debug level.
+    pub fn skip_invalid(mut self, skip: bool) -> Self {
+        self.skip_invalid = skip;
+        self
+    }
+
+    /// ID of the prediction being streamed.
+    pub fn prediction_id(&self) -> &str {
+        self.events.prediction_id()
+    }
+
+    /// Parse the complete lines in the buffer, and with `flush` the rest too.
+    fn parse_buffered(&mut self, flush: bool) {
+        while let Some(end) = self.buffer.find('\n') {
+            let line: String = self.buffer.drain(..=end).collect();
+            self.parse_line(&line);
+        }
+        // A valid document that ends an event is complete, including a scalar
+        // that the next event could otherwise extend, e.g. `1` then `2`
+        let complete = serde_json::from_str::<serde::de::IgnoredAny>(&self.buffer).is_ok();
+        if flush || complete {
+            let rest = std::mem::take(&mut self.buffer);
+            self.parse_line(&rest);
+        }
+    }
+
+    fn parse_line(&mut self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        match parse_document(line) {
+            Ok(value) => self.pending.push_back(Ok(value)),
+            Err(e) if self.skip_invalid => {
+                let mut excerpt: String = line.chars().take(LINE_EXCERPT_LEN).collect();
+                if excerpt.len() < line.len() {
+                    excerpt.push_str("...");
+                }
+                tracing::debug!("Skipping invalid JSON output line ({}): {}", e, excerpt);
+            }
+            Err(e) => self.pending.push_back(Err(e)),
+        }
+    }
+
+    /// End the stream with `error` after the documents parsed so far.
+    fn finish_with(&mut self, error: Option<Error>) {
+        self.parse_buffered(true);
+        self.pending.extend(error.map(Err));
+        self.finished = true;
+    }
+}
+
+/// Deserialize one JSON document, reporting the JSON path on failure.
+fn parse_document<T: DeserializeOwned>(text: &str) -> Result<T> {
+    let mut deserializer = serde_json::Deserializer::from_str(text);
+    let value =
+        serde_path_to_error::deserialize(&mut deserializer).map_err(|e| Error::Deserialize {
+            path: e.path().to_string(),
+            source: e.into_inner(),
+        })?;
+    deserializer.end().map_err(|source| Error::Deserialize {
+        path: ".".to_string(),
+        source,
+    })?;
+    Ok(value)
+}
+
+// Values of `T` are only ever moved out, never pinned
+impl<T> Unpin for JsonObjects<T> {}
+
+impl<T: DeserializeOwned> Stream for JsonObjects<T> {
+    type Item = Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(item) = this.pending.pop_front() {
+                return Poll::Ready(Some(item));
+            }
+            if this.finished {
+                return Poll::Ready(None);
+            }
+            match ready!(this.events.poll_next_unpin(cx)) {
+                Some(Ok(StreamEvent::Output(chunk))) => {
+                    this.buffer.push_str(&chunk);
+                    this.parse_buffered(false);
+                }
+                Some(Ok(StreamEvent::Logs(chunk))) => {
+                    this.logs.push_str(&chunk);
+                    this.logs.push('\n');
+                }
+                Some(Ok(StreamEvent::Error(message))) => {
+                    let error = this.events.failure(message, std::mem::take(&mut this.logs));
+                    this.finish_with(Some(error));
+                }
+                Some(Ok(StreamEvent::Done {
+                    reason: Some(reason),
+                })) => {
+                    let error = this.events.failure(
+                        format!("Prediction {}", reason),
+                        std::mem::take(&mut this.logs),
+                    );
+                    this.finish_with(Some(error));
+                }
+                Some(Ok(StreamEvent::Done { reason: None })) | None => this.finish_with(None),
+                Some(Err(error)) => this.finish_with(Some(error)),
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for JsonObjects<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JsonObjects")
+            .field("prediction_id", &self.events.prediction_id)
+            .field("skip_invalid", &self.skip_invalid)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Stream for PredictionStream {
+    type Item = Result<StreamEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
+
+impl fmt::Debug for PredictionStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PredictionStream")
+            .field("prediction_id", &self.prediction_id)
+            .finish_non_exhaustive()
+    }
+}
+
+struct ParseState {
+    prediction_id: String,
+    body: Pin<Box<dyn Stream<Item = Result<Bytes>> + Send>>,
+    parser: SseParser,
+    pending: VecDeque<StreamEvent>,
+    finished: bool,
+}
+
+/// Incremental parser for the `text/event-stream` format.
+#[derive(Debug, Default)]
+struct SseParser {
+    buffer: BytesMut,
+    event: Option<String>,
+    data: Option<String>,
+}
+
+impl SseParser {
+    /// Feed a chunk of the body and return the events it completed.
+    fn feed(&mut self, chunk: &[u8]) -> Vec<StreamEvent> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line = self.buffer.split_to(end);
+            self.buffer.advance(1);
+
+            let line = String::from_utf8_lossy(&line);
+            let line = line.strip_suffix('\r').unwrap_or(&line);
+            if let Some(event) = self.process_line(line) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<StreamEvent> {
+        if line.is_empty() {
+            let event = self.event.take();
+            let data = self.data.take()?;
+            return StreamEvent::from_sse(event.as_deref().unwrap_or("message"), data);
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => match &mut self.data {
+                Some(data) => {
+                    data.push('\n');
+                    data.push_str(value);
+                }
+                None => self.data = Some(value.to_string()),
+            },
+            _ => {}
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn stream_of(chunks: &[&'static str]) -> PredictionStream {
+        let chunks: Vec<Result<Bytes>> = chunks
+            .iter()
+            .map(|chunk| Ok(Bytes::from_static(chunk.as_bytes())))
+            .collect();
+        PredictionStream::from_bytes("p1", stream::iter(chunks), None)
+    }
+
+    #[test]
+    fn test_parser_handles_split_chunks_and_multiline_data() {
+        let mut parser = SseParser::default();
+        assert!(parser.feed(b": keep-alive\r\nevent: out").is_empty());
+        let events =
+            parser.feed(b"put\r\ndata: Hello\r\ndata: world\r\n\r\nevent: done\ndata: {}\n\n");
+        assert_eq!(
+            events,
+            vec![
+                StreamEvent::Output("Hello\nworld".into()),
+                StreamEvent::Done { reason: None },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_collect_output() {
+        let stream = stream_of(&[
+            "event: output\ndata: Hel",
+            "lo\n\nevent: logs\ndata: step 1\n\n",
+            "event: output\ndata: , world\n\n",
+            "event: done\ndata: {}\n\n",
+        ]);
+        assert_eq!(stream.collect_output().await.unwrap(), "Hello, world");
+    }
+
+    #[tokio::test]
+    async fn test_collect_output_propagates_errors() {
+        let stream = stream_of(&[
+            "event: logs\ndata: loading\n\n",
+            "event: error\ndata: CUDA out of memory\n\n",
+            "event: done\ndata: {}\n\n",
+        ]);
+        match stream.collect_output().await {
+            Err(Error::ModelExecution {
+                prediction_id,
+                error_message,
+                logs,
+            }) => {
+                assert_eq!(prediction_id, "p1");
+                assert_eq!(error_message.as_deref(), Some("CUDA out of memory"));
+                assert_eq!(logs.as_deref(), Some("loading\n"));
+            }
+            other => panic!("Expected a model execution error, got {:?}", other),
+        }
+
+        let canceled = stream_of(&["event: done\ndata: {\"reason\": \"canceled\"}\n\n"]);
+        assert!(matches!(
+            canceled.collect_output().await,
+            Err(Error::ModelExecution { .. })
+        ));
+
+        let truncated = stream_of(&["event: output\ndata: partial\n\n"]);
+        assert!(matches!(
+            truncated.collect_output().await,
+            Err(Error::StreamClosed { prediction_id }) if prediction_id == "p1"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_json_objects_reassemble_fragments() {
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct Progress {
+            step: u32,
+        }
+
+        let stream = stream_of(&[
+            "event: output\ndata: {\"step\": 1}\n\n",
+            "event: output\ndata: {\"st\n\n",
+            "event: logs\ndata: encoding\n\n",
+            "event: output\ndata: ep\": 2}\ndata: not json\ndata: {\"step\"\n\n",
+            "event: output\ndata: : 3}\n\nevent: output\ndata: {\"step\": 4}\n\n",
+            "event: done\ndata: {}\n\n",
+        ]);
+        let items: Vec<Result<Progress>> = stream.json_objects().collect().await;
+        assert_eq!(items.len(), 5);
+        assert_eq!(items[0].as_ref().unwrap(), &Progress { step: 1 });
+        assert_eq!(items[1].as_ref().unwrap(), &Progress { step: 2 });
+        assert!(
+            matches!(&items[2], Err(Error::Deserialize { .. })),
+            "{:?}",
+            items[2]
+        );
+        assert_eq!(items[3].as_ref().unwrap(), &Progress { step: 3 });
+        assert_eq!(items[4].as_ref().unwrap(), &Progress { step: 4 });
+
+        let skipping = stream_of(&[
+            "event: output\ndata: oops\ndata: {\"step\": 1}\n\n",
+            "event: error\ndata: CUDA out of memory\n\n",
+        ])
+        .json_objects::<Progress>()
+        .skip_invalid(true);
+        let items: Vec<_> = skipping.collect().await;
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].as_ref().unwrap(), &Progress { step: 1 });
+        assert!(matches!(items[1], Err(Error::ModelExecution { .. })));
+
+        // Scalars that end an event are not joined with the next one
+        let scalars = stream_of(&[
+            "event: output
+data: 1
+
+",
+            "event: output
+data: 2
+
+",
+            "event: output
+data: \"a\"
+
+",
+            "event: output
+data: \"b\"
+
+",
+            "event: output
+data: 3.
+
+",
+            "event: output
+data: 5
+
+",
+            "event: done
+data: {}
+
+",
+        ]);
+        let items: Vec<serde_json::Value> =
+            scalars.json_objects().map(Result::unwrap).collect().await;
+        assert_eq!(
+            items,
+            [json!(1), json!(2), json!("a"), json!("b"), json!(3.5)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout() {
+        let body = stream::iter([Ok(Bytes::from_static(b"event: output\ndata: Hi\n\n"))])
+            .chain(stream::iter([Ok(Bytes::from_static(b": keep-alive\n"))]))
+            .chain(stream::pending());
+        let mut stream = PredictionStream::from_bytes("p1", body, Some(Duration::from_millis(50)));
+
+        assert_eq!(
+            stream.next().await.unwrap().unwrap(),
+            StreamEvent::Output("Hi".into())
+        );
+        let started = Instant::now();
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(Error::Timeout { .. }))
+        ));
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert!(stream.next().await.is_none());
+    }
+}
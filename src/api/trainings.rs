@@ -0,0 +1,213 @@
+//! Trainings API implementation.
+
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::api::files::{FilesApi, process_file_inputs};
+use crate::error::Result;
+use crate::http::HttpClient;
+use crate::models::{
+    common::PaginatedResponse,
+    file::{FileEncodingStrategy, FileInput},
+    id::TrainingId,
+    training::{CreateTrainingRequest, Training},
+};
+
+/// API for managing trainings.
+#[derive(Debug, Clone)]
+pub struct TrainingsApi {
+    http: HttpClient,
+    files_api: Option<FilesApi>,
+}
+
+impl TrainingsApi {
+    /// Create a new trainings API instance.
+    pub fn new(http: HttpClient) -> Self {
+        Self {
+            http: http.clone(),
+            files_api: Some(FilesApi::new(http)),
+        }
+    }
+
+    /// Create a copy of this API that sends requests through another HTTP client.
+    pub(crate) fn with_http(&self, http: HttpClient) -> Self {
+        Self::new(http)
+    }
+
+    /// Create a new training.
+    pub async fn create(&self, mut request: CreateTrainingRequest) -> Result<Training> {
+        request.validate()?;
+
+        let processed = process_file_inputs(
+            &request.file_inputs,
+            &request.file_encoding_strategy,
+            self.files_api.as_ref(),
+        )
+        .await?;
+        request.input.extend(processed);
+
+        let (owner, name, version_id) = request.version_parts()?;
+        let path = format!(
+            "/v1/models/{}/{}/versions/{}/trainings",
+            owner, name, version_id
+        );
+        self.http.post_json(&path, &request).await
+    }
+
+    /// Get a training by ID.
+    pub async fn get(&self, id: impl Into<TrainingId>) -> Result<Training> {
+        self.http
+            .get_json(&format!("/v1/trainings/{}", id.into()))
+            .await
+    }
+
+    /// List trainings with optional pagination.
+    pub async fn list(&self, cursor: Option<&str>) -> Result<PaginatedResponse<Training>> {
+        let path = match cursor {
+            Some(cursor) => cursor.to_string(),
+            None => "/v1/trainings".to_string(),
+        };
+        self.http.get_json(&path).await
+    }
+
+    /// Cancel a training.
+    pub async fn cancel(&self, id: impl Into<TrainingId>) -> Result<Training> {
+        self.http
+            .post_empty_json(&format!("/v1/trainings/{}/cancel", id.into()))
+            .await
+    }
+}
+
+/// Builder for creating trainings with a fluent API.
+#[derive(Debug)]
+pub struct TrainingBuilder {
+    api: TrainingsApi,
+    request: CreateTrainingRequest,
+}
+
+impl TrainingBuilder {
+    /// Create a new training builder for a model version (`owner/name:version`).
+    pub fn new(api: TrainingsApi, version: impl Into<String>) -> Self {
+        Self {
+            api,
+            request: CreateTrainingRequest::new(version),
+        }
+    }
+
+    /// Set the destination model for the trained version.
+    pub fn destination(mut self, owner: &str, name: &str) -> Self {
+        self.request = self.request.with_destination(owner, name);
+        self
+    }
+
+    /// Add an input parameter.
+    pub fn input<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<Value>,
+    {
+        self.request = self.request.with_input(key, value);
+        self
+    }
+
+    /// Add multiple input parameters from a HashMap.
+    ///
+    /// Keys are added in sorted order so the serialized request is deterministic.
+    pub fn inputs(mut self, inputs: HashMap<String, Value>) -> Self {
+        let mut inputs: Vec<_> = inputs.into_iter().collect();
+        inputs.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (key, value) in inputs {
+            self.request = self.request.with_input(key, value);
+        }
+        self
+    }
+
+    /// Add a file input parameter, such as a training data archive.
+    pub fn file_input<K>(mut self, key: K, file: FileInput) -> Self
+    where
+        K: Into<String>,
+    {
+        self.request.file_inputs.insert(key.into(), file);
+        self
+    }
+
+    /// Add a file input with specific encoding strategy.
+    pub fn file_input_with_strategy<K>(
+        mut self,
+        key: K,
+        file: FileInput,
+        strategy: FileEncodingStrategy,
+    ) -> Self
+    where
+        K: Into<String>,
+    {
+        self.request.file_inputs.insert(key.into(), file);
+        self.request.file_encoding_strategy = strategy;
+        self
+    }
+
+    /// Set a webhook URL.
+    pub fn webhook(mut self, webhook: impl Into<String>) -> Self {
+        self.request = self.request.with_webhook(webhook);
+        self
+    }
+
+    /// Send the training request.
+    ///
+    /// The version and destination are validated before any request is made.
+    pub async fn send(self) -> Result<Training> {
+        self.api.create(self.request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_training_builder_send() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/models/ostris/trainer/versions/v1/trainings"))
+            .and(body_json(json!({
+                "destination": "me/my-lora",
+                "input": {"steps": 1000, "input_images": "https://example.com/data.zip"},
+                "webhook": "https://example.com/hook",
+            })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "id": "t1",
+                "model": "ostris/trainer",
+                "version": "v1",
+                "status": "starting",
+            })))
+            .mount(&server)
+            .await;
+
+        let http = HttpClient::with_base_url("test-token", server.uri()).unwrap();
+        let training = TrainingBuilder::new(TrainingsApi::new(http), "ostris/trainer:v1")
+            .destination("me", "my-lora")
+            .input("steps", 1000)
+            .input("input_images", "https://example.com/data.zip")
+            .webhook("https://example.com/hook")
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(training.id, "t1");
+    }
+
+    #[tokio::test]
+    async fn test_training_builder_rejects_bad_destination() {
+        let http = HttpClient::new("test-token").unwrap();
+        let result = TrainingBuilder::new(TrainingsApi::new(http), "ostris/trainer:v1")
+            .destination("me", "not/valid")
+            .send()
+            .await;
+
+        assert!(matches!(result, Err(Error::InvalidInput(_))));
+    }
+}
@@ -0,0 +1,538 @@
+//! Shared polling loop for waiting on many predictions at once.
+//!
+//! Every call to [`PredictionsApi::wait_for_completion`] runs its own polling
+//! loop, so waiting on hundreds of predictions multiplies the request rate.
+//! A [`PredictionWatcher`] instead runs a single background task that polls all
+//! registered predictions under a global request budget and hands each result
+//! to the callers waiting on it.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::future::join_all;
+use tokio::sync::oneshot;
+use tokio::time::{Instant, interval, timeout};
+
+use crate::api::predictions::PredictionsApi;
+use crate::error::{Error, Result};
+use crate::models::prediction::{Prediction, PredictionStatus};
+
+/// Configuration for a [`PredictionWatcher`].
+#[derive(Debug, Clone)]
+pub struct WatcherConfig {
+    /// Maximum number of polling requests per second across all watched predictions
+    pub max_requests_per_second: u32,
+    /// Number of predictions polled concurrently on each tick
+    pub batch_size: usize,
+    /// Minimum time between two polls of the same prediction
+    pub poll_interval: Duration,
+}
+
+impl Default for WatcherConfig {
+    fn default() -> Self {
+        Self {
+            max_requests_per_second: 10,
+            batch_size: 5,
+            poll_interval: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Transport failures in a row after which a prediction is given up on.
+///
+/// Each failure already went through the retry middleware.
+const MAX_TRANSPORT_FAILURES: u32 = 5;
+
+type Waiter = oneshot::Sender<Result<Prediction>>;
+
+#[derive(Debug, Default)]
+struct WatcherState {
+    /// Callers waiting on each prediction ID
+    waiters: HashMap<String, Vec<Waiter>>,
+    /// Prediction IDs in polling order, with the earliest time they may be polled again
+    queue: VecDeque<(String, Instant)>,
+    /// Last status fetched for each prediction that is still running
+    last_status: HashMap<String, PredictionStatus>,
+    /// Transport failures in a row for each prediction, while below the limit
+    failures: HashMap<String, u32>,
+    /// Whether the background polling task is running
+    running: bool,
+}
+
+#[derive(Debug)]
+struct WatcherInner {
+    api: PredictionsApi,
+    config: WatcherConfig,
+    state: Mutex<WatcherState>,
+}
+
+/// Multiplexes many prediction waits onto a single rate-limited polling loop.
+///
+/// The background task is started when the first prediction is registered and
+/// exits once nothing is left to watch.
+#[derive(Debug, Clone)]
+pub struct PredictionWatcher {
+    inner: Arc<WatcherInner>,
+}
+
+impl PredictionWatcher {
+    /// Create a new watcher that polls through the given API.
+    pub fn new(api: PredictionsApi, config: WatcherConfig) -> Self {
+        Self {
+            inner: Arc::new(WatcherInner {
+                api,
+                config,
+                state: Mutex::new(WatcherState::default()),
+            }),
+        }
+    }
+
+    /// Get the watcher configuration.
+    pub fn config(&self) -> &WatcherConfig {
+        &self.inner.config
+    }
+
+    /// Number of distinct predictions currently being watched.
+    pub fn watched_count(&self) -> usize {
+        self.inner.state.lock().unwrap().waiters.len()
+    }
+
+    /// Register a prediction and receive its terminal state through a channel.
+    ///
+    /// Failed predictions are delivered as [`Error::ModelExecution`], matching
+    /// [`PredictionsApi::wait_for_completion`]. Transport errors are retried on
+    /// later ticks, and delivered once polling failed five times in a row.
+    /// Dropping the receiver stops watching once no other caller waits on the
+    /// same prediction.
+    pub fn watch(&self, id: impl Into<String>) -> oneshot::Receiver<Result<Prediction>> {
+        let id = id.into();
+        let (tx, rx) = oneshot::channel();
+
+        let mut state = self.inner.state.lock().unwrap();
+        let waiters = state.waiters.entry(id.clone()).or_default();
+        let is_new = waiters.is_empty();
+        waiters.push(tx);
+        if is_new {
+            state.queue.push_back((id, Instant::now()));
+        }
+        if !state.running {
+            state.running = true;
+            tokio::spawn(Self::run(self.inner.clone()));
+        }
+
+        rx
+    }
+
+    /// Wait for a prediction to reach a terminal state through the shared loop.
+    ///
+    /// Fails with [`Error::WatcherStopped`] if the polling task ended without
+    /// delivering a result, e.g. because it panicked.
+    pub async fn wait(&self, id: &str, max_duration: Option<Duration>) -> Result<Prediction> {
+        let rx = self.watch(id);
+        let received = match max_duration {
+            Some(duration) => timeout(duration, rx).await.map_err(|_| {
+                let last_status = self
+                    .inner
+                    .state
+                    .lock()
+                    .unwrap()
+                    .last_status
+                    .get(id)
+                    .cloned();
+                Error::wait_timeout(
+                    id,
+                    last_status,
+                    format!("Prediction {} did not complete within {:?}", id, duration),
+                )
+            })?,
+            None => rx.await,
+        };
+
+        received.map_err(|_| Error::WatcherStopped {
+            prediction_id: id.to_string(),
+        })?
+    }
+
+    /// Background polling loop.
+    async fn run(inner: Arc<WatcherInner>) {
+        let batch_size = inner.config.batch_size.max(1);
+        let per_second = inner.config.max_requests_per_second.max(1);
+        let tick = Duration::from_secs(1) * batch_size as u32 / per_second;
+        let mut ticker = interval(tick.max(Duration::from_millis(1)));
+
+        loop {
+            ticker.tick().await;
+
+            let batch = {
+                let mut state = inner.state.lock().unwrap();
+                if state.waiters.is_empty() {
+                    state.running = false;
+                    state.queue.clear();
+                    state.last_status.clear();
+                    state.failures.clear();
+                    return;
+                }
+                Self::next_batch(&mut state, batch_size)
+            };
+            if batch.is_empty() {
+                continue;
+            }
+
+            let results = join_all(batch.iter().map(|id| inner.api.get(id))).await;
+
+            let mut state = inner.state.lock().unwrap();
+            let next_poll = Instant::now() + inner.config.poll_interval;
+            for (id, result) in batch.into_iter().zip(results) {
+                match result {
+                    Ok(prediction) if prediction.status.is_terminal() => {
+                        state.last_status.remove(&id);
+                        state.failures.remove(&id);
+                        let waiters = state.waiters.remove(&id).unwrap_or_default();
+                        for waiter in waiters {
+                            let result = if prediction.is_failed() {
+                                Err(Error::model_execution(
+                                    &id,
+                                    prediction.error.clone(),
+                                    prediction.logs.clone(),
+                                ))
+                            } else {
+                                Ok(prediction.clone())
+                            };
+                            let _ = waiter.send(result);
+                        }
+                    }
+                    Ok(prediction) => {
+                        state.failures.remove(&id);
+                        state.last_status.insert(id.clone(), prediction.status);
+                        state.queue.push_back((id, next_poll));
+                    }
+                    // Transport failures already went through the retry
+                    // middleware; try again on a later tick, up to a limit.
+                    Err(Error::Http(_)) | Err(Error::HttpMiddleware(_))
+                        if Self::record_failure(&mut state, &id) =>
+                    {
+                        state.queue.push_back((id, next_poll));
+                    }
+                    Err(error) => {
+                        state.last_status.remove(&id);
+                        state.failures.remove(&id);
+                        let mut waiters = state.waiters.remove(&id).unwrap_or_default();
+                        for waiter in waiters.drain(1..) {
+                            let _ = waiter.send(Err(duplicate_error(&id, &error)));
+                        }
+                        if let Some(waiter) = waiters.pop() {
+                            let _ = waiter.send(Err(error));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Count a transport failure for a prediction, returning whether it is
+    /// still below [`MAX_TRANSPORT_FAILURES`].
+    fn record_failure(state: &mut WatcherState, id: &str) -> bool {
+        let failures = state.failures.entry(id.to_string()).or_default();
+        *failures += 1;
+        *failures < MAX_TRANSPORT_FAILURES
+    }
+
+    /// Take up to `batch_size` predictions that are due for polling.
+    ///
+    /// Predictions whose callers have all gone away are dropped here.
+    fn next_batch(state: &mut WatcherState, batch_size: usize) -> Vec<String> {
+        let now = Instant::now();
+        let mut batch = Vec::with_capacity(batch_size);
+
+        for _ in 0..state.queue.len() {
+            if batch.len() >= batch_size {
+                break;
+            }
+            let Some((id, due)) = state.queue.pop_front() else {
+                break;
+            };
+
+            let abandoned = match state.waiters.get_mut(&id) {
+                Some(waiters) => {
+                    waiters.retain(|w| !w.is_closed());
+                    waiters.is_empty()
+                }
+                None => true,
+            };
+            if abandoned {
+                state.waiters.remove(&id);
+                state.last_status.remove(&id);
+                state.failures.remove(&id);
+            } else if due <= now {
+                batch.push(id);
+            } else {
+                state.queue.push_back((id, due));
+            }
+        }
+
+        batch
+    }
+}
+
+/// Copy of an error for delivery to additional waiters on prediction `id`.
+///
+/// Errors are copied with their variant; those that cannot be, such as
+/// transport errors, become [`Error::WatchFailed`].
+fn duplicate_error(id: &str, error: &Error) -> Error {
+    use serde::de::Error as _;
+
+    match error {
+        Error::Http(_) | Error::HttpMiddleware(_) => Error::WatchFailed {
+            prediction_id: id.to_string(),
+            message: error.to_string(),
+        },
+        Error::Json(source) => Error::Json(serde_json::Error::custom(source)),
+        Error::Deserialize { path, source } => Error::Deserialize {
+            path: path.clone(),
+            source: serde_json::Error::custom(source),
+        },
+        Error::Decode {
+            status,
+            content_type,
+            snippet,
+        } => Error::Decode {
+            status: *status,
+            content_type: content_type.clone(),
+            snippet: snippet.clone(),
+        },
+        Error::Api {
+            status,
+            message,
+            detail,
+        } => Error::Api {
+            status: *status,
+            message: message.clone(),
+            detail: detail.clone(),
+        },
+        Error::Auth(message) => Error::Auth(message.clone()),
+        Error::InvalidInput(message) => Error::InvalidInput(message.clone()),
+        Error::File(source) => Error::File(std::io::Error::new(source.kind(), source.to_string())),
+        Error::Url(source) => Error::Url(*source),
+        Error::ModelExecution {
+            prediction_id,
+            error_message,
+            logs,
+        } => Error::ModelExecution {
+            prediction_id: prediction_id.clone(),
+            error_message: error_message.clone(),
+            logs: logs.clone(),
+        },
+        Error::Timeout {
+            message,
+            prediction_id,
+            last_status,
+            kind,
+        } => Error::Timeout {
+            message: message.clone(),
+            prediction_id: prediction_id.clone(),
+            last_status: last_status.clone(),
+            kind: *kind,
+        },
+        Error::DataRemoved { prediction_id } => Error::DataRemoved {
+            prediction_id: prediction_id.clone(),
+        },
+        Error::PartialRecord { prediction_id } => Error::PartialRecord {
+            prediction_id: prediction_id.clone(),
+        },
+        Error::WatcherStopped { prediction_id } => Error::WatcherStopped {
+            prediction_id: prediction_id.clone(),
+        },
+        Error::StreamClosed { prediction_id } => Error::StreamClosed {
+            prediction_id: prediction_id.clone(),
+        },
+        Error::WatchFailed {
+            prediction_id,
+            message,
+        } => Error::WatchFailed {
+            prediction_id: prediction_id.clone(),
+            message: message.clone(),
+        },
+        Error::ClientClosed => Error::ClientClosed,
+        Error::UploadAborted => Error::UploadAborted,
+        Error::AlreadyExists(message) => Error::AlreadyExists(message.clone()),
+        Error::Unsupported(message) => Error::Unsupported(message.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::HttpClient;
+    use serde_json::json;
+    use wiremock::matchers::{method, path_regex};
+    use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+    /// Reports predictions as processing until `ready_after` has elapsed.
+    struct DelayedCompletion {
+        started: std::time::Instant,
+        ready_after: Duration,
+    }
+
+    impl Respond for DelayedCompletion {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            let id = request.url.path().rsplit('/').next().unwrap().to_string();
+            let status = if self.started.elapsed() >= self.ready_after {
+                "succeeded"
+            } else {
+                "processing"
+            };
+            ResponseTemplate::new(200).set_body_json(json!({
+                "id": id,
+                "model": "owner/model",
+                "version": "v1",
+                "status": status,
+            }))
+        }
+    }
+
+    async fn start_server(ready_after: Duration) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path_regex("^/v1/predictions/[^/]+$"))
+            .respond_with(DelayedCompletion {
+                started: std::time::Instant::now(),
+                ready_after,
+            })
+            .mount(&server)
+            .await;
+        server
+    }
+
+    fn api_for(server: &MockServer) -> PredictionsApi {
+        PredictionsApi::new(HttpClient::with_base_url("test-token", server.uri()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_shared_watcher_reduces_requests() {
+        const WAITS: usize = 20;
+        let ready_after = Duration::from_millis(600);
+        let poll_interval = Duration::from_millis(50);
+
+        // Independent polling loops
+        let server = start_server(ready_after).await;
+        let api = api_for(&server);
+        let waits = (0..WAITS).map(|i| {
+            let api = api.clone();
+            async move {
+                api.wait_for_completion(&format!("p{}", i), None, Some(poll_interval))
+                    .await
+            }
+        });
+        for result in join_all(waits).await {
+            assert!(result.unwrap().is_successful());
+        }
+        let independent = server.received_requests().await.unwrap().len();
+
+        // Shared watcher
+        let server = start_server(ready_after).await;
+        let watcher = PredictionWatcher::new(
+            api_for(&server),
+            WatcherConfig {
+                max_requests_per_second: 40,
+                batch_size: 10,
+                poll_interval,
+            },
+        );
+        let waits = (0..WAITS).map(|i| {
+            let watcher = watcher.clone();
+            async move { watcher.wait(&format!("p{}", i), None).await }
+        });
+        for result in join_all(waits).await {
+            assert!(result.unwrap().is_successful());
+        }
+        let shared = server.received_requests().await.unwrap().len();
+
+        assert!(
+            shared * 4 < independent,
+            "shared watcher made {} requests, independent loops made {}",
+            shared,
+            independent
+        );
+        assert_eq!(watcher.watched_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_waits_share_polls() {
+        let server = start_server(Duration::from_millis(100)).await;
+        let watcher = PredictionWatcher::new(api_for(&server), WatcherConfig::default());
+
+        let first = watcher.watch("same");
+        let second = watcher.watch("same");
+        assert_eq!(watcher.watched_count(), 1);
+
+        assert!(first.await.unwrap().unwrap().is_successful());
+        assert!(second.await.unwrap().unwrap().is_successful());
+    }
+
+    #[tokio::test]
+    async fn test_watcher_timeout() {
+        let server = start_server(Duration::from_secs(60)).await;
+        let watcher = PredictionWatcher::new(api_for(&server), WatcherConfig::default());
+
+        let result = watcher.wait("slow", Some(Duration::from_millis(100))).await;
+        match result.unwrap_err() {
+            Error::Timeout {
+                prediction_id,
+                last_status,
+                ..
+            } => {
+                assert_eq!(prediction_id.as_deref(), Some("slow"));
+                assert_eq!(last_status, Some(PredictionStatus::Processing));
+            }
+            other => panic!("Expected a timeout, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_watcher_gives_up_after_transport_failures() {
+        // Nothing listens on a port that was just released
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let mut http = HttpClient::with_base_url("test-token", url).unwrap();
+        http.configure_retries(0, Duration::from_millis(1), Duration::from_millis(1))
+            .unwrap();
+        let watcher = PredictionWatcher::new(
+            PredictionsApi::new(http),
+            WatcherConfig {
+                poll_interval: Duration::from_millis(1),
+                ..WatcherConfig::default()
+            },
+        );
+
+        let first = watcher.watch("p1");
+        let second = watcher.watch("p1");
+        let first = timeout(Duration::from_secs(10), first).await.unwrap();
+        assert!(matches!(
+            first.unwrap(),
+            Err(Error::Http(_)) | Err(Error::HttpMiddleware(_))
+        ));
+        match second.await.unwrap() {
+            Err(Error::WatchFailed { prediction_id, .. }) => assert_eq!(prediction_id, "p1"),
+            other => panic!("Expected a watch failure, got {:?}", other.map(|_| ())),
+        }
+        assert_eq!(watcher.watched_count(), 0);
+    }
+
+    #[test]
+    fn test_duplicate_error_keeps_variant() {
+        let io = Error::File(std::io::Error::new(std::io::ErrorKind::NotFound, "missing"));
+        assert!(matches!(
+            duplicate_error("p1", &io),
+            Error::File(source) if source.kind() == std::io::ErrorKind::NotFound
+        ));
+        let removed = duplicate_error("p1", &Error::data_removed("p1"));
+        assert!(matches!(removed, Error::DataRemoved { prediction_id } if prediction_id == "p1"));
+        let json = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        assert!(matches!(
+            duplicate_error("p1", &Error::Json(json)),
+            Error::Json(_)
+        ));
+    }
+}
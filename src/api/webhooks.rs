@@ -0,0 +1,915 @@
+//! Webhooks API and helpers.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use base64::{Engine as _, engine::general_purpose};
+use reqwest::header::HeaderMap;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, Result};
+use crate::http::HttpClient;
+use crate::models::prediction::Prediction;
+
+/// ID used for the synthetic prediction sent by test deliveries.
+pub const TEST_DELIVERY_PREDICTION_ID: &str = "replicate-rs-test-delivery";
+
+/// Result of a successful test webhook delivery.
+#[derive(Debug, Clone)]
+pub struct WebhookDelivery {
+    /// The URL the test event was delivered to
+    pub url: String,
+    /// HTTP status returned by the receiver
+    pub status: u16,
+    /// Time taken for the receiver to respond
+    pub elapsed: Duration,
+}
+
+/// Default time allowed for each network step of a [`WebhookCheck`].
+const DEFAULT_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Client-side checks that a webhook URL can receive callbacks.
+///
+/// Replicate delivers webhooks on a best-effort basis, so a URL it cannot
+/// reach only shows up as notifications that never arrive. These checks
+/// catch the common mistakes before a prediction is created: the URL must
+/// parse, use `https` (or `http` when allowed), and have a host that
+/// resolves. An optional `HEAD` preflight additionally requires the receiver
+/// to answer; any status counts, since receivers usually only accept `POST`.
+///
+/// Passing the checks does not guarantee delivery, e.g. a URL that resolves
+/// only on a private network will still be unreachable for Replicate.
+#[derive(Debug, Clone)]
+pub struct WebhookCheck {
+    /// Accept `http` URLs, e.g. for a local receiver during development
+    pub allow_http: bool,
+    /// Send a `HEAD` request to the URL and require a response
+    pub preflight: bool,
+    /// Time allowed for the host lookup and for the preflight
+    pub timeout: Duration,
+}
+
+impl Default for WebhookCheck {
+    fn default() -> Self {
+        Self {
+            allow_http: false,
+            preflight: false,
+            timeout: DEFAULT_CHECK_TIMEOUT,
+        }
+    }
+}
+
+impl WebhookCheck {
+    /// Create checks that require https and a resolvable host.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept `http` URLs as well as `https` ones.
+    pub fn allow_http(mut self, allow: bool) -> Self {
+        self.allow_http = allow;
+        self
+    }
+
+    /// Require the receiver to answer a `HEAD` request.
+    pub fn preflight(mut self, enabled: bool) -> Self {
+        self.preflight = enabled;
+        self
+    }
+
+    /// Set the time allowed for the host lookup and for the preflight.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Run the checks on a webhook URL.
+    ///
+    /// Failures are returned as [`Error::InvalidInput`] with the reason.
+    pub async fn check(&self, url: &str) -> Result<()> {
+        let target = url::Url::parse(url).map_err(|e| {
+            Error::invalid_input(format!("Webhook URL '{}' is not a valid URL: {}", url, e))
+        })?;
+        match target.scheme() {
+            "https" => {}
+            "http" if self.allow_http => {}
+            _ => {
+                let allowed = match self.allow_http {
+                    true => "http or https",
+                    false => "https",
+                };
+                return Err(Error::invalid_input(format!(
+                    "Webhook URL '{}' must use {}",
+                    url, allowed
+                )));
+            }
+        }
+
+        let port = target.port_or_known_default().unwrap_or(443);
+        match target.host() {
+            Some(url::Host::Domain(host)) => self.resolve(host, port).await?,
+            Some(_) => {}
+            None => {
+                return Err(Error::invalid_input(format!(
+                    "Webhook URL '{}' has no host",
+                    url
+                )));
+            }
+        }
+
+        if self.preflight {
+            let client = reqwest::Client::builder().timeout(self.timeout).build()?;
+            client.head(target).send().await.map_err(|e| {
+                Error::invalid_input(format!("Webhook URL '{}' is unreachable: {}", url, e))
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Check that a host name resolves to at least one address.
+    async fn resolve(&self, host: &str, port: u16) -> Result<()> {
+        let lookup = tokio::time::timeout(self.timeout, tokio::net::lookup_host((host, port)));
+        let mut addresses = match lookup.await {
+            Ok(Ok(addresses)) => addresses,
+            Ok(Err(e)) => {
+                return Err(Error::invalid_input(format!(
+                    "Webhook host '{}' does not resolve: {}",
+                    host, e
+                )));
+            }
+            Err(_) => {
+                return Err(Error::invalid_input(format!(
+                    "Timed out resolving webhook host '{}'",
+                    host
+                )));
+            }
+        };
+        match addresses.next() {
+            Some(_) => Ok(()),
+            None => Err(Error::invalid_input(format!(
+                "Webhook host '{}' resolved to no addresses",
+                host
+            ))),
+        }
+    }
+}
+
+/// Check that a webhook URL uses https, resolves and answers a `HEAD` request.
+///
+/// Shorthand for [`WebhookCheck`] with the preflight enabled; use it directly
+/// to allow `http` or change the timeout.
+pub async fn check_reachability(url: &str) -> Result<()> {
+    WebhookCheck::new().preflight(true).check(url).await
+}
+
+/// Default number of delivery IDs a [`DeliveryDeduplicator`] remembers.
+pub const DEFAULT_DEDUP_CAPACITY: usize = 10_000;
+
+/// Default time a [`DeliveryDeduplicator`] remembers a delivery ID.
+pub const DEFAULT_DEDUP_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Detects repeated deliveries of the same webhook.
+///
+/// Replicate may deliver a webhook more than once, each time with the same
+/// `webhook-id` header. Keep one deduplicator in the receiver's state and
+/// check each delivery's ID before acting on it. IDs are remembered for a
+/// TTL, and the oldest are forgotten first when the capacity is reached.
+/// Safe to share between threads.
+///
+/// An ID is recorded before the delivery is handled, so a concurrent
+/// redelivery is not handled twice. If handling fails, call
+/// [`forget`](Self::forget) so that the next redelivery is processed.
+///
+/// # Examples
+///
+/// ```
+/// use replicate_client::DeliveryDeduplicator;
+///
+/// let deduplicator = DeliveryDeduplicator::default();
+/// assert!(deduplicator.check_and_record("msg_1"));
+/// assert!(!deduplicator.check_and_record("msg_1"));
+///
+/// // Handling failed: let the redelivery through
+/// deduplicator.forget("msg_1");
+/// assert!(deduplicator.check_and_record("msg_1"));
+/// ```
+#[derive(Debug)]
+pub struct DeliveryDeduplicator {
+    capacity: usize,
+    ttl: Duration,
+    seen: Mutex<SeenDeliveries>,
+}
+
+/// Delivery IDs with the time they were first seen.
+#[derive(Debug, Default)]
+struct SeenDeliveries {
+    /// Remembered IDs with the sequence number they were recorded under
+    ids: HashMap<String, u64>,
+    /// Recorded IDs with their sequence number and time, oldest first;
+    /// entries for IDs forgotten or re-recorded since are skipped when popped
+    order: VecDeque<(String, u64, Instant)>,
+    next_seq: u64,
+}
+
+impl SeenDeliveries {
+    /// Remember an ID.
+    fn record(&mut self, id: &str, now: Instant) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.ids.insert(id.to_string(), seq);
+        self.order.push_back((id.to_string(), seq, now));
+    }
+
+    /// Check if an entry of `order` is still the current one for its ID.
+    fn is_current(&self, id: &str, seq: u64) -> bool {
+        self.ids.get(id) == Some(&seq)
+    }
+
+    /// Drop the oldest entry, returning `false` if there is none.
+    fn pop_oldest(&mut self) -> bool {
+        let Some((id, seq, _)) = self.order.pop_front() else {
+            return false;
+        };
+        if self.is_current(&id, seq) {
+            self.ids.remove(&id);
+        }
+        true
+    }
+}
+
+impl Default for DeliveryDeduplicator {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEDUP_CAPACITY, DEFAULT_DEDUP_TTL)
+    }
+}
+
+impl DeliveryDeduplicator {
+    /// Create a deduplicator remembering up to `capacity` IDs for `ttl` each.
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            seen: Mutex::new(SeenDeliveries::default()),
+        }
+    }
+
+    /// Record a delivery ID, returning `true` if it was not seen within the
+    /// TTL and the delivery should be processed, or `false` for a duplicate.
+    pub fn check_and_record(&self, id: &str) -> bool {
+        let now = Instant::now();
+        let mut seen = self.seen.lock().unwrap();
+        while let Some((_, _, first_seen)) = seen.order.front()
+            && now.duration_since(*first_seen) >= self.ttl
+        {
+            seen.pop_oldest();
+        }
+        if seen.ids.contains_key(id) {
+            return false;
+        }
+        if self.capacity > 0 {
+            while seen.ids.len() >= self.capacity && seen.pop_oldest() {}
+            seen.record(id, now);
+            if seen.order.len() > 2 * self.capacity {
+                let SeenDeliveries { ids, order, .. } = &mut *seen;
+                order.retain(|(id, seq, _)| ids.get(id) == Some(seq));
+            }
+        }
+        true
+    }
+
+    /// Forget a delivery ID, so that its next delivery is processed again.
+    ///
+    /// Call this when handling a delivery recorded by
+    /// [`check_and_record`](Self::check_and_record) or [`parse_event`]
+    /// failed. Returns `true` if the ID was remembered.
+    pub fn forget(&self, id: &str) -> bool {
+        self.seen.lock().unwrap().ids.remove(id).is_some()
+    }
+
+    /// Get the number of remembered IDs, including any past their TTL that
+    /// have not been dropped yet.
+    pub fn len(&self) -> usize {
+        self.seen.lock().unwrap().ids.len()
+    }
+
+    /// Check if no IDs are remembered.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Headers Replicate sends with each webhook delivery.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookHeaders {
+    /// Unique ID of the delivery, repeated when it is retried
+    pub id: String,
+    /// Unix time the delivery was attempted, in seconds
+    pub timestamp: u64,
+    /// Signatures of the delivery, if signing is configured
+    pub signature: Option<String>,
+}
+
+impl WebhookHeaders {
+    /// Read the `webhook-id`, `webhook-timestamp` and `webhook-signature`
+    /// headers of a request.
+    ///
+    /// Fails with [`Error::InvalidInput`] if the ID or timestamp is missing
+    /// or malformed.
+    pub fn from_headers(headers: &HeaderMap) -> Result<Self> {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        let id = header("webhook-id")
+            .filter(|id| !id.is_empty())
+            .ok_or_else(|| Error::invalid_input("Webhook is missing the webhook-id header"))?;
+        let timestamp = header("webhook-timestamp")
+            .and_then(|timestamp| timestamp.trim().parse().ok())
+            .ok_or_else(|| {
+                Error::invalid_input("Webhook is missing a valid webhook-timestamp header")
+            })?;
+        Ok(Self {
+            id: id.to_string(),
+            timestamp,
+            signature: header("webhook-signature").map(str::to_string),
+        })
+    }
+}
+
+/// Secret that signs webhook deliveries, as `whsec_` followed by a base64 key.
+///
+/// Replicate signs each delivery with the account's secret, see
+/// [`WebhooksApi::default_secret`]. The signature is an HMAC-SHA256 of
+/// `{webhook-id}.{webhook-timestamp}.{body}`, sent base64-encoded as
+/// `v1,<signature>` in the `webhook-signature` header.
+#[derive(Clone)]
+pub struct WebhookSecret {
+    key: Vec<u8>,
+}
+
+impl WebhookSecret {
+    /// Parse a signing secret, with or without its `whsec_` prefix.
+    ///
+    /// Fails with [`Error::InvalidInput`] if the key is not valid base64.
+    pub fn new(secret: &str) -> Result<Self> {
+        let encoded = secret.trim();
+        let encoded = encoded.strip_prefix("whsec_").unwrap_or(encoded);
+        let key = general_purpose::STANDARD
+            .decode(encoded)
+            .ok()
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| Error::invalid_input("Webhook secret is not a valid whsec_ key"))?;
+        Ok(Self { key })
+    }
+
+    /// Compute the `webhook-signature` header value of a delivery.
+    pub fn sign(&self, id: &str, timestamp: u64, body: &[u8]) -> String {
+        let mut message = format!("{}.{}.", id, timestamp).into_bytes();
+        message.extend_from_slice(body);
+        format!(
+            "v1,{}",
+            general_purpose::STANDARD.encode(hmac_sha256(&self.key, &message))
+        )
+    }
+
+    /// Check that a delivery was signed with this secret and sent recently.
+    ///
+    /// Accepts the delivery if any `v1` signature in its `webhook-signature`
+    /// header matches, comparing in constant time. Fails with [`Error::Auth`]
+    /// if the signature is missing or does not match, or if the timestamp
+    /// is more than five minutes away from the current time, which stops
+    /// captured deliveries from being replayed.
+    pub fn verify(&self, headers: &WebhookHeaders, body: &[u8]) -> Result<()> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        if now.abs_diff(headers.timestamp) > SIGNATURE_TOLERANCE.as_secs() {
+            return Err(Error::Auth(
+                "Webhook timestamp is too far from the current time".to_string(),
+            ));
+        }
+
+        let signature = headers
+            .signature
+            .as_deref()
+            .ok_or_else(|| Error::Auth("Webhook is missing the webhook-signature header".into()))?;
+        let expected = self.sign(&headers.id, headers.timestamp, body);
+        let matched = signature
+            .split_whitespace()
+            .filter(|candidate| candidate.starts_with("v1,"))
+            .any(|candidate| constant_time_eq(candidate.as_bytes(), expected.as_bytes()));
+        if !matched {
+            return Err(Error::Auth("Webhook signature does not match".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// How far a signed delivery's timestamp may be from the current time.
+const SIGNATURE_TOLERANCE: Duration = Duration::from_secs(5 * 60);
+
+/// Compare two byte strings in time that depends only on their lengths.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+impl std::fmt::Debug for WebhookSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookSecret").finish_non_exhaustive()
+    }
+}
+
+/// HMAC-SHA256 (RFC 2104).
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| block.map(|b| b ^ byte);
+
+    let inner = Sha256::new()
+        .chain_update(pad(0x36))
+        .chain_update(message)
+        .finalize();
+    Sha256::new()
+        .chain_update(pad(0x5c))
+        .chain_update(inner)
+        .finalize()
+        .into()
+}
+
+/// Outcome of [`parse_event`].
+#[derive(Debug, Clone)]
+pub enum WebhookEvent {
+    /// A delivery seen for the first time
+    Prediction(Box<Prediction>),
+    /// A repeated delivery, already handled under this ID
+    Duplicate { id: String },
+}
+
+/// Parse a webhook delivery, telling repeated deliveries apart.
+///
+/// The body is parsed before the delivery ID is recorded, so a delivery
+/// with an invalid body is not marked as seen. If handling the returned
+/// prediction fails, call [`DeliveryDeduplicator::forget`] with the
+/// delivery ID so that Replicate's redelivery is processed. The signature
+/// is not checked here; check it first with [`WebhookSecret::verify`].
+pub fn parse_event(
+    headers: &WebhookHeaders,
+    body: &[u8],
+    deduplicator: &DeliveryDeduplicator,
+) -> Result<WebhookEvent> {
+    let prediction: Prediction = serde_json::from_slice(body)?;
+    if !deduplicator.check_and_record(&headers.id) {
+        return Ok(WebhookEvent::Duplicate {
+            id: headers.id.clone(),
+        });
+    }
+    Ok(WebhookEvent::Prediction(Box::new(prediction)))
+}
+
+/// API for webhook configuration and testing.
+#[derive(Debug, Clone)]
+pub struct WebhooksApi {
+    http: HttpClient,
+}
+
+impl WebhooksApi {
+    /// Create a new webhooks API instance.
+    pub fn new(http: HttpClient) -> Self {
+        Self { http }
+    }
+
+    /// Create a copy of this API that sends requests through another HTTP client.
+    pub(crate) fn with_http(&self, http: HttpClient) -> Self {
+        Self { http }
+    }
+
+    /// Get the secret Replicate signs this account's webhook deliveries with.
+    pub async fn default_secret(&self) -> Result<WebhookSecret> {
+        #[derive(serde::Deserialize)]
+        struct SecretResponse {
+            key: String,
+        }
+
+        let response: SecretResponse = self.http.get_json("/v1/webhooks/default/secret").await?;
+        WebhookSecret::new(&response.key)
+    }
+
+    /// Send an unsigned test event to a webhook receiver.
+    ///
+    /// This is a local smoke test of reachability: Replicate does not expose a
+    /// server-side test delivery endpoint, so the event is posted directly
+    /// from this client. The payload has the shape of a completed prediction
+    /// with ID [`TEST_DELIVERY_PREDICTION_ID`] and carries `webhook-id` and
+    /// `webhook-timestamp` headers but no `webhook-signature`, so a receiver
+    /// that verifies signatures should reject it; use
+    /// [`test_delivery_signed`](Self::test_delivery_signed) to exercise that
+    /// path too. The API token is never sent to the receiver.
+    ///
+    /// Non-success responses from the receiver are returned as [`Error::Api`].
+    pub async fn test_delivery(&self, url: &str) -> Result<WebhookDelivery> {
+        self.deliver_test_event(url, None).await
+    }
+
+    /// Send a test event to a webhook receiver, signed with `secret` the way
+    /// Replicate signs real deliveries.
+    ///
+    /// Pass the account's secret from [`default_secret`](Self::default_secret)
+    /// to check that the receiver accepts genuine deliveries. Otherwise the
+    /// same as [`test_delivery`](Self::test_delivery).
+    pub async fn test_delivery_signed(
+        &self,
+        url: &str,
+        secret: &WebhookSecret,
+    ) -> Result<WebhookDelivery> {
+        self.deliver_test_event(url, Some(secret)).await
+    }
+
+    async fn deliver_test_event(
+        &self,
+        url: &str,
+        secret: Option<&WebhookSecret>,
+    ) -> Result<WebhookDelivery> {
+        let target = url::Url::parse(url)?;
+        if !matches!(target.scheme(), "http" | "https") {
+            return Err(Error::invalid_input(format!(
+                "Webhook URL must use http or https: {}",
+                url
+            )));
+        }
+
+        let id = format!("msg_{}", uuid::Uuid::new_v4().simple());
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let body = serde_json::to_vec(&json!({
+            "id": TEST_DELIVERY_PREDICTION_ID,
+            "model": "replicate/test",
+            "version": "test",
+            "status": "succeeded",
+            "input": {},
+            "output": null,
+            "logs": "",
+            "error": null,
+        }))?;
+
+        let mut request = self
+            .http
+            .current_inner()
+            .post(target)
+            .header("Content-Type", "application/json")
+            .header("webhook-timestamp", timestamp.to_string());
+        if let Some(secret) = secret {
+            request = request.header("webhook-signature", secret.sign(&id, timestamp, &body));
+        }
+        let started = Instant::now();
+        let response = request.header("webhook-id", id).body(body).send().await?;
+        let elapsed = started.elapsed();
+
+        let status = response.status().as_u16();
+        if !response.status().is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(Error::api_error_with_detail(
+                status,
+                "Webhook test delivery failed",
+                body,
+            ));
+        }
+
+        Ok(WebhookDelivery {
+            url: url.to_string(),
+            status,
+            elapsed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_delivery_success() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/hooks/replicate"))
+            .and(header_exists("webhook-id"))
+            .respond_with(ResponseTemplate::new(204))
+            .mount(&server)
+            .await;
+
+        let api = WebhooksApi::new(HttpClient::new("test-token").unwrap());
+        let url = format!("{}/hooks/replicate", server.uri());
+        let delivery = api.test_delivery(&url).await.unwrap();
+        assert_eq!(delivery.status, 204);
+
+        let requests = server.received_requests().await.unwrap();
+        assert!(requests[0].headers.get("authorization").is_none());
+        assert!(requests[0].headers.get("webhook-signature").is_none());
+        let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!(body["id"], TEST_DELIVERY_PREDICTION_ID);
+    }
+
+    #[tokio::test]
+    async fn test_signed_delivery_with_default_secret() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/webhooks/default/secret"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({"key": "whsec_MfKQ9r8GKYqrTwjUPD8ILPZIo2LaLaSw"})),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/hooks/replicate"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let http = HttpClient::with_base_url("test-token", server.uri()).unwrap();
+        let api = WebhooksApi::new(http);
+        let secret = api.default_secret().await.unwrap();
+        assert_eq!(format!("{:?}", secret), "WebhookSecret { .. }");
+
+        let url = format!("{}/hooks/replicate", server.uri());
+        api.test_delivery_signed(&url, &secret).await.unwrap();
+        let requests = server.received_requests().await.unwrap();
+        let delivery = &requests[1];
+        let headers = WebhookHeaders::from_headers(&delivery.headers).unwrap();
+        assert_eq!(
+            headers.signature.as_deref(),
+            Some(
+                secret
+                    .sign(&headers.id, headers.timestamp, &delivery.body)
+                    .as_str()
+            )
+        );
+        assert!(delivery.headers.get("authorization").is_none());
+    }
+
+    #[test]
+    fn test_webhook_secret_signing() {
+        // RFC 4231 test case 2
+        let mac = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(
+            mac.iter().map(|b| format!("{:02x}", b)).collect::<String>(),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        // Keys longer than a block are hashed first (RFC 4231 test case 6)
+        let mac = hmac_sha256(
+            &[0xaa; 131],
+            b"Test Using Larger Than Block-Size Key - Hash Key First",
+        );
+        assert_eq!(
+            mac.iter().map(|b| format!("{:02x}", b)).collect::<String>(),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+
+        let secret = WebhookSecret::new("whsec_MfKQ9r8GKYqrTwjUPD8ILPZIo2LaLaSw").unwrap();
+        assert_eq!(
+            secret.sign(
+                "msg_p5jXN8AQM9LWM0D4loKWxJek",
+                1614265330,
+                br#"{"test": 2432232314}"#
+            ),
+            "v1,g0hM9SsE+OTPJTGt/tmIKtSyZlE3uFJELVlNIOLJ1OE="
+        );
+        assert!(WebhookSecret::new("MfKQ9r8GKYqrTwjUPD8ILPZIo2LaLaSw").is_ok());
+        assert!(constant_time_eq(b"v1,abc", b"v1,abc"));
+        assert!(!constant_time_eq(b"v1,abc", b"v1,abd"));
+        assert!(!constant_time_eq(b"v1,abc", b"v1,ab"));
+        assert!(matches!(
+            WebhookSecret::new("whsec_not base64!"),
+            Err(Error::InvalidInput(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_check_local_listener() {
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .respond_with(ResponseTemplate::new(405))
+            .mount(&server)
+            .await;
+        let url = format!("{}/hooks/replicate", server.uri());
+
+        let check = WebhookCheck::new().allow_http(true).preflight(true);
+        check.check(&url).await.unwrap();
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+
+        // Plain http is rejected unless allowed
+        let result = check_reachability(&url).await;
+        assert!(
+            matches!(result, Err(Error::InvalidInput(message)) if message.ends_with("must use https"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_reports_unreachable_receivers() {
+        let result = WebhookCheck::new()
+            .check("https://no-such-host.invalid/hook")
+            .await;
+        assert!(
+            matches!(result, Err(Error::InvalidInput(message)) if message.contains("does not resolve"))
+        );
+
+        // Nothing listens on a port that was just released
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        drop(listener);
+        let check = WebhookCheck::new().allow_http(true);
+        check.check(&url).await.unwrap();
+        let result = check.preflight(true).check(&url).await;
+        assert!(
+            matches!(result, Err(Error::InvalidInput(message)) if message.contains("is unreachable"))
+        );
+
+        let result = WebhookCheck::new().check("not a url").await;
+        assert!(matches!(result, Err(Error::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn test_delivery_failure_maps_to_api_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401).set_body_string("bad signature"))
+            .mount(&server)
+            .await;
+
+        let api = WebhooksApi::new(HttpClient::new("test-token").unwrap());
+        let result = api.test_delivery(&server.uri()).await;
+        assert!(matches!(result, Err(Error::Api { status: 401, .. })));
+
+        let result = api.test_delivery("ftp://example.com/hook").await;
+        assert!(matches!(result, Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_webhook_secret_verify() {
+        let secret = WebhookSecret::new("whsec_MfKQ9r8GKYqrTwjUPD8ILPZIo2LaLaSw").unwrap();
+        let body = br#"{"id": "p1"}"#;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let headers = |timestamp: u64, signature: Option<String>| WebhookHeaders {
+            id: "msg_1".to_string(),
+            timestamp,
+            signature,
+        };
+
+        let signature = secret.sign("msg_1", now, body);
+        secret
+            .verify(&headers(now, Some(signature.clone())), body)
+            .unwrap();
+        // Any of several space-separated signatures may match, e.g. while
+        // the secret is being rotated
+        let rotated = format!("v1,c3RhbGU= {}", signature);
+        secret.verify(&headers(now, Some(rotated)), body).unwrap();
+
+        let rejected = [
+            headers(now, None),
+            headers(now, Some("v1,c3RhbGU=".to_string())),
+            headers(now, Some(signature.replacen("v1", "v2", 1))),
+            headers(now + 1, Some(signature.clone())),
+        ];
+        for headers in &rejected {
+            assert!(matches!(secret.verify(headers, body), Err(Error::Auth(_))));
+        }
+        assert!(matches!(
+            secret.verify(&headers(now, Some(signature.clone())), b"{}"),
+            Err(Error::Auth(_))
+        ));
+
+        let stale = now - 10 * 60;
+        let headers = headers(stale, Some(secret.sign("msg_1", stale, body)));
+        assert!(matches!(
+            secret.verify(&headers, body),
+            Err(Error::Auth(message)) if message.contains("timestamp")
+        ));
+    }
+
+    #[test]
+    fn test_deduplicator_ttl_and_eviction() {
+        let deduplicator = DeliveryDeduplicator::new(2, Duration::from_secs(60));
+        assert!(deduplicator.check_and_record("a"));
+        assert!(deduplicator.check_and_record("b"));
+        assert!(!deduplicator.check_and_record("a"));
+        // The oldest ID is forgotten to make room
+        assert!(deduplicator.check_and_record("c"));
+        assert_eq!(deduplicator.len(), 2);
+        assert!(deduplicator.check_and_record("a"));
+        assert!(!deduplicator.check_and_record("c"));
+
+        let deduplicator = DeliveryDeduplicator::new(10, Duration::from_millis(20));
+        assert!(deduplicator.check_and_record("a"));
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(deduplicator.check_and_record("a"));
+        assert_eq!(deduplicator.len(), 1);
+
+        let disabled = DeliveryDeduplicator::new(0, Duration::from_secs(60));
+        assert!(disabled.check_and_record("a"));
+        assert!(disabled.check_and_record("a"));
+        assert!(disabled.is_empty());
+
+        // A forgotten ID is processed again and keeps no stale place in line
+        let deduplicator = DeliveryDeduplicator::new(2, Duration::from_secs(60));
+        assert!(deduplicator.check_and_record("a"));
+        assert!(deduplicator.forget("a"));
+        assert!(!deduplicator.forget("a"));
+        assert!(deduplicator.check_and_record("b"));
+        assert!(deduplicator.check_and_record("a"));
+        assert!(!deduplicator.check_and_record("b"));
+        assert!(deduplicator.check_and_record("c"));
+        assert!(!deduplicator.check_and_record("a"));
+        assert!(deduplicator.check_and_record("b"));
+        for i in 0..100 {
+            let id = format!("retry{}", i);
+            assert!(deduplicator.check_and_record(&id));
+            deduplicator.forget(&id);
+        }
+        assert!(deduplicator.seen.lock().unwrap().order.len() <= 4);
+    }
+
+    #[test]
+    fn test_deduplicator_concurrent_access() {
+        let deduplicator =
+            std::sync::Arc::new(DeliveryDeduplicator::new(50, Duration::from_secs(60)));
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let deduplicator = deduplicator.clone();
+                std::thread::spawn(move || {
+                    (0..50)
+                        .filter(|i| deduplicator.check_and_record(&format!("msg_{}", i)))
+                        .count()
+                })
+            })
+            .collect();
+        let processed: usize = handles.into_iter().map(|h| h.join().unwrap()).sum();
+        assert_eq!(processed, 50);
+        assert_eq!(deduplicator.len(), 50);
+
+        // Concurrent inserts past the capacity keep it bounded
+        let handles: Vec<_> = (0..8)
+            .map(|thread| {
+                let deduplicator = deduplicator.clone();
+                std::thread::spawn(move || {
+                    for i in 0..100 {
+                        deduplicator.check_and_record(&format!("t{}_{}", thread, i));
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(deduplicator.len(), 50);
+        assert!(deduplicator.check_and_record("msg_0"));
+    }
+
+    #[test]
+    fn test_parse_event_detects_duplicates() {
+        let mut header_map = HeaderMap::new();
+        header_map.insert("webhook-id", "msg_1".parse().unwrap());
+        header_map.insert("webhook-timestamp", "1700000000".parse().unwrap());
+        let headers = WebhookHeaders::from_headers(&header_map).unwrap();
+        assert_eq!(headers.timestamp, 1_700_000_000);
+        assert_eq!(headers.signature, None);
+
+        let body = serde_json::to_vec(&json!({
+            "id": "p1",
+            "model": "owner/model",
+            "version": "v1",
+            "status": "succeeded",
+            "input": {},
+            "output": "done",
+        }))
+        .unwrap();
+        let deduplicator = DeliveryDeduplicator::default();
+
+        // An invalid body does not mark the delivery as seen
+        assert!(parse_event(&headers, b"not json", &deduplicator).is_err());
+        match parse_event(&headers, &body, &deduplicator).unwrap() {
+            WebhookEvent::Prediction(prediction) => assert_eq!(prediction.id, "p1"),
+            other => panic!("expected a prediction, got {:?}", other),
+        }
+        assert!(matches!(
+            parse_event(&headers, &body, &deduplicator).unwrap(),
+            WebhookEvent::Duplicate { id } if id == "msg_1"
+        ));
+
+        header_map.remove("webhook-id");
+        assert!(matches!(
+            WebhookHeaders::from_headers(&header_map),
+            Err(Error::InvalidInput(_))
+        ));
+    }
+}
@@ -1,8 +1,27 @@
 //! Main client implementation for the Replicate API.
 
-use crate::api::{FilesApi, PredictionsApi, predictions::PredictionBuilder};
+use crate::api::{
+    CollectionsApi, DeploymentsApi, FilesApi, HardwareApi, ModelsApi, PredictionsApi, RunMode,
+    TrainingBuilder, TrainingsApi, WebhookDelivery, WebhooksApi, predictions::PredictionBuilder,
+};
+use crate::cost::{CostSummary, CostTracker, PriceTable};
+use crate::diagnostics::Diagnostics;
+use crate::env_config::{
+    AppliedEnv, ENV_BASE_URL, ENV_DEFAULT_WEBHOOK, ENV_FILE_ENCODING, EnvSettings,
+};
 use crate::error::{Error, Result};
-use crate::http::{HttpClient, HttpConfig, TimeoutConfig};
+use crate::http::{HttpClient, HttpConfig, ShutdownReport, TimeoutConfig};
+use crate::models::{
+    file::FileEncodingStrategy, image_edit::ImageEditRequest, prediction::Prediction,
+    template::InputTemplate,
+};
+use crate::observer::{LifecycleObserver, ObserverHandle};
+use crate::sanitize::InputSanitizer;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::{env, time::Duration};
 
 /// Main client for interacting with the Replicate API.
@@ -11,20 +30,105 @@ pub struct Client {
     http: HttpClient,
     predictions_api: PredictionsApi,
     files_api: FilesApi,
+    collections_api: CollectionsApi,
+    deployments_api: DeploymentsApi,
+    hardware_api: HardwareApi,
+    models_api: ModelsApi,
+    webhooks_api: WebhooksApi,
+    trainings_api: TrainingsApi,
 }
 
 impl Client {
     /// Create a new client with the given API token.
     pub fn new(api_token: impl Into<String>) -> Result<Self> {
         let http = HttpClient::new(api_token)?;
+        Ok(Self::from_http_client(http))
+    }
+
+    /// Create a builder for configuring a client.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use replicate_client::Client;
+    /// # use std::time::Duration;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::builder("your-api-token")
+    ///     .enable_collection_cache(Duration::from_secs(300))
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn builder(api_token: impl Into<String>) -> ClientBuilder {
+        ClientBuilder::new(api_token)
+    }
+
+    /// Create a client around an already configured HTTP client.
+    pub(crate) fn from_http_client(http: HttpClient) -> Self {
         let predictions_api = PredictionsApi::new(http.clone());
         let files_api = FilesApi::new(http.clone());
+        let collections_api = CollectionsApi::new(http.clone());
+        let deployments_api = DeploymentsApi::new(http.clone());
+        let hardware_api = HardwareApi::new(http.clone());
+        let models_api = ModelsApi::new(http.clone());
+        let webhooks_api = WebhooksApi::new(http.clone());
+        let trainings_api = TrainingsApi::new(http.clone());
 
-        Ok(Self {
+        Self {
             http,
             predictions_api,
             files_api,
-        })
+            collections_api,
+            deployments_api,
+            hardware_api,
+            models_api,
+            webhooks_api,
+            trainings_api,
+        }
+    }
+
+    /// Create a copy of this client that sends requests through another HTTP client.
+    ///
+    /// API settings and caches are carried over.
+    fn with_http_client(&self, http: HttpClient) -> Self {
+        Self {
+            predictions_api: self.predictions_api.with_http(http.clone()),
+            files_api: self.files_api.with_http(http.clone()),
+            collections_api: self.collections_api.with_http(http.clone()),
+            deployments_api: self.deployments_api.with_http(http.clone()),
+            hardware_api: self.hardware_api.with_http(http.clone()),
+            models_api: self.models_api.with_http(http.clone()),
+            webhooks_api: self.webhooks_api.with_http(http.clone()),
+            trainings_api: self.trainings_api.with_http(http.clone()),
+            http,
+        }
+    }
+
+    /// Create a copy of this client that tags every request with an `X-Correlation-Id`.
+    ///
+    /// The same ID is sent on every retry attempt and on the polling requests made
+    /// while waiting, so one logical operation can be traced across logs.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use replicate_client::Client;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::new("your-api-token")?;
+    ///
+    /// let prediction = client
+    ///     .with_correlation_id("checkout-42")?
+    ///     .run("stability-ai/sdxl:version-id")
+    ///     .input("prompt", "A futuristic city skyline")
+    ///     .send_and_wait()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_correlation_id(&self, correlation_id: impl AsRef<str>) -> Result<Self> {
+        let http = self.http.with_correlation_id(correlation_id)?;
+        Ok(self.with_http_client(http))
     }
 
     /// Create a new client using the API token from the environment.
@@ -42,14 +146,7 @@ impl Client {
         base_url: impl Into<String>,
     ) -> Result<Self> {
         let http = HttpClient::with_base_url(api_token, base_url)?;
-        let predictions_api = PredictionsApi::new(http.clone());
-        let files_api = FilesApi::new(http.clone());
-
-        Ok(Self {
-            http,
-            predictions_api,
-            files_api,
-        })
+        Ok(Self::from_http_client(http))
     }
 
     /// Get access to the predictions API.
@@ -57,11 +154,114 @@ impl Client {
         &self.predictions_api
     }
 
+    /// Enable or disable raw prediction output mode.
+    ///
+    /// When enabled, fetched predictions keep their output as unparsed JSON in
+    /// [`Prediction::output_raw`](crate::Prediction::output_raw), which avoids
+    /// building a large `Value` tree for multi-megabyte outputs. Use
+    /// [`Prediction::output_as`](crate::Prediction::output_as) to decode it.
+    pub fn set_raw_prediction_output(&mut self, enabled: bool) {
+        self.predictions_api.set_raw_output(enabled);
+    }
+
     /// Get access to the files API.
     pub fn files(&self) -> &FilesApi {
         &self.files_api
     }
 
+    /// Get access to the collections API.
+    pub fn collections(&self) -> &CollectionsApi {
+        &self.collections_api
+    }
+
+    /// Get access to the deployments API.
+    pub fn deployments(&self) -> &DeploymentsApi {
+        &self.deployments_api
+    }
+
+    /// Get access to the hardware API.
+    pub fn hardware(&self) -> &HardwareApi {
+        &self.hardware_api
+    }
+
+    /// Get access to the models API.
+    pub fn models(&self) -> &ModelsApi {
+        &self.models_api
+    }
+
+    /// Establish a connection to the API ahead of the first real request.
+    ///
+    /// Performs a cheap authenticated `GET /v1/account` so that DNS, TCP and TLS
+    /// setup are paid up front and the connection is kept in the pool for later
+    /// requests. An error is returned if the request fails, but the client stays
+    /// fully usable.
+    pub async fn warmup(&self) -> Result<()> {
+        let response = self.http.get("/v1/account").await?;
+        // Drain the body so the connection is returned to the pool
+        response.bytes().await?;
+        Ok(())
+    }
+
+    /// Get access to the trainings API.
+    pub fn trainings(&self) -> &TrainingsApi {
+        &self.trainings_api
+    }
+
+    /// Create a new training with a fluent builder API.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use replicate_client::Client;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::new("your-api-token")?;
+    ///
+    /// let training = client
+    ///     .create_training("ostris/flux-dev-lora-trainer:version-id")
+    ///     .destination("your-username", "my-flux-lora")
+    ///     .input("input_images", "https://example.com/images.zip")
+    ///     .input("steps", 1000)
+    ///     .send()
+    ///     .await?;
+    ///
+    /// println!("Training ID: {}", training.id);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_training(&self, version: impl Into<String>) -> TrainingBuilder {
+        TrainingBuilder::new(self.trainings_api.clone(), version)
+    }
+
+    /// Get access to the webhooks API.
+    pub fn webhooks(&self) -> &WebhooksApi {
+        &self.webhooks_api
+    }
+
+    /// Send an unsigned test event to a webhook receiver to confirm it is
+    /// reachable.
+    ///
+    /// See [`WebhooksApi::test_delivery`] for the payload that is sent, and
+    /// [`WebhooksApi::test_delivery_signed`] for a signed test event.
+    /// Failures are returned as [`Error::Api`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use replicate_client::Client;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::new("your-api-token")?;
+    ///
+    /// let delivery = client.test_webhook("https://example.com/hooks/replicate").await?;
+    /// println!("Receiver answered {} in {:?}", delivery.status, delivery.elapsed);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn test_webhook(&self, url: &str) -> Result<WebhookDelivery> {
+        self.webhooks_api.test_delivery(url).await
+    }
+
     /// Create a new prediction with a fluent builder API.
     ///
     /// # Examples
@@ -114,6 +314,178 @@ impl Client {
         self.create_prediction(version)
     }
 
+    /// Run a model and return only its output.
+    ///
+    /// Creates the prediction, waits for it with
+    /// [`send_and_wait`](PredictionBuilder::send_and_wait) and returns its
+    /// `output`, or `Value::Null` if it produced none. Raw output mode and
+    /// the default [`RunMode`] are honored. Failed and canceled
+    /// predictions are returned as [`Error::ModelExecution`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use replicate_client::Client;
+    /// # use std::collections::HashMap;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::new("your-api-token")?;
+    ///
+    /// let inputs = HashMap::from([("prompt".to_string(), "A futuristic city skyline".into())]);
+    /// let output = client
+    ///     .run_and_get_output("stability-ai/sdxl:version-id", inputs)
+    ///     .await?;
+    /// println!("Output: {}", output);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn run_and_get_output(
+        &self,
+        version: impl Into<String>,
+        inputs: HashMap<String, Value>,
+    ) -> Result<Value> {
+        let prediction = self.run(version).inputs(inputs).send_and_wait().await?;
+        if prediction.is_canceled() {
+            return Err(Error::model_execution(
+                &prediction.id,
+                Some("Prediction was canceled".to_string()),
+                prediction.logs,
+            ));
+        }
+        Ok(prediction.output_as()?.unwrap_or(Value::Null))
+    }
+
+    /// Create a prediction for each row of variables, rendered into a template.
+    ///
+    /// Every row is rendered with [`InputTemplate::render`] before any
+    /// prediction is created, so a bad row fails the whole batch up front.
+    /// The predictions are then created one after another, without waiting
+    /// for them; the result of each creation is returned in row order.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use replicate_client::{Client, InputTemplate};
+    /// # use std::collections::HashMap;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::new("your-api-token")?;
+    ///
+    /// let template = InputTemplate::new(HashMap::from([(
+    ///     "prompt".to_string(),
+    ///     "A {style} painting of {subject}".into(),
+    /// )]));
+    /// let rows = ["a lighthouse", "a fox"].map(|subject| {
+    ///     HashMap::from([
+    ///         ("style".to_string(), "watercolor".to_string()),
+    ///         ("subject".to_string(), subject.to_string()),
+    ///     ])
+    /// });
+    /// for result in client
+    ///     .run_batch_templated("stability-ai/sdxl:version-id", &template, rows)
+    ///     .await?
+    /// {
+    ///     println!("Created {}", result?.id);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn run_batch_templated(
+        &self,
+        version: impl Into<String>,
+        template: &InputTemplate,
+        rows: impl IntoIterator<Item = HashMap<String, String>>,
+    ) -> Result<Vec<Result<Prediction>>> {
+        let version = version.into();
+        let inputs = rows
+            .into_iter()
+            .enumerate()
+            .map(|(index, row)| {
+                template.render(&row).map_err(|e| match e {
+                    Error::InvalidInput(message) => {
+                        Error::invalid_input(format!("Row {}: {}", index, message))
+                    }
+                    e => e,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut predictions = Vec::with_capacity(inputs.len());
+        for inputs in inputs {
+            predictions.push(self.run(version.as_str()).inputs(inputs).send().await);
+        }
+        Ok(predictions)
+    }
+
+    /// Edit an image with a model and save the resulting images.
+    ///
+    /// URL images are passed to the model as-is. Local images are sent with
+    /// [`FileEncodingStrategy::Auto`]: inlined as data URLs, or uploaded
+    /// through the files API first when inlining would exceed the
+    /// [body size limit](crate::api::PredictionsApi::set_max_body_size). The outputs are streamed to `output_dir` as
+    /// `<prediction id>-<index>.<extension>`, and their paths are returned in
+    /// output order. A model that returns no files fails with
+    /// [`Error::InvalidInput`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use replicate_client::{Client, FileInput, ImageEditRequest};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::new("your-api-token")?;
+    ///
+    /// let request = ImageEditRequest::new(FileInput::from_path("cat.png"), "make it a watercolor")
+    ///     .input("strength", 0.6)
+    ///     .output_dir("edits");
+    /// let paths = client
+    ///     .run_image_edit("stability-ai/sdxl:version-id", request)
+    ///     .await?;
+    /// println!("Saved {:?}", paths);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn run_image_edit(
+        &self,
+        model_ref: impl Into<String>,
+        request: ImageEditRequest,
+    ) -> Result<Vec<PathBuf>> {
+        let ImageEditRequest {
+            image,
+            prompt,
+            extra_inputs,
+            image_key,
+            output_dir,
+            timeout,
+        } = request;
+        let model_ref = model_ref.into();
+
+        let prediction = self
+            .create_prediction(model_ref.as_str())
+            .inputs(extra_inputs)
+            .input("prompt", prompt)
+            .file_input_with_strategy(image_key, image, FileEncodingStrategy::Auto)
+            .send_and_wait_with_timeout(timeout)
+            .await?;
+        let files = prediction.file_outputs()?;
+        if files.is_empty() {
+            return Err(Error::invalid_input(format!(
+                "Model {} returned no files for prediction {}",
+                model_ref, prediction.id
+            )));
+        }
+
+        tokio::fs::create_dir_all(&output_dir).await?;
+        let mut paths = Vec::with_capacity(files.len());
+        for (index, file) in files.iter().enumerate() {
+            let extension = file.extension().unwrap_or_else(|| "bin".to_string());
+            let path = output_dir.join(format!("{}-{}.{}", prediction.id, index, extension));
+            file.save_to_path(&path).await?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
     /// Get the underlying HTTP client.
     pub fn http_client(&self) -> &HttpClient {
         &self.http
@@ -128,7 +500,9 @@ impl Client {
 
     /// Configure retry settings for this client.
     ///
-    /// This is a convenience method that delegates to the HTTP client.
+    /// This is a convenience method that delegates to the HTTP client. Only
+    /// this client is affected; its clones keep their configuration. Use
+    /// [`reconfigure`](Self::reconfigure) to retune all of them.
     ///
     /// # Examples
     ///
@@ -155,12 +529,16 @@ impl Client {
         max_delay: Duration,
     ) -> Result<()> {
         self.http
-            .configure_retries(max_retries, min_delay, max_delay)
+            .configure_retries(max_retries, min_delay, max_delay)?;
+        *self = self.with_http_client(self.http.clone());
+        Ok(())
     }
 
     /// Configure timeout settings for this client.
     ///
-    /// This is a convenience method that delegates to the HTTP client.
+    /// This is a convenience method that delegates to the HTTP client. Only
+    /// this client is affected; its clones keep their configuration. Use
+    /// [`reconfigure`](Self::reconfigure) to retune all of them.
     ///
     /// # Examples
     ///
@@ -185,31 +563,377 @@ impl Client {
         request_timeout: Option<Duration>,
     ) -> Result<()> {
         self.http
-            .configure_timeouts(connect_timeout, request_timeout)
+            .configure_timeouts(connect_timeout, request_timeout)?;
+        *self = self.with_http_client(self.http.clone());
+        Ok(())
     }
 
     /// Create a new client with custom HTTP configuration.
     pub fn with_http_config(api_token: impl Into<String>, http_config: HttpConfig) -> Result<Self> {
         let http = HttpClient::with_http_config(api_token, http_config)?;
-        let predictions_api = PredictionsApi::new(http.clone());
-        let files_api = FilesApi::new(http.clone());
-
-        Ok(Self {
-            http,
-            predictions_api,
-            files_api,
-        })
+        Ok(Self::from_http_client(http))
     }
 
-    /// Get the current timeout configuration.
+    /// Get the timeout configuration.
+    ///
+    /// This is the configuration as of creation or the last `&mut self`
+    /// configuration; see [`current_http_config`](Self::current_http_config)
+    /// to observe [`reconfigure`](Self::reconfigure).
     pub fn timeout_config(&self) -> &TimeoutConfig {
         self.http.timeout_config()
     }
 
-    /// Get the current HTTP configuration.
+    /// Get the HTTP configuration.
+    ///
+    /// See [`timeout_config`](Self::timeout_config) for which configuration
+    /// this is.
     pub fn http_config(&self) -> &HttpConfig {
         self.http.http_config()
     }
+
+    /// Get the HTTP configuration requests currently use, including changes
+    /// made with [`reconfigure`](Self::reconfigure) through any clone.
+    pub fn current_http_config(&self) -> HttpConfig {
+        self.http.current_http_config()
+    }
+
+    /// Replace the HTTP configuration through a shared reference.
+    ///
+    /// Applies to this client, its clones and all its API handles; see
+    /// [`HttpClient::reconfigure`] for the tradeoffs.
+    pub fn reconfigure(&self, update: impl FnOnce(&mut HttpConfig)) -> Result<()> {
+        self.http.reconfigure(update)
+    }
+
+    /// Stop accepting new requests and let in-flight operations finish.
+    ///
+    /// New requests through this client, its clones and its API handles fail
+    /// with [`Error::ClientClosed`] from now on. Waits for up to `grace` until
+    /// nothing is in flight and reports how many operations finished in time.
+    ///
+    /// A prediction create, including its file uploads, counts as one
+    /// operation, and so does a polling wait and a whole
+    /// [`send_and_wait`](PredictionBuilder::send_and_wait); their remaining
+    /// requests are still sent. Other calls count as one operation per
+    /// request. Waits through the shared
+    /// [`PredictionWatcher`](crate::PredictionWatcher) are not covered and
+    /// fail once the client is closed. Operations still running after `grace`
+    /// are not canceled.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use replicate_client::Client;
+    /// # use std::time::Duration;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::new("your-api-token")?;
+    /// // ... on SIGTERM:
+    /// let report = client.shutdown(Duration::from_secs(10)).await;
+    /// println!("{} finished, {} abandoned", report.completed, report.abandoned);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn shutdown(&self, grace: Duration) -> ShutdownReport {
+        self.http.shutdown(grace).await
+    }
+
+    /// Check if [`shutdown`](Self::shutdown) has been called on this client or a clone.
+    pub fn is_closed(&self) -> bool {
+        self.http.is_closed()
+    }
+
+    /// Get the estimated cost of completed predictions, grouped by model.
+    ///
+    /// Empty unless cost tracking was enabled with
+    /// [`ClientBuilder::cost_tracking`].
+    pub fn cost_summary(&self) -> CostSummary {
+        self.predictions_api
+            .cost_tracker()
+            .map(CostTracker::summary)
+            .unwrap_or_default()
+    }
+
+    /// Summarize the client's configuration and activity for a bug report.
+    ///
+    /// The snapshot includes request counters and the last request ID and
+    /// rate-limit headers, but never the API token.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use replicate_client::Client;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::new("your-api-token")?;
+    /// println!("{}", client.diagnostics());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn diagnostics(&self) -> Diagnostics {
+        Diagnostics::from_http(&self.http)
+    }
+}
+
+/// Builder for a [`Client`] with non-default configuration.
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    api_token: String,
+    base_url: Option<String>,
+    http_config: HttpConfig,
+    collection_cache_ttl: Option<Duration>,
+    eager_warmup: bool,
+    prices: Option<PriceTable>,
+    input_sanitizer: Option<InputSanitizer>,
+    max_body_size: Option<Option<usize>>,
+    observer: Option<ObserverHandle>,
+    /// Whether `http_config` was set explicitly, so the environment does not override it
+    http_config_set: bool,
+    default_webhook: Option<String>,
+    default_file_encoding: Option<FileEncodingStrategy>,
+    default_inputs: Option<HashMap<String, Value>>,
+    run_mode: Option<RunMode>,
+    dns_overrides: Vec<(String, SocketAddr)>,
+}
+
+impl ClientBuilder {
+    /// Create a new builder with the given API token.
+    pub fn new(api_token: impl Into<String>) -> Self {
+        Self {
+            api_token: api_token.into(),
+            base_url: None,
+            http_config: HttpConfig::default(),
+            collection_cache_ttl: None,
+            eager_warmup: false,
+            prices: None,
+            input_sanitizer: None,
+            max_body_size: None,
+            observer: None,
+            http_config_set: false,
+            default_webhook: None,
+            default_file_encoding: None,
+            default_inputs: None,
+            run_mode: None,
+            dns_overrides: Vec::new(),
+        }
+    }
+
+    /// Set a custom base URL.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Set the HTTP configuration.
+    pub fn http_config(mut self, http_config: HttpConfig) -> Self {
+        self.http_config = http_config;
+        self.http_config_set = true;
+        self
+    }
+
+    /// Send predictions to `webhook` unless they set their own.
+    ///
+    /// See [`PredictionsApi::set_default_webhook`].
+    pub fn default_webhook(mut self, webhook: impl Into<String>) -> Self {
+        self.default_webhook = Some(webhook.into());
+        self
+    }
+
+    /// Encode file inputs with `strategy` unless a prediction sets its own.
+    ///
+    /// See [`PredictionsApi::set_default_file_encoding`].
+    pub fn default_file_encoding(mut self, strategy: FileEncodingStrategy) -> Self {
+        self.default_file_encoding = Some(strategy);
+        self
+    }
+
+    /// Wait on predictions in `mode` unless they choose their own, including
+    /// in helpers such as [`Client::run_and_get_output`].
+    ///
+    /// See [`PredictionsApi::set_default_run_mode`].
+    pub fn run_mode(mut self, mode: RunMode) -> Self {
+        self.run_mode = Some(mode);
+        self
+    }
+
+    /// Resolve `host` to `addr` instead of looking it up through DNS.
+    ///
+    /// Added to the overrides of the HTTP configuration; see
+    /// [`HttpConfig::resolve`].
+    pub fn resolve(mut self, host: &str, addr: SocketAddr) -> Self {
+        self.dns_overrides.push((host.to_string(), addr));
+        self
+    }
+
+    /// Add `inputs` to every created prediction unless it sets them itself.
+    ///
+    /// See [`PredictionsApi::set_default_inputs`].
+    pub fn default_inputs(mut self, inputs: HashMap<String, Value>) -> Self {
+        self.default_inputs = Some(inputs);
+        self
+    }
+
+    /// Apply the configuration variables listed in
+    /// [`env_config`](crate::env_config), returning which were picked up.
+    ///
+    /// Values set on the builder win over the environment, whether they are
+    /// set before or after this call, though only those set before are
+    /// reported as overridden. An explicit
+    /// [`http_config`](Self::http_config) overrides every retry and timeout
+    /// variable. Invalid values fail with [`Error::InvalidInput`] naming
+    /// every offending variable.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use replicate_client::Client;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let (builder, applied) = Client::builder("your-api-token").apply_env()?;
+    /// println!("Configuration: {}", applied);
+    /// let client = builder.build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn apply_env(self) -> Result<(Self, AppliedEnv)> {
+        Ok(self.apply_env_settings(EnvSettings::from_env()?))
+    }
+
+    fn apply_env_settings(mut self, settings: EnvSettings) -> (Self, AppliedEnv) {
+        let mut applied = AppliedEnv::default();
+        if let Some(base_url) = settings.base_url.clone() {
+            let apply = self.base_url.is_none();
+            if apply {
+                self.base_url = Some(base_url);
+            }
+            applied.record(ENV_BASE_URL, apply);
+        }
+        settings.apply_http(&mut self.http_config, !self.http_config_set, &mut applied);
+        if let Some(webhook) = settings.default_webhook {
+            let apply = self.default_webhook.is_none();
+            if apply {
+                self.default_webhook = Some(webhook);
+            }
+            applied.record(ENV_DEFAULT_WEBHOOK, apply);
+        }
+        if let Some(strategy) = settings.file_encoding {
+            let apply = self.default_file_encoding.is_none();
+            if apply {
+                self.default_file_encoding = Some(strategy);
+            }
+            applied.record(ENV_FILE_ENCODING, apply);
+        }
+        (self, applied)
+    }
+
+    /// Cache collection `list` and `get` responses for `ttl`.
+    ///
+    /// Invalidation is purely time-based.
+    pub fn enable_collection_cache(mut self, ttl: Duration) -> Self {
+        self.collection_cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Warm up the connection in the background as soon as the client is built.
+    ///
+    /// Only takes effect when `build` is called inside a tokio runtime. Warmup
+    /// failures are logged and never affect the client.
+    pub fn eager_warmup(mut self, enabled: bool) -> Self {
+        self.eager_warmup = enabled;
+        self
+    }
+
+    /// Track the estimated cost of completed predictions with `prices`.
+    ///
+    /// Read the totals with [`Client::cost_summary`]; see [`CostTracker`]
+    /// for which predictions are recorded.
+    pub fn cost_tracking(mut self, prices: PriceTable) -> Self {
+        self.prices = Some(prices);
+        self
+    }
+
+    /// Check prediction inputs for secrets before they are sent.
+    ///
+    /// See [`InputSanitizer`] for what is flagged and how.
+    pub fn input_sanitizer(mut self, sanitizer: InputSanitizer) -> Self {
+        self.input_sanitizer = Some(sanitizer);
+        self
+    }
+
+    /// Set the largest prediction create body to send, or `None` for no limit.
+    ///
+    /// See [`PredictionsApi::set_max_body_size`].
+    pub fn max_body_size(mut self, limit: Option<usize>) -> Self {
+        self.max_body_size = Some(limit);
+        self
+    }
+
+    /// Notify `observer` of prediction lifecycle events and retries.
+    ///
+    /// See [`LifecycleObserver`] for which events are reported; use
+    /// [`LogObserver`](crate::LogObserver) to log them through `tracing`.
+    pub fn observer(mut self, observer: Arc<dyn LifecycleObserver>) -> Self {
+        self.observer = Some(ObserverHandle::new(observer));
+        self
+    }
+
+    /// Build the client.
+    pub fn build(mut self) -> Result<Client> {
+        self.http_config.dns_overrides.extend(self.dns_overrides);
+        let http = match self.base_url {
+            Some(base_url) => HttpClient::with_base_url_and_http_config(
+                self.api_token,
+                base_url,
+                self.http_config,
+            )?,
+            None => HttpClient::with_http_config(self.api_token, self.http_config)?,
+        };
+
+        if let Some(observer) = self.observer {
+            http.set_observer(Some(observer))?;
+        }
+
+        let mut client = Client::from_http_client(http);
+        if let Some(ttl) = self.collection_cache_ttl {
+            client.collections_api = CollectionsApi::with_cache(client.http.clone(), ttl);
+        }
+        if let Some(prices) = self.prices {
+            client
+                .predictions_api
+                .set_cost_tracker(Some(CostTracker::new(prices)));
+        }
+        if let Some(sanitizer) = self.input_sanitizer {
+            client.predictions_api.set_input_sanitizer(Some(sanitizer));
+        }
+        if self.default_webhook.is_some() {
+            client
+                .predictions_api
+                .set_default_webhook(self.default_webhook);
+        }
+        if let Some(strategy) = self.default_file_encoding {
+            client.predictions_api.set_default_file_encoding(strategy);
+        }
+        if let Some(inputs) = self.default_inputs {
+            client.predictions_api.set_default_inputs(inputs);
+        }
+        if let Some(mode) = self.run_mode {
+            client.predictions_api.set_default_run_mode(mode);
+        }
+        if let Some(limit) = self.max_body_size {
+            client.predictions_api.set_max_body_size(limit);
+        }
+
+        if self.eager_warmup
+            && let Ok(handle) = tokio::runtime::Handle::try_current()
+        {
+            let warmup_client = client.clone();
+            handle.spawn(async move {
+                if let Err(e) = warmup_client.warmup().await {
+                    tracing::warn!("Connection warmup failed: {}", e);
+                }
+            });
+        }
+
+        Ok(client)
+    }
 }
 
 #[cfg(test)]
@@ -229,6 +953,444 @@ mod tests {
         assert!(matches!(client.unwrap_err(), Error::Auth(_)));
     }
 
+    #[test]
+    fn test_client_builder() {
+        let client = Client::builder("test-token")
+            .base_url("http://localhost:8080")
+            .enable_collection_cache(Duration::from_secs(60))
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            client.http_client().base_url().as_str(),
+            "http://localhost:8080/"
+        );
+        assert_eq!(
+            client.collections().cache_ttl(),
+            Some(Duration::from_secs(60))
+        );
+
+        assert!(Client::builder("").build().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dns_overrides() {
+        use serde_json::json;
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let prediction = |status: &str| {
+            json!({
+                "id": "p1",
+                "model": "owner/model",
+                "version": "v1",
+                "status": status,
+                "output": "done",
+            })
+        };
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions"))
+            .and(header("host", "api.replicate.com"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(prediction("starting")))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions/p1"))
+            .and(header("host", "api.replicate.com"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(prediction("succeeded")))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/files"))
+            .and(header("host", "api.replicate.com"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "id": "file-1",
+                "name": "notes.txt",
+                "content_type": "text/plain",
+                "size": 5,
+                "etag": "etag",
+                "checksums": {},
+                "metadata": {},
+                "created_at": "2024-01-01T00:00:00Z",
+                "expires_at": null,
+                "urls": {"get": "https://api.replicate.com/v1/files/file-1"},
+            })))
+            .mount(&server)
+            .await;
+
+        // Terminate TLS in front of the plain HTTP mock server with a
+        // certificate for api.replicate.com issued by a test CA, so the
+        // client keeps the default https base URL and verifies the
+        // certificate against the overridden host.
+        let tls_addr = start_tls_proxy(*server.address()).await;
+        let unreachable: SocketAddr = "127.0.0.1:1".parse().unwrap();
+        let mut http_config = HttpConfig::default();
+        http_config.retry.max_retries = 0;
+        let untrusted = Client::builder("test-token")
+            .http_config(http_config.clone())
+            .resolve("api.replicate.com", tls_addr)
+            .build()
+            .unwrap();
+        // Without the test CA the certificate is rejected
+        assert!(untrusted.predictions().get("p1").await.is_err());
+
+        http_config.root_certificates = vec![
+            reqwest::Certificate::from_pem(include_bytes!("../tests/fixtures/tls/ca.pem")).unwrap(),
+        ];
+        let client = Client::builder("test-token")
+            .http_config(http_config)
+            .resolve("api.replicate.com", tls_addr)
+            .resolve("api.replicate.com", unreachable)
+            .build()
+            .unwrap();
+        assert_eq!(client.http_client().http_config().dns_overrides.len(), 2);
+
+        let inputs = HashMap::from([("prompt".to_string(), json!("hi"))]);
+        assert_eq!(
+            client.run_and_get_output("v1", inputs).await.unwrap(),
+            json!("done")
+        );
+        let file = client
+            .files()
+            .create_from_bytes(b"Hello", Some("notes.txt"), Some("text/plain"), None)
+            .await
+            .unwrap();
+        assert_eq!(file.id, "file-1");
+    }
+
+    /// TLS proxy for `backend` serving the api.replicate.com test
+    /// certificate from `tests/fixtures/tls`.
+    async fn start_tls_proxy(backend: SocketAddr) -> SocketAddr {
+        use tokio_native_tls::native_tls;
+
+        let identity = native_tls::Identity::from_pkcs8(
+            include_bytes!("../tests/fixtures/tls/api.replicate.com.pem"),
+            include_bytes!("../tests/fixtures/tls/api.replicate.com.key"),
+        )
+        .unwrap();
+        let acceptor =
+            tokio_native_tls::TlsAcceptor::from(native_tls::TlsAcceptor::new(identity).unwrap());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            loop {
+                let (socket, _) = listener.accept().await.unwrap();
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let Ok(mut tls) = acceptor.accept(socket).await else {
+                        return;
+                    };
+                    let mut upstream = tokio::net::TcpStream::connect(backend).await.unwrap();
+                    let _ = tokio::io::copy_bidirectional(&mut tls, &mut upstream).await;
+                });
+            }
+        });
+        addr
+    }
+
+    /// Minimal keep-alive HTTP server that counts accepted connections.
+    async fn start_counting_server() -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = connections.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 1024];
+                    loop {
+                        let n = match socket.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => n,
+                        };
+                        buf.extend_from_slice(&chunk[..n]);
+                        while let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                            buf.drain(..end + 4);
+                            let response = "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n{}";
+                            if socket.write_all(response.as_bytes()).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                });
+            }
+        });
+
+        (format!("http://{}", addr), connections)
+    }
+
+    #[tokio::test]
+    async fn test_warmup_reuses_connection() {
+        use std::sync::atomic::Ordering;
+
+        let (base_url, connections) = start_counting_server().await;
+        let client = Client::with_base_url("test-token", base_url).unwrap();
+
+        client.warmup().await.unwrap();
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+        client.http_client().get("/v1/predictions").await.unwrap();
+        client.http_client().get("/v1/files").await.unwrap();
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_eager_warmup() {
+        use std::sync::atomic::Ordering;
+
+        let (base_url, connections) = start_counting_server().await;
+        let _client = Client::builder("test-token")
+            .base_url(base_url)
+            .eager_warmup(true)
+            .build()
+            .unwrap();
+
+        for _ in 0..100 {
+            if connections.load(Ordering::SeqCst) > 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_warmup_failure_does_not_poison_client() {
+        let mut http_config = HttpConfig::default();
+        http_config.retry.max_retries = 0;
+        let client = Client::builder("test-token")
+            .base_url("http://127.0.0.1:1")
+            .http_config(http_config)
+            .build()
+            .unwrap();
+        assert!(client.warmup().await.is_err());
+        assert!(client.warmup().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_and_get_output() {
+        use serde_json::json;
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        for (id, status, output) in [
+            ("ok", "succeeded", json!(["a", "b"])),
+            ("bad", "failed", json!(null)),
+            ("stop", "canceled", json!(null)),
+        ] {
+            let prediction = |status: &str| {
+                json!({
+                    "id": id,
+                    "model": "owner/model",
+                    "version": id,
+                    "status": status,
+                    "output": output,
+                    "error": (status == "failed").then_some("boom"),
+                })
+            };
+            Mock::given(method("POST"))
+                .and(path("/v1/predictions"))
+                .and(body_partial_json(
+                    json!({"version": id, "input": {"prompt": "hi"}}),
+                ))
+                .respond_with(ResponseTemplate::new(201).set_body_json(prediction("starting")))
+                .mount(&server)
+                .await;
+            Mock::given(method("GET"))
+                .and(path(format!("/v1/predictions/{}", id)))
+                .respond_with(ResponseTemplate::new(200).set_body_json(prediction(status)))
+                .mount(&server)
+                .await;
+        }
+
+        let client = Client::with_base_url("test-token", server.uri()).unwrap();
+        let inputs = || HashMap::from([("prompt".to_string(), json!("hi"))]);
+        assert_eq!(
+            client.run_and_get_output("ok", inputs()).await.unwrap(),
+            json!(["a", "b"])
+        );
+        match client.run_and_get_output("bad", inputs()).await {
+            Err(Error::ModelExecution { error_message, .. }) => {
+                assert_eq!(error_message.as_deref(), Some("boom"))
+            }
+            other => panic!("Expected a model execution error, got {:?}", other),
+        }
+        assert!(matches!(
+            client.run_and_get_output("stop", inputs()).await,
+            Err(Error::ModelExecution { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_run_mode_applies_to_helpers() {
+        use serde_json::json;
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions"))
+            .and(header("prefer", "wait=2"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "id": "p1",
+                "model": "owner/model",
+                "version": "v1",
+                "status": "succeeded",
+                "output": "done",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let client = Client::builder("test-token")
+            .base_url(server.uri())
+            .run_mode(RunMode::Auto {
+                sync_cutoff: Duration::from_millis(1500),
+            })
+            .build()
+            .unwrap();
+        assert_eq!(
+            client
+                .run_and_get_output("v1", HashMap::new())
+                .await
+                .unwrap(),
+            json!("done")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_batch_templated() {
+        use serde_json::json;
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        for subject in ["fox", "owl"] {
+            Mock::given(method("POST"))
+                .and(path("/v1/predictions"))
+                .and(body_partial_json(json!({
+                    "version": "v1",
+                    "input": {"prompt": format!("A {} at dawn", subject), "tags": [subject]},
+                })))
+                .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                    "id": subject,
+                    "model": "owner/model",
+                    "version": "v1",
+                    "status": "starting",
+                })))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+
+        let client = Client::with_base_url("test-token", server.uri()).unwrap();
+        let template = InputTemplate::new(HashMap::from([
+            ("prompt".to_string(), json!("A {subject} at dawn")),
+            ("tags".to_string(), json!(["{subject}"])),
+        ]));
+        let row = |subject: &str| HashMap::from([("subject".to_string(), subject.to_string())]);
+
+        let results = client
+            .run_batch_templated("v1", &template, [row("fox"), row("owl")])
+            .await
+            .unwrap();
+        let ids: Vec<_> = results
+            .into_iter()
+            .map(|result| result.unwrap().id)
+            .collect();
+        assert_eq!(ids, ["fox", "owl"]);
+
+        // A bad row fails before any prediction is created
+        match client
+            .run_batch_templated("v1", &template, [row("fox"), HashMap::new()])
+            .await
+        {
+            Err(Error::InvalidInput(message)) => assert!(message.starts_with("Row 1: ")),
+            other => panic!("Expected a missing variable, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cost_tracking() {
+        use crate::models::common::HardwareSku;
+        use serde_json::json;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let prediction = |status: &str| {
+            json!({
+                "id": "p1",
+                "model": "owner/model",
+                "version": "v1",
+                "status": status,
+                "metrics": {"predict_time": 2.5},
+            })
+        };
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(prediction("starting")))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions/p1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(prediction("succeeded")))
+            .mount(&server)
+            .await;
+
+        let client = Client::builder("test-token")
+            .base_url(server.uri())
+            .cost_tracking(
+                PriceTable::new()
+                    .default_hardware(HardwareSku::CPU)
+                    .per_second(HardwareSku::CPU, 0.1),
+            )
+            .build()
+            .unwrap();
+        let created = client.create_prediction("v1").send().await.unwrap();
+        assert!(client.cost_summary().by_model.is_empty());
+
+        client
+            .create_prediction("v1")
+            .send_and_wait()
+            .await
+            .unwrap();
+        client
+            .with_correlation_id("job-1")
+            .unwrap()
+            .predictions()
+            .wait_for_completion(&created.id, None, None)
+            .await
+            .unwrap();
+
+        let summary = client.cost_summary();
+        assert_eq!(summary.by_model["owner/model"].predictions, 2);
+        assert!((summary.total() - 0.5).abs() < 1e-9);
+        assert!(
+            Client::new("test-token")
+                .unwrap()
+                .cost_summary()
+                .by_model
+                .is_empty()
+        );
+    }
+
     #[test]
     fn test_client_from_env_missing() {
         // Save current value and remove it for test
@@ -268,4 +1430,491 @@ mod tests {
             }
         }
     }
+
+    #[tokio::test]
+    async fn test_input_sanitizer() {
+        use crate::sanitize::{REDACTED, SanitizePolicy};
+        use serde_json::json;
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions"))
+            .and(body_partial_json(
+                json!({"input": {"prompt": "hi", "options": {"api_key": REDACTED}}}),
+            ))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "id": "p1",
+                "model": "owner/model",
+                "version": "v1",
+                "status": "starting",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = |policy| {
+            Client::builder("test-token")
+                .base_url(server.uri())
+                .input_sanitizer(InputSanitizer::with_defaults(policy))
+                .build()
+                .unwrap()
+        };
+        let create = |client: Client| async move {
+            client
+                .create_prediction("v1")
+                .input("prompt", "hi")
+                .input("options", json!({"api_key": "r8_abc"}))
+                .send()
+                .await
+        };
+
+        match create(client(SanitizePolicy::Reject)).await {
+            Err(Error::InvalidInput(message)) => assert!(message.contains("options.api_key")),
+            other => panic!("Expected invalid input, got {:?}", other),
+        }
+        create(client(SanitizePolicy::Redact)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reconfigure_shared_client() {
+        use serde_json::json;
+        use std::sync::Arc;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions/p1"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_delay(Duration::from_millis(300))
+                    .set_body_json(json!({
+                        "id": "p1",
+                        "model": "owner/model",
+                        "version": "v1",
+                        "status": "processing",
+                    })),
+            )
+            .mount(&server)
+            .await;
+
+        let client = Arc::new(Client::with_base_url("test-token", server.uri()).unwrap());
+        let clone = client.clone();
+        assert!(client.predictions().get("p1").await.is_ok());
+
+        clone
+            .reconfigure(|config| {
+                config.timeout.request_timeout = Some(Duration::from_millis(50));
+                config.retry.max_retries = 0;
+            })
+            .unwrap();
+        assert_eq!(
+            client.current_http_config().timeout.request_timeout,
+            Some(Duration::from_millis(50))
+        );
+        assert_eq!(client.http_client().current_retry_config().max_retries, 0);
+        // The borrowing getters keep the configuration the client was built with
+        assert_eq!(client.http_config().retry.max_retries, 3);
+        // API handles pick up the new configuration too
+        assert!(client.predictions().get("p1").await.is_err());
+
+        // A panicking update leaves the configuration as it was
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            clone.reconfigure(|_| panic!("update failed"))
+        }));
+        assert!(result.is_err());
+        assert_eq!(client.http_client().current_retry_config().max_retries, 0);
+        clone
+            .reconfigure(|config| config.timeout.request_timeout = None)
+            .unwrap();
+        assert!(client.predictions().get("p1").await.is_ok());
+    }
+
+    #[test]
+    fn test_configure_retries_is_per_instance() {
+        let mut client = Client::new("test-token").unwrap();
+        let copy = client.clone();
+        client
+            .configure_retries(7, Duration::from_millis(10), Duration::from_secs(1))
+            .unwrap();
+
+        assert_eq!(client.http_config().retry.max_retries, 7);
+        assert_eq!(client.current_http_config().retry.max_retries, 7);
+        assert_eq!(copy.current_http_config().retry.max_retries, 3);
+
+        // Shared reconfiguration no longer reaches the detached client
+        copy.reconfigure(|config| config.retry.max_retries = 1)
+            .unwrap();
+        assert_eq!(client.current_http_config().retry.max_retries, 7);
+    }
+
+    #[derive(Default)]
+    struct RecordingObserver {
+        events: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl LifecycleObserver for RecordingObserver {
+        fn on_created(&self, prediction: &crate::Prediction) {
+            self.record(format!("created {}", prediction.id));
+        }
+
+        fn on_status_change(
+            &self,
+            _prediction: &crate::Prediction,
+            old: &crate::PredictionStatus,
+            new: &crate::PredictionStatus,
+        ) {
+            self.record(format!("{:?} -> {:?}", old, new));
+        }
+
+        fn on_terminal(&self, prediction: &crate::Prediction) {
+            self.record(format!("terminal {:?}", prediction.status));
+        }
+
+        fn on_retry(&self, path: &str, attempt: u32) {
+            self.record(format!("retry {} {}", path, attempt));
+        }
+    }
+
+    impl RecordingObserver {
+        fn record(&self, event: String) {
+            self.events.lock().unwrap().push(event);
+        }
+    }
+
+    struct PanickingObserver;
+
+    impl LifecycleObserver for PanickingObserver {
+        fn on_created(&self, _prediction: &crate::Prediction) {
+            panic!("observer failure");
+        }
+
+        fn on_terminal(&self, _prediction: &crate::Prediction) {
+            panic!("observer failure");
+        }
+    }
+
+    async fn lifecycle_server() -> wiremock::MockServer {
+        use serde_json::json;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let prediction = |status| {
+            json!({
+                "id": "p1",
+                "model": "owner/model",
+                "version": "v1",
+                "status": status,
+            })
+        };
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(prediction("starting")))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions/p1"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions/p1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(prediction("processing")))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions/p1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(prediction("succeeded")))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    fn observed_client(
+        server: &wiremock::MockServer,
+        observer: Arc<dyn LifecycleObserver>,
+    ) -> Client {
+        let mut http_config = HttpConfig::default();
+        http_config.retry.min_delay = Duration::from_millis(1);
+        http_config.retry.max_delay = Duration::from_millis(5);
+        Client::builder("test-token")
+            .base_url(server.uri())
+            .http_config(http_config)
+            .observer(observer)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_lifecycle_observer() {
+        use crate::api::predictions::RunOptions;
+
+        let server = lifecycle_server().await;
+        let observer = Arc::new(RecordingObserver::default());
+        let client = observed_client(&server, observer.clone());
+
+        let prediction = client
+            .create_prediction("v1")
+            .input("prompt", "hi")
+            .run_options(RunOptions::new().poll_interval(Duration::from_millis(10)))
+            .send_and_wait()
+            .await
+            .unwrap();
+        assert!(prediction.is_successful());
+        assert_eq!(
+            *observer.events.lock().unwrap(),
+            vec![
+                "created p1",
+                "retry /v1/predictions/p1 1",
+                "Starting -> Processing",
+                "Processing -> Succeeded",
+                "terminal Succeeded",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_panicking_observer_is_ignored() {
+        use crate::api::predictions::RunOptions;
+
+        let server = lifecycle_server().await;
+        let client = observed_client(&server, Arc::new(PanickingObserver));
+        let prediction = client
+            .create_prediction("v1")
+            .run_options(RunOptions::new().poll_interval(Duration::from_millis(10)))
+            .send_and_wait()
+            .await
+            .unwrap();
+        assert!(prediction.is_successful());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_in_flight_requests() {
+        use serde_json::json;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        for (id, delay) in [("fast-1", 100), ("fast-2", 100), ("slow", 3000)] {
+            Mock::given(method("GET"))
+                .and(path(format!("/v1/predictions/{}", id)))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .set_delay(Duration::from_millis(delay))
+                        .set_body_json(json!({
+                            "id": id,
+                            "model": "owner/model",
+                            "version": "v1",
+                            "status": "processing",
+                        })),
+                )
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let client = Client::with_base_url("test-token", server.uri()).unwrap();
+        let requests: Vec<_> = ["fast-1", "fast-2", "slow"]
+            .into_iter()
+            .map(|id| {
+                let client = client.clone();
+                tokio::spawn(async move { client.predictions().get(id).await })
+            })
+            .collect();
+        tokio::time::sleep(Duration::from_millis(30)).await;
+
+        let report = client.clone().shutdown(Duration::from_millis(600)).await;
+        assert_eq!(
+            report,
+            ShutdownReport {
+                completed: 2,
+                abandoned: 1
+            }
+        );
+        assert!(client.is_closed());
+        for request in requests.into_iter().take(2) {
+            assert!(request.await.unwrap().is_ok());
+        }
+
+        assert!(matches!(
+            client.predictions().get("fast-1").await,
+            Err(Error::ClientClosed)
+        ));
+        assert!(matches!(
+            client
+                .create_prediction("v1")
+                .input("prompt", "hi")
+                .send()
+                .await,
+            Err(Error::ClientClosed)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_lets_running_wait_finish() {
+        use crate::api::predictions::RunOptions;
+        use serde_json::json;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let prediction = |status| {
+            json!({
+                "id": "p1",
+                "model": "owner/model",
+                "version": "v1",
+                "status": status,
+            })
+        };
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(prediction("starting")))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions/p1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(prediction("processing")))
+            .up_to_n_times(3)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions/p1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(prediction("succeeded")))
+            .mount(&server)
+            .await;
+
+        let client = Client::with_base_url("test-token", server.uri()).unwrap();
+        let run = tokio::spawn({
+            let client = client.clone();
+            async move {
+                client
+                    .create_prediction("v1")
+                    .run_options(RunOptions::new().poll_interval(Duration::from_millis(50)))
+                    .send_and_wait()
+                    .await
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let report = client.shutdown(Duration::from_secs(5)).await;
+        assert_eq!(report.completed, 1);
+        assert_eq!(report.abandoned, 0);
+        assert!(run.await.unwrap().unwrap().is_successful());
+    }
+
+    #[tokio::test]
+    async fn test_apply_env_precedence() {
+        use crate::env_config::{ENV_MAX_RETRIES, ENV_REQUEST_TIMEOUT_SECS};
+        use serde_json::json;
+        use wiremock::matchers::{body_partial_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions"))
+            .and(body_partial_json(
+                json!({"webhook": "https://example.com/env"}),
+            ))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "id": "p1",
+                "model": "owner/model",
+                "version": "v1",
+                "status": "starting",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let env = HashMap::from([
+            (ENV_BASE_URL, server.uri()),
+            (ENV_MAX_RETRIES, "7".to_string()),
+            (ENV_REQUEST_TIMEOUT_SECS, "15".to_string()),
+            (ENV_DEFAULT_WEBHOOK, "https://example.com/env".to_string()),
+            (ENV_FILE_ENCODING, "base64".to_string()),
+        ]);
+        let settings = || EnvSettings::read(|name| env.get(name).cloned()).unwrap();
+
+        let (builder, applied) = Client::builder("test-token").apply_env_settings(settings());
+        assert!(applied.overridden.is_empty());
+        let client = builder.build().unwrap();
+        assert_eq!(client.http_config().retry.max_retries, 7);
+        assert_eq!(
+            client.timeout_config().request_timeout,
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(
+            client.http_client().base_url().as_str(),
+            format!("{}/", server.uri())
+        );
+        assert_eq!(
+            client
+                .create_prediction("v1")
+                .request()
+                .file_encoding_strategy,
+            FileEncodingStrategy::Base64DataUrl
+        );
+        client.create_prediction("v1").send().await.unwrap();
+
+        // Explicit builder values win, before or after applying the environment
+        let (builder, applied) = Client::builder("test-token")
+            .http_config(HttpConfig::default())
+            .apply_env_settings(settings());
+        let client = builder
+            .base_url("https://api.example.com")
+            .default_webhook("https://example.com/explicit")
+            .default_file_encoding(FileEncodingStrategy::Auto)
+            .default_inputs(HashMap::from([("seed".to_string(), Value::from(42))]))
+            .build()
+            .unwrap();
+        assert_eq!(
+            applied.applied,
+            [ENV_BASE_URL, ENV_DEFAULT_WEBHOOK, ENV_FILE_ENCODING]
+        );
+        assert_eq!(
+            applied.overridden,
+            [ENV_MAX_RETRIES, ENV_REQUEST_TIMEOUT_SECS]
+        );
+        assert_eq!(client.http_config().retry.max_retries, 3);
+        assert_eq!(
+            client.http_client().base_url().as_str(),
+            "https://api.example.com/"
+        );
+        assert_eq!(
+            client.predictions().default_webhook(),
+            Some("https://example.com/explicit")
+        );
+        assert_eq!(
+            client.predictions().default_file_encoding(),
+            &FileEncodingStrategy::Auto
+        );
+        assert_eq!(client.predictions().default_inputs()["seed"], 42);
+    }
+
+    #[test]
+    fn test_builder_max_body_size() {
+        let client = Client::builder("test-token").build().unwrap();
+        assert!(client.predictions().max_body_size().is_some());
+
+        let client = Client::builder("test-token")
+            .max_body_size(Some(2048))
+            .build()
+            .unwrap();
+        assert_eq!(client.predictions().max_body_size(), Some(2048));
+
+        let client = Client::builder("test-token")
+            .max_body_size(None)
+            .build()
+            .unwrap();
+        assert_eq!(client.predictions().max_body_size(), None);
+    }
 }
@@ -0,0 +1,267 @@
+//! Estimated prediction costs from user-supplied prices.
+
+use crate::models::common::HardwareSku;
+use crate::models::prediction::Prediction;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+/// Prices per input and output token, for models billed by token.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TokenPrices {
+    /// Price of one input token
+    pub input: f64,
+    /// Price of one output token
+    pub output: f64,
+}
+
+/// Prices used to estimate what a prediction cost.
+///
+/// The API does not report prices, so they are supplied by the caller in any
+/// currency unit. Predictions are priced by token when [`per_token`](Self::per_token)
+/// is set and their metrics carry token counts, and otherwise by
+/// `metrics.predict_time` times the per-second price of the hardware the
+/// model runs on. Predictions do not say which hardware ran them, so it is
+/// looked up in [`model_hardware`](Self::model_hardware), falling back to
+/// [`default_hardware`](Self::default_hardware).
+///
+/// # Examples
+///
+/// ```
+/// use replicate_client::{HardwareSku, PriceTable};
+///
+/// let prices = PriceTable::new()
+///     .per_second(HardwareSku::NVIDIA_T4, 0.000225)
+///     .per_second(HardwareSku::NVIDIA_A100_80GB, 0.0014)
+///     .model_hardware("stability-ai/sdxl", HardwareSku::NVIDIA_A100_80GB)
+///     .default_hardware(HardwareSku::NVIDIA_T4);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PriceTable {
+    /// Price per second of prediction time, by hardware
+    pub per_second_by_hardware: HashMap<HardwareSku, f64>,
+    /// Prices per token, for models reporting token counts
+    pub per_token: Option<TokenPrices>,
+    /// Hardware each model (`owner/name`) runs on
+    pub model_hardware: HashMap<String, HardwareSku>,
+    /// Hardware assumed for models missing from `model_hardware`
+    pub default_hardware: Option<HardwareSku>,
+}
+
+impl PriceTable {
+    /// Create an empty price table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the price per second of prediction time on a hardware SKU.
+    pub fn per_second(mut self, sku: HardwareSku, price: f64) -> Self {
+        self.per_second_by_hardware.insert(sku, price);
+        self
+    }
+
+    /// Set the prices per input and output token.
+    pub fn per_token(mut self, input: f64, output: f64) -> Self {
+        self.per_token = Some(TokenPrices { input, output });
+        self
+    }
+
+    /// Set the hardware a model (`owner/name`) runs on.
+    pub fn model_hardware(mut self, model: impl Into<String>, sku: HardwareSku) -> Self {
+        self.model_hardware.insert(model.into(), sku);
+        self
+    }
+
+    /// Set the hardware assumed for models without a specific entry.
+    pub fn default_hardware(mut self, sku: HardwareSku) -> Self {
+        self.default_hardware = Some(sku);
+        self
+    }
+
+    /// Estimate the cost of a prediction, or `None` if it cannot be priced.
+    pub fn estimate(&self, prediction: &Prediction) -> Option<f64> {
+        let metric = |name: &str| prediction.metrics.as_ref()?.get(name)?.as_f64();
+
+        if let Some(prices) = self.per_token {
+            let input = metric("input_token_count");
+            let output = metric("output_token_count");
+            if input.is_some() || output.is_some() {
+                return Some(
+                    input.unwrap_or_default() * prices.input
+                        + output.unwrap_or_default() * prices.output,
+                );
+            }
+        }
+
+        let sku = self
+            .model_hardware
+            .get(&prediction.model)
+            .or(self.default_hardware.as_ref())?;
+        let price = self.per_second_by_hardware.get(sku)?;
+        Some(metric("predict_time")? * price)
+    }
+}
+
+/// Estimated costs recorded for one model.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct ModelCost {
+    /// Completed predictions recorded
+    pub predictions: u64,
+    /// Predictions that could not be priced and are missing from `total`
+    pub unpriced: u64,
+    /// Sum of the estimated costs
+    pub total: f64,
+}
+
+/// Estimated costs recorded by a [`CostTracker`], grouped by model.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CostSummary {
+    /// Costs by model (`owner/name`), in name order
+    pub by_model: BTreeMap<String, ModelCost>,
+}
+
+impl CostSummary {
+    /// Sum of the estimated costs of all models.
+    pub fn total(&self) -> f64 {
+        self.by_model.values().map(|cost| cost.total).sum()
+    }
+}
+
+/// Records the estimated cost of completed predictions.
+///
+/// Configure it with [`ClientBuilder::cost_tracking`](crate::ClientBuilder::cost_tracking);
+/// predictions awaited through `send_and_wait` or
+/// [`wait_for_completion`](crate::api::PredictionsApi::wait_for_completion)
+/// are then recorded, and [`Client::cost_summary`](crate::Client::cost_summary)
+/// reports the totals. Clones share the recorded costs.
+#[derive(Debug, Clone)]
+pub struct CostTracker {
+    prices: Arc<PriceTable>,
+    summary: Arc<Mutex<CostSummary>>,
+}
+
+impl CostTracker {
+    /// Create a tracker estimating costs with `prices`.
+    pub fn new(prices: PriceTable) -> Self {
+        Self {
+            prices: Arc::new(prices),
+            summary: Arc::new(Mutex::new(CostSummary::default())),
+        }
+    }
+
+    /// Get the prices used for estimates.
+    pub fn prices(&self) -> &PriceTable {
+        &self.prices
+    }
+
+    /// Record the estimated cost of a completed prediction.
+    pub fn record(&self, prediction: &Prediction) {
+        let cost = self.prices.estimate(prediction);
+        let mut summary = self.summary.lock().unwrap();
+        let entry = summary
+            .by_model
+            .entry(prediction.model.clone())
+            .or_default();
+        entry.predictions += 1;
+        match cost {
+            Some(cost) => entry.total += cost,
+            None => entry.unpriced += 1,
+        }
+    }
+
+    /// Get the costs recorded so far.
+    pub fn summary(&self) -> CostSummary {
+        self.summary.lock().unwrap().clone()
+    }
+
+    /// Forget all recorded costs.
+    pub fn reset(&self) {
+        *self.summary.lock().unwrap() = CostSummary::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn prediction(model: &str, metrics: serde_json::Value) -> Prediction {
+        serde_json::from_value(json!({
+            "id": "p1",
+            "model": model,
+            "version": "v1",
+            "status": "succeeded",
+            "metrics": metrics,
+        }))
+        .unwrap()
+    }
+
+    fn prices() -> PriceTable {
+        PriceTable::new()
+            .per_second(HardwareSku::NVIDIA_T4, 0.5)
+            .per_second(HardwareSku::NVIDIA_H100, 2.0)
+            .model_hardware("owner/big", HardwareSku::NVIDIA_H100)
+            .default_hardware(HardwareSku::NVIDIA_T4)
+    }
+
+    #[test]
+    fn test_estimate() {
+        let prices = prices();
+        let estimate = |model, metrics| prices.estimate(&prediction(model, metrics));
+
+        assert_eq!(
+            estimate("owner/big", json!({"predict_time": 3.0})),
+            Some(6.0)
+        );
+        assert_eq!(
+            estimate("owner/small", json!({"predict_time": 3.0})),
+            Some(1.5)
+        );
+        assert_eq!(estimate("owner/small", json!({})), None);
+
+        // Token counts are ignored until token prices are set
+        let tokens =
+            json!({"predict_time": 1.0, "input_token_count": 100, "output_token_count": 10});
+        assert_eq!(estimate("owner/llm", tokens.clone()), Some(0.5));
+        let prices = prices.clone().per_token(0.001, 0.01);
+        assert_eq!(prices.estimate(&prediction("owner/llm", tokens)), Some(0.2));
+
+        let no_hardware = PriceTable::new().per_second(HardwareSku::CPU, 1.0);
+        assert_eq!(
+            no_hardware.estimate(&prediction("owner/small", json!({"predict_time": 1.0}))),
+            None
+        );
+    }
+
+    #[test]
+    fn test_tracker_groups_by_model() {
+        let tracker = CostTracker::new(prices());
+        let shared = tracker.clone();
+        tracker.record(&prediction("owner/big", json!({"predict_time": 1.0})));
+        shared.record(&prediction("owner/big", json!({"predict_time": 0.5})));
+        tracker.record(&prediction("owner/small", json!({"predict_time": 4.0})));
+        tracker.record(&prediction("owner/small", json!(null)));
+
+        let summary = tracker.summary();
+        assert_eq!(
+            summary.by_model["owner/big"],
+            ModelCost {
+                predictions: 2,
+                unpriced: 0,
+                total: 3.0
+            }
+        );
+        assert_eq!(
+            summary.by_model["owner/small"],
+            ModelCost {
+                predictions: 2,
+                unpriced: 1,
+                total: 2.0
+            }
+        );
+        assert_eq!(summary.total(), 5.0);
+
+        shared.reset();
+        assert_eq!(tracker.summary(), CostSummary::default());
+    }
+}
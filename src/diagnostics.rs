@@ -0,0 +1,198 @@
+//! Snapshot of client state for bug reports and debugging.
+
+use crate::VERSION;
+use crate::http::{ClientStats, HttpClient, HttpConfig, RateLimitInfo};
+use serde::Serialize;
+use std::fmt;
+use std::time::Duration;
+
+/// Scheme used in the `Authorization` header.
+const AUTH_SCHEME: &str = "Token";
+
+/// Summary of a client's configuration and activity.
+///
+/// Returned by [`Client::diagnostics`](crate::Client::diagnostics). It never
+/// contains the API token or the values of extra request headers, so it is
+/// safe to paste into a bug report. Serializes to JSON with durations in
+/// milliseconds and fields in declaration order.
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostics {
+    /// Version of this crate
+    pub crate_version: String,
+    /// Base URL requests are sent to
+    pub base_url: String,
+    /// Scheme of the `Authorization` header; the token itself is omitted
+    pub auth_scheme: String,
+    /// Whether transient failures are retried
+    pub retries_enabled: bool,
+    /// Effective retry and timeout configuration
+    pub http_config: HttpConfig,
+    /// Names of extra headers sent with every request, without their values
+    pub extra_headers: Vec<String>,
+    /// Request ID of the most recent response that carried one
+    pub last_request_id: Option<String>,
+    /// Rate-limit headers of the most recent response that had any
+    pub last_rate_limit: Option<RateLimitInfo>,
+    /// Cumulative request counters
+    pub stats: ClientStats,
+}
+
+impl Diagnostics {
+    /// Take a snapshot of an HTTP client.
+    pub(crate) fn from_http(http: &HttpClient) -> Self {
+        let mut extra_headers: Vec<String> = http
+            .request_headers()
+            .keys()
+            .map(|name| name.as_str().to_string())
+            .collect();
+        extra_headers.sort();
+        extra_headers.dedup();
+
+        let http_config = http.current_http_config();
+        Self {
+            crate_version: VERSION.to_string(),
+            base_url: http.base_url().to_string(),
+            auth_scheme: AUTH_SCHEME.to_string(),
+            retries_enabled: http_config.retry.max_retries > 0,
+            http_config,
+            extra_headers,
+            last_request_id: http.last_request_id(),
+            last_rate_limit: http.last_rate_limit(),
+            stats: http.stats(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let timeout = |timeout: Option<Duration>| match timeout {
+            Some(timeout) => format!("{:?}", timeout),
+            None => "none".to_string(),
+        };
+        let optional = |value: Option<&str>| value.unwrap_or("none").to_string();
+        let retry = &self.http_config.retry;
+        let timeouts = &self.http_config.timeout;
+        let stats = &self.stats;
+
+        writeln!(f, "replicate-rs {}", self.crate_version)?;
+        writeln!(f, "  Base URL:        {}", self.base_url)?;
+        writeln!(f, "  Auth:            {} (token hidden)", self.auth_scheme)?;
+        if self.retries_enabled {
+            writeln!(
+                f,
+                "  Retries:         up to {}, {:?} to {:?}, backoff x{}",
+                retry.max_retries, retry.min_delay, retry.max_delay, retry.base_multiplier
+            )?;
+        } else {
+            writeln!(f, "  Retries:         disabled")?;
+        }
+        writeln!(
+            f,
+            "  Timeouts:        connect {}, request {}, stream idle {}",
+            timeout(timeouts.connect_timeout),
+            timeout(timeouts.request_timeout),
+            timeout(timeouts.stream_idle_timeout)
+        )?;
+        let extra_headers = match self.extra_headers.is_empty() {
+            true => "none".to_string(),
+            false => self.extra_headers.join(", "),
+        };
+        writeln!(f, "  Extra headers:   {}", extra_headers)?;
+        writeln!(
+            f,
+            "  Last request ID: {}",
+            optional(self.last_request_id.as_deref())
+        )?;
+        match &self.last_rate_limit {
+            Some(rate_limit) => writeln!(
+                f,
+                "  Rate limit:      {} of {} remaining, resets {}",
+                rate_limit
+                    .remaining
+                    .map_or("?".to_string(), |remaining| remaining.to_string()),
+                rate_limit
+                    .limit
+                    .map_or("?".to_string(), |limit| limit.to_string()),
+                optional(rate_limit.reset.as_deref())
+            )?,
+            None => writeln!(f, "  Rate limit:      unknown")?,
+        }
+        write!(
+            f,
+            "  Requests:        {} sent, {} succeeded, {} API errors ({} rate limited), {} transport errors",
+            stats.requests,
+            stats.successes,
+            stats.api_errors,
+            stats.rate_limited,
+            stats.transport_errors
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Client;
+    use serde_json::json;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const TOKEN: &str = "r8_super_secret_token";
+
+    #[tokio::test]
+    async fn test_diagnostics_hide_secrets() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("x-request-id", "req-42")
+                    .insert_header("ratelimit-limit", "600")
+                    .insert_header("ratelimit-remaining", "599")
+                    .set_body_json(json!({"results": [], "next": null, "previous": null})),
+            )
+            .mount(&server)
+            .await;
+
+        let client = Client::with_base_url(TOKEN, server.uri())
+            .unwrap()
+            .with_correlation_id("checkout-42")
+            .unwrap();
+        client.predictions().list(None).await.unwrap();
+
+        let diagnostics = client.diagnostics();
+        assert_eq!(diagnostics.last_request_id.as_deref(), Some("req-42"));
+        assert_eq!(
+            diagnostics.last_rate_limit.as_ref().unwrap().remaining,
+            Some(599)
+        );
+        assert_eq!(diagnostics.stats.requests, 1);
+        assert_eq!(diagnostics.extra_headers, ["x-correlation-id"]);
+
+        let rendered = diagnostics.to_string();
+        let serialized = serde_json::to_string(&diagnostics).unwrap();
+        for output in [&rendered, &serialized] {
+            assert!(!output.contains(TOKEN));
+            assert!(!output.contains("checkout-42"));
+        }
+        assert!(rendered.contains("Last request ID: req-42"));
+        assert!(rendered.contains("599 of 600 remaining"));
+    }
+
+    #[test]
+    fn test_diagnostics_json_is_stable() {
+        let client = Client::with_base_url(TOKEN, "https://gateway.example.com/replicate").unwrap();
+        let json = serde_json::to_string(&client.diagnostics()).unwrap();
+        let expected = concat!(
+            r#"{"crate_version":"VERSION","base_url":"https://gateway.example.com/replicate/","#,
+            r#""auth_scheme":"Token","retries_enabled":true,"http_config":{"retry":{"#,
+            r#""max_retries":3,"min_delay_ms":500,"max_delay_ms":30000,"base_multiplier":2,"#,
+            r#""retry_non_idempotent":true,"immediate_first_retry":false},"#,
+            r#""timeout":{"connect_timeout_ms":30000,"request_timeout_ms":60000,"#,
+            r#""stream_idle_timeout_ms":60000},"compress_requests":false},"extra_headers":[],"#,
+            r#""last_request_id":null,"#,
+            r#""last_rate_limit":null,"stats":{"requests":0,"successes":0,"api_errors":0,"#,
+            r#""rate_limited":0,"transport_errors":0}}"#
+        );
+        assert_eq!(json, expected.replace("VERSION", VERSION));
+    }
+}
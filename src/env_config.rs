@@ -0,0 +1,348 @@
+//! Client configuration from environment variables.
+//!
+//! [`HttpConfig::from_env`] and [`ClientBuilder::apply_env`] read these
+//! variables; unset or empty variables are ignored:
+//!
+//! | Variable | Value |
+//! |----------|-------|
+//! | `REPLICATE_BASE_URL` | API base URL |
+//! | `REPLICATE_MAX_RETRIES` | Retries per request |
+//! | `REPLICATE_RETRY_MIN_MS` | Shortest delay between retries, in milliseconds |
+//! | `REPLICATE_RETRY_MAX_MS` | Longest delay between retries, in milliseconds |
+//! | `REPLICATE_CONNECT_TIMEOUT_SECS` | Connect timeout in seconds, `0` for none |
+//! | `REPLICATE_REQUEST_TIMEOUT_SECS` | Request timeout in seconds, `0` for none |
+//! | `REPLICATE_STREAM_IDLE_TIMEOUT_SECS` | Output stream idle timeout in seconds, `0` for none |
+//! | `REPLICATE_DEFAULT_WEBHOOK` | Webhook URL of predictions that set none |
+//! | `REPLICATE_FILE_ENCODING` | File encoding of predictions that set none: `base64`, `multipart` or `auto` |
+//!
+//! Invalid values fail with [`Error::InvalidInput`] naming every offending
+//! variable. `REPLICATE_BASE_URL`, `REPLICATE_DEFAULT_WEBHOOK` and
+//! `REPLICATE_FILE_ENCODING` only apply to [`ClientBuilder::apply_env`].
+//!
+//! [`HttpConfig::from_env`]: crate::http::HttpConfig::from_env
+//! [`ClientBuilder::apply_env`]: crate::ClientBuilder::apply_env
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::error::{Error, Result};
+use crate::http::HttpConfig;
+use crate::models::file::FileEncodingStrategy;
+
+/// API base URL.
+pub const ENV_BASE_URL: &str = "REPLICATE_BASE_URL";
+/// Retries per request.
+pub const ENV_MAX_RETRIES: &str = "REPLICATE_MAX_RETRIES";
+/// Shortest delay between retries, in milliseconds.
+pub const ENV_RETRY_MIN_MS: &str = "REPLICATE_RETRY_MIN_MS";
+/// Longest delay between retries, in milliseconds.
+pub const ENV_RETRY_MAX_MS: &str = "REPLICATE_RETRY_MAX_MS";
+/// Connect timeout in seconds, `0` for none.
+pub const ENV_CONNECT_TIMEOUT_SECS: &str = "REPLICATE_CONNECT_TIMEOUT_SECS";
+/// Request timeout in seconds, `0` for none.
+pub const ENV_REQUEST_TIMEOUT_SECS: &str = "REPLICATE_REQUEST_TIMEOUT_SECS";
+/// Output stream idle timeout in seconds, `0` for none.
+pub const ENV_STREAM_IDLE_TIMEOUT_SECS: &str = "REPLICATE_STREAM_IDLE_TIMEOUT_SECS";
+/// Webhook URL of predictions that set none.
+pub const ENV_DEFAULT_WEBHOOK: &str = "REPLICATE_DEFAULT_WEBHOOK";
+/// File encoding of predictions that set none: `base64`, `multipart` or `auto`.
+pub const ENV_FILE_ENCODING: &str = "REPLICATE_FILE_ENCODING";
+
+/// Which configuration variables were picked up from the environment.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AppliedEnv {
+    /// Variables whose values were applied
+    pub applied: Vec<&'static str>,
+    /// Variables that were set but lost to a value set explicitly on the builder
+    pub overridden: Vec<&'static str>,
+}
+
+impl AppliedEnv {
+    /// Check if no variable was set.
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty() && self.overridden.is_empty()
+    }
+
+    pub(crate) fn record(&mut self, name: &'static str, applied: bool) {
+        if applied {
+            self.applied.push(name);
+        } else {
+            self.overridden.push(name);
+        }
+    }
+}
+
+/// Lists variable names only, as values such as webhook URLs may hold secrets.
+impl fmt::Display for AppliedEnv {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.applied.is_empty() {
+            write!(f, "no environment settings applied")?;
+        } else {
+            write!(f, "applied {}", self.applied.join(", "))?;
+        }
+        if !self.overridden.is_empty() {
+            write!(f, "; overridden by builder: {}", self.overridden.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Settings read from configuration variables, `None` where unset.
+#[derive(Debug, Default)]
+pub(crate) struct EnvSettings {
+    pub base_url: Option<String>,
+    pub default_webhook: Option<String>,
+    pub file_encoding: Option<FileEncodingStrategy>,
+    max_retries: Option<u32>,
+    retry_min: Option<Duration>,
+    retry_max: Option<Duration>,
+    connect_timeout: Option<Option<Duration>>,
+    request_timeout: Option<Option<Duration>>,
+    stream_idle_timeout: Option<Option<Duration>>,
+}
+
+impl EnvSettings {
+    /// Read the settings from the process environment.
+    pub fn from_env() -> Result<Self> {
+        Self::read(|name| std::env::var(name).ok())
+    }
+
+    /// Read the settings through `lookup`, reporting all invalid values at once.
+    pub fn read(lookup: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let mut invalid = Vec::new();
+        let mut vars = Vars {
+            lookup: &lookup,
+            invalid: &mut invalid,
+        };
+        let url = |value: &str| {
+            url::Url::parse(value)
+                .ok()
+                .filter(|url| matches!(url.scheme(), "http" | "https"))
+                .map(|_| value.to_string())
+        };
+        let millis = |value: &str| value.parse().ok().map(Duration::from_millis);
+        let timeout = |value: &str| {
+            value.parse().ok().map(|seconds| match seconds {
+                0 => None,
+                seconds => Some(Duration::from_secs(seconds)),
+            })
+        };
+
+        let settings = Self {
+            base_url: vars.get(ENV_BASE_URL, "an http(s) URL", url),
+            default_webhook: vars.get(ENV_DEFAULT_WEBHOOK, "an http(s) URL", url),
+            file_encoding: vars.get(ENV_FILE_ENCODING, "base64, multipart or auto", |value| {
+                match value.to_ascii_lowercase().as_str() {
+                    "base64" => Some(FileEncodingStrategy::Base64DataUrl),
+                    "multipart" => Some(FileEncodingStrategy::Multipart),
+                    "auto" => Some(FileEncodingStrategy::Auto),
+                    _ => None,
+                }
+            }),
+            max_retries: vars.get(ENV_MAX_RETRIES, "a whole number", |value| {
+                value.parse().ok()
+            }),
+            retry_min: vars.get(ENV_RETRY_MIN_MS, "milliseconds", millis),
+            retry_max: vars.get(ENV_RETRY_MAX_MS, "milliseconds", millis),
+            connect_timeout: vars.get(ENV_CONNECT_TIMEOUT_SECS, "seconds", timeout),
+            request_timeout: vars.get(ENV_REQUEST_TIMEOUT_SECS, "seconds", timeout),
+            stream_idle_timeout: vars.get(ENV_STREAM_IDLE_TIMEOUT_SECS, "seconds", timeout),
+        };
+        if let (Some(min), Some(max)) = (settings.retry_min, settings.retry_max)
+            && min > max
+        {
+            invalid.push(format!(
+                "{} is greater than {}",
+                ENV_RETRY_MIN_MS, ENV_RETRY_MAX_MS
+            ));
+        }
+
+        if invalid.is_empty() {
+            Ok(settings)
+        } else {
+            Err(Error::invalid_input(format!(
+                "Invalid environment configuration: {}",
+                invalid.join(", ")
+            )))
+        }
+    }
+
+    /// Apply the HTTP settings to `config`, or only record them as
+    /// overridden when `apply` is false.
+    pub fn apply_http(&self, config: &mut HttpConfig, apply: bool, summary: &mut AppliedEnv) {
+        let present = [
+            (ENV_MAX_RETRIES, self.max_retries.is_some()),
+            (ENV_RETRY_MIN_MS, self.retry_min.is_some()),
+            (ENV_RETRY_MAX_MS, self.retry_max.is_some()),
+            (ENV_CONNECT_TIMEOUT_SECS, self.connect_timeout.is_some()),
+            (ENV_REQUEST_TIMEOUT_SECS, self.request_timeout.is_some()),
+            (
+                ENV_STREAM_IDLE_TIMEOUT_SECS,
+                self.stream_idle_timeout.is_some(),
+            ),
+        ];
+        for (name, _) in present.iter().filter(|(_, present)| *present) {
+            summary.record(name, apply);
+        }
+        if !apply {
+            return;
+        }
+
+        let retry = &mut config.retry;
+        if let Some(max_retries) = self.max_retries {
+            retry.max_retries = max_retries;
+        }
+        // Keep the bounds ordered when only one is set
+        if let Some(min) = self.retry_min {
+            retry.min_delay = min;
+            retry.max_delay = retry.max_delay.max(min);
+        }
+        if let Some(max) = self.retry_max {
+            retry.max_delay = max;
+            retry.min_delay = retry.min_delay.min(max);
+        }
+        let timeout = &mut config.timeout;
+        if let Some(connect_timeout) = self.connect_timeout {
+            timeout.connect_timeout = connect_timeout;
+        }
+        if let Some(request_timeout) = self.request_timeout {
+            timeout.request_timeout = request_timeout;
+        }
+        if let Some(stream_idle_timeout) = self.stream_idle_timeout {
+            timeout.stream_idle_timeout = stream_idle_timeout;
+        }
+    }
+}
+
+/// Variable lookup collecting the invalid values it meets.
+struct Vars<'a> {
+    lookup: &'a dyn Fn(&str) -> Option<String>,
+    invalid: &'a mut Vec<String>,
+}
+
+impl Vars<'_> {
+    /// Parse a variable, ignoring it when unset or empty.
+    fn get<T>(
+        &mut self,
+        name: &'static str,
+        expected: &str,
+        parse: impl Fn(&str) -> Option<T>,
+    ) -> Option<T> {
+        let value = (self.lookup)(name)?;
+        let value = value.trim();
+        if value.is_empty() {
+            return None;
+        }
+        let parsed = parse(value);
+        if parsed.is_none() {
+            // The value is left out, as it may be a secret set by mistake
+            self.invalid
+                .push(format!("{} is not valid (expected {})", name, expected));
+        }
+        parsed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn read(vars: &[(&str, &str)]) -> Result<EnvSettings> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        EnvSettings::read(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_read_each_variable() {
+        let settings = read(&[
+            (ENV_BASE_URL, "https://proxy.example.com/replicate"),
+            (ENV_MAX_RETRIES, "5"),
+            (ENV_RETRY_MIN_MS, " 250 "),
+            (ENV_RETRY_MAX_MS, "4000"),
+            (ENV_CONNECT_TIMEOUT_SECS, "5"),
+            (ENV_REQUEST_TIMEOUT_SECS, "0"),
+            (ENV_STREAM_IDLE_TIMEOUT_SECS, "90"),
+            (ENV_DEFAULT_WEBHOOK, "https://example.com/hook"),
+            (ENV_FILE_ENCODING, "Base64"),
+        ])
+        .unwrap();
+        assert_eq!(
+            settings.base_url.as_deref(),
+            Some("https://proxy.example.com/replicate")
+        );
+        assert_eq!(
+            settings.default_webhook.as_deref(),
+            Some("https://example.com/hook")
+        );
+        assert_eq!(
+            settings.file_encoding,
+            Some(FileEncodingStrategy::Base64DataUrl)
+        );
+
+        let mut config = HttpConfig::default();
+        let mut applied = AppliedEnv::default();
+        settings.apply_http(&mut config, true, &mut applied);
+        assert_eq!(config.retry.max_retries, 5);
+        assert_eq!(config.retry.min_delay, Duration::from_millis(250));
+        assert_eq!(config.retry.max_delay, Duration::from_secs(4));
+        assert_eq!(config.timeout.connect_timeout, Some(Duration::from_secs(5)));
+        assert_eq!(config.timeout.request_timeout, None);
+        assert_eq!(
+            config.timeout.stream_idle_timeout,
+            Some(Duration::from_secs(90))
+        );
+        assert_eq!(applied.applied.len(), 6);
+        assert!(applied.overridden.is_empty());
+
+        // Unset and empty variables leave the defaults alone
+        let mut config = HttpConfig::default();
+        let mut applied = AppliedEnv::default();
+        read(&[(ENV_MAX_RETRIES, "")])
+            .unwrap()
+            .apply_http(&mut config, true, &mut applied);
+        assert_eq!(config.retry.max_retries, 3);
+        assert!(applied.is_empty());
+        assert_eq!(applied.to_string(), "no environment settings applied");
+
+        // A lone bound keeps the range ordered
+        let mut config = HttpConfig::default();
+        read(&[(ENV_RETRY_MIN_MS, "60000")]).unwrap().apply_http(
+            &mut config,
+            true,
+            &mut AppliedEnv::default(),
+        );
+        assert_eq!(config.retry.max_delay, Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_invalid_values_name_every_variable() {
+        let error = read(&[
+            (ENV_MAX_RETRIES, "three"),
+            (ENV_CONNECT_TIMEOUT_SECS, "-1"),
+            (ENV_DEFAULT_WEBHOOK, "ftp://example.com"),
+            (ENV_REQUEST_TIMEOUT_SECS, "30"),
+            (ENV_FILE_ENCODING, "gzip"),
+        ])
+        .err()
+        .unwrap();
+        let Error::InvalidInput(message) = error else {
+            panic!("unexpected error: {:?}", error);
+        };
+        for name in [
+            ENV_MAX_RETRIES,
+            ENV_CONNECT_TIMEOUT_SECS,
+            ENV_DEFAULT_WEBHOOK,
+            ENV_FILE_ENCODING,
+        ] {
+            assert!(message.contains(name), "{} missing from {}", name, message);
+        }
+        assert!(!message.contains(ENV_REQUEST_TIMEOUT_SECS));
+        assert!(!message.contains("three") && !message.contains("ftp://"));
+
+        assert!(read(&[(ENV_RETRY_MIN_MS, "500"), (ENV_RETRY_MAX_MS, "100")]).is_err());
+    }
+}
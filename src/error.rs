@@ -1,10 +1,22 @@
 //! Error types for the Replicate client.
 
+use crate::models::prediction::PredictionStatus;
 use thiserror::Error;
 
 /// Result type alias for Replicate operations.
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// Limit that caused an [`Error::Timeout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimeoutKind {
+    /// A single operation took too long, e.g. a stalled stream
+    Operation,
+    /// Waiting for a prediction or training exceeded its maximum duration
+    Wait,
+    /// The overall [`Deadline`](crate::http::Deadline) of an operation passed
+    Deadline,
+}
+
 /// Main error type for the Replicate client.
 #[derive(Error, Debug)]
 pub enum Error {
@@ -20,6 +32,25 @@ pub enum Error {
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
 
+    /// Deserialization into a typed value failed at a specific JSON path
+    #[error("Failed to deserialize at '{path}': {source}")]
+    Deserialize {
+        path: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    /// A JSON response was expected but the server sent another content type
+    ///
+    /// Typically an HTML error page from a proxy or load balancer in front of
+    /// the API. `snippet` holds the start of the body.
+    #[error("Expected a JSON response, got '{content_type}' (status {status}): {snippet}")]
+    Decode {
+        status: u16,
+        content_type: String,
+        snippet: String,
+    },
+
     /// API returned an error response
     #[error("API error: {status} - {message}")]
     Api {
@@ -53,8 +84,67 @@ pub enum Error {
     },
 
     /// Timeout error
-    #[error("Operation timed out: {0}")]
-    Timeout(String),
+    ///
+    /// When waiting on a prediction or training times out, the resource keeps
+    /// running server-side. `prediction_id` and `last_status` then identify it
+    /// and its last fetched status, so callers can keep waiting or cancel it.
+    /// `kind` tells which limit was hit.
+    #[error("Operation timed out: {message}")]
+    Timeout {
+        message: String,
+        prediction_id: Option<String>,
+        last_status: Option<PredictionStatus>,
+        kind: TimeoutKind,
+    },
+
+    /// The input and output of a prediction were purged by Replicate
+    ///
+    /// Replicate removes the data of old predictions and marks them with
+    /// `data_removed`; their metadata stays available.
+    #[error("Data of prediction {prediction_id} has been removed")]
+    DataRemoved { prediction_id: String },
+
+    /// A field was read from a trimmed record, as returned by list endpoints
+    ///
+    /// Call [`PredictionsApi::hydrate`](crate::api::PredictionsApi::hydrate)
+    /// to fetch the full record first.
+    #[error(
+        "Prediction {prediction_id} is a partial record from a list; call PredictionsApi::hydrate to fetch the full record"
+    )]
+    PartialRecord { prediction_id: String },
+
+    /// A [`PredictionWatcher`](crate::PredictionWatcher) stopped before the
+    /// prediction reached a terminal state
+    #[error("Prediction watcher stopped before prediction {prediction_id} completed")]
+    WatcherStopped { prediction_id: String },
+
+    /// A prediction stream closed before its `done` event
+    #[error("Stream of prediction {prediction_id} closed before the prediction finished")]
+    StreamClosed { prediction_id: String },
+
+    /// Polling a prediction through a
+    /// [`PredictionWatcher`](crate::PredictionWatcher) failed
+    ///
+    /// Delivered to the other callers waiting on the same prediction when the
+    /// error the first caller gets cannot be copied, e.g. a transport error.
+    #[error("Watching prediction {prediction_id} failed: {message}")]
+    WatchFailed {
+        prediction_id: String,
+        message: String,
+    },
+
+    /// The client was shut down with [`Client::shutdown`](crate::Client::shutdown)
+    #[error("Client has been shut down")]
+    ClientClosed,
+
+    /// A file upload was aborted through its
+    /// [`UploadAbortHandle`](crate::UploadAbortHandle)
+    #[error("File upload was aborted")]
+    UploadAborted,
+
+    /// The resource to create already exists, e.g. a model of the same name
+    #[error("Already exists: {0}")]
+    AlreadyExists(String),
 
     /// Unsupported operation
     #[error("Unsupported operation: {0}")]
@@ -84,6 +174,15 @@ impl Error {
         }
     }
 
+    /// Create an error for a response that is not JSON, keeping the start of its body
+    pub fn decode(status: u16, content_type: impl Into<String>, body: &[u8]) -> Self {
+        Self::Decode {
+            status,
+            content_type: content_type.into(),
+            snippet: body_snippet(body),
+        }
+    }
+
     /// Create an authentication error
     pub fn auth_error(message: impl Into<String>) -> Self {
         Self::Auth(message.into())
@@ -109,7 +208,64 @@ impl Error {
 
     /// Create a timeout error
     pub fn timeout(message: impl Into<String>) -> Self {
-        Self::Timeout(message.into())
+        Self::Timeout {
+            message: message.into(),
+            prediction_id: None,
+            last_status: None,
+            kind: TimeoutKind::Operation,
+        }
+    }
+
+    /// Create a timeout error for an operation whose deadline has passed
+    pub fn deadline_exceeded(message: impl Into<String>) -> Self {
+        Self::Timeout {
+            message: message.into(),
+            prediction_id: None,
+            last_status: None,
+            kind: TimeoutKind::Deadline,
+        }
+    }
+
+    /// Create a timeout error for a wait on a resource that is still running
+    pub fn wait_timeout(
+        prediction_id: impl Into<String>,
+        last_status: Option<PredictionStatus>,
+        message: impl Into<String>,
+    ) -> Self {
+        Self::Timeout {
+            message: message.into(),
+            prediction_id: Some(prediction_id.into()),
+            last_status,
+            kind: TimeoutKind::Wait,
+        }
+    }
+
+    /// Create an error for a prediction whose data was removed
+    pub fn data_removed(prediction_id: impl Into<String>) -> Self {
+        Self::DataRemoved {
+            prediction_id: prediction_id.into(),
+        }
+    }
+
+    /// Create an error for a field read from a partial record
+    pub fn partial_record(prediction_id: impl Into<String>) -> Self {
+        Self::PartialRecord {
+            prediction_id: prediction_id.into(),
+        }
+    }
+
+    /// Check if a request failed because a transport-level timeout elapsed
+    pub(crate) fn is_transport_timeout(&self) -> bool {
+        match self {
+            Self::Http(error) => error.is_timeout(),
+            Self::HttpMiddleware(reqwest_middleware::Error::Reqwest(error)) => error.is_timeout(),
+            _ => false,
+        }
+    }
+
+    /// Create an error for a resource that already exists
+    pub fn already_exists(message: impl Into<String>) -> Self {
+        Self::AlreadyExists(message.into())
     }
 
     /// Create an unsupported operation error
@@ -118,6 +274,19 @@ impl Error {
     }
 }
 
+/// Get the start of a body with runs of whitespace collapsed, as kept by
+/// [`Error::Decode`].
+pub(crate) fn body_snippet(body: &[u8]) -> String {
+    const SNIPPET_CHARS: usize = 200;
+    let text = String::from_utf8_lossy(body);
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut snippet: String = collapsed.chars().take(SNIPPET_CHARS).collect();
+    if collapsed.chars().count() > SNIPPET_CHARS {
+        snippet.push_str("...");
+    }
+    snippet
+}
+
 /// Helper trait for converting HTTP status codes to errors
 pub trait StatusCodeExt {
     fn to_replicate_error(self, body: String) -> Error;
@@ -129,10 +298,12 @@ impl StatusCodeExt for reqwest::StatusCode {
             401 => Error::auth_error("Invalid API token"),
             402 => Error::auth_error("Insufficient credits"),
             403 => Error::auth_error("Forbidden"),
-            404 => Error::api_error(404, "Resource not found"),
+            404 if body.trim().is_empty() => Error::api_error(404, "Resource not found"),
+            404 => Error::api_error_with_detail(404, "Resource not found", body),
             422 => Error::api_error_with_detail(422, "Validation error", body),
             429 => Error::api_error(429, "Rate limit exceeded"),
-            500..=599 => Error::api_error(self.as_u16(), "Server error"),
+            500..=599 if body.trim().is_empty() => Error::api_error(self.as_u16(), "Server error"),
+            500..=599 => Error::api_error_with_detail(self.as_u16(), "Server error", body),
             _ => Error::api_error(self.as_u16(), body),
         }
     }
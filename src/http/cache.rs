@@ -0,0 +1,139 @@
+//! Bounded in-memory cache for conditional GET requests.
+
+use bytes::Bytes;
+use indexmap::IndexMap;
+use url::Url;
+
+/// Default maximum number of cached responses.
+pub const DEFAULT_ETAG_CACHE_CAPACITY: usize = 256;
+
+/// A cached response body with its validators.
+#[derive(Debug, Clone)]
+pub(crate) struct CachedResponse {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub body: Bytes,
+}
+
+/// Response cache keyed by URL, evicting the least recently used entry when full.
+#[derive(Debug)]
+pub(crate) struct EtagCache {
+    capacity: usize,
+    entries: IndexMap<String, CachedResponse>,
+}
+
+impl EtagCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: IndexMap::new(),
+        }
+    }
+
+    /// Look up a cached response, marking it as recently used.
+    pub fn get(&mut self, url: &Url) -> Option<CachedResponse> {
+        let index = self.entries.get_index_of(url.as_str())?;
+        let last = self.entries.len() - 1;
+        self.entries.move_index(index, last);
+        self.entries.get_index(last).map(|(_, entry)| entry.clone())
+    }
+
+    /// Store a response, evicting the oldest entry if the cache is full.
+    pub fn insert(&mut self, url: &Url, response: CachedResponse) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.entries.shift_remove(url.as_str());
+        while self.entries.len() >= self.capacity {
+            self.entries.shift_remove_index(0);
+        }
+        self.entries.insert(url.as_str().to_string(), response);
+    }
+
+    /// Drop every entry for the same path as `url` or a parent of it,
+    /// regardless of query string, so a write to `/v1/files/abc` also drops
+    /// the cached `/v1/files` list.
+    pub fn invalidate_path(&mut self, url: &Url) {
+        let path = url.path();
+        self.entries.retain(|key, _| {
+            Url::parse(key)
+                .map(|cached| {
+                    let cached_path = cached.path().trim_end_matches('/');
+                    let affected = path == cached.path()
+                        || path
+                            .strip_prefix(cached_path)
+                            .is_some_and(|rest| rest.starts_with('/'));
+                    cached.origin() != url.origin() || !affected
+                })
+                .unwrap_or(false)
+        });
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(body: &'static str) -> CachedResponse {
+        CachedResponse {
+            etag: Some("\"tag\"".to_string()),
+            last_modified: None,
+            body: Bytes::from_static(body.as_bytes()),
+        }
+    }
+
+    #[test]
+    fn test_eviction_is_least_recently_used() {
+        let mut cache = EtagCache::new(2);
+        let a = Url::parse("https://api.replicate.com/v1/a").unwrap();
+        let b = Url::parse("https://api.replicate.com/v1/b").unwrap();
+        let c = Url::parse("https://api.replicate.com/v1/c").unwrap();
+
+        cache.insert(&a, entry("a"));
+        cache.insert(&b, entry("b"));
+        assert!(cache.get(&a).is_some());
+        cache.insert(&c, entry("c"));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&a).is_some());
+        assert!(cache.get(&b).is_none());
+    }
+
+    #[test]
+    fn test_invalidate_path_ignores_query() {
+        let mut cache = EtagCache::new(8);
+        let list = Url::parse("https://api.replicate.com/v1/files?cursor=x").unwrap();
+        let other = Url::parse("https://api.replicate.com/v1/files/abc").unwrap();
+        cache.insert(&list, entry("list"));
+        cache.insert(&other, entry("file"));
+
+        cache.invalidate_path(&Url::parse("https://api.replicate.com/v1/files").unwrap());
+
+        assert!(cache.get(&list).is_none());
+        assert!(cache.get(&other).is_some());
+    }
+
+    #[test]
+    fn test_invalidate_path_drops_parents() {
+        let mut cache = EtagCache::new(8);
+        let list = Url::parse("https://api.replicate.com/v1/files").unwrap();
+        let file = Url::parse("https://api.replicate.com/v1/files/abc").unwrap();
+        let sibling = Url::parse("https://api.replicate.com/v1/files/abcd").unwrap();
+        let models = Url::parse("https://api.replicate.com/v1/models").unwrap();
+        for url in [&list, &file, &sibling, &models] {
+            cache.insert(url, entry("body"));
+        }
+
+        cache.invalidate_path(&file);
+
+        assert!(cache.get(&list).is_none());
+        assert!(cache.get(&file).is_none());
+        assert!(cache.get(&sibling).is_some());
+        assert!(cache.get(&models).is_some());
+    }
+}
@@ -1,26 +1,97 @@
 //! HTTP client implementation for the Replicate API with retry logic.
 
 use crate::VERSION;
-use crate::error::{Error, Result, StatusCodeExt};
-use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue, USER_AGENT};
+use crate::env_config::{AppliedEnv, EnvSettings};
+use crate::error::{Error, Result, StatusCodeExt, body_snippet};
+use crate::http::cache::{CachedResponse, DEFAULT_ETAG_CACHE_CAPACITY, EtagCache};
+use crate::http::deadline::Deadline;
+use crate::http::retry::{
+    ErrorBodyMiddleware, ErrorBodyStrategy, ImmediateRetryMiddleware, ObserverMiddleware,
+};
+use crate::http::shutdown::{InFlight, Lifecycle, ShutdownReport};
+use crate::http::stats::{ClientStats, RateLimitInfo, StatsRecorder};
+use crate::observer::ObserverHandle;
+use bytes::{Buf, Bytes};
+use indexmap::IndexMap;
+use reqwest::header::{
+    ACCEPT, AUTHORIZATION, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_TYPE, ETAG, HeaderMap,
+    HeaderName, HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, USER_AGENT,
+};
 use reqwest::{Method, Response};
 use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
 use reqwest_retry::{RetryTransientMiddleware, policies::ExponentialBackoff};
 use retry_policies::Jitter;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::net::SocketAddr;
 use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Duration;
+use url::Url;
 
 /// Base URL for the Replicate API.
 const DEFAULT_BASE_URL: &str = "https://api.replicate.com";
 
+/// Media type of JSON request and response bodies.
+const JSON_CONTENT_TYPE: &str = "application/json";
+
+/// Size in bytes above which JSON request bodies are gzipped when
+/// [`HttpConfig::compress_requests`] is enabled.
+pub const COMPRESSION_THRESHOLD: usize = 8 * 1024;
+
+/// Number of body chunks buffered between the network and a streaming JSON parser.
+const STREAMING_CHUNK_QUEUE: usize = 16;
+
+/// Header used to correlate all attempts of a logical operation in logs.
+pub const CORRELATION_ID_HEADER: &str = "x-correlation-id";
+
+/// Header marking a request as safe to retry even if its method is not idempotent.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
 /// Configuration for retry behavior.
-#[derive(Debug, Clone)]
+///
+/// Serializes with delays in milliseconds, as used by [`Diagnostics`](crate::Diagnostics).
+#[derive(Debug, Clone, Serialize)]
 pub struct RetryConfig {
     pub max_retries: u32,
+    #[serde(rename = "min_delay_ms", serialize_with = "serialize_millis")]
     pub min_delay: Duration,
+    #[serde(rename = "max_delay_ms", serialize_with = "serialize_millis")]
     pub max_delay: Duration,
     pub base_multiplier: u32,
+    /// Also retry requests with non-idempotent methods such as `POST`
+    ///
+    /// Retrying a `POST` that reached the server before failing can, for
+    /// example, create a prediction twice. Setting this to `false` is
+    /// recommended; requests sent with an [`IDEMPOTENCY_KEY_HEADER`] are
+    /// retried either way. Defaults to `true` for compatibility.
+    pub retry_non_idempotent: bool,
+    /// Retry once without delay when a connection is reset or cannot be opened
+    ///
+    /// Load balancers often drop idle connections, and a fresh connection
+    /// usually succeeds at once. The immediate attempt comes on top of
+    /// `max_retries` and is followed by the regular backoff if it fails too.
+    /// Only applies while retries are enabled. Defaults to `false`.
+    pub immediate_first_retry: bool,
+    /// Regular expressions for error response bodies that are worth retrying
+    ///
+    /// An error response whose body matches one is retried even if its
+    /// status alone would not be, e.g. a model cold-start message. Defaults
+    /// to none.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub transient_error_patterns: Vec<String>,
+    /// Regular expressions for error response bodies that will not succeed
+    /// when retried
+    ///
+    /// An error response whose body matches one is returned at once, e.g. a
+    /// deterministic model failure reported as a `500`. Checked before
+    /// `transient_error_patterns`. Defaults to none.
+    ///
+    /// Invalid patterns of either list fail client construction with
+    /// [`Error::InvalidInput`].
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub permanent_error_patterns: Vec<String>,
 }
 
 impl Default for RetryConfig {
@@ -30,15 +101,29 @@ impl Default for RetryConfig {
             min_delay: Duration::from_millis(500),
             max_delay: Duration::from_secs(30),
             base_multiplier: 2,
+            retry_non_idempotent: true,
+            immediate_first_retry: false,
+            transient_error_patterns: Vec::new(),
+            permanent_error_patterns: Vec::new(),
         }
     }
 }
 
 /// Configuration for HTTP timeouts.
-#[derive(Debug, Clone)]
+///
+/// Serializes with timeouts in milliseconds, as used by [`Diagnostics`](crate::Diagnostics).
+#[derive(Debug, Clone, Serialize)]
 pub struct TimeoutConfig {
+    #[serde(rename = "connect_timeout_ms", serialize_with = "serialize_opt_millis")]
     pub connect_timeout: Option<Duration>,
+    #[serde(rename = "request_timeout_ms", serialize_with = "serialize_opt_millis")]
     pub request_timeout: Option<Duration>,
+    /// Maximum time to wait for the next event on an output stream
+    #[serde(
+        rename = "stream_idle_timeout_ms",
+        serialize_with = "serialize_opt_millis"
+    )]
+    pub stream_idle_timeout: Option<Duration>,
 }
 
 impl Default for TimeoutConfig {
@@ -46,24 +131,208 @@ impl Default for TimeoutConfig {
         Self {
             connect_timeout: Some(Duration::from_secs(30)),
             request_timeout: Some(Duration::from_secs(60)),
+            stream_idle_timeout: Some(Duration::from_secs(60)),
         }
     }
 }
 
 /// Combined HTTP client configuration.
-#[derive(Debug, Clone, Default)]
+///
+/// Build it from [`HttpConfig::default`] with the setters below, e.g.
+/// `HttpConfig::default().retry(retry).timeout(timeout)`. The struct is
+/// `#[non_exhaustive]`, so it cannot be written as a struct literal outside
+/// this crate and new settings can be added without breaking callers.
+#[derive(Debug, Clone, Default, Serialize)]
+#[non_exhaustive]
 pub struct HttpConfig {
     pub retry: RetryConfig,
     pub timeout: TimeoutConfig,
+    /// Gzip JSON request bodies larger than [`COMPRESSION_THRESHOLD`] and send
+    /// them with `Content-Encoding: gzip`, e.g. predictions with long text
+    /// inputs.
+    ///
+    /// Off by default: the Replicate API does not document accepting
+    /// compressed request bodies, so only enable it against endpoints known
+    /// to decode them, such as a proxy in front of the API. Requires the
+    /// `gzip` feature; without it, building a client with compression
+    /// enabled fails with [`Error::InvalidInput`].
+    pub compress_requests: bool,
+    /// Hosts resolved to fixed addresses instead of through DNS; see
+    /// [`resolve`](Self::resolve)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dns_overrides: Vec<(String, SocketAddr)>,
+    /// Extra trusted root certificates, for unit tests against local TLS
+    /// servers
+    #[cfg(test)]
+    #[serde(skip)]
+    pub(crate) root_certificates: Vec<reqwest::Certificate>,
+}
+
+impl HttpConfig {
+    /// Set the retry configuration.
+    pub fn retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Set the timeout configuration.
+    pub fn timeout(mut self, timeout: TimeoutConfig) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set whether large JSON request bodies are gzipped; see
+    /// [`compress_requests`](Self::compress_requests).
+    pub fn compress_requests(mut self, enabled: bool) -> Self {
+        self.compress_requests = enabled;
+        self
+    }
+
+    /// Resolve `host` to `addr` instead of looking it up through DNS, e.g. to
+    /// send `api.replicate.com` traffic to a gateway or a local test server.
+    ///
+    /// Adding several addresses for one host makes all of them candidates.
+    /// A port in the URL wins over the port of `addr`; a port of 0 uses the
+    /// scheme's default. TLS still verifies the certificate against `host`.
+    pub fn resolve(mut self, host: &str, addr: SocketAddr) -> Self {
+        self.dns_overrides.push((host.to_string(), addr));
+        self
+    }
+
+    /// Apply the DNS overrides to a reqwest client builder.
+    fn apply_dns_overrides(&self, mut builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        let mut hosts: IndexMap<&str, Vec<SocketAddr>> = IndexMap::new();
+        for (host, addr) in &self.dns_overrides {
+            hosts.entry(host.as_str()).or_default().push(*addr);
+        }
+        for (host, addrs) in hosts {
+            builder = builder.resolve_to_addrs(host, &addrs);
+        }
+        builder
+    }
+
+    /// Trust the extra root certificates of a unit test.
+    #[cfg(test)]
+    fn trust_root_certificates(
+        &self,
+        mut builder: reqwest::ClientBuilder,
+    ) -> reqwest::ClientBuilder {
+        for certificate in &self.root_certificates {
+            builder = builder.add_root_certificate(certificate.clone());
+        }
+        builder
+    }
+
+    /// Create the default configuration, tuned by environment variables.
+    ///
+    /// Reads the retry and timeout variables listed in
+    /// [`env_config`](crate::env_config); invalid values fail with
+    /// [`Error::InvalidInput`] naming every offending variable.
+    pub fn from_env() -> Result<Self> {
+        let mut config = Self::default();
+        EnvSettings::from_env()?.apply_http(&mut config, true, &mut AppliedEnv::default());
+        Ok(config)
+    }
+}
+
+fn serialize_millis<S: serde::Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
+fn serialize_opt_millis<S: serde::Serializer>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    match duration {
+        Some(duration) => serialize_millis(duration, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Per-request options for [`HttpClient`] calls.
+#[derive(Debug, Clone, Default)]
+pub struct RequestOptions {
+    /// Revalidate against a cached response using `ETag`/`Last-Modified`
+    pub use_etag_cache: bool,
+}
+
+impl RequestOptions {
+    /// Create default request options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enable conditional GETs backed by the client's response cache.
+    ///
+    /// The cached body is returned when the server answers `304 Not Modified`.
+    pub fn use_etag_cache(mut self, enabled: bool) -> Self {
+        self.use_etag_cache = enabled;
+        self
+    }
 }
 
 /// HTTP client for making requests to the Replicate API with retry logic.
+///
+/// Clones share their connection pool and configuration: reconfiguring one
+/// clone with [`reconfigure`](Self::reconfigure) retunes all of them. The
+/// `&mut self` methods such as [`configure_retries`](Self::configure_retries)
+/// only retune the client they are called on.
 #[derive(Debug, Clone)]
 pub struct HttpClient {
-    client: ClientWithMiddleware,
-    base_url: String,
+    /// Clients built from the current configuration, swapped as a whole on
+    /// reconfiguration and shared by all clones.
+    transport: Arc<RwLock<Arc<Transport>>>,
+    /// Clients as of creation or the last `&mut self` reconfiguration,
+    /// backing the borrowing configuration getters.
+    snapshot: Arc<Transport>,
+    base_url: Url,
+    /// `base_url` pre-joined with `v1/`, the prefix used by nearly every request.
+    v1_url: Url,
     api_token: String,
+    /// Extra headers sent with every request made through this client.
+    request_headers: HeaderMap,
+    /// Responses kept for conditional GETs, shared by all clones.
+    etag_cache: Arc<Mutex<EtagCache>>,
+    /// Request counters and last response metadata, shared by all clones.
+    stats: Arc<Mutex<StatsRecorder>>,
+    /// Time by which every request made through this client must finish.
+    deadline: Option<Deadline>,
+    /// Closed flag and in-flight counter, shared by all clones.
+    lifecycle: Arc<Lifecycle>,
+    /// Operation this client's requests belong to, if scoped to one.
+    operation: Option<Arc<InFlight>>,
+}
+
+/// Reqwest clients built from one [`HttpConfig`].
+#[derive(Debug)]
+struct Transport {
+    client: ClientWithMiddleware,
+    /// Same connection pool as `client`, without the retry middleware.
+    no_retry_client: ClientWithMiddleware,
+    /// Client for long-lived streams, without an overall request timeout.
+    stream_client: reqwest::Client,
     http_config: HttpConfig,
+    /// Lifecycle observer notified of retries and prediction events.
+    observer: Option<ObserverHandle>,
+}
+
+impl Transport {
+    /// Build the clients for a configuration.
+    fn new(http_config: HttpConfig, observer: Option<ObserverHandle>) -> Result<Self> {
+        let (client, no_retry_client) =
+            HttpClient::build_client_with_config(&http_config, observer.as_ref())?;
+        let stream_client = HttpClient::build_stream_client(&http_config)?;
+        Ok(Self {
+            client,
+            no_retry_client,
+            stream_client,
+            http_config,
+            observer,
+        })
+    }
 }
 
 impl HttpClient {
@@ -79,7 +348,7 @@ impl HttpClient {
     ) -> Result<Self> {
         let http_config = HttpConfig {
             retry: retry_config,
-            timeout: TimeoutConfig::default(),
+            ..HttpConfig::default()
         };
         Self::with_http_config(api_token, http_config)
     }
@@ -90,19 +359,70 @@ impl HttpClient {
         if api_token.is_empty() {
             return Err(Error::auth_error("API token cannot be empty"));
         }
+        Self::with_token_unchecked(api_token, http_config)
+    }
+
+    /// Create a client without a token, for [`with_token`](Self::with_token)
+    /// copies to share its connection pool.
+    pub(crate) fn without_token(http_config: HttpConfig, base_url: Option<&str>) -> Result<Self> {
+        let mut client = Self::with_token_unchecked(String::new(), http_config)?;
+        if let Some(base_url) = base_url {
+            client.set_base_url(base_url)?;
+        }
+        Ok(client)
+    }
 
-        let client = Self::build_client_with_config(&http_config)?;
+    fn with_token_unchecked(api_token: String, http_config: HttpConfig) -> Result<Self> {
+        let transport = Arc::new(Transport::new(http_config, None)?);
+        let base_url = Self::parse_base_url(DEFAULT_BASE_URL)?;
+        let v1_url = base_url.join("v1/")?;
 
         Ok(Self {
-            client,
-            base_url: DEFAULT_BASE_URL.to_string(),
+            transport: Arc::new(RwLock::new(transport.clone())),
+            snapshot: transport,
+            base_url,
+            v1_url,
             api_token,
-            http_config,
+            request_headers: HeaderMap::new(),
+            etag_cache: Arc::new(Mutex::new(EtagCache::new(DEFAULT_ETAG_CACHE_CAPACITY))),
+            stats: Arc::new(Mutex::new(StatsRecorder::default())),
+            deadline: None,
+            lifecycle: Arc::new(Lifecycle::default()),
+            operation: None,
         })
     }
 
-    /// Build a reqwest client with retry middleware and timeout configuration.
-    fn build_client_with_config(http_config: &HttpConfig) -> Result<ClientWithMiddleware> {
+    /// Parse a base URL, normalizing it to end with a slash so that
+    /// `Url::join` appends to any path prefix instead of replacing it.
+    fn parse_base_url(base_url: &str) -> Result<Url> {
+        let mut url = Url::parse(base_url)?;
+        if url.cannot_be_a_base() {
+            return Err(Error::invalid_input(format!(
+                "Base URL cannot be used as a base: {}",
+                base_url
+            )));
+        }
+        if !url.path().ends_with('/') {
+            let path = format!("{}/", url.path());
+            url.set_path(&path);
+        }
+        Ok(url)
+    }
+
+    /// Replace the base URL, re-deriving the cached `v1/` join.
+    fn set_base_url(&mut self, base_url: &str) -> Result<()> {
+        let base_url = Self::parse_base_url(base_url)?;
+        self.v1_url = base_url.join("v1/")?;
+        self.base_url = base_url;
+        Ok(())
+    }
+
+    /// Build reqwest clients with and without retry middleware, sharing one
+    /// connection pool and the timeout configuration.
+    fn build_client_with_config(
+        http_config: &HttpConfig,
+        observer: Option<&ObserverHandle>,
+    ) -> Result<(ClientWithMiddleware, ClientWithMiddleware)> {
         // Create exponential backoff retry policy
         let retry_policy = ExponentialBackoff::builder()
             .retry_bounds(http_config.retry.min_delay, http_config.retry.max_delay)
@@ -110,7 +430,63 @@ impl HttpClient {
             .base(http_config.retry.base_multiplier)
             .build_with_max_retries(http_config.retry.max_retries);
 
-        // Build reqwest client with timeout configuration
+        let reqwest_client = Self::build_reqwest_client(http_config)?;
+
+        // Build client with retry middleware
+        let no_retry_client = ClientBuilder::new(reqwest_client.clone()).build();
+        let error_body = ErrorBodyMiddleware::new(&http_config.retry)?;
+        let mut builder = ClientBuilder::new(reqwest_client).with(
+            RetryTransientMiddleware::new_with_policy_and_strategy(retry_policy, ErrorBodyStrategy),
+        );
+        if http_config.retry.immediate_first_retry && http_config.retry.max_retries > 0 {
+            builder = builder.with(ImmediateRetryMiddleware);
+        }
+        if let Some(observer) = observer {
+            builder = builder.with(ObserverMiddleware {
+                observer: observer.clone(),
+            });
+        }
+        if let Some(error_body) = error_body
+            && http_config.retry.max_retries > 0
+        {
+            builder = builder.with(error_body);
+        }
+        let client = builder.build();
+
+        Ok((client, no_retry_client))
+    }
+
+    /// Pick the client for a request, skipping retries for non-idempotent
+    /// methods unless they are enabled or the request has an idempotency key.
+    fn client_for(&self, method: &Method, headers: Option<&HeaderMap>) -> ClientWithMiddleware {
+        let transport = self.transport();
+        if method.is_idempotent()
+            || transport.http_config.retry.retry_non_idempotent
+            || self.request_headers.contains_key(IDEMPOTENCY_KEY_HEADER)
+            || headers.is_some_and(|headers| headers.contains_key(IDEMPOTENCY_KEY_HEADER))
+        {
+            transport.client.clone()
+        } else {
+            transport.no_retry_client.clone()
+        }
+    }
+
+    /// Get the clients built from the current configuration.
+    fn transport(&self) -> Arc<Transport> {
+        self.transport
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Build a plain reqwest client with the configured timeouts.
+    fn build_reqwest_client(http_config: &HttpConfig) -> Result<reqwest::Client> {
+        if http_config.compress_requests && !cfg!(feature = "gzip") {
+            return Err(Error::invalid_input(
+                "Request compression requires the `gzip` feature of replicate-client",
+            ));
+        }
+
         let mut client_builder =
             reqwest::Client::builder().user_agent(format!("replicate-rs/{}", crate::VERSION));
 
@@ -122,14 +498,24 @@ impl HttpClient {
             client_builder = client_builder.timeout(request_timeout);
         }
 
-        let reqwest_client = client_builder.build()?;
-
-        // Build client with retry middleware
-        let client = ClientBuilder::new(reqwest_client)
-            .with(RetryTransientMiddleware::new_with_policy(retry_policy))
-            .build();
+        #[cfg(test)]
+        let client_builder = http_config.trust_root_certificates(client_builder);
+        Ok(http_config.apply_dns_overrides(client_builder).build()?)
+    }
 
-        Ok(client)
+    /// Build a reqwest client for server-sent event streams and uploads.
+    ///
+    /// Streams stay open for as long as the model generates output, so only the
+    /// connect timeout applies; stalls are caught by the stream idle timeout.
+    fn build_stream_client(http_config: &HttpConfig) -> Result<reqwest::Client> {
+        let mut client_builder =
+            reqwest::Client::builder().user_agent(format!("replicate-rs/{}", crate::VERSION));
+        if let Some(connect_timeout) = http_config.timeout.connect_timeout {
+            client_builder = client_builder.connect_timeout(connect_timeout);
+        }
+        #[cfg(test)]
+        let client_builder = http_config.trust_root_certificates(client_builder);
+        Ok(http_config.apply_dns_overrides(client_builder).build()?)
     }
 
     /// Create a new HTTP client with custom base URL.
@@ -138,7 +524,7 @@ impl HttpClient {
         base_url: impl Into<String>,
     ) -> Result<Self> {
         let mut client = Self::new(api_token)?;
-        client.base_url = base_url.into();
+        client.set_base_url(&base_url.into())?;
         Ok(client)
     }
 
@@ -149,7 +535,7 @@ impl HttpClient {
         retry_config: RetryConfig,
     ) -> Result<Self> {
         let mut client = Self::with_retry_config(api_token, retry_config)?;
-        client.base_url = base_url.into();
+        client.set_base_url(&base_url.into())?;
         Ok(client)
     }
 
@@ -160,100 +546,679 @@ impl HttpClient {
         http_config: HttpConfig,
     ) -> Result<Self> {
         let mut client = Self::with_http_config(api_token, http_config)?;
-        client.base_url = base_url.into();
+        client.set_base_url(&base_url.into())?;
         Ok(client)
     }
 
     /// Get a reference to the underlying client with middleware.
+    ///
+    /// This is the client as of creation or the last `&mut self`
+    /// configuration; see [`current_inner`](Self::current_inner) for the one
+    /// requests use after [`reconfigure`](Self::reconfigure).
     pub fn inner(&self) -> &ClientWithMiddleware {
-        &self.client
+        &self.snapshot.client
+    }
+
+    /// Get the underlying client with middleware that requests currently use.
+    ///
+    /// The returned client is not affected by later reconfiguration.
+    pub fn current_inner(&self) -> ClientWithMiddleware {
+        self.transport().client.clone()
+    }
+
+    /// Create a copy of this client that sends the given headers on every request.
+    ///
+    /// Headers are attached when a request is built, before it reaches the retry
+    /// middleware, so every retry attempt carries exactly the same values.
+    /// Headers already set on this client are kept unless overridden.
+    pub fn with_headers(&self, headers: HeaderMap) -> Self {
+        let mut client = self.clone();
+        client.request_headers.extend(headers);
+        client
+    }
+
+    /// Create a copy of this client that authenticates with another API token.
+    ///
+    /// The copy shares the connection pool, configuration and shutdown state,
+    /// and the token is attached to each request as it is sent. Cached
+    /// conditional GET responses are not shared, so one token never sees
+    /// another's responses.
+    pub fn with_token(&self, api_token: impl Into<String>) -> Result<Self> {
+        let api_token = api_token.into();
+        if api_token.is_empty() {
+            return Err(Error::auth_error("API token cannot be empty"));
+        }
+        Ok(Self {
+            api_token,
+            etag_cache: Arc::new(Mutex::new(EtagCache::new(DEFAULT_ETAG_CACHE_CAPACITY))),
+            ..self.clone()
+        })
+    }
+
+    /// Create a client for another tenant that shares only this client's
+    /// connection pool.
+    ///
+    /// Unlike [`with_token`](Self::with_token) copies, the tenant has its own
+    /// configuration slot, stats and shutdown state, so neither reconfiguring
+    /// nor shutting it down affects other tenants. Shutting this client down
+    /// still closes every tenant and waits for their operations.
+    pub(crate) fn for_tenant(&self, api_token: impl Into<String>) -> Result<Self> {
+        let mut client = self.with_token(api_token)?;
+        client.transport = Arc::new(RwLock::new(self.transport()));
+        client.stats = Arc::new(Mutex::new(StatsRecorder::default()));
+        client.lifecycle = self.lifecycle.child();
+        Ok(client)
+    }
+
+    /// Get a plain client for downloading files from outside the API.
+    ///
+    /// It applies the connect timeout and DNS overrides but no overall
+    /// request timeout, so large files are not cut off, and sends no token.
+    pub(crate) fn download_client(&self) -> reqwest::Client {
+        self.transport().stream_client.clone()
+    }
+
+    /// Create a copy of this client that tags every request with an `X-Correlation-Id`.
+    ///
+    /// Use one scoped client per logical operation so the same ID appears on the
+    /// initial request, every retry, and any follow-up polling.
+    pub fn with_correlation_id(&self, correlation_id: impl AsRef<str>) -> Result<Self> {
+        let value = HeaderValue::from_str(correlation_id.as_ref())
+            .map_err(|_| Error::invalid_input("Invalid correlation ID"))?;
+        let mut headers = HeaderMap::new();
+        headers.insert(HeaderName::from_static(CORRELATION_ID_HEADER), value);
+        Ok(self.with_headers(headers))
+    }
+
+    /// Create a copy of this client whose requests must all finish by `deadline`.
+    ///
+    /// Each request, including its retries, only gets the time left and fails
+    /// with a [`TimeoutKind::Deadline`](crate::error::TimeoutKind::Deadline)
+    /// timeout once the deadline has passed. Event streams are not limited.
+    pub fn with_deadline(&self, deadline: Deadline) -> Self {
+        let mut client = self.clone();
+        client.deadline = Some(deadline);
+        client
+    }
+
+    /// Get the deadline requests must finish by, if any.
+    pub fn deadline(&self) -> Option<Deadline> {
+        self.deadline
+    }
+
+    /// Create a copy of this client whose requests make up one operation.
+    ///
+    /// The operation counts as in flight until every copy is dropped, and its
+    /// requests are still sent after [`shutdown`](Self::shutdown), so a poll
+    /// loop started before it can finish. Fails with [`Error::ClientClosed`]
+    /// once the client is shut down, unless this client is already scoped.
+    pub(crate) fn begin_operation(&self) -> Result<Self> {
+        let mut client = self.clone();
+        if client.operation.is_none() {
+            client.operation = Some(Arc::new(self.lifecycle.admit()?));
+        }
+        Ok(client)
+    }
+
+    /// Count a single request as in flight, unless it is part of an operation.
+    fn admit(&self) -> Result<Option<InFlight>> {
+        match self.operation {
+            Some(_) => Ok(None),
+            None => self.lifecycle.admit().map(Some),
+        }
+    }
+
+    /// Stop accepting requests and wait up to `grace` for in-flight ones.
+    ///
+    /// From now on, new requests through this client or any of its clones
+    /// fail with [`Error::ClientClosed`]. See
+    /// [`Client::shutdown`](crate::Client::shutdown) for what counts as in
+    /// flight.
+    pub async fn shutdown(&self, grace: Duration) -> ShutdownReport {
+        self.lifecycle.shutdown(grace).await
+    }
+
+    /// Check if [`shutdown`](Self::shutdown) has been called on this client
+    /// or a clone.
+    pub fn is_closed(&self) -> bool {
+        self.lifecycle.is_closed()
+    }
+
+    /// Send a request within the deadline, if any.
+    ///
+    /// A request timing out because of [`deadline_timeout`](Self::deadline_timeout)
+    /// is reported as a deadline timeout too.
+    async fn within_deadline<T>(&self, request: impl Future<Output = Result<T>>) -> Result<T> {
+        let Some(deadline) = self.deadline else {
+            return request.await;
+        };
+        match deadline.run(request).await {
+            Err(error) if error.is_transport_timeout() && deadline.is_expired() => Err(
+                Error::deadline_exceeded(format!("Deadline passed during the request: {}", error)),
+            ),
+            outcome => outcome,
+        }
+    }
+
+    /// Limit a request, body included, to the time left before the deadline.
+    fn deadline_timeout(
+        &self,
+        request: reqwest_middleware::RequestBuilder,
+    ) -> reqwest_middleware::RequestBuilder {
+        match self.deadline.and_then(|deadline| deadline.remaining()) {
+            Some(remaining) => request.timeout(remaining),
+            None => request,
+        }
+    }
+
+    /// Combine the client's extra headers with those of a single request.
+    ///
+    /// Request headers take precedence, except `Authorization`, which always
+    /// carries the client's token.
+    fn merged_headers(&self, headers: Option<&HeaderMap>) -> HeaderMap {
+        let mut merged = self.request_headers.clone();
+        if let Some(headers) = headers {
+            for name in headers.keys() {
+                if name != AUTHORIZATION {
+                    merged.remove(name);
+                }
+            }
+            for (name, value) in headers {
+                if name != AUTHORIZATION {
+                    merged.append(name, value.clone());
+                }
+            }
+        }
+        merged
+    }
+
+    /// Get the extra headers sent with every request.
+    pub fn request_headers(&self) -> &HeaderMap {
+        &self.request_headers
+    }
+
+    /// Get the correlation ID attached to requests, if any.
+    pub fn correlation_id(&self) -> Option<&str> {
+        self.request_headers
+            .get(CORRELATION_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+    }
+
+    /// Get the base URL requests are resolved against.
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
     }
 
     /// Build a full URL from a path.
-    fn build_url(&self, path: &str) -> String {
+    ///
+    /// Paths are resolved relative to the base URL, so a base with a path
+    /// prefix (e.g. `https://gw/internal/replicate/`) is preserved. Absolute
+    /// URLs, such as pagination cursors, are returned as-is.
+    fn build_url(&self, path: &str) -> Result<Url> {
         let path = path.strip_prefix('/').unwrap_or(path);
-        format!("{}/{}", self.base_url.trim_end_matches('/'), path)
+        let url = match path.strip_prefix("v1/") {
+            Some(rest) => self.v1_url.join(rest)?,
+            None => self.base_url.join(path)?,
+        };
+        Ok(url)
     }
 
-    /// Execute a request and handle errors.
-    async fn execute_request(&self, method: Method, path: &str) -> Result<Response> {
-        let url = self.build_url(path);
-        let response = self
-            .client
-            .request(method, &url)
-            .header("Authorization", format!("Token {}", self.api_token))
-            .header("Content-Type", "application/json")
-            .send()
-            .await?;
+    /// Check that an absolute URL returned by the API points at the API host.
+    ///
+    /// Requests carry the API token, so URLs taken from responses or from
+    /// persisted state must share the scheme, host and port of the base URL.
+    pub(crate) fn validate_api_url(&self, url: &str) -> Result<Url> {
+        let parsed = Url::parse(url)
+            .map_err(|e| Error::invalid_input(format!("Invalid URL '{}': {}", url, e)))?;
+        let same_origin = parsed.scheme() == self.base_url.scheme()
+            && parsed.host_str() == self.base_url.host_str()
+            && parsed.port_or_known_default() == self.base_url.port_or_known_default();
+        if !same_origin {
+            return Err(Error::invalid_input(format!(
+                "URL '{}' does not point at the API host {}",
+                url,
+                self.base_url.host_str().unwrap_or_default()
+            )));
+        }
+        Ok(parsed)
+    }
 
-        if response.status().is_success() {
-            Ok(response)
+    /// Check that a stream URL returned by the API may be sent the API token.
+    ///
+    /// Like [`validate_api_url`](Self::validate_api_url), but over HTTPS a
+    /// sibling host of the API host is accepted too, as Replicate serves
+    /// streams from `stream.replicate.com` next to `api.replicate.com`.
+    pub(crate) fn validate_stream_url(&self, url: &str) -> Result<Url> {
+        let error = match self.validate_api_url(url) {
+            Ok(parsed) => return Ok(parsed),
+            Err(error) => error,
+        };
+        let parsed = Url::parse(url)
+            .map_err(|e| Error::invalid_input(format!("Invalid URL '{}': {}", url, e)))?;
+        let parent = self
+            .base_url
+            .domain()
+            .and_then(|host| host.split_once('.'))
+            .map(|(_, parent)| parent)
+            .filter(|parent| parent.contains('.'));
+        let sibling = match (parent, parsed.domain()) {
+            (Some(parent), Some(host)) => host
+                .strip_suffix(parent)
+                .and_then(|label| label.strip_suffix('.'))
+                .is_some_and(|label| !label.is_empty() && !label.contains('.')),
+            _ => false,
+        };
+        if sibling && parsed.scheme() == "https" && self.base_url.scheme() == "https" {
+            Ok(parsed)
         } else {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            Err(status.to_replicate_error(body))
+            Err(error)
         }
     }
 
+    /// Record the outcome of sending a request in the client's stats.
+    fn record<E>(
+        &self,
+        outcome: std::result::Result<Response, E>,
+    ) -> std::result::Result<Response, E> {
+        self.stats.lock().unwrap().record(&outcome);
+        outcome
+    }
+
+    /// Get the cumulative request counters of this client and its clones.
+    pub fn stats(&self) -> ClientStats {
+        self.stats.lock().unwrap().stats
+    }
+
+    /// Get the request ID of the most recent response that carried one.
+    pub fn last_request_id(&self) -> Option<String> {
+        self.stats.lock().unwrap().last_request_id.clone()
+    }
+
+    /// Get the rate-limit headers of the most recent response that had any.
+    pub fn last_rate_limit(&self) -> Option<RateLimitInfo> {
+        self.stats.lock().unwrap().last_rate_limit.clone()
+    }
+
+    /// Build the headers of an API request.
+    ///
+    /// Carries the token and user agent, `Content-Type` only when a JSON body
+    /// is sent (multipart forms set their own), and `Accept: application/json`
+    /// when a JSON response is expected. The client's extra headers and then
+    /// `headers` are layered on top, replacing defaults of the same name; see
+    /// [`merged_headers`](Self::merged_headers).
+    fn api_headers(
+        &self,
+        body: BodyKind,
+        accept_json: bool,
+        headers: Option<&HeaderMap>,
+    ) -> Result<HeaderMap> {
+        let mut built = HeaderMap::new();
+        built.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Token {}", self.api_token))
+                .map_err(|_| Error::auth_error("Invalid API token format"))?,
+        );
+        built.insert(
+            USER_AGENT,
+            HeaderValue::from_str(&format!("replicate-rs/{}", VERSION))
+                .map_err(|_| Error::invalid_input("Invalid user agent format"))?,
+        );
+        if let BodyKind::Json { gzipped } = body {
+            built.insert(CONTENT_TYPE, HeaderValue::from_static(JSON_CONTENT_TYPE));
+            if gzipped {
+                built.insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+            }
+        }
+        if accept_json {
+            built.insert(ACCEPT, HeaderValue::from_static(JSON_CONTENT_TYPE));
+        }
+
+        let extra = self.merged_headers(headers);
+        for name in extra.keys() {
+            built.remove(name);
+        }
+        for (name, value) in &extra {
+            built.append(name, value.clone());
+        }
+        Ok(built)
+    }
+
+    /// Execute a request without a body and handle errors.
+    ///
+    /// With `accept_json`, the request asks for a JSON response unless its
+    /// headers say otherwise.
+    async fn execute_request(
+        &self,
+        method: Method,
+        path: &str,
+        headers: Option<&HeaderMap>,
+        accept_json: bool,
+    ) -> Result<Response> {
+        self.execute(method, path, None, headers, accept_json).await
+    }
+
     /// Execute a request with JSON body and handle errors.
     async fn execute_request_with_json<T: Serialize>(
         &self,
         method: Method,
         path: &str,
         body: &T,
+        headers: Option<&HeaderMap>,
     ) -> Result<Response> {
-        let url = self.build_url(path);
-        let json_body = serde_json::to_vec(body)?;
-        let response = self
-            .client
-            .request(method, &url)
-            .header("Authorization", format!("Token {}", self.api_token))
-            .header("Content-Type", "application/json")
-            .body(json_body)
-            .send()
-            .await?;
+        let body = self.encode_json_body(body)?;
+        self.execute(method, path, Some(body), headers, true).await
+    }
 
+    /// Send a request through the retrying client and turn error statuses
+    /// into errors.
+    ///
+    /// `body` is an encoded JSON body and whether it is gzipped.
+    async fn execute(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<(Vec<u8>, bool)>,
+        headers: Option<&HeaderMap>,
+        accept_json: bool,
+    ) -> Result<Response> {
+        let response = self.send(method, path, body, headers, accept_json).await?;
         if response.status().is_success() {
             Ok(response)
         } else {
             let status = response.status();
-            let body = response.text().await.unwrap_or_default();
+            let body = error_body(response).await;
             Err(status.to_replicate_error(body))
         }
     }
 
+    /// Send a request through the retrying client, whatever the response status.
+    async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<(Vec<u8>, bool)>,
+        headers: Option<&HeaderMap>,
+        accept_json: bool,
+    ) -> Result<Response> {
+        let _in_flight = self.admit()?;
+        let url = self.build_url(path)?;
+        self.invalidate_cached(&method, &url);
+        let kind = match &body {
+            Some((_, gzipped)) => BodyKind::Json { gzipped: *gzipped },
+            None => BodyKind::Empty,
+        };
+        let mut request = self
+            .client_for(&method, headers)
+            .request(method.clone(), url)
+            .headers(self.api_headers(kind, accept_json, headers)?);
+        if let Some((data, _)) = body {
+            request = request.body(data);
+        }
+        self.within_deadline(async {
+            Ok(self.record(self.deadline_timeout(request).send().await)?)
+        })
+        .await
+    }
+
+    /// Serialize a JSON request body, gzipping it if compression is enabled
+    /// and the body exceeds [`COMPRESSION_THRESHOLD`].
+    ///
+    /// Returns the body and whether it was compressed.
+    fn encode_json_body<T: Serialize>(&self, body: &T) -> Result<(Vec<u8>, bool)> {
+        let json_body = serde_json::to_vec(body)?;
+        if !self.transport().http_config.compress_requests
+            || json_body.len() <= COMPRESSION_THRESHOLD
+        {
+            return Ok((json_body, false));
+        }
+        gzip(&json_body).map(|compressed| (compressed, true))
+    }
+
     /// Make a GET request.
     pub async fn get(&self, path: &str) -> Result<Response> {
-        self.execute_request(Method::GET, path).await
+        self.execute_request(Method::GET, path, None, false).await
     }
 
     /// Make a POST request with JSON body.
     pub async fn post<T: Serialize>(&self, path: &str, body: &T) -> Result<Response> {
-        self.execute_request_with_json(Method::POST, path, body)
+        self.execute_request_with_json(Method::POST, path, body, None)
             .await
     }
 
     /// Make a POST request without a body.
     pub async fn post_empty(&self, path: &str) -> Result<Response> {
-        self.execute_request(Method::POST, path).await
+        self.execute_request(Method::POST, path, None, true).await
     }
 
     /// Make a PUT request with JSON body.
     pub async fn put<T: Serialize>(&self, path: &str, body: &T) -> Result<Response> {
-        self.execute_request_with_json(Method::PUT, path, body)
+        self.execute_request_with_json(Method::PUT, path, body, None)
             .await
     }
 
     /// Make a DELETE request.
     pub async fn delete(&self, path: &str) -> Result<Response> {
-        self.execute_request(Method::DELETE, path).await
+        self.execute_request(Method::DELETE, path, None, true).await
     }
 
     /// Make a GET request and deserialize the response as JSON.
+    ///
+    /// Sends `Accept: application/json`. A response declaring another content
+    /// type, such as an HTML error page from a proxy, fails with
+    /// [`Error::Decode`].
     pub async fn get_json<T: for<'de> Deserialize<'de>>(&self, path: &str) -> Result<T> {
-        let response = self.get(path).await?;
-        let json = response.json().await?;
-        Ok(json)
+        self.within_deadline(async {
+            read_json(self.execute_request(Method::GET, path, None, true).await?).await
+        })
+        .await
+    }
+
+    /// Make a GET request with per-request options and deserialize the response as JSON.
+    ///
+    /// With [`RequestOptions::use_etag_cache`], the last response for this URL
+    /// is kept in a bounded in-memory cache and revalidated with
+    /// `If-None-Match`/`If-Modified-Since`; on `304 Not Modified` the cached body
+    /// is deserialized instead, and without one the request is sent again
+    /// without validators. Any non-GET request to the same path or a path
+    /// below it drops the cached entry, so deleting a file drops the cached
+    /// file list.
+    pub async fn get_json_with_options<T: for<'de> Deserialize<'de>>(
+        &self,
+        path: &str,
+        options: &RequestOptions,
+    ) -> Result<T> {
+        if !options.use_etag_cache {
+            return self.get_json(path).await;
+        }
+
+        let url = self.build_url(path)?;
+        let cached = self.etag_cache.lock().unwrap().get(&url);
+
+        let mut validators = HeaderMap::new();
+        if let Some(cached) = &cached {
+            if let Some(etag) = cached
+                .etag
+                .as_deref()
+                .and_then(|value| HeaderValue::from_str(value).ok())
+            {
+                validators.insert(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = cached
+                .last_modified
+                .as_deref()
+                .and_then(|value| HeaderValue::from_str(value).ok())
+            {
+                validators.insert(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let response = self
+            .send(Method::GET, path, None, Some(&validators), true)
+            .await?;
+        let status = response.status();
+
+        if status == reqwest::StatusCode::NOT_MODIFIED {
+            return match cached {
+                Some(cached) => Ok(serde_json::from_slice(&cached.body)?),
+                // Nothing to revalidate against, e.g. a proxy answered for
+                // another client; ask for the full response
+                None => self.get_json(path).await,
+            };
+        }
+        if !status.is_success() {
+            let body = error_body(response).await;
+            return Err(status.to_replicate_error(body));
+        }
+
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v: &HeaderValue| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        let content_type = header(CONTENT_TYPE);
+        let body = response.bytes().await?;
+        let value = parse_json(status.as_u16(), content_type.as_deref(), &body)?;
+
+        if etag.is_some() || last_modified.is_some() {
+            self.etag_cache.lock().unwrap().insert(
+                &url,
+                CachedResponse {
+                    etag,
+                    last_modified,
+                    body,
+                },
+            );
+        }
+        Ok(value)
+    }
+
+    /// Drop cached GET responses for a path that is about to be modified.
+    fn invalidate_cached(&self, method: &Method, url: &Url) {
+        if method != Method::GET {
+            self.etag_cache.lock().unwrap().invalidate_path(url);
+        }
+    }
+
+    /// Open a server-sent event stream.
+    ///
+    /// The request is not subject to the overall request timeout. Non-success
+    /// responses are returned as errors. An absolute URL must point at the API
+    /// host or, over HTTPS, a sibling host like `stream.replicate.com`, since
+    /// the request carries the API token.
+    pub async fn get_event_stream(&self, path: &str) -> Result<Response> {
+        let _in_flight = self.admit()?;
+        let url = match Url::parse(path) {
+            Ok(_) => self.validate_stream_url(path)?,
+            Err(_) => self.build_url(path)?,
+        };
+        let outcome = self
+            .transport()
+            .stream_client
+            .get(url)
+            .header("Authorization", format!("Token {}", self.api_token))
+            .header("Accept", "text/event-stream")
+            .header("Cache-Control", "no-store")
+            .headers(self.request_headers.clone())
+            .send()
+            .await;
+        let response = self.record(outcome)?;
+
+        if response.status().is_success() {
+            Ok(response)
+        } else {
+            let status = response.status();
+            let body = error_body(response).await;
+            Err(status.to_replicate_error(body))
+        }
+    }
+
+    /// Make a GET request and deserialize the response body as it arrives.
+    ///
+    /// Unlike [`get_json`](Self::get_json), the body is never buffered in full:
+    /// chunks are fed to a blocking `serde_json::from_reader` as they are
+    /// received, so peak memory is roughly the deserialized value plus a few
+    /// chunks. Prefer this for very large responses.
+    pub async fn get_json_streaming<T>(&self, path: &str) -> Result<T>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        self.stream_json(path, None).await
+    }
+
+    /// Make a GET request with extra headers and deserialize the response
+    /// body as it arrives.
+    ///
+    /// Headers are merged like in [`get_json_with_headers`](Self::get_json_with_headers);
+    /// the body is read like in [`get_json_streaming`](Self::get_json_streaming).
+    pub async fn get_json_streaming_with_headers<T>(
+        &self,
+        path: &str,
+        headers: &HeaderMap,
+    ) -> Result<T>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        self.stream_json(path, Some(headers)).await
+    }
+
+    async fn stream_json<T>(&self, path: &str, headers: Option<&HeaderMap>) -> Result<T>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        let mut response = self
+            .execute_request(Method::GET, path, headers, true)
+            .await?;
+        if let Some(content_type) = content_type_of(&response)
+            && !is_json_content_type(&content_type)
+        {
+            let status = response.status().as_u16();
+            let body = response.bytes().await?;
+            return Err(Error::decode(status, content_type, &body));
+        }
+        let (tx, rx) = tokio::sync::mpsc::channel::<Bytes>(STREAMING_CHUNK_QUEUE);
+        let parser = tokio::task::spawn_blocking(move || {
+            // serde_json reads byte by byte, so buffer ahead of the chunk reader
+            let reader = std::io::BufReader::with_capacity(64 * 1024, ChunkReader::new(rx));
+            serde_json::from_reader::<_, T>(reader)
+        });
+
+        let mut read_error = None;
+        loop {
+            match response.chunk().await {
+                Ok(Some(chunk)) => {
+                    // The parser stops early on malformed input; its error is reported below
+                    if tx.send(chunk).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    read_error = Some(e);
+                    break;
+                }
+            }
+        }
+        drop(tx);
+
+        let parsed = match parser.await {
+            Ok(parsed) => parsed,
+            Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            Err(e) => {
+                return Err(std::io::Error::other(format!(
+                    "Streaming JSON parser did not finish: {}",
+                    e
+                ))
+                .into());
+            }
+        };
+        if let Some(e) = read_error {
+            return Err(e.into());
+        }
+        Ok(parsed?)
     }
 
     /// Make a POST request and deserialize the response as JSON.
@@ -262,21 +1227,180 @@ impl HttpClient {
         path: &str,
         body: &B,
     ) -> Result<T> {
-        let response = self.post(path, body).await?;
-        let json = response.json().await?;
-        Ok(json)
+        self.within_deadline(async { read_json(self.post(path, body).await?).await })
+            .await
+    }
+
+    /// Make a single POST attempt, bypassing the retry middleware, and
+    /// deserialize the response as JSON.
+    ///
+    /// For requests that must not be sent twice without the caller checking
+    /// first whether the previous attempt went through.
+    pub(crate) async fn post_json_once<B: Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T> {
+        let _in_flight = self.admit()?;
+        let url = self.build_url(path)?;
+        self.invalidate_cached(&Method::POST, &url);
+        let (json_body, gzipped) = self.encode_json_body(body)?;
+        let request = self
+            .transport()
+            .no_retry_client
+            .post(url)
+            .headers(self.api_headers(BodyKind::Json { gzipped }, true, None)?)
+            .body(json_body);
+        let response = self
+            .within_deadline(async {
+                Ok(self.record(self.deadline_timeout(request).send().await)?)
+            })
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = error_body(response).await;
+            return Err(status.to_replicate_error(body));
+        }
+        read_json(response).await
+    }
+
+    /// Make a GET request with extra headers and deserialize the response as JSON.
+    ///
+    /// `headers` are sent with this request only, on top of the client's own
+    /// headers, e.g. a feature flag required by a beta endpoint. They override
+    /// client headers of the same name, except `Authorization`, which is
+    /// always the client's token.
+    pub async fn get_json_with_headers<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        headers: &HeaderMap,
+    ) -> Result<T> {
+        self.within_deadline(async {
+            let response = self
+                .execute_request(Method::GET, path, Some(headers), true)
+                .await?;
+            read_json(response).await
+        })
+        .await
+    }
+
+    /// Make a POST request with extra headers and deserialize the response as JSON.
+    ///
+    /// Headers are merged like in [`get_json_with_headers`](Self::get_json_with_headers).
+    /// An idempotency key passed here enables retries for this request.
+    pub async fn post_json_with_headers<B: Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+        headers: &HeaderMap,
+    ) -> Result<T> {
+        self.within_deadline(async {
+            let response = self
+                .execute_request_with_json(Method::POST, path, body, Some(headers))
+                .await?;
+            read_json(response).await
+        })
+        .await
     }
 
     /// Make a POST request without body and deserialize the response as JSON.
     pub async fn post_empty_json<T: for<'de> Deserialize<'de>>(&self, path: &str) -> Result<T> {
-        let response = self.post_empty(path).await?;
-        let json = response.json().await?;
-        Ok(json)
+        self.within_deadline(async {
+            read_json(self.execute_request(Method::POST, path, None, true).await?).await
+        })
+        .await
+    }
+
+    /// Replace the HTTP configuration through a shared reference.
+    ///
+    /// `update` receives a copy of the current configuration, and the clients
+    /// are rebuilt from the result and swapped in for every clone of this
+    /// client, including the ones held by the API handles of a
+    /// [`Client`](crate::Client). This lets a client shared behind an `Arc`
+    /// be retuned at runtime, e.g. to tighten timeouts under load.
+    ///
+    /// Tradeoffs:
+    /// - Requests already sent finish with the old configuration; only
+    ///   requests started afterwards use the new one.
+    /// - The connection pool is rebuilt, so the next requests open new
+    ///   connections.
+    /// - Scoped copies such as [`with_headers`](Self::with_headers) share the
+    ///   configuration with the client they were made from.
+    /// - Every request takes a short read lock to pick up the current
+    ///   clients. `update` runs and the clients are rebuilt outside the lock,
+    ///   so concurrent calls do not block requests; the last one to finish
+    ///   wins.
+    /// - The borrowing getters such as [`http_config`](Self::http_config)
+    ///   keep returning the configuration this client was created with; use
+    ///   [`current_http_config`](Self::current_http_config) and friends.
+    ///
+    /// Nothing changes if rebuilding the clients fails or `update` panics.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use replicate_client::Client;
+    /// # use std::sync::Arc;
+    /// # use std::time::Duration;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Arc::new(Client::new("your-api-token")?);
+    ///
+    /// client.http_client().reconfigure(|config| {
+    ///     config.timeout.request_timeout = Some(Duration::from_secs(10));
+    ///     config.retry.max_retries = 1;
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reconfigure(&self, update: impl FnOnce(&mut HttpConfig)) -> Result<()> {
+        let current = self.transport();
+        let mut http_config = current.http_config.clone();
+        update(&mut http_config);
+        let transport = Transport::new(http_config, current.observer.clone())?;
+        self.swap_transport(transport);
+        Ok(())
+    }
+
+    /// Install a lifecycle observer for this client and all its clones.
+    pub(crate) fn set_observer(&self, observer: Option<ObserverHandle>) -> Result<()> {
+        let transport = Transport::new(self.transport().http_config.clone(), observer)?;
+        self.swap_transport(transport);
+        Ok(())
+    }
+
+    /// Replace the clients shared by all clones, holding the lock only for
+    /// the swap.
+    fn swap_transport(&self, transport: Transport) {
+        *self
+            .transport
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(transport);
+    }
+
+    /// Rebuild the clients of this client alone, detaching it from the
+    /// configuration shared with its clones.
+    fn configure(&mut self, update: impl FnOnce(&mut HttpConfig)) -> Result<()> {
+        let current = self.transport();
+        let mut http_config = current.http_config.clone();
+        update(&mut http_config);
+        let transport = Arc::new(Transport::new(http_config, current.observer.clone())?);
+        self.transport = Arc::new(RwLock::new(transport.clone()));
+        self.snapshot = transport;
+        Ok(())
+    }
+
+    /// Get the installed lifecycle observer, if any.
+    pub(crate) fn observer(&self) -> Option<ObserverHandle> {
+        self.transport().observer.clone()
     }
 
     /// Configure retry policy for this client.
     ///
-    /// This rebuilds the underlying HTTP client with new retry settings.
+    /// This rebuilds the underlying HTTP client with new retry settings. Only
+    /// this client is affected: it stops sharing its configuration with
+    /// clones, such as [`with_headers`](Self::with_headers) copies. Use
+    /// [`reconfigure`](Self::reconfigure) to retune all of them through `&self`.
     ///
     /// # Examples
     ///
@@ -307,7 +1431,8 @@ impl HttpClient {
 
     /// Configure retry policy with advanced settings.
     ///
-    /// This rebuilds the underlying HTTP client with new retry settings.
+    /// This rebuilds the underlying HTTP client with new retry settings for
+    /// this client only; see [`configure_retries`](Self::configure_retries).
     ///
     /// # Arguments
     ///
@@ -322,31 +1447,21 @@ impl HttpClient {
         max_delay: Duration,
         base_multiplier: u32,
     ) -> Result<()> {
-        let new_retry_config = RetryConfig {
-            max_retries,
-            min_delay,
-            max_delay,
-            base_multiplier,
-        };
-
-        let new_http_config = HttpConfig {
-            retry: new_retry_config,
-            timeout: self.http_config.timeout.clone(),
-        };
-
-        // Rebuild the client with new configuration
-        let new_client = Self::build_client_with_config(&new_http_config)?;
-
-        // Update the client and configuration
-        self.client = new_client;
-        self.http_config = new_http_config;
-
-        Ok(())
+        self.configure(|config| {
+            config.retry = RetryConfig {
+                max_retries,
+                min_delay,
+                max_delay,
+                base_multiplier,
+                ..config.retry.clone()
+            };
+        })
     }
 
     /// Configure timeout settings for this client.
     ///
-    /// This rebuilds the underlying HTTP client with new timeout settings.
+    /// This rebuilds the underlying HTTP client with new timeout settings for
+    /// this client only; see [`configure_retries`](Self::configure_retries).
     ///
     /// # Arguments
     ///
@@ -357,39 +1472,51 @@ impl HttpClient {
         connect_timeout: Option<Duration>,
         request_timeout: Option<Duration>,
     ) -> Result<()> {
-        let new_timeout_config = TimeoutConfig {
-            connect_timeout,
-            request_timeout,
-        };
-
-        let new_http_config = HttpConfig {
-            retry: self.http_config.retry.clone(),
-            timeout: new_timeout_config,
-        };
-
-        // Rebuild the client with new configuration
-        let new_client = Self::build_client_with_config(&new_http_config)?;
-
-        // Update the client and configuration
-        self.client = new_client;
-        self.http_config = new_http_config;
-
-        Ok(())
+        self.configure(|config| {
+            config.timeout.connect_timeout = connect_timeout;
+            config.timeout.request_timeout = request_timeout;
+        })
     }
 
-    /// Get the current retry configuration.
+    /// Get the retry configuration.
+    ///
+    /// This is the configuration as of creation or the last `&mut self`
+    /// configuration; see [`current_retry_config`](Self::current_retry_config)
+    /// to observe [`reconfigure`](Self::reconfigure).
     pub fn retry_config(&self) -> &RetryConfig {
-        &self.http_config.retry
+        &self.snapshot.http_config.retry
     }
 
-    /// Get the current timeout configuration.
+    /// Get the timeout configuration.
+    ///
+    /// See [`retry_config`](Self::retry_config) for which configuration this is.
     pub fn timeout_config(&self) -> &TimeoutConfig {
-        &self.http_config.timeout
+        &self.snapshot.http_config.timeout
     }
 
-    /// Get the current HTTP configuration.
+    /// Get the HTTP configuration.
+    ///
+    /// See [`retry_config`](Self::retry_config) for which configuration this is.
     pub fn http_config(&self) -> &HttpConfig {
-        &self.http_config
+        &self.snapshot.http_config
+    }
+
+    /// Get the retry configuration requests currently use, including changes
+    /// made with [`reconfigure`](Self::reconfigure) through any clone.
+    pub fn current_retry_config(&self) -> RetryConfig {
+        self.transport().http_config.retry.clone()
+    }
+
+    /// Get the timeout configuration requests currently use; see
+    /// [`current_retry_config`](Self::current_retry_config).
+    pub fn current_timeout_config(&self) -> TimeoutConfig {
+        self.transport().http_config.timeout.clone()
+    }
+
+    /// Get the HTTP configuration requests currently use; see
+    /// [`current_retry_config`](Self::current_retry_config).
+    pub fn current_http_config(&self) -> HttpConfig {
+        self.transport().http_config.clone()
     }
 
     /// Execute a multipart form request.
@@ -399,35 +1526,33 @@ impl HttpClient {
         path: &str,
         form: reqwest::multipart::Form,
     ) -> Result<Response> {
-        let url = self.build_url(path);
-
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            AUTHORIZATION,
-            HeaderValue::from_str(&format!("Token {}", self.api_token))
-                .map_err(|_| Error::auth_error("Invalid API token format"))?,
-        );
-        headers.insert(
-            USER_AGENT,
-            HeaderValue::from_str(&format!("replicate-rs/{}", VERSION))
-                .map_err(|_| Error::InvalidInput("Invalid user agent format".to_string()))?,
-        );
-
-        // For multipart requests, we need to use the underlying reqwest client directly
-        // since reqwest-middleware doesn't support multipart forms
-        let inner_client = reqwest::Client::new();
-        let request = inner_client
-            .request(method, &url)
+        let _in_flight = self.admit()?;
+        let url = self.build_url(path)?;
+        self.invalidate_cached(&method, &url);
+
+        let headers = self.api_headers(BodyKind::Multipart, true, None)?;
+
+        // For multipart requests, we need to use a plain reqwest client since
+        // reqwest-middleware doesn't support multipart forms. The stream client
+        // has no overall timeout, which suits large uploads.
+        let inner_client = self.transport().stream_client.clone();
+        let mut request = inner_client
+            .request(method, url)
             .headers(headers)
             .multipart(form);
+        if let Some(remaining) = self.deadline.and_then(|deadline| deadline.remaining()) {
+            request = request.timeout(remaining);
+        }
 
-        let response = request.send().await?;
+        let response = self
+            .within_deadline(async { Ok(self.record(request.send().await)?) })
+            .await?;
 
         if response.status().is_success() {
             Ok(response)
         } else {
             let status = response.status().as_u16();
-            let text = response.text().await.unwrap_or_default();
+            let text = error_body(response).await;
 
             // Try to parse as JSON error
             if let Ok(api_error) = serde_json::from_str::<serde_json::Value>(&text) {
@@ -467,9 +1592,8 @@ impl HttpClient {
         path: &str,
         form: reqwest::multipart::Form,
     ) -> Result<T> {
-        let response = self.post_multipart(path, form).await?;
-        let text = response.text().await?;
-        serde_json::from_str(&text).map_err(Into::into)
+        self.within_deadline(async { read_json(self.post_multipart(path, form).await?).await })
+            .await
     }
 
     /// Create a multipart form from file and optional metadata.
@@ -505,8 +1629,22 @@ impl HttpClient {
         file_path: &Path,
         metadata: Option<&std::collections::HashMap<String, serde_json::Value>>,
     ) -> Result<reqwest::multipart::Form> {
-        // Read file content
-        let file_content = tokio::fs::read(file_path).await?;
+        Self::file_form_from_path(file_path, metadata, false).await
+    }
+
+    /// Create a multipart form from a file path, optionally checking that the
+    /// file does not change size while it is read; see
+    /// [`UploadOptions::check_size`](crate::api::UploadOptions::check_size).
+    pub(crate) async fn file_form_from_path(
+        file_path: &Path,
+        metadata: Option<&std::collections::HashMap<String, serde_json::Value>>,
+        check_size: bool,
+    ) -> Result<reqwest::multipart::Form> {
+        let file_content = if check_size {
+            read_file_checked(file_path, |path| tokio::fs::read(path.to_path_buf())).await?
+        } else {
+            tokio::fs::read(file_path).await?
+        };
 
         // Determine filename and content type
         let filename = file_path
@@ -522,25 +1660,292 @@ impl HttpClient {
     }
 }
 
+/// Read a file with `read`, checking the bytes read against its size.
+///
+/// A file that is still being written can change size between the stat and
+/// the read. The read is retried once; a second mismatch is reported as
+/// [`Error::InvalidInput`] rather than sent with a wrong length.
+async fn read_file_checked<F, Fut>(file_path: &Path, mut read: F) -> Result<Vec<u8>>
+where
+    F: FnMut(&Path) -> Fut,
+    Fut: Future<Output = std::io::Result<Vec<u8>>>,
+{
+    let mut attempt = 0;
+    loop {
+        let expected = tokio::fs::metadata(file_path).await?.len();
+        let content = read(file_path).await?;
+        if content.len() as u64 == expected {
+            return Ok(content);
+        }
+        attempt += 1;
+        if attempt > 1 {
+            return Err(size_changed_error(
+                file_path,
+                expected,
+                content.len() as u64,
+            ));
+        }
+        tracing::debug!(
+            "File {} changed size while reading, retrying",
+            file_path.display()
+        );
+    }
+}
+
+/// Body of an API request, deciding its content headers.
+#[derive(Debug, Clone, Copy)]
+enum BodyKind {
+    /// No body, so no `Content-Type`
+    Empty,
+    /// A JSON body, gzipped or not
+    Json { gzipped: bool },
+    /// A multipart form, whose `Content-Type` carries the boundary
+    Multipart,
+}
+
+/// Get the `Content-Type` header of a response.
+fn content_type_of(response: &Response) -> Option<String> {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Check if a content type is JSON, e.g. `application/json; charset=utf-8`
+/// or `application/problem+json`.
+fn is_json_content_type(content_type: &str) -> bool {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    essence == JSON_CONTENT_TYPE
+        || (essence.starts_with("application/") && essence.ends_with("+json"))
+}
+
+/// Deserialize a JSON body, failing with [`Error::Decode`] when the response
+/// declares a content type other than JSON. A body without a content type is
+/// parsed as JSON.
+fn parse_json<T: DeserializeOwned>(
+    status: u16,
+    content_type: Option<&str>,
+    body: &[u8],
+) -> Result<T> {
+    if let Some(content_type) = content_type
+        && !is_json_content_type(content_type)
+    {
+        return Err(Error::decode(status, content_type, body));
+    }
+    Ok(serde_json::from_slice(body)?)
+}
+
+/// Gzip a request body.
+#[cfg(feature = "gzip")]
+fn gzip(body: &[u8]) -> Result<Vec<u8>> {
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(body)?;
+    Ok(encoder.finish()?)
+}
+
+/// Gzip a request body; unreachable, as clients cannot enable compression
+/// without the `gzip` feature.
+#[cfg(not(feature = "gzip"))]
+fn gzip(_body: &[u8]) -> Result<Vec<u8>> {
+    Err(Error::invalid_input(
+        "Request compression requires the `gzip` feature of replicate-client",
+    ))
+}
+
+/// Read the body of an error response as text.
+///
+/// A body that is neither JSON nor plain text, such as the HTML page of a
+/// proxy, is described by its content type and start like in
+/// [`Error::Decode`], so a whole page never ends up in an error message.
+async fn error_body(response: Response) -> String {
+    let content_type = content_type_of(&response);
+    let body = response.bytes().await.unwrap_or_default();
+    match content_type {
+        Some(content_type)
+            if !body.is_empty()
+                && !is_json_content_type(&content_type)
+                && !content_type
+                    .trim()
+                    .to_ascii_lowercase()
+                    .starts_with("text/plain") =>
+        {
+            format!("{} response: {}", content_type, body_snippet(&body))
+        }
+        _ => String::from_utf8_lossy(&body).into_owned(),
+    }
+}
+
+/// Read a response body and deserialize it like [`parse_json`].
+async fn read_json<T: DeserializeOwned>(response: Response) -> Result<T> {
+    let status = response.status().as_u16();
+    let content_type = content_type_of(&response);
+    let body = response.bytes().await?;
+    parse_json(status, content_type.as_deref(), &body)
+}
+
+/// Headers asking any cache in front of the API to revalidate, so that a
+/// polled status is never a stale stored response.
+pub(crate) fn no_cache_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    headers
+}
+
+/// Error for a file that changed size while it was being uploaded.
+pub(crate) fn size_changed_error(file_path: &Path, expected: u64, actual: u64) -> Error {
+    Error::invalid_input(format!(
+        "File {} changed size during upload: expected {} bytes, read {}",
+        file_path.display(),
+        expected,
+        actual
+    ))
+}
+
+/// Blocking reader over body chunks sent from an async task.
+struct ChunkReader {
+    rx: tokio::sync::mpsc::Receiver<Bytes>,
+    current: Bytes,
+}
+
+impl ChunkReader {
+    fn new(rx: tokio::sync::mpsc::Receiver<Bytes>) -> Self {
+        Self {
+            rx,
+            current: Bytes::new(),
+        }
+    }
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.current.is_empty() {
+            match self.rx.blocking_recv() {
+                Some(chunk) => self.current = chunk,
+                None => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.current.len());
+        buf[..n].copy_from_slice(&self.current[..n]);
+        self.current.advance(n);
+        Ok(n)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn test_read_file_checked_retries_once() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let growing = temp_dir.path().join("growing.txt");
+        std::fs::write(&growing, b"a").unwrap();
+
+        // Appends to the file between the stat and the read the first `times`
+        // times, like a concurrent writer
+        let grow = |times: usize| {
+            let mut reads = 0;
+            move |path: &Path| {
+                let path = path.to_path_buf();
+                reads += 1;
+                let append = reads <= times;
+                async move {
+                    if append {
+                        let mut content = tokio::fs::read(&path).await?;
+                        content.push(b'b');
+                        tokio::fs::write(&path, content).await?;
+                    }
+                    tokio::fs::read(&path).await
+                }
+            }
+        };
+
+        assert_eq!(read_file_checked(&growing, grow(1)).await.unwrap(), b"ab");
+        match read_file_checked(&growing, grow(2)).await {
+            Err(Error::InvalidInput(message)) => {
+                assert!(message.contains("changed size"), "{}", message)
+            }
+            other => panic!("Expected invalid input, got {:?}", other),
+        }
+    }
+
     #[test]
     fn test_build_url() {
         let client = HttpClient::new("test-token").unwrap();
 
         assert_eq!(
-            client.build_url("/v1/predictions"),
+            client.build_url("/v1/predictions").unwrap().as_str(),
             "https://api.replicate.com/v1/predictions"
         );
 
         assert_eq!(
-            client.build_url("v1/predictions"),
+            client.build_url("v1/predictions").unwrap().as_str(),
             "https://api.replicate.com/v1/predictions"
         );
     }
 
+    #[test]
+    fn test_build_url_with_path_prefix() {
+        let client =
+            HttpClient::with_base_url("test-token", "https://gw/internal/replicate/").unwrap();
+        assert_eq!(
+            client.build_url("/v1/predictions").unwrap().as_str(),
+            "https://gw/internal/replicate/v1/predictions"
+        );
+
+        // Missing trailing slash must not drop the last path segment
+        let client =
+            HttpClient::with_base_url("test-token", "https://gw/internal/replicate").unwrap();
+        assert_eq!(
+            client.build_url("/v1/files/abc").unwrap().as_str(),
+            "https://gw/internal/replicate/v1/files/abc"
+        );
+    }
+
+    #[test]
+    fn test_build_url_with_port_and_trailing_slash() {
+        let client = HttpClient::with_base_url("test-token", "http://localhost:8080/").unwrap();
+        assert_eq!(
+            client
+                .build_url("v1/predictions?cursor=abc")
+                .unwrap()
+                .as_str(),
+            "http://localhost:8080/v1/predictions?cursor=abc"
+        );
+        assert_eq!(
+            client.build_url("/health").unwrap().as_str(),
+            "http://localhost:8080/health"
+        );
+    }
+
+    #[test]
+    fn test_build_url_absolute() {
+        let client = HttpClient::new("test-token").unwrap();
+        assert_eq!(
+            client
+                .build_url("https://api.replicate.com/v1/predictions?cursor=abc")
+                .unwrap()
+                .as_str(),
+            "https://api.replicate.com/v1/predictions?cursor=abc"
+        );
+    }
+
+    #[test]
+    fn test_invalid_base_url() {
+        let result = HttpClient::with_base_url("test-token", "not a url");
+        assert!(matches!(result.unwrap_err(), Error::Url(_)));
+    }
+
     #[test]
     fn test_empty_token_error() {
         let result = HttpClient::new("");
@@ -593,6 +1998,7 @@ mod tests {
             min_delay: Duration::from_millis(200),
             max_delay: Duration::from_secs(10),
             base_multiplier: 3,
+            ..RetryConfig::default()
         };
 
         let client = HttpClient::with_retry_config("test-token", custom_config.clone());
@@ -611,11 +2017,12 @@ mod tests {
         let timeout_config = TimeoutConfig {
             connect_timeout: Some(Duration::from_secs(15)),
             request_timeout: Some(Duration::from_secs(90)),
+            ..TimeoutConfig::default()
         };
 
         let http_config = HttpConfig {
-            retry: RetryConfig::default(),
             timeout: timeout_config,
+            ..HttpConfig::default()
         };
 
         let client = HttpClient::with_http_config("test-token", http_config);
@@ -674,6 +2081,699 @@ mod tests {
         assert_eq!(config.request_timeout, None);
     }
 
+    #[test]
+    fn test_with_correlation_id() {
+        let client = HttpClient::new("test-token").unwrap();
+        assert_eq!(client.correlation_id(), None);
+
+        let scoped = client.with_correlation_id("op-123").unwrap();
+        assert_eq!(scoped.correlation_id(), Some("op-123"));
+        // The original client is left untouched
+        assert_eq!(client.correlation_id(), None);
+
+        assert!(client.with_correlation_id("bad\nid").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_retry_classifies_error_bodies() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        for (route, body) in [
+            ("/v1/cold", r#"{"detail": "Model is booting, try again"}"#),
+            ("/v1/broken", r#"{"detail": "CUDA error: invalid weights"}"#),
+            ("/v1/other", r#"{"detail": "Internal server error"}"#),
+        ] {
+            Mock::given(method("GET"))
+                .and(path(route))
+                .respond_with(ResponseTemplate::new(500).set_body_string(body))
+                .up_to_n_times(1)
+                .with_priority(1)
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+            .mount(&server)
+            .await;
+
+        let retry = RetryConfig {
+            max_retries: 2,
+            min_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+            transient_error_patterns: vec!["(?i)booting".to_string()],
+            permanent_error_patterns: vec!["CUDA error".to_string()],
+            ..RetryConfig::default()
+        };
+        let client =
+            HttpClient::with_base_url_and_retry("test-token", server.uri(), retry).unwrap();
+        let attempts = |route: &'static str| {
+            let server = &server;
+            async move {
+                let requests = server.received_requests().await.unwrap();
+                requests.iter().filter(|r| r.url.path() == route).count()
+            }
+        };
+
+        client.get("/v1/cold").await.unwrap();
+        assert_eq!(attempts("/v1/cold").await, 2);
+
+        // A permanent failure is returned at once
+        let result = client.get("/v1/broken").await;
+        assert!(matches!(result, Err(Error::Api { status: 500, .. })));
+        assert_eq!(attempts("/v1/broken").await, 1);
+
+        // Unclassified errors keep the default behavior
+        client.get("/v1/other").await.unwrap();
+        assert_eq!(attempts("/v1/other").await, 2);
+
+        let invalid = RetryConfig {
+            permanent_error_patterns: vec!["(".to_string()],
+            ..RetryConfig::default()
+        };
+        assert!(matches!(
+            HttpClient::with_retry_config("test-token", invalid),
+            Err(Error::InvalidInput(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_correlation_id_persists_across_retries() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/predictions"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+            .mount(&server)
+            .await;
+
+        let retry = RetryConfig {
+            max_retries: 3,
+            min_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+            base_multiplier: 2,
+            ..RetryConfig::default()
+        };
+        let client = HttpClient::with_base_url_and_retry("test-token", server.uri(), retry)
+            .unwrap()
+            .with_correlation_id("op-123")
+            .unwrap();
+
+        client.get("/v1/predictions").await.unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 3);
+        for request in &requests {
+            assert_eq!(
+                request.headers.get(CORRELATION_ID_HEADER).unwrap(),
+                "op-123"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_per_request_headers() {
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/beta"))
+            .and(header("authorization", "Token test-token"))
+            .and(header("x-replicate-feature", "beta"))
+            .and(header(CORRELATION_ID_HEADER, "per-request"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"ok": true})))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/beta"))
+            .and(header("authorization", "Token test-token"))
+            .and(header("x-replicate-feature", "beta"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(serde_json::json!({"ok": true})))
+            .mount(&server)
+            .await;
+
+        let client = HttpClient::with_base_url("test-token", server.uri())
+            .unwrap()
+            .with_correlation_id("client-wide")
+            .unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("x-replicate-feature", HeaderValue::from_static("beta"));
+        headers.insert(
+            CORRELATION_ID_HEADER,
+            HeaderValue::from_static("per-request"),
+        );
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Token stolen"));
+
+        let value: serde_json::Value = client
+            .get_json_with_headers("/v1/beta", &headers)
+            .await
+            .unwrap();
+        assert_eq!(value["ok"], true);
+        let value: serde_json::Value = client
+            .post_json_with_headers("/v1/beta", &serde_json::json!({}), &headers)
+            .await
+            .unwrap();
+        assert_eq!(value["ok"], true);
+
+        // Headers apply to one request only
+        client.get("/v1/other").await.ok();
+        let requests = server.received_requests().await.unwrap();
+        let last = requests.last().unwrap();
+        assert!(last.headers.get("x-replicate-feature").is_none());
+        assert_eq!(
+            last.headers.get(CORRELATION_ID_HEADER).unwrap(),
+            "client-wide"
+        );
+        for request in &requests {
+            assert_eq!(request.headers.get_all("authorization").iter().count(), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_non_idempotent_retries_can_be_disabled() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+
+        let retry = RetryConfig {
+            max_retries: 2,
+            min_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+            retry_non_idempotent: false,
+            ..RetryConfig::default()
+        };
+        let client =
+            HttpClient::with_base_url_and_retry("test-token", server.uri(), retry).unwrap();
+        let count = |verb: &'static str| {
+            let server = &server;
+            async move {
+                let requests = server.received_requests().await.unwrap();
+                requests
+                    .iter()
+                    .filter(|r| r.method.as_str() == verb)
+                    .count()
+            }
+        };
+
+        // POSTs are sent once, GETs are still retried
+        assert!(client.post_empty("/v1/predictions").await.is_err());
+        assert_eq!(count("POST").await, 1);
+        assert!(client.get("/v1/predictions").await.is_err());
+        assert_eq!(count("GET").await, 3);
+
+        // An idempotency key makes POSTs safe to retry again
+        let mut headers = HeaderMap::new();
+        headers.insert(IDEMPOTENCY_KEY_HEADER, HeaderValue::from_static("key-1"));
+        let keyed = client.with_headers(headers);
+        assert!(keyed.post_empty("/v1/predictions").await.is_err());
+        assert_eq!(count("POST").await, 4);
+    }
+
+    /// Serve HTTP on a local port, resetting the first `resets` connections.
+    async fn resetting_server(resets: usize) -> (String, Arc<Mutex<usize>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let connections = Arc::new(Mutex::new(0));
+        let counter = connections.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let count = {
+                    let mut count = counter.lock().unwrap();
+                    *count += 1;
+                    *count
+                };
+                let mut buf = [0u8; 4096];
+                let _ = stream.read(&mut buf).await;
+                if count <= resets {
+                    // Closing with a zero linger sends a reset instead of a FIN
+                    stream.set_linger(Some(Duration::ZERO)).unwrap();
+                    continue;
+                }
+                let _ = stream
+                    .write_all(
+                        b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok",
+                    )
+                    .await;
+            }
+        });
+        (url, connections)
+    }
+
+    #[tokio::test]
+    async fn test_immediate_first_retry_on_connection_reset() {
+        let retry = RetryConfig {
+            max_retries: 1,
+            min_delay: Duration::from_secs(10),
+            max_delay: Duration::from_secs(10),
+            immediate_first_retry: true,
+            ..RetryConfig::default()
+        };
+        let (url, connections) = resetting_server(1).await;
+        let client = HttpClient::with_base_url_and_retry("test-token", url, retry).unwrap();
+
+        let started = std::time::Instant::now();
+        let response = client.get("/v1/predictions").await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "ok");
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(*connections.lock().unwrap(), 2);
+
+        // Without retries the reset is reported as is
+        let retry = RetryConfig {
+            max_retries: 0,
+            immediate_first_retry: true,
+            ..RetryConfig::default()
+        };
+        let (url, connections) = resetting_server(1).await;
+        let client = HttpClient::with_base_url_and_retry("test-token", url, retry).unwrap();
+        assert!(client.get("/v1/predictions").await.is_err());
+        assert_eq!(*connections.lock().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_etag_cache_conditional_get() {
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/files"))
+            .and(header("if-none-match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/files"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("etag", "\"v1\"")
+                    .set_body_json(serde_json::json!({"results": [1, 2, 3]})),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/files"))
+            .respond_with(ResponseTemplate::new(201).set_body_string("{}"))
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/v1/files/abc"))
+            .respond_with(ResponseTemplate::new(204))
+            .mount(&server)
+            .await;
+
+        let client = HttpClient::with_base_url("test-token", server.uri()).unwrap();
+        let options = RequestOptions::new().use_etag_cache(true);
+
+        let first: serde_json::Value = client
+            .get_json_with_options("/v1/files", &options)
+            .await
+            .unwrap();
+        let second: serde_json::Value = client
+            .get_json_with_options("/v1/files", &options)
+            .await
+            .unwrap();
+        assert_eq!(first, second);
+
+        // A write to the same path invalidates the cached entry
+        client
+            .post("/v1/files", &serde_json::json!({}))
+            .await
+            .unwrap();
+        let _: serde_json::Value = client
+            .get_json_with_options("/v1/files", &options)
+            .await
+            .unwrap();
+
+        // So does a write to a path below it
+        client.delete("/v1/files/abc").await.unwrap();
+        let _: serde_json::Value = client
+            .get_json_with_options("/v1/files", &options)
+            .await
+            .unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 6);
+        assert!(requests[0].headers.get("if-none-match").is_none());
+        assert_eq!(requests[1].headers.get("if-none-match").unwrap(), "\"v1\"");
+        assert!(requests[3].headers.get("if-none-match").is_none());
+        assert!(requests[5].headers.get("if-none-match").is_none());
+    }
+
+    #[test]
+    fn test_validate_stream_url() {
+        let client = HttpClient::new("test-token").unwrap();
+        for url in [
+            "https://api.replicate.com/v1/predictions/p1/stream",
+            "https://stream.replicate.com/v1/files/abc",
+        ] {
+            assert!(client.validate_stream_url(url).is_ok(), "{}", url);
+        }
+        for url in [
+            "http://stream.replicate.com/v1/files/abc",
+            "https://evilreplicate.com/stream",
+            "https://a.b.replicate.com/stream",
+            "https://stream.example.com/v1/files/abc",
+            "not a url",
+        ] {
+            assert!(
+                matches!(client.validate_stream_url(url), Err(Error::InvalidInput(_))),
+                "{}",
+                url
+            );
+        }
+
+        let local = HttpClient::with_base_url("test-token", "http://127.0.0.1:9").unwrap();
+        assert!(
+            local
+                .validate_stream_url("http://127.0.0.1:9/stream")
+                .is_ok()
+        );
+        assert!(
+            local
+                .validate_stream_url("http://127.0.0.2:9/stream")
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_etag_cache_not_modified_without_entry() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        // A stale 304 for a request the client never cached
+        Mock::given(method("GET"))
+            .and(path("/v1/files"))
+            .respond_with(ResponseTemplate::new(304))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/files"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"ok": true})))
+            .mount(&server)
+            .await;
+
+        let client = HttpClient::with_base_url("test-token", server.uri()).unwrap();
+        let body: serde_json::Value = client
+            .get_json_with_options("/v1/files", &RequestOptions::new().use_etag_cache(true))
+            .await
+            .unwrap();
+        assert_eq!(body, serde_json::json!({"ok": true}));
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].headers.get("if-none-match").is_none());
+    }
+
+    #[cfg(feature = "gzip")]
+    #[tokio::test]
+    async fn test_compress_large_request_bodies() {
+        use flate2::read::GzDecoder;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(serde_json::json!({})))
+            .mount(&server)
+            .await;
+
+        let http_config = HttpConfig::default().compress_requests(true);
+        let client =
+            HttpClient::with_base_url_and_http_config("test-token", server.uri(), http_config)
+                .unwrap();
+        let large = serde_json::json!({"input": {"text": "lorem ipsum ".repeat(2000)}});
+        let small = serde_json::json!({"input": {"text": "hi"}});
+        let _: serde_json::Value = client.post_json("/v1/predictions", &large).await.unwrap();
+        let _: serde_json::Value = client.post_json("/v1/predictions", &small).await.unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests[0].headers.get("content-encoding").unwrap(), "gzip");
+        assert!(requests[0].body.len() < COMPRESSION_THRESHOLD);
+        let mut decoded = Vec::new();
+        GzDecoder::new(requests[0].body.as_slice())
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&decoded).unwrap(),
+            large
+        );
+        assert!(requests[1].headers.get("content-encoding").is_none());
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&requests[1].body).unwrap(),
+            small
+        );
+
+        // Off by default
+        let client = HttpClient::with_base_url("test-token", server.uri()).unwrap();
+        let _: serde_json::Value = client.post_json("/v1/predictions", &large).await.unwrap();
+        let requests = server.received_requests().await.unwrap();
+        assert!(requests[2].headers.get("content-encoding").is_none());
+    }
+
+    #[cfg(not(feature = "gzip"))]
+    #[test]
+    fn test_compression_requires_feature() {
+        let http_config = HttpConfig::default().compress_requests(true);
+        assert!(matches!(
+            HttpClient::with_http_config("test-token", http_config),
+            Err(Error::InvalidInput(message)) if message.contains("`gzip` feature")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_request_headers_per_method() {
+        use wiremock::matchers::any;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({})))
+            .mount(&server)
+            .await;
+        let client = HttpClient::with_base_url("test-token", server.uri()).unwrap();
+
+        client.get("/v1/raw").await.unwrap();
+        let _: serde_json::Value = client.get_json("/v1/json").await.unwrap();
+        client.delete("/v1/json").await.unwrap();
+        let _: serde_json::Value = client.post_empty_json("/v1/json").await.unwrap();
+        let _: serde_json::Value = client
+            .post_json("/v1/json", &serde_json::json!({"a": 1}))
+            .await
+            .unwrap();
+        client
+            .put("/v1/json", &serde_json::json!({"a": 1}))
+            .await
+            .unwrap();
+        let form = reqwest::multipart::Form::new().text("content", "x");
+        let _: serde_json::Value = client.post_multipart_json("/v1/files", form).await.unwrap();
+
+        let bodyless = ["accept", "authorization", "host", "user-agent"];
+        let with_body = [
+            "accept",
+            "authorization",
+            "content-length",
+            "content-type",
+            "host",
+            "user-agent",
+        ];
+        let expected: [(&str, &[&str], &str, Option<&str>); 7] = [
+            ("GET", &bodyless, "*/*", None),
+            ("GET", &bodyless, JSON_CONTENT_TYPE, None),
+            ("DELETE", &bodyless, JSON_CONTENT_TYPE, None),
+            ("POST", &bodyless, JSON_CONTENT_TYPE, None),
+            (
+                "POST",
+                &with_body,
+                JSON_CONTENT_TYPE,
+                Some(JSON_CONTENT_TYPE),
+            ),
+            (
+                "PUT",
+                &with_body,
+                JSON_CONTENT_TYPE,
+                Some(JSON_CONTENT_TYPE),
+            ),
+            (
+                "POST",
+                &with_body,
+                JSON_CONTENT_TYPE,
+                Some("multipart/form-data"),
+            ),
+        ];
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), expected.len());
+        for (request, (method, names, accept, content_type)) in requests.iter().zip(expected) {
+            let header = |name: &str| request.headers.get(name).map(|v| v.to_str().unwrap());
+            let mut sent: Vec<_> = request.headers.keys().map(|name| name.as_str()).collect();
+            sent.sort();
+            assert_eq!(request.method.as_str(), method);
+            assert_eq!(sent, names, "{} {}", method, request.url.path());
+            assert_eq!(header("authorization"), Some("Token test-token"));
+            assert_eq!(header("accept"), Some(accept));
+            assert_eq!(
+                header("content-type").map(|value| value.split(';').next().unwrap()),
+                content_type
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streaming_parser_panics_propagate() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        struct Explodes;
+        impl<'de> Deserialize<'de> for Explodes {
+            fn deserialize<D: serde::Deserializer<'de>>(
+                _: D,
+            ) -> std::result::Result<Self, D::Error> {
+                panic!("parser exploded")
+            }
+        }
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/large"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({})))
+            .mount(&server)
+            .await;
+
+        let client = HttpClient::with_base_url("test-token", server.uri()).unwrap();
+        let outcome =
+            tokio::spawn(async move { client.get_json_streaming::<Explodes>("/v1/large").await })
+                .await;
+        let panic = outcome.err().expect("the parser panic reaches the caller");
+        assert!(panic.is_panic());
+        assert_eq!(
+            panic.into_panic().downcast_ref::<&str>(),
+            Some(&"parser exploded")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_non_json_responses_are_rejected() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let page = "<html>\n  <body><h1>Bad Gateway</h1></body>\n</html>";
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/proxied"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(page, "text/html"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/gateway"))
+            .respond_with(ResponseTemplate::new(502).set_body_raw(page, "text/html"))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/files"))
+            .respond_with(ResponseTemplate::new(201).set_body_raw(page, "text/html"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/forbidden-page"))
+            .respond_with(ResponseTemplate::new(418).set_body_raw(page.repeat(20), "text/html"))
+            .mount(&server)
+            .await;
+
+        let retry = RetryConfig {
+            max_retries: 0,
+            ..RetryConfig::default()
+        };
+        let client =
+            HttpClient::with_base_url_and_retry("test-token", server.uri(), retry).unwrap();
+
+        match client.get_json::<serde_json::Value>("/v1/proxied").await {
+            Err(Error::Decode {
+                status,
+                content_type,
+                snippet,
+            }) => {
+                assert_eq!(status, 200);
+                assert_eq!(content_type, "text/html");
+                assert_eq!(snippet, "<html> <body><h1>Bad Gateway</h1></body> </html>");
+            }
+            other => panic!("expected a decode error, got {:?}", other),
+        }
+        // Error pages keep their status and are described by content type
+        // and snippet rather than copied whole
+        match client.get_json::<serde_json::Value>("/v1/gateway").await {
+            Err(Error::Api {
+                status: 502,
+                message,
+                detail,
+            }) => {
+                assert_eq!(message, "Server error");
+                assert_eq!(
+                    detail.as_deref(),
+                    Some("text/html response: <html> <body><h1>Bad Gateway</h1></body> </html>")
+                );
+            }
+            other => panic!("expected a server error, got {:?}", other),
+        }
+        match client
+            .get_json::<serde_json::Value>("/v1/forbidden-page")
+            .await
+        {
+            Err(Error::Api {
+                status: 418,
+                message,
+                ..
+            }) => {
+                assert!(
+                    message.starts_with("text/html response: <html>"),
+                    "{}",
+                    message
+                );
+                assert!(message.ends_with("..."), "{}", message);
+                assert!(message.len() < 250, "{}", message);
+            }
+            other => panic!("expected an API error, got {:?}", other),
+        }
+        let form = reqwest::multipart::Form::new().text("content", "x");
+        assert!(matches!(
+            client
+                .post_multipart_json::<serde_json::Value>("/v1/files", form)
+                .await,
+            Err(Error::Decode { status: 201, .. })
+        ));
+
+        let requests = server.received_requests().await.unwrap();
+        assert!(
+            requests
+                .iter()
+                .all(|request| request.headers.get("accept").unwrap() == "application/json")
+        );
+    }
+
     #[test]
     fn test_http_config_accessors() {
         let http_config = HttpConfig {
@@ -682,11 +2782,14 @@ mod tests {
                 min_delay: Duration::from_millis(100),
                 max_delay: Duration::from_secs(20),
                 base_multiplier: 4,
+                ..RetryConfig::default()
             },
             timeout: TimeoutConfig {
                 connect_timeout: Some(Duration::from_secs(10)),
                 request_timeout: Some(Duration::from_secs(45)),
+                ..TimeoutConfig::default()
             },
+            ..HttpConfig::default()
         };
 
         let client = HttpClient::with_http_config("test-token", http_config);
@@ -0,0 +1,128 @@
+//! Overall time budgets shared by the requests of one operation.
+
+use std::future::Future;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::error::{Error, Result};
+
+/// Point in time by which a whole operation must be finished.
+///
+/// Unlike per-request timeouts, a deadline is shared by every step of an
+/// operation: file uploads, the create request, each poll and all retries
+/// get only the time that is left, and the operation fails with
+/// [`Error::Timeout`] of kind [`TimeoutKind::Deadline`](crate::error::TimeoutKind::Deadline)
+/// once it has passed.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use replicate_client::{Client, Deadline, RunOptions};
+/// # use std::time::Duration;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = Client::new("your-api-token")?;
+///
+/// let prediction = client
+///     .create_prediction("stability-ai/sdxl:version-id")
+///     .input("prompt", "A futuristic city skyline")
+///     .run_options(RunOptions::new().deadline(Deadline::after(Duration::from_secs(30))))
+///     .send_and_wait()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Deadline(Instant);
+
+impl Deadline {
+    /// Create a deadline at the given instant.
+    pub fn at(instant: impl Into<Instant>) -> Self {
+        Self(instant.into())
+    }
+
+    /// Create a deadline `duration` from now.
+    pub fn after(duration: Duration) -> Self {
+        Self(Instant::now() + duration)
+    }
+
+    /// Get the instant of the deadline.
+    pub fn instant(&self) -> Instant {
+        self.0
+    }
+
+    /// Get the time left, or `None` once the deadline has passed.
+    pub fn remaining(&self) -> Option<Duration> {
+        let remaining = self.0.saturating_duration_since(Instant::now());
+        (!remaining.is_zero()).then_some(remaining)
+    }
+
+    /// Check if the deadline has passed.
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_none()
+    }
+
+    /// Limit `duration` to the time left before the deadline.
+    pub fn cap(&self, duration: Option<Duration>) -> Duration {
+        let remaining = self.remaining().unwrap_or_default();
+        duration.map_or(remaining, |duration| duration.min(remaining))
+    }
+
+    /// Run `future`, failing with a deadline timeout if it does not finish in time.
+    pub(crate) async fn run<T>(self, future: impl Future<Output = Result<T>>) -> Result<T> {
+        if self.is_expired() {
+            return Err(Error::deadline_exceeded(
+                "Deadline passed before the request was sent",
+            ));
+        }
+        tokio::time::timeout_at(self.0, future)
+            .await
+            .unwrap_or_else(|_| {
+                Err(Error::deadline_exceeded(
+                    "Deadline passed during the request",
+                ))
+            })
+    }
+}
+
+impl From<Instant> for Deadline {
+    fn from(instant: Instant) -> Self {
+        Self(instant)
+    }
+}
+
+impl From<std::time::Instant> for Deadline {
+    fn from(instant: std::time::Instant) -> Self {
+        Self(instant.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::TimeoutKind;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_deadline_budget() {
+        let deadline = Deadline::after(Duration::from_secs(10));
+        assert_eq!(deadline.remaining(), Some(Duration::from_secs(10)));
+        assert_eq!(
+            deadline.cap(Some(Duration::from_secs(3))),
+            Duration::from_secs(3)
+        );
+        assert_eq!(deadline.cap(None), Duration::from_secs(10));
+
+        let slow = deadline.run(async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok(())
+        });
+        match slow.await {
+            Err(Error::Timeout { kind, .. }) => assert_eq!(kind, TimeoutKind::Deadline),
+            other => panic!("Expected a deadline timeout, got {:?}", other),
+        }
+        assert!(deadline.is_expired());
+        assert_eq!(deadline.cap(Some(Duration::from_secs(3))), Duration::ZERO);
+        assert!(deadline.run(async { Ok(()) }).await.is_err());
+    }
+}
@@ -1,6 +1,17 @@
 //! HTTP client functionality for the Replicate API.
 
+mod cache;
 pub mod client;
+mod deadline;
+mod retry;
+mod shutdown;
+mod stats;
 
 // Re-export the main client
-pub use client::{HttpClient, HttpConfig, RetryConfig, TimeoutConfig};
+pub use client::{
+    COMPRESSION_THRESHOLD, CORRELATION_ID_HEADER, HttpClient, HttpConfig, IDEMPOTENCY_KEY_HEADER,
+    RequestOptions, RetryConfig, TimeoutConfig,
+};
+pub use deadline::Deadline;
+pub use shutdown::ShutdownReport;
+pub use stats::{ClientStats, REQUEST_ID_HEADER, RateLimitInfo};
@@ -0,0 +1,214 @@
+//! Retry middlewares: immediate retry of requests that fail on a dropped
+//! connection, and retry decisions based on error response bodies.
+
+use crate::http::RetryConfig;
+use crate::observer::ObserverHandle;
+use http::Extensions;
+use regex::RegexSet;
+use reqwest::{Request, Response, ResponseBuilderExt};
+use reqwest_middleware::{Middleware, Next, Result};
+use reqwest_retry::{DefaultRetryableStrategy, Retryable, RetryableStrategy};
+use std::error::Error as _;
+use std::io::ErrorKind;
+
+/// Marks a request whose immediate retry has been spent.
+#[derive(Debug, Clone, Copy)]
+struct ImmediateRetryUsed;
+
+/// Retries a request once without delay when its connection fails.
+///
+/// Connection resets usually come from a load balancer closing an idle pooled
+/// connection, so a second attempt on a fresh connection succeeds right away.
+/// Installed inside the backoff middleware, which passes the same extensions
+/// to every attempt; the marker stored there limits this to the first failure
+/// of each request, after which the regular backoff applies.
+#[derive(Debug, Default)]
+pub(crate) struct ImmediateRetryMiddleware;
+
+#[async_trait::async_trait]
+impl Middleware for ImmediateRetryMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        if extensions.get::<ImmediateRetryUsed>().is_some() {
+            return next.run(req, extensions).await;
+        }
+        let Some(retry) = req.try_clone() else {
+            return next.run(req, extensions).await;
+        };
+
+        match next.clone().run(req, extensions).await {
+            Err(reqwest_middleware::Error::Reqwest(error)) if is_connection_error(&error) => {
+                tracing::debug!("Connection failed ({}), retrying immediately", error);
+                extensions.insert(ImmediateRetryUsed);
+                next.run(retry, extensions).await
+            }
+            outcome => outcome,
+        }
+    }
+}
+
+/// Number of the attempt a request is on, starting at 0.
+#[derive(Debug, Clone, Copy)]
+struct Attempt(u32);
+
+/// Reports every retried attempt of a request to the lifecycle observer.
+///
+/// Installed innermost, so it sees each attempt made by the backoff and
+/// immediate retry middlewares; the attempt count lives in the extensions
+/// they share across attempts.
+#[derive(Debug)]
+pub(crate) struct ObserverMiddleware {
+    pub(crate) observer: ObserverHandle,
+}
+
+#[async_trait::async_trait]
+impl Middleware for ObserverMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let attempt = extensions
+            .get::<Attempt>()
+            .map_or(0, |attempt| attempt.0 + 1);
+        extensions.insert(Attempt(attempt));
+        if attempt > 0 {
+            self.observer.retried(req.url().path(), attempt);
+        }
+        next.run(req, extensions).await
+    }
+}
+
+/// Retry decision taken from the body of an error response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BodyClass {
+    Transient,
+    Permanent,
+}
+
+/// Classifies error responses by their body with
+/// [`RetryConfig::transient_error_patterns`] and
+/// [`RetryConfig::permanent_error_patterns`].
+///
+/// Installed inside the backoff middleware: the body is read, matched and
+/// put back, and the outcome stored on the response for
+/// [`ErrorBodyStrategy`] to act on.
+#[derive(Debug)]
+pub(crate) struct ErrorBodyMiddleware {
+    transient: RegexSet,
+    permanent: RegexSet,
+}
+
+impl ErrorBodyMiddleware {
+    /// Compile the patterns of a retry configuration, if it has any.
+    pub(crate) fn new(config: &RetryConfig) -> crate::Result<Option<Self>> {
+        if config.transient_error_patterns.is_empty() && config.permanent_error_patterns.is_empty()
+        {
+            return Ok(None);
+        }
+        let compile = |patterns: &[String]| {
+            RegexSet::new(patterns).map_err(|e| {
+                crate::Error::invalid_input(format!("Invalid retry error pattern: {}", e))
+            })
+        };
+        Ok(Some(Self {
+            transient: compile(&config.transient_error_patterns)?,
+            permanent: compile(&config.permanent_error_patterns)?,
+        }))
+    }
+
+    fn classify(&self, body: &str) -> Option<BodyClass> {
+        if self.permanent.is_match(body) {
+            Some(BodyClass::Permanent)
+        } else if self.transient.is_match(body) {
+            Some(BodyClass::Transient)
+        } else {
+            None
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for ErrorBodyMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> Result<Response> {
+        let response = next.run(req, extensions).await?;
+        let status = response.status();
+        if !status.is_client_error() && !status.is_server_error() {
+            return Ok(response);
+        }
+
+        let url = response.url().clone();
+        let version = response.version();
+        let headers = response.headers().clone();
+        let body = response.bytes().await?;
+        let class = self.classify(&String::from_utf8_lossy(&body));
+        if let Some(class) = class {
+            tracing::debug!("{} response body classified as {:?}", status, class);
+        }
+
+        let mut rebuilt = http::Response::builder()
+            .status(status)
+            .version(version)
+            .url(url)
+            .body(body)
+            .expect("parts of a valid response");
+        *rebuilt.headers_mut() = headers;
+        let mut response = Response::from(rebuilt);
+        if let Some(class) = class {
+            response.extensions_mut().insert(class);
+        }
+        Ok(response)
+    }
+}
+
+/// Retry strategy honoring the class set by [`ErrorBodyMiddleware`], and
+/// the default strategy otherwise.
+pub(crate) struct ErrorBodyStrategy;
+
+impl RetryableStrategy for ErrorBodyStrategy {
+    fn handle(&self, res: &Result<Response>) -> Option<Retryable> {
+        match res
+            .as_ref()
+            .ok()
+            .and_then(|response| response.extensions().get::<BodyClass>())
+        {
+            Some(BodyClass::Permanent) => Some(Retryable::Fatal),
+            Some(BodyClass::Transient) => Some(Retryable::Transient),
+            None => DefaultRetryableStrategy.handle(res),
+        }
+    }
+}
+
+/// Check if a request failed because the connection could not be established
+/// or was dropped by the peer.
+fn is_connection_error(error: &reqwest::Error) -> bool {
+    if error.is_connect() {
+        return true;
+    }
+    let mut source = error.source();
+    while let Some(cause) = source {
+        if let Some(io) = cause.downcast_ref::<std::io::Error>()
+            && matches!(
+                io.kind(),
+                ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::BrokenPipe
+                    | ErrorKind::UnexpectedEof
+            )
+        {
+            return true;
+        }
+        source = cause.source();
+    }
+    false
+}
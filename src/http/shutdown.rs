@@ -0,0 +1,104 @@
+//! Tracking of in-flight operations for graceful shutdown.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+use crate::error::{Error, Result};
+
+/// Outcome of [`Client::shutdown`](crate::Client::shutdown).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// Operations in flight at shutdown that finished within the grace period
+    pub completed: usize,
+    /// Operations still in flight when the grace period ran out
+    ///
+    /// They are not canceled and may still finish later.
+    pub abandoned: usize,
+}
+
+/// Closed flag and in-flight counter shared by all clones of a client.
+#[derive(Debug, Default)]
+pub(crate) struct Lifecycle {
+    closed: AtomicBool,
+    in_flight: AtomicUsize,
+    /// Notified when the last in-flight operation finishes.
+    idle: Notify,
+    /// Lifecycle of the pool this client belongs to, which also counts its
+    /// operations and closes it when shut down.
+    parent: Option<Arc<Lifecycle>>,
+}
+
+impl Lifecycle {
+    /// Create the lifecycle of a client belonging to this one's pool.
+    pub(crate) fn child(self: &Arc<Self>) -> Arc<Self> {
+        Arc::new(Self {
+            parent: Some(self.clone()),
+            ..Self::default()
+        })
+    }
+
+    /// Iterate over this lifecycle and those of the pools it belongs to.
+    fn chain(&self) -> impl Iterator<Item = &Lifecycle> {
+        std::iter::successors(Some(self), |lifecycle| lifecycle.parent.as_deref())
+    }
+
+    /// Check if the client or its pool has been shut down.
+    pub(crate) fn is_closed(&self) -> bool {
+        self.chain()
+            .any(|lifecycle| lifecycle.closed.load(Ordering::SeqCst))
+    }
+
+    /// Count a new operation as in flight, failing once the client is closed.
+    pub(crate) fn admit(self: &Arc<Self>) -> Result<InFlight> {
+        // Count first, so a concurrent shutdown either sees the operation or
+        // the operation sees the closed flag.
+        for lifecycle in self.chain() {
+            lifecycle.in_flight.fetch_add(1, Ordering::SeqCst);
+        }
+        let guard = InFlight(self.clone());
+        if self.is_closed() {
+            return Err(Error::ClientClosed);
+        }
+        Ok(guard)
+    }
+
+    /// Refuse new operations and wait up to `grace` for in-flight ones.
+    pub(crate) async fn shutdown(&self, grace: Duration) -> ShutdownReport {
+        self.closed.store(true, Ordering::SeqCst);
+        let started = self.in_flight.load(Ordering::SeqCst);
+        let deadline = Instant::now() + grace;
+        loop {
+            // Created before the check so a wakeup in between is not missed
+            let idle = self.idle.notified();
+            if self.in_flight.load(Ordering::SeqCst) == 0
+                || tokio::time::timeout_at(deadline, idle).await.is_err()
+            {
+                break;
+            }
+        }
+
+        let abandoned = self.in_flight.load(Ordering::SeqCst).min(started);
+        ShutdownReport {
+            completed: started - abandoned,
+            abandoned,
+        }
+    }
+}
+
+/// Marks one operation as in flight until dropped.
+#[derive(Debug)]
+pub(crate) struct InFlight(Arc<Lifecycle>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        for lifecycle in self.0.chain() {
+            if lifecycle.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+                lifecycle.idle.notify_waiters();
+            }
+        }
+    }
+}
@@ -0,0 +1,96 @@
+//! Request counters and the last response metadata seen by a client.
+
+use reqwest::Response;
+use reqwest::header::HeaderMap;
+use serde::Serialize;
+
+/// Response header carrying the server-assigned request ID.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Cumulative request counters for a client and all of its clones.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ClientStats {
+    /// Requests sent, counting each logical request once regardless of retries
+    pub requests: u64,
+    /// Requests answered with a success status
+    pub successes: u64,
+    /// Requests answered with an error status
+    pub api_errors: u64,
+    /// Requests answered with `429 Too Many Requests`, also counted as API errors
+    pub rate_limited: u64,
+    /// Requests that failed without a response, e.g. on timeouts
+    pub transport_errors: u64,
+}
+
+/// Rate-limit headers from the most recent response that had any.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RateLimitInfo {
+    /// Requests allowed in the current window
+    pub limit: Option<u64>,
+    /// Requests left in the current window
+    pub remaining: Option<u64>,
+    /// When the window resets, as sent by the server
+    pub reset: Option<String>,
+}
+
+impl RateLimitInfo {
+    /// Read `ratelimit-*` or `x-ratelimit-*` headers, if any are present.
+    fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .or_else(|| headers.get(format!("x-{}", name)))
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.trim().to_string())
+        };
+        let info = Self {
+            limit: header("ratelimit-limit").and_then(|value| value.parse().ok()),
+            remaining: header("ratelimit-remaining").and_then(|value| value.parse().ok()),
+            reset: header("ratelimit-reset"),
+        };
+        (info != Self::default()).then_some(info)
+    }
+}
+
+/// Mutable state behind [`ClientStats`], shared by all clones of a client.
+#[derive(Debug, Default)]
+pub(crate) struct StatsRecorder {
+    pub stats: ClientStats,
+    pub last_request_id: Option<String>,
+    pub last_rate_limit: Option<RateLimitInfo>,
+}
+
+impl StatsRecorder {
+    /// Record the outcome of sending one request.
+    pub fn record<E>(&mut self, outcome: &std::result::Result<Response, E>) {
+        self.stats.requests += 1;
+        let response = match outcome {
+            Ok(response) => response,
+            Err(_) => {
+                self.stats.transport_errors += 1;
+                return;
+            }
+        };
+
+        let status = response.status();
+        if status.is_success() || status.is_redirection() {
+            self.stats.successes += 1;
+        } else {
+            self.stats.api_errors += 1;
+            if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                self.stats.rate_limited += 1;
+            }
+        }
+
+        let headers = response.headers();
+        if let Some(request_id) = headers
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+        {
+            self.last_request_id = Some(request_id.to_string());
+        }
+        if let Some(rate_limit) = RateLimitInfo::from_headers(headers) {
+            self.last_rate_limit = Some(rate_limit);
+        }
+    }
+}
@@ -27,19 +27,62 @@
 
 pub mod api;
 pub mod client;
+pub mod cost;
+pub mod diagnostics;
+pub mod env_config;
 pub mod error;
 pub mod http;
 pub mod models;
+pub mod observer;
+pub mod pool;
+pub mod sanitize;
 
 // Re-export main types for convenience
-pub use api::files::{File, FilesApi};
-pub use client::Client;
-pub use error::{Error, Result};
-pub use http::{HttpConfig, RetryConfig, TimeoutConfig};
+pub use api::collections::CollectionsApi;
+pub use api::deployments::DeploymentsApi;
+pub use api::files::{
+    File, FileChecksums, FilesApi, UploadAbortHandle, UploadOptions, UploadProgress, UploadedFile,
+};
+pub use api::hardware::HardwareApi;
+pub use api::models::ModelsApi;
+pub use api::polling::{PollOptions, Pollable, poll_until_terminal};
+pub use api::predictions::{
+    CancelOutcome, CancelReport, DEFAULT_FALLBACK_STATUSES, PollUpdate, PredictionFilter, RunMode,
+    RunOptions,
+};
+pub use api::stream::{JsonObjects, PredictionStream};
+pub use api::trainings::{TrainingBuilder, TrainingsApi};
+pub use api::watcher::{PredictionWatcher, WatcherConfig};
+pub use api::webhooks::{
+    DeliveryDeduplicator, WebhookCheck, WebhookDelivery, WebhookEvent, WebhookHeaders,
+    WebhookSecret, WebhooksApi, parse_event,
+};
+pub use client::{Client, ClientBuilder};
+pub use cost::{CostSummary, CostTracker, ModelCost, PriceTable, TokenPrices};
+pub use diagnostics::Diagnostics;
+pub use env_config::AppliedEnv;
+pub use error::{Error, Result, TimeoutKind};
+pub use http::{
+    ClientStats, Deadline, HttpConfig, RateLimitInfo, RequestOptions, RetryConfig, ShutdownReport,
+    TimeoutConfig,
+};
 pub use models::{
-    file::{FileEncodingStrategy, FileInput, FileOutput},
-    prediction::{Prediction, PredictionStatus},
+    collection::Collection,
+    common::{CreateModelRequest, Hardware, HardwareSku, ModelRef, Visibility},
+    file::{FileEncodingStrategy, FileInput, FileOutput, decode_data_url},
+    id::{FileId, PredictionId, TrainingId},
+    image_edit::ImageEditRequest,
+    manifest::{MANIFEST_FORMAT_VERSION, Manifest, ManifestArtifact, ManifestPrediction},
+    output::{ModelOutput, OutputClassifier, OutputSelector, TypedOutput},
+    prediction::{FallbackTarget, Prediction, PredictionHandle, PredictionStatus, TypedPrediction},
+    schema::{DynamicInput, InputSchema, PropertySchema},
+    stream::StreamEvent,
+    template::InputTemplate,
+    training::{CreateTrainingRequest, Training},
 };
+pub use observer::{LifecycleObserver, LogObserver};
+pub use pool::ClientPool;
+pub use sanitize::{InputSanitizer, SanitizePolicy};
 
 // Version information
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
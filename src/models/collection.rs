@@ -0,0 +1,23 @@
+//! Collection-related types and structures.
+
+use crate::models::common::Model;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// A curated collection of models on Replicate.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Collection {
+    /// Collection name
+    pub name: String,
+    /// URL-safe collection identifier
+    pub slug: String,
+    /// Collection description
+    pub description: Option<String>,
+    /// Models in the collection (only present when fetching a single collection)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub models: Option<Vec<Model>>,
+    /// Fields not modelled by this crate, kept as returned by the API
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
@@ -1,6 +1,17 @@
 //! Common types and structures used across the API.
 
-use serde::{Deserialize, Serialize};
+use crate::error::Error;
+use crate::http::HttpClient;
+use crate::models::prediction::Prediction;
+use crate::models::schema::InputSchema;
+use futures::stream::{self, Stream};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// Generic API response wrapper.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,7 +24,10 @@ pub struct ApiResponse<T> {
 /// Paginated response structure.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PaginatedResponse<T> {
-    /// The results for this page
+    /// The results for this page, empty when the API omits them
+    ///
+    /// Also read from `models`, as collections such as `official` list them.
+    #[serde(default = "Vec::new", alias = "models")]
     pub results: Vec<T>,
     /// URL for the next page (if available)
     pub next: Option<String>,
@@ -43,15 +57,212 @@ impl<T> PaginatedResponse<T> {
     }
 }
 
+impl<T: DeserializeOwned + Send + 'static> PaginatedResponse<T> {
+    /// Turn this page into a stream over every result, following `next`.
+    ///
+    /// The results of this page are yielded first; later pages are fetched
+    /// lazily through `http`. A failed fetch is yielded once and ends the
+    /// stream, as is a `next` URL that does not point at the API host, which
+    /// is never sent the API token.
+    pub fn into_stream(
+        self,
+        http: &HttpClient,
+    ) -> impl Stream<Item = crate::Result<T>> + Send + 'static {
+        let state = (self.results.into_iter(), self.next, http.clone());
+        stream::unfold(state, |(mut results, mut next, http)| async move {
+            loop {
+                if let Some(item) = results.next() {
+                    return Some((Ok(item), (results, next, http)));
+                }
+                let url = next.take()?;
+                let page = match http.validate_api_url(&url) {
+                    Ok(url) => http.get_json::<PaginatedResponse<T>>(url.as_str()).await,
+                    Err(error) => Err(error),
+                };
+                match page {
+                    Ok(page) => {
+                        results = page.results.into_iter();
+                        next = page.next;
+                    }
+                    Err(error) => return Some((Err(error), (results, None, http))),
+                }
+            }
+        })
+    }
+}
+
 /// Hardware configuration for running models.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Hardware {
     /// Hardware identifier
-    pub sku: String,
+    pub sku: HardwareSku,
     /// Human-readable name
     pub name: String,
 }
 
+/// Visibility of a model on Replicate, with a fallback for values this crate
+/// doesn't know yet.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Visibility {
+    /// Anyone can see and run the model
+    Public,
+    /// Only the owner can see and run the model
+    Private,
+    /// Any other visibility, kept verbatim
+    Other(String),
+}
+
+impl Visibility {
+    /// Get the visibility string as used by the API
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Public => "public",
+            Self::Private => "private",
+            Self::Other(visibility) => visibility,
+        }
+    }
+}
+
+impl From<&str> for Visibility {
+    fn from(visibility: &str) -> Self {
+        match visibility {
+            "public" => Self::Public,
+            "private" => Self::Private,
+            _ => Self::Other(visibility.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Visibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for Visibility {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Visibility {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let visibility = String::deserialize(deserializer)?;
+        Ok(Self::from(visibility.as_str()))
+    }
+}
+
+/// A hardware SKU, with constants for the SKUs this crate knows.
+///
+/// Any SKU string can be used, so new SKUs work without a crate update.
+/// Parsing maps known SKUs case-insensitively to their constant and keeps
+/// other SKUs verbatim; `Display` yields the SKU string.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HardwareSku(Cow<'static, str>);
+
+impl HardwareSku {
+    /// CPU only (`cpu`)
+    pub const CPU: Self = Self::known("cpu");
+    /// Nvidia T4 GPU (`gpu-t4`)
+    pub const NVIDIA_T4: Self = Self::known("gpu-t4");
+    /// Nvidia A40 GPU (`gpu-a40-small`)
+    pub const NVIDIA_A40: Self = Self::known("gpu-a40-small");
+    /// Nvidia A40 (Large) GPU (`gpu-a40-large`)
+    pub const NVIDIA_A40_LARGE: Self = Self::known("gpu-a40-large");
+    /// Nvidia A100 (40GB) GPU (`gpu-a100-small`)
+    pub const NVIDIA_A100_40GB: Self = Self::known("gpu-a100-small");
+    /// Nvidia A100 (80GB) GPU (`gpu-a100-large`)
+    pub const NVIDIA_A100_80GB: Self = Self::known("gpu-a100-large");
+    /// 2x Nvidia A100 (80GB) GPU (`gpu-a100-large-2x`)
+    pub const NVIDIA_A100_80GB_2X: Self = Self::known("gpu-a100-large-2x");
+    /// Nvidia L40S GPU (`gpu-l40s`)
+    pub const NVIDIA_L40S: Self = Self::known("gpu-l40s");
+    /// Nvidia H100 GPU (`gpu-h100`)
+    pub const NVIDIA_H100: Self = Self::known("gpu-h100");
+
+    /// The SKUs with a constant.
+    const KNOWN: [Self; 9] = [
+        Self::CPU,
+        Self::NVIDIA_T4,
+        Self::NVIDIA_A40,
+        Self::NVIDIA_A40_LARGE,
+        Self::NVIDIA_A100_40GB,
+        Self::NVIDIA_A100_80GB,
+        Self::NVIDIA_A100_80GB_2X,
+        Self::NVIDIA_L40S,
+        Self::NVIDIA_H100,
+    ];
+
+    const fn known(sku: &'static str) -> Self {
+        Self(Cow::Borrowed(sku))
+    }
+
+    /// Get the SKU string as used by the API
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Check if this SKU is a GPU
+    pub fn is_gpu(&self) -> bool {
+        self.0.starts_with("gpu-")
+    }
+}
+
+impl From<&str> for HardwareSku {
+    fn from(sku: &str) -> Self {
+        let trimmed = sku.trim();
+        Self::KNOWN
+            .into_iter()
+            .find(|known| known.as_str().eq_ignore_ascii_case(trimmed))
+            .unwrap_or_else(|| Self(Cow::Owned(sku.to_string())))
+    }
+}
+
+impl From<String> for HardwareSku {
+    fn from(sku: String) -> Self {
+        Self::from(sku.as_str())
+    }
+}
+
+impl FromStr for HardwareSku {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::from(s))
+    }
+}
+
+impl PartialEq<str> for HardwareSku {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for HardwareSku {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Display for HardwareSku {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for HardwareSku {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for HardwareSku {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let sku = String::deserialize(deserializer)?;
+        Ok(Self::from(sku))
+    }
+}
+
 /// Model version metadata.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelVersion {
@@ -63,6 +274,22 @@ pub struct ModelVersion {
     pub cog_version: Option<String>,
     /// OpenAPI schema for the model
     pub openapi_schema: Option<serde_json::Value>,
+    /// Fields not modelled by this crate, kept as returned by the API
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl ModelVersion {
+    /// Get the input schema of this version.
+    ///
+    /// Fails if the version has no OpenAPI schema or the schema defines no inputs.
+    pub fn input_schema(&self) -> Result<InputSchema, Error> {
+        let openapi = self
+            .openapi_schema
+            .as_ref()
+            .ok_or_else(|| Error::invalid_input(format!("Version {} has no schema", self.id)))?;
+        InputSchema::from_openapi(openapi)
+    }
 }
 
 /// Basic model information.
@@ -75,7 +302,7 @@ pub struct Model {
     /// Model description
     pub description: Option<String>,
     /// Model visibility
-    pub visibility: String,
+    pub visibility: Visibility,
     /// GitHub URL
     pub github_url: Option<String>,
     /// Paper URL
@@ -86,6 +313,27 @@ pub struct Model {
     pub cover_image_url: Option<String>,
     /// Latest version
     pub latest_version: Option<ModelVersion>,
+    /// Example prediction shown on the model page
+    ///
+    /// Missing in older responses; an example that is not a valid prediction
+    /// is treated as missing too.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_lenient_prediction",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub default_example: Option<Prediction>,
+    /// Fields not modelled by this crate, kept as returned by the API
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// Deserialize an optional prediction, yielding `None` for malformed values.
+fn deserialize_lenient_prediction<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Prediction>, D::Error> {
+    let value = Option::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(value.and_then(|value| serde_json::from_value(value).ok()))
 }
 
 impl Model {
@@ -94,3 +342,439 @@ impl Model {
         format!("{}/{}", self.owner, self.name)
     }
 }
+
+/// Request to create a new model, e.g. as the destination of a training.
+#[derive(Debug, Clone, Serialize)]
+pub struct CreateModelRequest {
+    /// User or organization that will own the model
+    pub owner: String,
+    /// Model name
+    pub name: String,
+    /// Who can see and run the model
+    pub visibility: Visibility,
+    /// Hardware the model runs on
+    pub hardware: HardwareSku,
+    /// Model description
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// GitHub URL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub github_url: Option<String>,
+    /// Paper URL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paper_url: Option<String>,
+    /// License URL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license_url: Option<String>,
+    /// Cover image URL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cover_image_url: Option<String>,
+}
+
+impl CreateModelRequest {
+    /// Create a request for a model without description or links
+    pub fn new(
+        owner: impl Into<String>,
+        name: impl Into<String>,
+        visibility: Visibility,
+        hardware: HardwareSku,
+    ) -> Self {
+        Self {
+            owner: owner.into(),
+            name: name.into(),
+            visibility,
+            hardware,
+            description: None,
+            github_url: None,
+            paper_url: None,
+            license_url: None,
+            cover_image_url: None,
+        }
+    }
+
+    /// Set the description
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+/// Reference to a model, optionally pinned to a version.
+///
+/// Parses from and displays as `owner/name` or `owner/name:version`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ModelRef {
+    /// Model owner
+    pub owner: String,
+    /// Model name
+    pub name: String,
+    /// Version ID, or `None` for the latest version
+    pub version: Option<String>,
+}
+
+impl ModelRef {
+    /// Reference the latest version of a model.
+    pub fn new(owner: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            owner: owner.into(),
+            name: name.into(),
+            version: None,
+        }
+    }
+
+    /// Pin the reference to a version.
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+}
+
+impl FromStr for ModelRef {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::invalid_input(format!("Invalid model reference '{}'", s));
+        let (model, version) = match s.split_once(':') {
+            Some((model, version)) if !version.is_empty() => (model, Some(version)),
+            Some(_) => return Err(invalid()),
+            None => (s, None),
+        };
+        let (owner, name) = model.split_once('/').ok_or_else(invalid)?;
+        if owner.is_empty() || name.is_empty() || name.contains('/') {
+            return Err(invalid());
+        }
+        Ok(Self {
+            owner: owner.to_string(),
+            name: name.to_string(),
+            version: version.map(str::to_string),
+        })
+    }
+}
+
+impl fmt::Display for ModelRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.owner, self.name)?;
+        match &self.version {
+            Some(version) => write!(f, ":{}", version),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Parse an RFC 3339 timestamp as returned by the API, e.g.
+/// `2024-01-01T12:00:00.123456Z` or `2024-01-01T12:00:00+02:00`.
+pub(crate) fn parse_timestamp(value: &str) -> Option<SystemTime> {
+    let (date, time) = value.trim().split_once(['T', 't', ' '])?;
+    let mut date_parts = date.splitn(3, '-');
+    let year: i64 = date_parts.next()?.parse().ok()?;
+    let month: i64 = date_parts.next()?.parse().ok()?;
+    let day: i64 = date_parts.next()?.parse().ok()?;
+
+    let (clock, offset) = match time.strip_suffix(['Z', 'z']) {
+        Some(clock) => (clock, 0),
+        None => {
+            let (clock, offset) = time.split_at(time.rfind(['+', '-'])?);
+            let (hours, minutes) = offset[1..].split_once(':')?;
+            let seconds = hours.parse::<i64>().ok()? * 3600 + minutes.parse::<i64>().ok()? * 60;
+            (
+                clock,
+                if offset.starts_with('-') {
+                    -seconds
+                } else {
+                    seconds
+                },
+            )
+        }
+    };
+    let (clock, fraction) = clock.split_once('.').unwrap_or((clock, ""));
+    let mut clock_parts = clock.splitn(3, ':');
+    let hour: i64 = clock_parts.next()?.parse().ok()?;
+    let minute: i64 = clock_parts.next()?.parse().ok()?;
+    let second: i64 = clock_parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+        || !fraction.bytes().all(|byte| byte.is_ascii_digit())
+    {
+        return None;
+    }
+    let nanos = format!("{:0<9}", &fraction[..fraction.len().min(9)])
+        .parse::<u32>()
+        .ok()?;
+
+    let seconds =
+        days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second - offset;
+    let since_epoch = Duration::from_secs(seconds.unsigned_abs());
+    let time = if seconds >= 0 {
+        UNIX_EPOCH + since_epoch
+    } else {
+        UNIX_EPOCH - since_epoch
+    };
+    Some(time + Duration::from_nanos(nanos.into()))
+}
+
+/// Count the days from 1970-01-01 to a date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_into_stream_rejects_foreign_next() {
+        use futures::StreamExt;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let api = MockServer::start().await;
+        let foreign = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "results": [3],
+                "next": null,
+            })))
+            .mount(&foreign)
+            .await;
+
+        let page = PaginatedResponse {
+            results: vec![1, 2],
+            next: Some(format!("{}/v1/models?cursor=abc", foreign.uri())),
+            previous: None,
+        };
+        let http = HttpClient::with_base_url("test-token", api.uri()).unwrap();
+        let items: Vec<_> = page.into_stream(&http).collect().await;
+
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].as_ref().unwrap(), &1);
+        assert!(
+            matches!(&items[2], Err(Error::InvalidInput(message)) if message.contains("API host"))
+        );
+        assert!(foreign.received_requests().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        let at = |seconds: u64, nanos: u32| Some(UNIX_EPOCH + Duration::new(seconds, nanos));
+        assert_eq!(parse_timestamp("1970-01-01T00:00:00Z"), at(0, 0));
+        assert_eq!(
+            parse_timestamp("2024-03-01T12:30:15.123456Z"),
+            at(1709296215, 123_456_000)
+        );
+        assert_eq!(
+            parse_timestamp("2024-03-01T14:30:15+02:00"),
+            at(1709296215, 0)
+        );
+        assert_eq!(
+            parse_timestamp("2024-03-01T07:30:15.5-05:00"),
+            at(1709296215, 500_000_000)
+        );
+        assert_eq!(
+            parse_timestamp("1969-12-31T23:59:59Z"),
+            Some(UNIX_EPOCH - Duration::from_secs(1))
+        );
+        assert_eq!(parse_timestamp("2024-13-01T00:00:00Z"), None);
+        assert_eq!(parse_timestamp("2024-03-01"), None);
+        assert_eq!(parse_timestamp("soon"), None);
+    }
+
+    #[test]
+    fn test_hardware_sku_parsing() {
+        assert_eq!("cpu".parse::<HardwareSku>().unwrap(), HardwareSku::CPU);
+        assert_eq!("CPU".parse::<HardwareSku>().unwrap(), HardwareSku::CPU);
+        assert_eq!(
+            "gpu-a100-large".parse::<HardwareSku>().unwrap(),
+            HardwareSku::NVIDIA_A100_80GB
+        );
+        assert_eq!(
+            "gpu-b200".parse::<HardwareSku>().unwrap(),
+            HardwareSku::from("gpu-b200".to_string())
+        );
+    }
+
+    #[test]
+    fn test_hardware_sku_round_trip() {
+        assert_eq!(HardwareSku::NVIDIA_T4.to_string(), "gpu-t4");
+        assert_eq!(HardwareSku::from("gpu-b200").to_string(), "gpu-b200");
+        assert!(HardwareSku::NVIDIA_H100.is_gpu());
+        assert!(HardwareSku::from("gpu-b200").is_gpu());
+        assert!(!HardwareSku::CPU.is_gpu());
+
+        let hardware: Hardware =
+            serde_json::from_str(r#"{"sku": "gpu-l40s", "name": "Nvidia L40S GPU"}"#).unwrap();
+        assert_eq!(hardware.sku, "gpu-l40s");
+        assert_eq!(hardware.sku, HardwareSku::NVIDIA_L40S);
+
+        let json = serde_json::to_string(&HardwareSku::NVIDIA_A40_LARGE).unwrap();
+        assert_eq!(json, r#""gpu-a40-large""#);
+    }
+
+    #[test]
+    fn test_visibility_serialization() {
+        for (visibility, json) in [
+            (Visibility::Public, r#""public""#),
+            (Visibility::Private, r#""private""#),
+            (Visibility::Other("unlisted".into()), r#""unlisted""#),
+        ] {
+            assert_eq!(serde_json::to_string(&visibility).unwrap(), json);
+            assert_eq!(
+                serde_json::from_str::<Visibility>(json).unwrap(),
+                visibility
+            );
+        }
+    }
+
+    #[test]
+    fn test_hardware_sku_serialization() {
+        let all = [
+            (HardwareSku::CPU, "cpu"),
+            (HardwareSku::NVIDIA_T4, "gpu-t4"),
+            (HardwareSku::NVIDIA_A40, "gpu-a40-small"),
+            (HardwareSku::NVIDIA_A40_LARGE, "gpu-a40-large"),
+            (HardwareSku::NVIDIA_A100_40GB, "gpu-a100-small"),
+            (HardwareSku::NVIDIA_A100_80GB, "gpu-a100-large"),
+            (HardwareSku::NVIDIA_A100_80GB_2X, "gpu-a100-large-2x"),
+            (HardwareSku::NVIDIA_L40S, "gpu-l40s"),
+            (HardwareSku::NVIDIA_H100, "gpu-h100"),
+            (HardwareSku::from("gpu-b200"), "gpu-b200"),
+        ];
+        for (sku, name) in all {
+            let json = serde_json::to_string(&sku).unwrap();
+            assert_eq!(json, format!("\"{}\"", name));
+            assert_eq!(serde_json::from_str::<HardwareSku>(&json).unwrap(), sku);
+            assert_eq!(sku, name);
+        }
+    }
+
+    #[test]
+    fn test_model_unknown_fields_round_trip() {
+        let payload = serde_json::json!({
+            "owner": "stability-ai",
+            "name": "sdxl",
+            "description": null,
+            "visibility": "public",
+            "github_url": null,
+            "paper_url": null,
+            "license_url": null,
+            "cover_image_url": null,
+            "run_count": 71_000_000,
+            "latest_version": {
+                "id": "v1",
+                "created_at": "2023-07-26T00:00:00Z",
+                "cog_version": "0.8.1",
+                "openapi_schema": null,
+                "files": ["weights.tar"],
+            },
+        });
+
+        let model: Model = serde_json::from_value(payload.clone()).unwrap();
+        assert_eq!(model.extra["run_count"], 71_000_000);
+        let version = model.latest_version.as_ref().unwrap();
+        assert_eq!(version.extra["files"][0], "weights.tar");
+
+        assert_eq!(serde_json::to_value(&model).unwrap(), payload);
+        assert!(model.default_example.is_none());
+    }
+
+    #[test]
+    fn test_model_default_example() {
+        let model = |default_example: serde_json::Value| -> Model {
+            serde_json::from_value(serde_json::json!({
+                "owner": "stability-ai",
+                "name": "sdxl",
+                "description": null,
+                "visibility": "public",
+                "github_url": null,
+                "paper_url": null,
+                "license_url": null,
+                "cover_image_url": null,
+                "latest_version": null,
+                "default_example": default_example,
+            }))
+            .unwrap()
+        };
+
+        let example = model(serde_json::json!({
+            "id": "example-1",
+            "model": "stability-ai/sdxl",
+            "version": "v1",
+            "status": "succeeded",
+            "input": {"prompt": "An astronaut riding a rainbow unicorn"},
+            "output": ["https://replicate.delivery/example/out-0.png"],
+        }))
+        .default_example
+        .unwrap();
+        assert_eq!(example.id, "example-1");
+        assert!(example.is_successful());
+
+        assert!(model(serde_json::Value::Null).default_example.is_none());
+        assert!(
+            model(serde_json::json!({"id": "partial"}))
+                .default_example
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_paginated_response_into_stream() {
+        use futures::StreamExt;
+        use serde_json::json;
+        use wiremock::matchers::{method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/items"))
+            .and(query_param("cursor", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "results": [3],
+                "next": format!("{}/v1/items?cursor=3", server.uri()),
+                "previous": null,
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/items"))
+            .and(query_param("cursor", "3"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        let http = HttpClient::with_base_url("test-token", server.uri()).unwrap();
+        let first = PaginatedResponse {
+            results: vec![1, 2],
+            next: Some(format!("{}/v1/items?cursor=2", server.uri())),
+            previous: None,
+        };
+        let items: Vec<crate::Result<u32>> = first.into_stream(&http).collect().await;
+        assert_eq!(items.len(), 4);
+        let values: Vec<u32> = items[..3]
+            .iter()
+            .map(|item| *item.as_ref().unwrap())
+            .collect();
+        assert_eq!(values, [1, 2, 3]);
+        assert!(items[3].is_err());
+    }
+
+    #[test]
+    fn test_model_ref() {
+        let model: ModelRef = "acme/flux:abc123".parse().unwrap();
+        assert_eq!(model, ModelRef::new("acme", "flux").with_version("abc123"));
+        assert_eq!(model.to_string(), "acme/flux:abc123");
+        assert_eq!("acme/flux".parse::<ModelRef>().unwrap().version, None);
+
+        for invalid in ["acme", "/flux", "acme/", "acme/flux:", "a/b/c"] {
+            assert!(
+                matches!(invalid.parse::<ModelRef>(), Err(Error::InvalidInput(_))),
+                "{invalid}"
+            );
+        }
+    }
+}
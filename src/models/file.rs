@@ -1,11 +1,26 @@
 //! File handling types for inputs and outputs.
 
+use crate::http::HttpClient;
 use bytes::Bytes;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 
 /// Represents different ways to provide file input to a model.
-#[derive(Debug, Clone)]
+///
+/// Serializes as an object tagged with its kind, so pending requests can be
+/// persisted and resumed:
+///
+/// ```json
+/// {"type": "url", "url": "https://example.com/image.png"}
+/// {"type": "path", "path": "/tmp/image.png"}
+/// {"type": "bytes", "data": "aGVsbG8=", "filename": "hello.txt", "content_type": "text/plain"}
+/// ```
+///
+/// Bytes are inlined as base64, a third larger than the data itself, so
+/// prefer paths or URLs for large files. Paths are stored as is and must
+/// still exist when the input is used.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "FileInputRepr", into = "FileInputRepr")]
 pub enum FileInput {
     /// A URL to a publicly accessible file
     Url(String),
@@ -52,6 +67,70 @@ impl FileInput {
         }
     }
 
+    /// Create a file input from a `data:` URL
+    ///
+    /// The payload is decoded into [`FileInput::Bytes`] with the URL's media
+    /// type as content type; see [`decode_data_url`]. Malformed data URLs
+    /// fail with [`Error::InvalidInput`](crate::Error::InvalidInput).
+    pub fn from_data_url(url: &str) -> crate::Result<Self> {
+        let (content_type, data) = decode_data_url(url)?;
+        Ok(Self::Bytes {
+            data,
+            filename: None,
+            content_type: Some(content_type),
+        })
+    }
+
+    /// Create a file input from an in-memory image, encoded to `format`
+    ///
+    /// The content type and a filename such as `image.png` are set from the
+    /// format. PNG and JPEG are supported; other formats need the matching
+    /// feature of the `image` crate enabled. Encoding failures fail with
+    /// [`Error::InvalidInput`](crate::Error::InvalidInput).
+    #[cfg(feature = "image")]
+    pub fn from_image(
+        img: &image::DynamicImage,
+        format: image::ImageFormat,
+    ) -> crate::Result<Self> {
+        let mut data = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut data, format).map_err(|e| {
+            crate::Error::invalid_input(format!("Failed to encode image as {:?}: {}", format, e))
+        })?;
+        let extension = format.extensions_str().first().copied().unwrap_or("bin");
+        Ok(Self::Bytes {
+            data: data.into_inner().into(),
+            filename: Some(format!("image.{}", extension)),
+            content_type: Some(format.to_mime_type().to_string()),
+        })
+    }
+
+    /// Create a file input from an HTTP response body.
+    ///
+    /// The body is read into memory, the content type is taken from the
+    /// `Content-Type` header and the filename from `Content-Disposition`, if present.
+    ///
+    /// Fails with [`Error::Http`](crate::Error::Http) if the response has an
+    /// error status, rather than uploading the error page as the file.
+    pub async fn from_response(response: reqwest::Response) -> crate::Result<Self> {
+        let response = response.error_for_status()?;
+        let headers = response.headers();
+        let content_type = headers
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let filename = headers
+            .get(reqwest::header::CONTENT_DISPOSITION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_content_disposition_filename);
+
+        let data = response.bytes().await?;
+        Ok(Self::Bytes {
+            data,
+            filename,
+            content_type,
+        })
+    }
+
     /// Check if this is a URL input
     pub fn is_url(&self) -> bool {
         matches!(self, Self::Url(_))
@@ -84,6 +163,144 @@ impl FileInput {
     }
 }
 
+/// Extract the filename from a `Content-Disposition` header value.
+fn parse_content_disposition_filename(value: &str) -> Option<String> {
+    let mut filename = None;
+    for (name, raw) in content_disposition_params(value) {
+        match name.as_str() {
+            // RFC 5987 extended form takes precedence: filename*=UTF-8''name.png
+            "filename*" => {
+                if let Some((_, encoded)) = raw.split_once("''") {
+                    return Some(percent_decode(encoded));
+                }
+            }
+            "filename" if !raw.is_empty() => filename = Some(raw),
+            _ => {}
+        }
+    }
+    filename
+}
+
+/// Split the parameters of a `Content-Disposition` value into lowercase
+/// names and values, unquoting quoted strings (RFC 6266).
+///
+/// A `;` inside a quoted string does not end the parameter, and a backslash
+/// escapes the character after it.
+fn content_disposition_params(value: &str) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+    // Skip the disposition type
+    let Some((_, mut rest)) = value.split_once(';') else {
+        return params;
+    };
+    loop {
+        rest = rest.trim_start_matches([' ', '\t', ';']);
+        let Some((name, after)) = rest.split_once('=') else {
+            break;
+        };
+        // A parameter without a value
+        if let Some(end) = name.find(';') {
+            rest = &rest[end + 1..];
+            continue;
+        }
+        let after = after.trim_start();
+        let (raw, remainder) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let mut raw = String::new();
+                let mut end = quoted.len();
+                let mut chars = quoted.char_indices();
+                while let Some((index, c)) = chars.next() {
+                    match c {
+                        '\\' => raw.extend(chars.next().map(|(_, escaped)| escaped)),
+                        '"' => {
+                            end = index + 1;
+                            break;
+                        }
+                        c => raw.push(c),
+                    }
+                }
+                (raw, &quoted[end..])
+            }
+            None => {
+                let end = after.find(';').unwrap_or(after.len());
+                (after[..end].trim().to_string(), &after[end..])
+            }
+        };
+        params.push((name.trim().to_ascii_lowercase(), raw));
+        rest = remainder;
+    }
+    params
+}
+
+/// Decode `%XX` escapes, leaving malformed sequences untouched.
+fn percent_decode(input: &str) -> String {
+    String::from_utf8_lossy(&percent_decode_bytes(input)).into_owned()
+}
+
+/// Decode `%XX` escapes into raw bytes, leaving malformed sequences untouched.
+fn percent_decode_bytes(input: &str) -> Vec<u8> {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = input
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            out.push(byte);
+            i += 3;
+            continue;
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    out
+}
+
+/// Check if a string is a `data:` URL
+pub fn is_data_url(url: &str) -> bool {
+    url.len() >= 5 && url[..5].eq_ignore_ascii_case("data:")
+}
+
+/// Decode a `data:` URL into its media type and content
+///
+/// Both base64 and percent-encoded payloads are supported. The returned media
+/// type excludes the `;base64` marker and defaults to `text/plain;charset=US-ASCII`
+/// as specified by RFC 2397.
+pub fn decode_data_url(url: &str) -> crate::Result<(String, Bytes)> {
+    use base64::Engine as _;
+
+    if !is_data_url(url) {
+        return Err(crate::Error::invalid_input("Not a data URL"));
+    }
+    let (header, payload) = url[5..]
+        .split_once(',')
+        .ok_or_else(|| crate::Error::invalid_input("Data URL is missing ','"))?;
+
+    let (media_type, is_base64) = match header.strip_suffix(";base64") {
+        Some(media_type) => (media_type, true),
+        None => (header, false),
+    };
+    let media_type = if media_type.is_empty() {
+        "text/plain;charset=US-ASCII".to_string()
+    } else {
+        media_type.to_string()
+    };
+
+    let data = if is_base64 {
+        let payload: String = payload.chars().filter(|c| !c.is_whitespace()).collect();
+        base64::engine::general_purpose::STANDARD
+            .decode(percent_decode_bytes(&payload))
+            .map_err(|e| {
+                crate::Error::invalid_input(format!("Invalid base64 in data URL: {}", e))
+            })?
+    } else {
+        percent_decode_bytes(payload)
+    };
+
+    Ok((media_type, Bytes::from(data)))
+}
+
 impl From<String> for FileInput {
     fn from(s: String) -> Self {
         if s.starts_with("http://") || s.starts_with("https://") {
@@ -113,7 +330,7 @@ impl From<&Path> for FileInput {
 }
 
 /// Represents a file output from a model.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FileOutput {
     /// The URL to download the file
     pub url: String,
@@ -154,18 +371,245 @@ impl FileOutput {
         self
     }
 
+    /// Check if the file is inlined as a `data:` URL rather than hosted
+    pub fn is_data_url(&self) -> bool {
+        is_data_url(&self.url)
+    }
+
+    /// Guess the file extension, without the leading dot
+    ///
+    /// Uses the filename or URL path when it has an extension, and otherwise
+    /// the content type or the media type of a `data:` URL.
+    pub fn extension(&self) -> Option<String> {
+        let from_path = |path: &str| {
+            Path::new(path)
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map(str::to_ascii_lowercase)
+        };
+        let from_media_type = |media_type: &str| {
+            let essence = media_type.split(';').next().unwrap_or_default().trim();
+            // mime_guess lists `jfif` first for JPEG
+            if essence.eq_ignore_ascii_case("image/jpeg") {
+                return Some("jpg".to_string());
+            }
+            mime_guess::get_mime_extensions_str(essence)
+                .and_then(|exts| exts.first())
+                .map(|ext| ext.to_string())
+        };
+
+        if let Some(ext) = self.filename.as_deref().and_then(from_path) {
+            return Some(ext);
+        }
+        if self.is_data_url() {
+            let media_type = self.url["data:".len()..].split(',').next()?;
+            return from_media_type(media_type);
+        }
+        url::Url::parse(&self.url)
+            .ok()
+            .and_then(|url| from_path(url.path()))
+            .or_else(|| self.content_type.as_deref().and_then(from_media_type))
+    }
+
     /// Download the file as bytes
+    ///
+    /// `data:` URLs are decoded locally without any network request.
     pub async fn download(&self) -> crate::Result<Bytes> {
+        if self.is_data_url() {
+            return decode_data_url(&self.url).map(|(_, data)| data);
+        }
         let response = reqwest::get(&self.url).await?;
         let bytes = response.bytes().await?;
         Ok(bytes)
     }
 
-    /// Save the file to a local path
-    pub async fn save_to_path(&self, path: impl AsRef<Path>) -> crate::Result<()> {
-        let bytes = self.download().await?;
-        tokio::fs::write(path, bytes).await?;
-        Ok(())
+    /// Download the file and decode it as text
+    ///
+    /// The charset of the content type is honored, taken from the `data:` URL,
+    /// the response or [`content_type`](Self::content_type) in that order, and
+    /// defaults to UTF-8. Content that is not valid in its charset, or an
+    /// unknown charset, fails with [`Error::InvalidInput`](crate::Error::InvalidInput).
+    pub async fn download_text(&self) -> crate::Result<String> {
+        if self.is_data_url() {
+            let (media_type, data) = decode_data_url(&self.url)?;
+            return decode_text(&data, Some(&media_type));
+        }
+        let response = reqwest::get(&self.url).await?.error_for_status()?;
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+            .or_else(|| self.content_type.clone());
+        let bytes = response.bytes().await?;
+        decode_text(&bytes, content_type.as_deref())
+    }
+
+    /// Save the file to a local path, returning the number of bytes written
+    ///
+    /// The response body is streamed to disk chunk by chunk, so memory use stays
+    /// bounded regardless of the file size.
+    pub async fn save_to_path(&self, path: impl AsRef<Path>) -> crate::Result<u64> {
+        if self.is_data_url() {
+            let (_, data) = decode_data_url(&self.url)?;
+            tokio::fs::write(path, &data).await?;
+            return Ok(data.len() as u64);
+        }
+        let response = reqwest::get(&self.url).await?.error_for_status()?;
+        let file = tokio::fs::File::create(path).await?;
+        let mut writer = tokio::io::BufWriter::with_capacity(SAVE_BUFFER_SIZE, file);
+        stream_to_writer(response, &mut writer).await
+    }
+
+    /// Save the file to a local path, resuming a partial download
+    ///
+    /// When `path` already holds the start of the file and the server
+    /// advertises `Accept-Ranges: bytes`, only the missing bytes are requested
+    /// with a `Range` header and appended. The request carries the file's
+    /// `ETag` or `Last-Modified` as `If-Range`, so a file that changed on the
+    /// server is sent whole, even if the local copy already has its length.
+    /// Without range support, including when the server refuses the `HEAD`
+    /// request that asks for it, or when the server answers with the whole
+    /// file, it is downloaded from the start. Requests go through `http`'s
+    /// connection pool, connect timeout and DNS overrides, without its token.
+    /// Returns the size of the complete file.
+    pub async fn download_resumable(
+        &self,
+        http: &HttpClient,
+        path: impl AsRef<Path>,
+    ) -> crate::Result<u64> {
+        use reqwest::{StatusCode, header};
+
+        let path = path.as_ref();
+        let offset = match tokio::fs::metadata(path).await {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e.into()),
+        };
+        let client = http.download_client();
+        if offset == 0 || self.is_data_url() {
+            return self.download_whole(&client, path).await;
+        }
+
+        let head = client.head(&self.url).send().await?;
+        if !head.status().is_success() {
+            // Servers may refuse HEAD, e.g. with 403 or 405; resume is then unknown
+            return self.download_whole(&client, path).await;
+        }
+        let header_value = |name| {
+            head.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+        let accepts_ranges = header_value(header::ACCEPT_RANGES)
+            .is_some_and(|value| value.eq_ignore_ascii_case("bytes"));
+        let length =
+            header_value(header::CONTENT_LENGTH).and_then(|value| value.parse::<u64>().ok());
+        let validator = header_value(header::ETAG)
+            .or_else(|| header_value(header::LAST_MODIFIED))
+            .map(str::to_string);
+        if !accepts_ranges || length.is_some_and(|length| offset > length) {
+            return self.download_whole(&client, path).await;
+        }
+
+        let mut request = client
+            .get(&self.url)
+            .header(header::RANGE, format!("bytes={}-", offset));
+        if let Some(validator) = validator {
+            request = request.header(header::IF_RANGE, validator);
+        }
+        let response = request.send().await?;
+        let content_range = response
+            .headers()
+            .get(header::CONTENT_RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_content_range);
+        let append = match response.status() {
+            StatusCode::RANGE_NOT_SATISFIABLE => match content_range {
+                Some((_, Some(total))) if total == offset => return Ok(offset),
+                _ => return self.download_whole(&client, path).await,
+            },
+            StatusCode::PARTIAL_CONTENT => match content_range {
+                Some((Some(start), _)) if start == offset => true,
+                _ => return self.download_whole(&client, path).await,
+            },
+            _ => false,
+        };
+        let response = response.error_for_status()?;
+
+        let file = if append {
+            tokio::fs::OpenOptions::new()
+                .append(true)
+                .open(path)
+                .await?
+        } else {
+            tokio::fs::File::create(path).await?
+        };
+        let mut writer = tokio::io::BufWriter::with_capacity(SAVE_BUFFER_SIZE, file);
+        let written = stream_to_writer(response, &mut writer).await?;
+        Ok(if append { offset + written } else { written })
+    }
+
+    /// Download the whole file to `path` through `client`, replacing it.
+    async fn download_whole(&self, client: &reqwest::Client, path: &Path) -> crate::Result<u64> {
+        if self.is_data_url() {
+            return self.save_to_path(path).await;
+        }
+        let response = client.get(&self.url).send().await?.error_for_status()?;
+        let file = tokio::fs::File::create(path).await?;
+        let mut writer = tokio::io::BufWriter::with_capacity(SAVE_BUFFER_SIZE, file);
+        stream_to_writer(response, &mut writer).await
+    }
+
+    /// Write the file to `output` like [`save_to_path`](Self::save_to_path),
+    /// downloading through `http` with its credentials when the file is
+    /// hosted on the API.
+    pub(crate) async fn save_to_file_via(
+        &self,
+        http: &HttpClient,
+        output: tokio::fs::File,
+    ) -> crate::Result<u64> {
+        use tokio::io::AsyncWriteExt;
+
+        let mut writer = tokio::io::BufWriter::with_capacity(SAVE_BUFFER_SIZE, output);
+        if self.is_data_url() {
+            let (_, data) = decode_data_url(&self.url)?;
+            writer.write_all(&data).await?;
+            writer.flush().await?;
+            return Ok(data.len() as u64);
+        }
+        let response = match http.validate_api_url(&self.url) {
+            Ok(url) => http.get(url.as_str()).await?,
+            Err(_) => reqwest::get(&self.url).await?.error_for_status()?,
+        };
+        stream_to_writer(response, &mut writer).await
+    }
+
+    /// Name to save the file under, from its filename or URL path
+    ///
+    /// Falls back to `output-{index}` when neither gives a usable name, and
+    /// adds the guessed extension when the name has none.
+    pub(crate) fn local_file_name(&self, index: usize) -> String {
+        let usable = |name: &str| {
+            !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\'])
+        };
+        let from_url = || {
+            url::Url::parse(&self.url)
+                .ok()
+                .filter(|_| !self.is_data_url())
+                .and_then(|url| url.path_segments()?.next_back().map(str::to_string))
+        };
+        let name = self
+            .filename
+            .clone()
+            .or_else(from_url)
+            .filter(|name| usable(name))
+            .unwrap_or_else(|| format!("output-{}", index));
+
+        match (Path::new(&name).extension(), self.extension()) {
+            (None, Some(extension)) => format!("{}.{}", name, extension),
+            _ => name,
+        }
     }
 }
 
@@ -181,18 +625,647 @@ impl From<&str> for FileOutput {
     }
 }
 
+/// Decode text in the charset named by a media type, UTF-8 by default.
+fn decode_text(data: &[u8], media_type: Option<&str>) -> crate::Result<String> {
+    let charset = media_type.and_then(|media_type| {
+        media_type.split(';').skip(1).find_map(|param| {
+            let (name, value) = param.split_once('=')?;
+            name.trim()
+                .eq_ignore_ascii_case("charset")
+                .then(|| value.trim().trim_matches('"'))
+        })
+    });
+    let encoding = match charset {
+        Some(label) => encoding_rs::Encoding::for_label(label.as_bytes()).ok_or_else(|| {
+            crate::Error::invalid_input(format!("Unsupported charset '{}'", label))
+        })?,
+        None => encoding_rs::UTF_8,
+    };
+    encoding
+        .decode_without_bom_handling_and_without_replacement(data)
+        .map(|text| text.into_owned())
+        .ok_or_else(|| {
+            crate::Error::invalid_input(format!("File is not valid {} text", encoding.name()))
+        })
+}
+
+/// Parse a `Content-Range` header into its first byte and complete length.
+///
+/// `bytes 5-12/13` gives `(Some(5), Some(13))` and `bytes */13` gives
+/// `(None, Some(13))`; an unknown length (`*`) gives `None`.
+fn parse_content_range(value: &str) -> Option<(Option<u64>, Option<u64>)> {
+    let (range, total) = value.trim().strip_prefix("bytes ")?.split_once('/')?;
+    let start = match range.trim() {
+        "*" => None,
+        range => Some(range.split_once('-')?.0.parse().ok()?),
+    };
+    Some((start, total.trim().parse().ok()))
+}
+
+/// Buffer size used when streaming downloads to disk.
+const SAVE_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Copy a response body into a writer without buffering the whole payload.
+///
+/// Returns the number of bytes written. The writer is flushed before returning.
+pub(crate) async fn stream_to_writer<W>(
+    mut response: reqwest::Response,
+    writer: &mut W,
+) -> crate::Result<u64>
+where
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    use tokio::io::AsyncWriteExt;
+
+    let mut written = 0u64;
+    while let Some(chunk) = response.chunk().await? {
+        writer.write_all(&chunk).await?;
+        written += chunk.len() as u64;
+    }
+    writer.flush().await?;
+    Ok(written)
+}
+
+/// Serialized form of [`FileInput`].
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum FileInputRepr {
+    Url {
+        url: String,
+    },
+    Path {
+        path: PathBuf,
+    },
+    Bytes {
+        #[serde(with = "base64_bytes")]
+        data: Bytes,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        filename: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        content_type: Option<String>,
+    },
+}
+
+impl From<FileInput> for FileInputRepr {
+    fn from(input: FileInput) -> Self {
+        match input {
+            FileInput::Url(url) => Self::Url { url },
+            FileInput::Path(path) => Self::Path { path },
+            FileInput::Bytes {
+                data,
+                filename,
+                content_type,
+            } => Self::Bytes {
+                data,
+                filename,
+                content_type,
+            },
+        }
+    }
+}
+
+impl From<FileInputRepr> for FileInput {
+    fn from(repr: FileInputRepr) -> Self {
+        match repr {
+            FileInputRepr::Url { url } => Self::Url(url),
+            FileInputRepr::Path { path } => Self::Path(path),
+            FileInputRepr::Bytes {
+                data,
+                filename,
+                content_type,
+            } => Self::Bytes {
+                data,
+                filename,
+                content_type,
+            },
+        }
+    }
+}
+
+/// (De)serialize bytes as a standard base64 string.
+mod base64_bytes {
+    use base64::Engine as _;
+    use base64::engine::general_purpose::STANDARD;
+    use bytes::Bytes;
+    use serde::{Deserialize, Deserializer, Serializer, de};
+
+    pub fn serialize<S: Serializer>(data: &Bytes, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(data))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Bytes, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        STANDARD
+            .decode(encoded)
+            .map(Bytes::from)
+            .map_err(|e| de::Error::custom(format!("invalid base64: {}", e)))
+    }
+}
+
 /// File encoding strategy for uploads.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// New strategies may be added, so matches need a wildcard arm.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
+#[non_exhaustive]
 pub enum FileEncodingStrategy {
     /// Upload files as base64-encoded data URLs
     Base64DataUrl,
     /// Upload files as multipart form data
     Multipart,
+    /// Send files as base64-encoded data URLs, but upload them as multipart
+    /// form data when the request body would exceed the size limit
+    ///
+    /// The decision is made from the file sizes, before any file is read.
+    Auto,
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for FileEncodingStrategy {
     fn default() -> Self {
         Self::Multipart
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writer that records the largest single write it receives.
+    #[derive(Default)]
+    struct CountingWriter {
+        total: usize,
+        largest_write: usize,
+    }
+
+    impl tokio::io::AsyncWrite for CountingWriter {
+        fn poll_write(
+            mut self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+            buf: &[u8],
+        ) -> std::task::Poll<std::io::Result<usize>> {
+            self.total += buf.len();
+            self.largest_write = self.largest_write.max(buf.len());
+            std::task::Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_large_output_in_small_chunks() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        const SIZE: usize = 50 * 1024 * 1024;
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![7u8; SIZE]))
+            .mount(&server)
+            .await;
+
+        let response = reqwest::get(server.uri()).await.unwrap();
+        let mut writer = CountingWriter::default();
+        let written = stream_to_writer(response, &mut writer).await.unwrap();
+
+        assert_eq!(written, SIZE as u64);
+        assert_eq!(writer.total, SIZE);
+        assert!(
+            writer.largest_write <= 1024 * 1024,
+            "largest write was {} bytes",
+            writer.largest_write
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_text() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/utf8"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("héllo", "text/plain"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/latin1"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(&b"caf\xe9"[..], "text/plain; charset=ISO-8859-1"),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/binary"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(&b"\xff\xfe\xfd"[..]))
+            .mount(&server)
+            .await;
+
+        let text = |path: &str| FileOutput::new(format!("{}{}", server.uri(), path));
+        assert_eq!(text("/utf8").download_text().await.unwrap(), "héllo");
+        assert_eq!(text("/latin1").download_text().await.unwrap(), "café");
+        assert!(matches!(
+            text("/binary").download_text().await,
+            Err(crate::Error::InvalidInput(_))
+        ));
+        // The declared content type is used when the response has none
+        let declared = text("/binary").with_content_type("text/plain; charset=\"windows-1252\"");
+        assert_eq!(declared.download_text().await.unwrap(), "ÿþý");
+
+        let data_url = FileOutput::new("data:text/plain;charset=utf-8,h%C3%A9");
+        assert_eq!(data_url.download_text().await.unwrap(), "hé");
+        let unknown = FileOutput::new("data:text/plain;charset=klingon,hi");
+        assert!(unknown.download_text().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_save_to_path_returns_byte_count() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(&b"image-bytes"[..]))
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.png");
+        let written = FileOutput::new(server.uri())
+            .save_to_path(&path)
+            .await
+            .unwrap();
+
+        assert_eq!(written, 11);
+        assert_eq!(tokio::fs::read(&path).await.unwrap(), b"image-bytes");
+    }
+
+    #[tokio::test]
+    async fn test_download_resumable() {
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let content = &b"Hello, World!"[..];
+        Mock::given(method("HEAD"))
+            .and(path("/ranges"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("accept-ranges", "bytes")
+                    .insert_header("etag", "\"v1\""),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/ranges"))
+            .and(header("range", "bytes=5-"))
+            .and(header("if-range", "\"v1\""))
+            .respond_with(
+                ResponseTemplate::new(206)
+                    .insert_header("content-range", "bytes 5-12/13")
+                    .set_body_bytes(&content[5..]),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/no-ranges"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/no-head"))
+            .respond_with(ResponseTemplate::new(405))
+            .mount(&server)
+            .await;
+        for url_path in ["/no-ranges", "/no-head"] {
+            Mock::given(method("GET"))
+                .and(path(url_path))
+                .respond_with(ResponseTemplate::new(200).set_body_bytes(content))
+                .mount(&server)
+                .await;
+        }
+        // The local copy has the full length of an older version
+        Mock::given(method("HEAD"))
+            .and(path("/changed"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("accept-ranges", "bytes")
+                    .insert_header("etag", "\"v2\"")
+                    .insert_header("content-length", "5"),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/changed"))
+            .and(header("range", "bytes=5-"))
+            .and(header("if-range", "\"v2\""))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(&b"World"[..]))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let http = HttpClient::new("test-token").unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let download = |name: &str, url_path: &str| {
+            let path = dir.path().join(name);
+            let url = format!("{}{}", server.uri(), url_path);
+            let http = &http;
+            async move {
+                tokio::fs::write(&path, b"Hello").await.unwrap();
+                let size = FileOutput::new(url)
+                    .download_resumable(http, &path)
+                    .await
+                    .unwrap();
+                (size, tokio::fs::read(&path).await.unwrap())
+            }
+        };
+
+        assert_eq!(download("resumed", "/ranges").await, (13, content.to_vec()));
+        assert_eq!(download("full", "/no-ranges").await, (13, content.to_vec()));
+        assert_eq!(
+            download("no-head", "/no-head").await,
+            (13, content.to_vec())
+        );
+        assert_eq!(
+            download("changed", "/changed").await,
+            (5, b"World".to_vec())
+        );
+
+        // Nothing on disk yet is a plain download
+        let fresh = dir.path().join("fresh");
+        let output = FileOutput::new(format!("{}/no-ranges", server.uri()));
+        assert_eq!(output.download_resumable(&http, &fresh).await.unwrap(), 13);
+
+        assert_eq!(
+            parse_content_range("bytes 5-12/13"),
+            Some((Some(5), Some(13)))
+        );
+        assert_eq!(parse_content_range("bytes */13"), Some((None, Some(13))));
+        assert_eq!(parse_content_range("bytes 0-4/*"), Some((Some(0), None)));
+        assert_eq!(parse_content_range("items 0-4/5"), None);
+    }
+
+    #[test]
+    fn test_file_input_serde_round_trip() {
+        use serde_json::json;
+
+        let cases = [
+            (
+                FileInput::from_url("https://example.com/a.png"),
+                json!({"type": "url", "url": "https://example.com/a.png"}),
+            ),
+            (
+                FileInput::from_path("/tmp/a.png"),
+                json!({"type": "path", "path": "/tmp/a.png"}),
+            ),
+            (
+                FileInput::from_bytes_with_metadata(
+                    b"hello".to_vec(),
+                    Some("hello.txt".to_string()),
+                    Some("text/plain".to_string()),
+                ),
+                json!({
+                    "type": "bytes",
+                    "data": "aGVsbG8=",
+                    "filename": "hello.txt",
+                    "content_type": "text/plain",
+                }),
+            ),
+            (
+                FileInput::from_bytes(Bytes::new()),
+                json!({"type": "bytes", "data": ""}),
+            ),
+        ];
+        for (input, expected) in cases {
+            let value = serde_json::to_value(&input).unwrap();
+            assert_eq!(value, expected);
+            assert_eq!(serde_json::from_value::<FileInput>(value).unwrap(), input);
+        }
+
+        assert!(
+            serde_json::from_value::<FileInput>(json!({"type": "bytes", "data": "not base64!"}))
+                .is_err()
+        );
+        assert!(serde_json::from_value::<FileInput>(json!({"type": "ftp", "url": "x"})).is_err());
+    }
+
+    #[test]
+    fn test_file_input_from_data_url() {
+        match FileInput::from_data_url("data:image/png;base64,aGVsbG8=").unwrap() {
+            FileInput::Bytes {
+                data,
+                filename,
+                content_type,
+            } => {
+                assert_eq!(&data[..], b"hello");
+                assert_eq!(filename, None);
+                assert_eq!(content_type.as_deref(), Some("image/png"));
+            }
+            other => panic!("Expected bytes, got {:?}", other),
+        }
+        for malformed in [
+            "https://example.com/a.png",
+            "data:image/png;base64",
+            "data:;base64,!!",
+        ] {
+            assert!(matches!(
+                FileInput::from_data_url(malformed),
+                Err(crate::Error::InvalidInput(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_decode_data_url() {
+        let (mime, data) = decode_data_url("data:image/png;base64,aGVsbG8=").unwrap();
+        assert_eq!(mime, "image/png");
+        assert_eq!(&data[..], b"hello");
+
+        let (mime, data) = decode_data_url("data:,Hello%2C%20World").unwrap();
+        assert_eq!(mime, "text/plain;charset=US-ASCII");
+        assert_eq!(&data[..], b"Hello, World");
+
+        let (mime, _) = decode_data_url("data:text/plain;charset=utf-8;base64,aGk=").unwrap();
+        assert_eq!(mime, "text/plain;charset=utf-8");
+
+        assert!(decode_data_url("https://example.com/a.png").is_err());
+        assert!(decode_data_url("data:image/png;base64").is_err());
+        assert!(decode_data_url("data:image/png;base64,***").is_err());
+    }
+
+    #[test]
+    fn test_file_output_extension() {
+        let ext = |output: FileOutput| output.extension();
+        assert_eq!(
+            ext(FileOutput::new("https://replicate.delivery/abc/out-0.PNG")),
+            Some("png".into())
+        );
+        assert_eq!(
+            ext(FileOutput::new("data:image/webp;base64,AAAA")),
+            Some("webp".into())
+        );
+        assert_eq!(
+            ext(FileOutput::new("https://example.com/download").with_content_type("image/jpeg")),
+            Some("jpg".into())
+        );
+        assert_eq!(ext(FileOutput::new("https://example.com/download")), None);
+    }
+
+    #[test]
+    fn test_local_file_name() {
+        let name = |output: FileOutput| output.local_file_name(3);
+        assert_eq!(
+            name(FileOutput::new("https://replicate.delivery/x/out-0.webp")),
+            "out-0.webp"
+        );
+        assert_eq!(
+            name(
+                FileOutput::new("https://replicate.delivery/x/out").with_content_type("image/png")
+            ),
+            "out.png"
+        );
+        assert_eq!(
+            name(FileOutput::new("https://example.com/").with_content_type("image/jpeg")),
+            "output-3.jpg"
+        );
+        assert_eq!(
+            name(FileOutput::new("data:audio/wav;base64,AA==")),
+            "output-3.wav"
+        );
+        assert_eq!(
+            name(FileOutput::new("https://example.com/x.png").with_filename("../evil.png")),
+            "output-3.png"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_data_url_without_network() {
+        let output = FileOutput::new("data:image/png;base64,aGVsbG8=");
+        assert!(output.is_data_url());
+        assert_eq!(&output.download().await.unwrap()[..], b"hello");
+    }
+
+    #[test]
+    fn test_content_disposition_filename() {
+        assert_eq!(
+            parse_content_disposition_filename("attachment; filename=\"cat.png\""),
+            Some("cat.png".to_string())
+        );
+        assert_eq!(
+            parse_content_disposition_filename("inline; filename=cat.png"),
+            Some("cat.png".to_string())
+        );
+        assert_eq!(
+            parse_content_disposition_filename(
+                "attachment; filename=\"fallback.png\"; filename*=UTF-8''caf%C3%A9.png"
+            ),
+            Some("café.png".to_string())
+        );
+        assert_eq!(parse_content_disposition_filename("attachment"), None);
+        assert_eq!(
+            parse_content_disposition_filename(
+                r#"attachment; filename="a;b \"quoted\" \\ name.png"; size=3"#
+            ),
+            Some(r#"a;b "quoted" \ name.png"#.to_string())
+        );
+        assert_eq!(
+            parse_content_disposition_filename("attachment; inline; filename = cat.png ;"),
+            Some("cat.png".to_string())
+        );
+        assert_eq!(
+            parse_content_disposition_filename(r#"attachment; name="filename=x.png""#),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_from_response() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/missing"))
+            .respond_with(ResponseTemplate::new(404).set_body_string("<html>not found</html>"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "image/png")
+                    .insert_header("content-disposition", "attachment; filename=\"cat.png\"")
+                    .set_body_bytes(&b"png-bytes"[..]),
+            )
+            .mount(&server)
+            .await;
+
+        let response = reqwest::get(server.uri()).await.unwrap();
+        let input = FileInput::from_response(response).await.unwrap();
+
+        match input {
+            FileInput::Bytes {
+                data,
+                filename,
+                content_type,
+            } => {
+                assert_eq!(&data[..], b"png-bytes");
+                assert_eq!(filename.as_deref(), Some("cat.png"));
+                assert_eq!(content_type.as_deref(), Some("image/png"));
+            }
+            _ => panic!("Expected bytes input"),
+        }
+
+        let response = reqwest::get(format!("{}/missing", server.uri()))
+            .await
+            .unwrap();
+        match FileInput::from_response(response).await {
+            Err(crate::Error::Http(error)) => {
+                assert_eq!(error.status(), Some(reqwest::StatusCode::NOT_FOUND))
+            }
+            other => panic!("Expected an HTTP error, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_from_image() {
+        let img = image::DynamicImage::new_rgb8(4, 3);
+        let input = FileInput::from_image(&img, image::ImageFormat::Png).unwrap();
+        let FileInput::Bytes {
+            data,
+            filename,
+            content_type,
+        } = input
+        else {
+            panic!("expected bytes");
+        };
+        assert_eq!(filename.as_deref(), Some("image.png"));
+        assert_eq!(content_type.as_deref(), Some("image/png"));
+        let decoded = image::load_from_memory(&data).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (4, 3));
+
+        let jpeg = FileInput::from_image(&img, image::ImageFormat::Jpeg).unwrap();
+        assert!(
+            matches!(jpeg, FileInput::Bytes { content_type: Some(ref t), .. } if t == "image/jpeg")
+        );
+        assert!(matches!(
+            FileInput::from_image(&img, image::ImageFormat::Tiff),
+            Err(crate::Error::InvalidInput(_))
+        ));
+    }
+}
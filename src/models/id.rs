@@ -0,0 +1,160 @@
+//! Typed identifiers of API resources.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::Error;
+
+/// Define a string newtype for the ID of one kind of resource.
+///
+/// The ID converts from `&str` and `String`, so methods taking
+/// `impl Into<...>` keep accepting plain strings, but not from the ID of
+/// another kind of resource.
+macro_rules! resource_id {
+    ($(#[$meta:meta])* $name:ident, $kind:literal) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+        #[serde(transparent)]
+        pub struct $name(String);
+
+        impl $name {
+            /// Wrap an ID without validating it.
+            pub fn new(id: impl Into<String>) -> Self {
+                Self(id.into())
+            }
+
+            /// Get the ID as a string slice.
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+
+            /// Unwrap the ID into a string.
+            pub fn into_string(self) -> String {
+                self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        /// Parses a non-empty ID without whitespace or `/`.
+        impl FromStr for $name {
+            type Err = Error;
+
+            fn from_str(id: &str) -> Result<Self, Self::Err> {
+                if id.is_empty() || id.contains(|c: char| c.is_whitespace() || c == '/') {
+                    return Err(Error::invalid_input(format!(
+                        "Invalid {} ID '{}'",
+                        $kind, id
+                    )));
+                }
+                Ok(Self::new(id))
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(id: &str) -> Self {
+                Self::new(id)
+            }
+        }
+
+        impl From<&String> for $name {
+            fn from(id: &String) -> Self {
+                Self::new(id.as_str())
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(id: String) -> Self {
+                Self(id)
+            }
+        }
+
+        impl From<&$name> for $name {
+            fn from(id: &$name) -> Self {
+                id.clone()
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.0 == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.0 == *other
+            }
+        }
+    };
+}
+
+resource_id!(
+    /// ID of a prediction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use replicate_client::PredictionId;
+    ///
+    /// let id: PredictionId = "gm3qorzdhgbfurvjtvhg6dckhu".parse().unwrap();
+    /// assert_eq!(id.as_str(), "gm3qorzdhgbfurvjtvhg6dckhu");
+    /// ```
+    PredictionId,
+    "prediction"
+);
+
+resource_id!(
+    /// ID of an uploaded file.
+    FileId,
+    "file"
+);
+
+resource_id!(
+    /// ID of a training.
+    TrainingId,
+    "training"
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resource_ids() {
+        let id = PredictionId::new("abc123");
+        assert_eq!(id.to_string(), "abc123");
+        assert_eq!(id, "abc123");
+        assert_eq!(serde_json::to_string(&id).unwrap(), "\"abc123\"");
+        assert_eq!(
+            serde_json::from_str::<PredictionId>("\"abc123\"").unwrap(),
+            id
+        );
+        assert_eq!(FileId::from(String::from("f1")).as_ref(), "f1");
+        assert_eq!(String::from(TrainingId::from("t1")), "t1");
+
+        assert!("".parse::<FileId>().is_err());
+        assert!("owner/model".parse::<PredictionId>().is_err());
+        assert!(matches!(
+            "a b".parse::<TrainingId>(),
+            Err(Error::InvalidInput(message)) if message.contains("training")
+        ));
+    }
+}
@@ -0,0 +1,69 @@
+//! Request type for image-editing runs such as img2img and inpainting.
+
+use crate::models::file::FileInput;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Default time to wait for an image edit to finish.
+const DEFAULT_IMAGE_EDIT_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// An image edit to run with [`Client::run_image_edit`](crate::Client::run_image_edit).
+///
+/// The defaults suit most image-to-image models: the image is passed as the
+/// `image` input, outputs are saved to the current directory, and the run
+/// gives up after ten minutes.
+#[derive(Debug, Clone)]
+pub struct ImageEditRequest {
+    /// The image to edit
+    pub image: FileInput,
+    /// Description of the edit
+    pub prompt: String,
+    /// Other model inputs, e.g. `strength` or `num_inference_steps`
+    pub extra_inputs: HashMap<String, Value>,
+    /// Name of the model input that receives the image
+    pub image_key: String,
+    /// Directory the output images are saved to
+    pub output_dir: PathBuf,
+    /// Maximum time to wait for the prediction
+    pub timeout: Duration,
+}
+
+impl ImageEditRequest {
+    /// Create an image edit with the default input key, directory and timeout.
+    pub fn new(image: impl Into<FileInput>, prompt: impl Into<String>) -> Self {
+        Self {
+            image: image.into(),
+            prompt: prompt.into(),
+            extra_inputs: HashMap::new(),
+            image_key: "image".to_string(),
+            output_dir: PathBuf::from("."),
+            timeout: DEFAULT_IMAGE_EDIT_TIMEOUT,
+        }
+    }
+
+    /// Add another model input.
+    pub fn input(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.extra_inputs.insert(key.into(), value.into());
+        self
+    }
+
+    /// Set the name of the model input that receives the image.
+    pub fn image_key(mut self, key: impl Into<String>) -> Self {
+        self.image_key = key.into();
+        self
+    }
+
+    /// Set the directory the output images are saved to.
+    pub fn output_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.output_dir = dir.into();
+        self
+    }
+
+    /// Set the maximum time to wait for the prediction.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
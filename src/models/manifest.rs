@@ -0,0 +1,231 @@
+//! Machine-readable records of the artifacts predictions produced.
+
+use crate::models::file::FileOutput;
+use crate::models::prediction::{Prediction, PredictionStatus};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Version of the manifest JSON format, bumped on incompatible changes.
+pub const MANIFEST_FORMAT_VERSION: u32 = 1;
+
+/// Record of what one or more predictions produced, for data pipelines.
+///
+/// Serializes with a fixed field order and every field present, `null` when
+/// unknown, so the JSON can be consumed with a fixed schema. Built with
+/// [`Prediction::artifact_manifest`] and combined across a batch with
+/// [`merge`](Self::merge).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Version of the format, [`MANIFEST_FORMAT_VERSION`] when written
+    pub format_version: u32,
+    /// The predictions, in the order they were added
+    pub predictions: Vec<ManifestPrediction>,
+}
+
+/// A prediction and its artifacts in a [`Manifest`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestPrediction {
+    /// The prediction ID
+    pub id: String,
+    /// The model that ran, as `owner/name`
+    pub model: String,
+    /// The version that ran, if known
+    pub version: Option<String>,
+    /// The status when the manifest was made
+    pub status: PredictionStatus,
+    /// When the prediction was created, as returned by the API
+    pub created_at: Option<String>,
+    /// When the prediction started, as returned by the API
+    pub started_at: Option<String>,
+    /// When the prediction completed, as returned by the API
+    pub completed_at: Option<String>,
+    /// The prediction metrics, sorted by name
+    pub metrics: Option<BTreeMap<String, Value>>,
+    /// The output files, in output order
+    pub artifacts: Vec<ManifestArtifact>,
+}
+
+/// An output file in a [`Manifest`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestArtifact {
+    /// The URL of the file, or its `data:` URL
+    pub url: String,
+    /// The content type, as reported or inferred from the URL
+    pub content_type: Option<String>,
+    /// The size in bytes, if reported
+    pub size: Option<u64>,
+    /// Where the file was saved locally, if it was
+    pub path: Option<PathBuf>,
+}
+
+impl Manifest {
+    /// Record one prediction with the given artifacts.
+    pub(crate) fn for_prediction(
+        prediction: &Prediction,
+        artifacts: Vec<ManifestArtifact>,
+    ) -> Self {
+        Self {
+            format_version: MANIFEST_FORMAT_VERSION,
+            predictions: vec![ManifestPrediction::new(prediction, artifacts)],
+        }
+    }
+
+    /// Combine manifests, e.g. of the predictions of a batch run, keeping
+    /// their predictions in order.
+    pub fn merge(manifests: Vec<Manifest>) -> Self {
+        Self {
+            format_version: MANIFEST_FORMAT_VERSION,
+            predictions: manifests
+                .into_iter()
+                .flat_map(|manifest| manifest.predictions)
+                .collect(),
+        }
+    }
+
+    /// Get the artifacts of every prediction, in order.
+    pub fn artifacts(&self) -> impl Iterator<Item = &ManifestArtifact> {
+        self.predictions
+            .iter()
+            .flat_map(|prediction| &prediction.artifacts)
+    }
+
+    /// Write the manifest as pretty-printed JSON, replacing any existing file.
+    pub async fn write_json(&self, path: impl AsRef<Path>) -> crate::Result<()> {
+        let mut json = serde_json::to_vec_pretty(self)?;
+        json.push(b'\n');
+        tokio::fs::write(path, json).await?;
+        Ok(())
+    }
+}
+
+impl ManifestPrediction {
+    /// Record a prediction with the given artifacts.
+    fn new(prediction: &Prediction, artifacts: Vec<ManifestArtifact>) -> Self {
+        Self {
+            id: prediction.id.clone(),
+            model: prediction.model.clone(),
+            version: prediction.version().map(str::to_string),
+            status: prediction.status.clone(),
+            created_at: prediction.created_at.clone(),
+            started_at: prediction.started_at.clone(),
+            completed_at: prediction.completed_at.clone(),
+            metrics: prediction
+                .metrics
+                .as_ref()
+                .map(|metrics| metrics.clone().into_iter().collect()),
+            artifacts,
+        }
+    }
+}
+
+impl From<&FileOutput> for ManifestArtifact {
+    fn from(file: &FileOutput) -> Self {
+        Self {
+            url: file.url.clone(),
+            content_type: infer_content_type(file),
+            size: file.size,
+            path: None,
+        }
+    }
+}
+
+/// Get the reported content type of a file, or infer it from its `data:`
+/// media type or the extension of its name or URL path.
+fn infer_content_type(file: &FileOutput) -> Option<String> {
+    if let Some(content_type) = &file.content_type {
+        return Some(content_type.clone());
+    }
+    if file.is_data_url() {
+        let media_type = file.url["data:".len()..].split([',', ';']).next()?.trim();
+        // RFC 2397: the media type defaults to text/plain
+        return Some(if media_type.is_empty() {
+            "text/plain".to_string()
+        } else {
+            media_type.to_ascii_lowercase()
+        });
+    }
+    let path = match &file.filename {
+        Some(filename) => filename.clone(),
+        None => url::Url::parse(&file.url).ok()?.path().to_string(),
+    };
+    mime_guess::from_path(path)
+        .first()
+        .map(|mime| mime.essence_str().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn prediction(id: &str, output: Value) -> Prediction {
+        serde_json::from_value(json!({
+            "id": id,
+            "model": "acme/painter",
+            "version": "v1",
+            "status": "succeeded",
+            "input": {"prompt": "a lighthouse"},
+            "output": output,
+            "metrics": {"predict_time": 2.5, "image_count": 2},
+            "created_at": "2024-05-01T00:00:00.000Z",
+            "started_at": "2024-05-01T00:00:01.000Z",
+            "completed_at": "2024-05-01T00:00:03.500Z",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_manifest_json_snapshot() {
+        let manifest = prediction(
+            "p1",
+            json!([
+                "https://replicate.delivery/a/out.png",
+                {"url": "https://replicate.delivery/b/file", "content_type": "image/webp", "size": 5},
+                "data:;base64,aGk=",
+            ]),
+        )
+        .artifact_manifest();
+
+        assert_eq!(
+            serde_json::to_string(&manifest).unwrap(),
+            concat!(
+                r#"{"format_version":1,"predictions":[{"id":"p1","model":"acme/painter","#,
+                r#""version":"v1","status":"succeeded","created_at":"2024-05-01T00:00:00.000Z","#,
+                r#""started_at":"2024-05-01T00:00:01.000Z","#,
+                r#""completed_at":"2024-05-01T00:00:03.500Z","#,
+                r#""metrics":{"image_count":2,"predict_time":2.5},"artifacts":["#,
+                r#"{"url":"https://replicate.delivery/a/out.png","content_type":"image/png","#,
+                r#""size":null,"path":null},"#,
+                r#"{"url":"https://replicate.delivery/b/file","content_type":"image/webp","#,
+                r#""size":5,"path":null},"#,
+                r#"{"url":"data:;base64,aGk=","content_type":"text/plain","size":null,"path":null}"#,
+                r#"]}]}"#,
+            )
+        );
+        let parsed: Manifest =
+            serde_json::from_str(&serde_json::to_string(&manifest).unwrap()).unwrap();
+        assert_eq!(parsed, manifest);
+    }
+
+    #[tokio::test]
+    async fn test_merge_and_write_json() {
+        let mut queued = prediction("p2", Value::Null);
+        queued.metrics = None;
+        let merged = Manifest::merge(vec![
+            prediction("p1", json!("data:image/png;base64,aGk=")).artifact_manifest(),
+            queued.artifact_manifest(),
+        ]);
+        let ids: Vec<_> = merged.predictions.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, ["p1", "p2"]);
+        assert_eq!(merged.artifacts().count(), 1);
+        assert_eq!(merged.predictions[1].metrics, None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("manifest.json");
+        merged.write_json(&path).await.unwrap();
+        let written: Manifest = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(written, merged);
+    }
+}
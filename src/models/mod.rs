@@ -1,10 +1,34 @@
 //! Data models and types for the Replicate API.
 
+pub mod collection;
 pub mod common;
 pub mod file;
+pub mod id;
+pub mod image_edit;
+pub mod manifest;
+pub mod output;
 pub mod prediction;
+pub mod presets;
+pub mod schema;
+pub mod stream;
+pub mod template;
+pub mod training;
 
 // Re-export commonly used types
-pub use common::{ApiResponse, PaginatedResponse};
+pub use collection::Collection;
+pub use common::{
+    ApiResponse, CreateModelRequest, Hardware, HardwareSku, ModelRef, PaginatedResponse, Visibility,
+};
 pub use file::{FileInput, FileOutput};
-pub use prediction::{CreatePredictionRequest, Prediction, PredictionStatus};
+pub use id::{FileId, PredictionId, TrainingId};
+pub use image_edit::ImageEditRequest;
+pub use manifest::{Manifest, ManifestArtifact, ManifestPrediction};
+pub use output::{ModelOutput, OutputClassifier, OutputSelector, TypedOutput};
+pub use prediction::{
+    CreatePredictionRequest, FallbackTarget, PersistedRequest, Prediction, PredictionHandle,
+    PredictionStatus, TypedPrediction,
+};
+pub use schema::{DynamicInput, InputSchema, PropertySchema};
+pub use stream::StreamEvent;
+pub use template::InputTemplate;
+pub use training::{CreateTrainingRequest, Training};
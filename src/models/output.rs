@@ -0,0 +1,471 @@
+//! Normalized views over the output shapes models commonly return.
+
+use crate::models::file::FileOutput;
+use serde_json::Value;
+
+/// A prediction output classified into one of the common shapes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModelOutput {
+    /// A single piece of text
+    Text(String),
+    /// Text split into chunks, as returned by most language models
+    TextChunks(Vec<String>),
+    /// A single file URL, e.g. one generated image
+    FileUrl(String),
+    /// Several file URLs, e.g. a batch of generated images
+    FileUrls(Vec<String>),
+    /// A single file object, i.e. a URL with details like its content type
+    File(FileOutput),
+    /// Several file objects, flattened from nested arrays
+    Files(Vec<FileOutput>),
+    /// Anything else, such as structured transcripts
+    Json(Value),
+}
+
+impl ModelOutput {
+    /// Concatenate the output text, if this is a text output
+    pub fn concat_text(&self) -> Option<String> {
+        match self {
+            Self::Text(text) => Some(text.clone()),
+            Self::TextChunks(chunks) => Some(chunks.concat()),
+            _ => None,
+        }
+    }
+
+    /// Convert file URLs into file outputs; other shapes yield nothing
+    pub fn into_file_outputs(self) -> Vec<FileOutput> {
+        match self {
+            Self::FileUrl(url) => vec![FileOutput::new(url)],
+            Self::FileUrls(urls) => urls.into_iter().map(FileOutput::new).collect(),
+            Self::File(file) => vec![file],
+            Self::Files(files) => files,
+            _ => Vec::new(),
+        }
+    }
+
+    /// Check if this is a file output
+    pub fn is_file(&self) -> bool {
+        matches!(
+            self,
+            Self::FileUrl(_) | Self::FileUrls(_) | Self::File(_) | Self::Files(_)
+        )
+    }
+
+    /// Check if this is a text output
+    pub fn is_text(&self) -> bool {
+        matches!(self, Self::Text(_) | Self::TextChunks(_))
+    }
+}
+
+/// A prediction output decoded using the model version's output schema.
+///
+/// Returned by [`Prediction::output_typed_via_schema`](crate::Prediction::output_typed_via_schema).
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypedOutput {
+    /// Files, from a `uri` string or an array of them
+    Files(Vec<FileOutput>),
+    /// Text, from a string or a concatenated iterator of strings
+    Text(String),
+    /// Anything else, or output whose schema is unknown
+    Json(Value),
+}
+
+impl TypedOutput {
+    /// Decode an output as described by the `Output` component of an
+    /// OpenAPI schema.
+    ///
+    /// Output that does not match its schema, like a number where a file URL
+    /// was declared, is kept as [`Json`](Self::Json).
+    pub(crate) fn decode(output: Value, openapi: Option<&Value>) -> Self {
+        let Some((openapi, schema)) =
+            openapi.and_then(|openapi| Some((openapi, resolve_output_schema(openapi)?)))
+        else {
+            return Self::Json(output);
+        };
+        let is_uri = |schema: &Value| {
+            schema.get("type").and_then(Value::as_str) == Some("string")
+                && schema.get("format").and_then(Value::as_str) == Some("uri")
+        };
+
+        match (schema.get("type").and_then(Value::as_str), output) {
+            (Some("string"), Value::String(url)) if is_uri(schema) => {
+                Self::Files(vec![FileOutput::new(url)])
+            }
+            (Some("string"), Value::String(text)) => Self::Text(text),
+            (Some("array"), Value::Array(items)) => {
+                let item_schema = schema.get("items").map(|items| resolve_ref(openapi, items));
+                let strings: Option<Vec<String>> = items
+                    .iter()
+                    .map(|item| item.as_str().map(str::to_string))
+                    .collect();
+                match (item_schema, strings) {
+                    (Some(item), Some(urls)) if is_uri(item) => {
+                        Self::Files(urls.into_iter().map(FileOutput::new).collect())
+                    }
+                    (_, Some(chunks))
+                        if schema.get("x-cog-array-display").and_then(Value::as_str)
+                            == Some("concatenate") =>
+                    {
+                        Self::Text(chunks.concat())
+                    }
+                    _ => Self::Json(Value::Array(items)),
+                }
+            }
+            (_, output) => Self::Json(output),
+        }
+    }
+}
+
+/// Find the `Output` component of an OpenAPI schema.
+fn resolve_output_schema(openapi: &Value) -> Option<&Value> {
+    let output = openapi.pointer("/components/schemas/Output")?;
+    Some(resolve_ref(openapi, output))
+}
+
+/// Follow a local `$ref` like `#/components/schemas/Output`, also when the
+/// reference is wrapped in `allOf`.
+fn resolve_ref<'a>(openapi: &'a Value, schema: &'a Value) -> &'a Value {
+    let reference = schema.get("$ref").or_else(|| {
+        schema
+            .get("allOf")
+            .and_then(Value::as_array)
+            .and_then(|all| all.first())
+            .and_then(|first| first.get("$ref"))
+    });
+    reference
+        .and_then(Value::as_str)
+        .and_then(|reference| reference.strip_prefix('#'))
+        .and_then(|pointer| openapi.pointer(pointer))
+        .unwrap_or(schema)
+}
+
+/// Heuristics used to classify raw output values into a [`ModelOutput`].
+///
+/// The rules, applied in order:
+///
+/// 1. A string that parses as a URL with one of `file_url_schemes` is a
+///    [`ModelOutput::FileUrl`]; any other string is [`ModelOutput::Text`].
+/// 2. An array whose items are all such URLs is [`ModelOutput::FileUrls`].
+/// 3. An array of strings that are all at most `max_chunk_len` characters
+///    long (including the empty array) is [`ModelOutput::TextChunks`].
+/// 4. An object whose `url` field is such a URL is a [`ModelOutput::File`],
+///    with `filename` (or `name`), `content_type` and `size` taken from the
+///    fields of the same names. Other fields are ignored.
+/// 5. A non-empty array, possibly nested, of such objects is
+///    [`ModelOutput::Files`].
+/// 6. Everything else is [`ModelOutput::Json`].
+///
+/// Adjust the fields to override the defaults for a particular model.
+#[derive(Debug, Clone)]
+pub struct OutputClassifier {
+    /// URL schemes that indicate a file output
+    pub file_url_schemes: Vec<String>,
+    /// Maximum length, in characters, of a string in a text chunk array
+    pub max_chunk_len: usize,
+}
+
+impl Default for OutputClassifier {
+    fn default() -> Self {
+        Self {
+            file_url_schemes: vec!["https".into(), "http".into(), "data".into()],
+            max_chunk_len: 200,
+        }
+    }
+}
+
+impl OutputClassifier {
+    /// Classify an output value
+    pub fn classify(&self, output: &Value) -> ModelOutput {
+        match output {
+            Value::String(s) if self.is_file_url(s) => ModelOutput::FileUrl(s.clone()),
+            Value::String(s) => ModelOutput::Text(s.clone()),
+            Value::Object(_) => match self.file_object(output) {
+                Some(file) => ModelOutput::File(file),
+                None => ModelOutput::Json(output.clone()),
+            },
+            Value::Array(items) if items.first().is_some_and(|item| !item.is_string()) => {
+                match self.file_objects(items) {
+                    Some(files) if !files.is_empty() => ModelOutput::Files(files),
+                    _ => ModelOutput::Json(output.clone()),
+                }
+            }
+            Value::Array(items) => {
+                let strings: Option<Vec<&str>> = items.iter().map(Value::as_str).collect();
+                match strings {
+                    Some(strings)
+                        if !strings.is_empty() && strings.iter().all(|s| self.is_file_url(s)) =>
+                    {
+                        ModelOutput::FileUrls(strings.into_iter().map(str::to_string).collect())
+                    }
+                    Some(strings)
+                        if strings
+                            .iter()
+                            .all(|s| s.chars().count() <= self.max_chunk_len) =>
+                    {
+                        ModelOutput::TextChunks(strings.into_iter().map(str::to_string).collect())
+                    }
+                    _ => ModelOutput::Json(output.clone()),
+                }
+            }
+            other => ModelOutput::Json(other.clone()),
+        }
+    }
+
+    /// Parse an object with a file `url` into a file output.
+    fn file_object(&self, value: &Value) -> Option<FileOutput> {
+        let url = value
+            .get("url")?
+            .as_str()
+            .filter(|url| self.is_file_url(url))?;
+        let field = |name: &str| value.get(name).and_then(Value::as_str);
+        let mut file = FileOutput::new(url);
+        file.filename = field("filename")
+            .or_else(|| field("name"))
+            .map(str::to_string);
+        file.content_type = field("content_type").map(str::to_string);
+        file.size = value.get("size").and_then(Value::as_u64);
+        Some(file)
+    }
+
+    /// Flatten nested arrays of file objects, failing on any other item.
+    fn file_objects(&self, items: &[Value]) -> Option<Vec<FileOutput>> {
+        let mut files = Vec::new();
+        for item in items {
+            match item {
+                Value::Array(nested) => files.extend(self.file_objects(nested)?),
+                item => files.push(self.file_object(item)?),
+            }
+        }
+        Some(files)
+    }
+
+    fn is_file_url(&self, s: &str) -> bool {
+        url::Url::parse(s)
+            .map(|url| {
+                self.file_url_schemes
+                    .iter()
+                    .any(|scheme| scheme == url.scheme())
+            })
+            .unwrap_or(false)
+    }
+}
+
+/// Selects the part of a prediction output to pass on, e.g. as another
+/// prediction's input.
+///
+/// Integers convert into [`Index`](Self::Index) and strings into
+/// [`Pointer`](Self::Pointer).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputSelector {
+    /// The whole output; text chunks are joined into a single string
+    Whole,
+    /// The first element of an array output, or a non-array output itself
+    First,
+    /// The last element of an array output, or a non-array output itself
+    Last,
+    /// The element at an index of an array output
+    Index(usize),
+    /// A JSON pointer into the output, like `/segments/0/text`
+    Pointer(String),
+}
+
+impl OutputSelector {
+    /// Select a value from an output.
+    pub fn select(&self, output: &Value) -> Option<Value> {
+        match self {
+            Self::Whole => match OutputClassifier::default().classify(output) {
+                // Language models return text as chunks; pass the whole text on
+                ModelOutput::TextChunks(chunks) if !chunks.is_empty() => {
+                    Some(Value::String(chunks.concat()))
+                }
+                _ => Some(output.clone()),
+            },
+            Self::First => match output {
+                Value::Array(items) => items.first().cloned(),
+                other => Some(other.clone()),
+            },
+            Self::Last => match output {
+                Value::Array(items) => items.last().cloned(),
+                other => Some(other.clone()),
+            },
+            Self::Index(index) => output.as_array()?.get(*index).cloned(),
+            Self::Pointer(pointer) => output.pointer(pointer).cloned(),
+        }
+    }
+}
+
+impl From<usize> for OutputSelector {
+    fn from(index: usize) -> Self {
+        Self::Index(index)
+    }
+}
+
+impl From<&str> for OutputSelector {
+    fn from(pointer: &str) -> Self {
+        Self::Pointer(pointer.to_string())
+    }
+}
+
+impl From<String> for OutputSelector {
+    fn from(pointer: String) -> Self {
+        Self::Pointer(pointer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sdxl_style_output() {
+        let output = json!([
+            "https://replicate.delivery/pbxt/abc/out-0.png",
+            "https://replicate.delivery/pbxt/abc/out-1.png",
+        ]);
+        let classified = OutputClassifier::default().classify(&output);
+        assert!(matches!(&classified, ModelOutput::FileUrls(urls) if urls.len() == 2));
+        assert_eq!(classified.into_file_outputs().len(), 2);
+
+        let single = json!("https://replicate.delivery/xezq/out.webp");
+        assert_eq!(
+            OutputClassifier::default().classify(&single),
+            ModelOutput::FileUrl("https://replicate.delivery/xezq/out.webp".into())
+        );
+    }
+
+    #[test]
+    fn test_llama_style_output() {
+        let output = json!(["Hello", ",", " world", "!"]);
+        let classified = OutputClassifier::default().classify(&output);
+        assert!(matches!(classified, ModelOutput::TextChunks(_)));
+        assert_eq!(classified.concat_text().as_deref(), Some("Hello, world!"));
+        assert!(classified.into_file_outputs().is_empty());
+    }
+
+    #[test]
+    fn test_whisper_style_output() {
+        let output = json!({
+            "transcription": "hello world",
+            "segments": [{"start": 0.0, "end": 1.2, "text": "hello world"}],
+            "detected_language": "english",
+        });
+        let classified = OutputClassifier::default().classify(&output);
+        assert_eq!(classified, ModelOutput::Json(output));
+        assert_eq!(classified.concat_text(), None);
+    }
+
+    #[test]
+    fn test_classifier_overrides() {
+        let long_chunk = "x".repeat(50);
+        let output = json!([long_chunk, "short"]);
+
+        let strict = OutputClassifier {
+            max_chunk_len: 10,
+            ..Default::default()
+        };
+        assert!(matches!(strict.classify(&output), ModelOutput::Json(_)));
+        assert!(matches!(
+            OutputClassifier::default().classify(&output),
+            ModelOutput::TextChunks(_)
+        ));
+
+        let no_data_urls = OutputClassifier {
+            file_url_schemes: vec!["https".into()],
+            ..Default::default()
+        };
+        assert!(matches!(
+            no_data_urls.classify(&json!("data:text/plain,hi")),
+            ModelOutput::Text(_)
+        ));
+        assert_eq!(
+            OutputClassifier::default().classify(&json!("plain answer")),
+            ModelOutput::Text("plain answer".into())
+        );
+    }
+
+    #[test]
+    fn test_output_selector() {
+        let images = json!(["https://a/0.png", "https://a/1.png"]);
+        assert_eq!(
+            OutputSelector::First.select(&images),
+            Some(json!("https://a/0.png"))
+        );
+        assert_eq!(
+            OutputSelector::Last.select(&images),
+            Some(json!("https://a/1.png"))
+        );
+        assert_eq!(
+            OutputSelector::from(1).select(&images),
+            Some(json!("https://a/1.png"))
+        );
+        assert_eq!(OutputSelector::from(2).select(&images), None);
+        assert_eq!(OutputSelector::Whole.select(&images), Some(images.clone()));
+
+        let chunks = json!(["The", " sky", " is blue"]);
+        assert_eq!(
+            OutputSelector::Whole.select(&chunks),
+            Some(json!("The sky is blue"))
+        );
+
+        let transcript = json!({"segments": [{"text": "hello"}]});
+        assert_eq!(
+            OutputSelector::from("/segments/0/text").select(&transcript),
+            Some(json!("hello"))
+        );
+        assert_eq!(OutputSelector::from("/missing").select(&transcript), None);
+        assert_eq!(
+            OutputSelector::First.select(&json!("https://a/only.png")),
+            Some(json!("https://a/only.png"))
+        );
+    }
+
+    #[test]
+    fn test_file_object_output() {
+        let classifier = OutputClassifier::default();
+        let single = json!({
+            "url": "https://replicate.delivery/abc/song.mp3",
+            "content_type": "audio/mpeg",
+            "size": 1024,
+            "duration": 3.5,
+        });
+        assert_eq!(
+            classifier.classify(&single),
+            ModelOutput::File(
+                FileOutput::new("https://replicate.delivery/abc/song.mp3")
+                    .with_content_type("audio/mpeg")
+                    .with_size(1024)
+            )
+        );
+
+        let nested = json!([
+            [
+                {"url": "https://a/0.png", "filename": "first.png"},
+                {"url": "https://a/1.png", "name": "second.png", "size": 7},
+            ],
+            [{"url": "https://a/2.png"}],
+        ]);
+        let classified = classifier.classify(&nested);
+        assert!(classified.is_file());
+        let files = classified.into_file_outputs();
+        let urls: Vec<_> = files.iter().map(|file| file.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            ["https://a/0.png", "https://a/1.png", "https://a/2.png"]
+        );
+        assert_eq!(files[0].filename.as_deref(), Some("first.png"));
+        assert_eq!(files[1].filename.as_deref(), Some("second.png"));
+        assert_eq!(files[1].size, Some(7));
+
+        for not_files in [
+            json!({"url": "not a url", "size": 1}),
+            json!([{"url": "https://a/0.png"}, {"text": "hi"}]),
+            json!([[], []]),
+        ] {
+            assert_eq!(
+                classifier.classify(&not_files),
+                ModelOutput::Json(not_files.clone())
+            );
+        }
+    }
+}
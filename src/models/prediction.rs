@@ -1,9 +1,30 @@
 //! Prediction-related types and structures.
 
-use crate::models::file::{FileEncodingStrategy, FileInput};
+use crate::http::HttpClient;
+use crate::models::common::{ModelRef, ModelVersion, parse_timestamp};
+use crate::models::file::{
+    FileEncodingStrategy, FileInput, FileOutput, decode_data_url, is_data_url,
+};
+use crate::models::id::PredictionId;
+use crate::models::manifest::{Manifest, ManifestArtifact};
+use crate::models::output::{ModelOutput, OutputClassifier, OutputSelector, TypedOutput};
+use bytes::Bytes;
+use indexmap::IndexMap;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use serde_json::value::RawValue;
 use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Check if a string is a URL to a file that can be downloaded.
+fn is_file_url(s: &str) -> bool {
+    url::Url::parse(s)
+        .map(|url| matches!(url.scheme(), "http" | "https" | "data"))
+        .unwrap_or(false)
+}
 
 /// Status of a prediction.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -34,18 +55,116 @@ impl PredictionStatus {
 }
 
 /// URLs associated with a prediction.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Every link is optional: webhook payloads and some API variants omit links
+/// that do not apply to the prediction.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct PredictionUrls {
     /// URL to fetch the prediction
-    pub get: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub get: Option<String>,
     /// URL to cancel the prediction
-    pub cancel: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cancel: Option<String>,
     /// URL to stream the prediction output (if supported)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub stream: Option<String>,
+    /// URL of the prediction's page on replicate.com
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub web: Option<String>,
+    /// Links not modelled by this crate
+    #[serde(flatten)]
+    pub other: HashMap<String, Value>,
+}
+
+/// Minimal reference to a prediction that can be persisted and resumed later.
+///
+/// Serialize it to store a running prediction between processes, then use
+/// [`PredictionsApi::get_handle`](crate::api::PredictionsApi::get_handle),
+/// [`cancel_handle`](crate::api::PredictionsApi::cancel_handle) or
+/// [`wait_handle`](crate::api::PredictionsApi::wait_handle) without fetching
+/// the prediction first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PredictionHandle {
+    /// The unique ID of the prediction
+    pub id: String,
+    /// URLs returned when the prediction was created
+    #[serde(default)]
+    pub urls: PredictionUrls,
+}
+
+impl From<&Prediction> for PredictionHandle {
+    fn from(prediction: &Prediction) -> Self {
+        Self {
+            id: prediction.id.clone(),
+            urls: prediction.urls.clone().unwrap_or_default(),
+        }
+    }
+}
+
+impl From<&PredictionHandle> for PredictionHandle {
+    fn from(handle: &PredictionHandle) -> Self {
+        handle.clone()
+    }
+}
+
+/// Convert prediction IDs to handles without URLs, for APIs that take an ID
+/// or a handle.
+macro_rules! handle_from_id {
+    ($($id:ty),*) => {
+        $(
+            impl From<$id> for PredictionHandle {
+                fn from(id: $id) -> Self {
+                    Self {
+                        id: PredictionId::from(id).into_string(),
+                        urls: PredictionUrls::default(),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+handle_from_id!(&str, String, &String, PredictionId, &PredictionId);
+
+/// Where a prediction create goes when its primary target rejects it.
+///
+/// See [`PredictionBuilder::with_fallback`](crate::api::predictions::PredictionBuilder::with_fallback).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FallbackTarget {
+    /// A model, at its latest version unless the reference pins one
+    Model(ModelRef),
+    /// A deployment
+    Deployment {
+        /// Deployment owner
+        owner: String,
+        /// Deployment name
+        name: String,
+    },
+}
+
+impl fmt::Display for FallbackTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Model(model) => write!(f, "model {}", model),
+            Self::Deployment { owner, name } => write!(f, "deployment {}/{}", owner, name),
+        }
+    }
+}
+
+impl From<&Prediction> for PredictionId {
+    fn from(prediction: &Prediction) -> Self {
+        Self::new(prediction.id.as_str())
+    }
 }
 
 /// A prediction made by a model hosted on Replicate.
+///
+/// List endpoints return trimmed records without `input` and `logs`; those
+/// are marked [`is_partial`](Self::is_partial) and can be completed with
+/// [`PredictionsApi::hydrate`](crate::api::PredictionsApi::hydrate).
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 pub struct Prediction {
     /// The unique ID of the prediction
     pub id: String,
@@ -54,7 +173,12 @@ pub struct Prediction {
     pub model: String,
 
     /// The version ID of the model used
-    pub version: String,
+    ///
+    /// May be absent, e.g. for predictions created on a deployment or an
+    /// official model; see [`version`](Self::version) and
+    /// [`model_ref`](Self::model_ref).
+    #[serde(default)]
+    pub version: Option<String>,
 
     /// The current status of the prediction
     pub status: PredictionStatus,
@@ -65,6 +189,10 @@ pub struct Prediction {
     /// The output of the prediction (if completed)
     pub output: Option<Value>,
 
+    /// The unparsed output, populated instead of `output` when raw output mode is enabled
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub output_raw: Option<Box<RawValue>>,
+
     /// Logs from the prediction execution
     pub logs: Option<String>,
 
@@ -85,6 +213,83 @@ pub struct Prediction {
 
     /// URLs associated with the prediction
     pub urls: Option<PredictionUrls>,
+
+    /// Whether Replicate has removed the input and output of this prediction
+    ///
+    /// Data of old predictions is purged after a retention period; `input`
+    /// and `output` are then `None` while the rest is kept. See
+    /// [`has_data`](Self::has_data).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_removed: Option<bool>,
+
+    /// Fields not modelled by this crate, kept as returned by the API
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+
+    /// Whether this is a trimmed record, as returned when listing predictions
+    ///
+    /// Set when `input` or `logs` is absent from the JSON. Such records fail
+    /// [`input_as`](Self::input_as), and [`output_as`](Self::output_as) once
+    /// succeeded without an `output` key, with
+    /// [`Error::PartialRecord`](crate::Error::PartialRecord).
+    #[serde(skip)]
+    pub is_partial: bool,
+
+    /// Whether the JSON had no `output` key, as some listed records do
+    ///
+    /// A [partial](Self::is_partial) record that succeeded without an
+    /// `output` key fails [`output_as`](Self::output_as); a `null` output is
+    /// present and reads as no output.
+    #[serde(skip)]
+    pub output_omitted: bool,
+
+    /// Fallback target that created this prediction, if its create was failed over
+    ///
+    /// `None` when the primary target served the create. See
+    /// [`PredictionBuilder::with_fallback`](crate::api::predictions::PredictionBuilder::with_fallback).
+    #[serde(skip)]
+    pub failed_over_to: Option<FallbackTarget>,
+}
+
+impl Serialize for Prediction {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Prediction::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Prediction {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        /// A prediction, with the fields that listed records may lack
+        /// defaulting to `None` when absent and `Some` when present.
+        #[derive(Deserialize)]
+        struct Record {
+            #[serde(default, deserialize_with = "present")]
+            input: Option<Option<HashMap<String, Value>>>,
+            #[serde(default, deserialize_with = "present")]
+            logs: Option<Option<String>>,
+            #[serde(default, deserialize_with = "present")]
+            output: Option<Option<Value>>,
+            #[serde(flatten, deserialize_with = "Prediction::deserialize")]
+            prediction: Prediction,
+        }
+
+        fn present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+            T: Deserialize<'de>,
+        {
+            T::deserialize(deserializer).map(Some)
+        }
+
+        let record = Record::deserialize(deserializer)?;
+        let mut prediction = record.prediction;
+        prediction.is_partial = record.input.is_none() || record.logs.is_none();
+        prediction.output_omitted = record.output.is_none();
+        prediction.input = record.input.flatten();
+        prediction.logs = record.logs.flatten();
+        prediction.output = record.output.flatten();
+        Ok(prediction)
+    }
 }
 
 impl Prediction {
@@ -107,40 +312,784 @@ impl Prediction {
     pub fn is_canceled(&self) -> bool {
         self.status == PredictionStatus::Canceled
     }
+
+    /// Get the version ID of the model that ran the prediction
+    ///
+    /// Returns `None` when the response carried no version or an empty one.
+    pub fn version(&self) -> Option<&str> {
+        self.version
+            .as_deref()
+            .filter(|version| !version.is_empty())
+    }
+
+    /// Get a reference to the model that ran the prediction, pinned to its
+    /// version when the response carried one
+    ///
+    /// Returns `None` if `model` is not of the form `owner/name`.
+    pub fn model_ref(&self) -> Option<ModelRef> {
+        let (owner, name) = self.model.split_once('/')?;
+        if owner.is_empty() || name.is_empty() || name.contains('/') {
+            return None;
+        }
+        let model = ModelRef::new(owner, name);
+        Some(match self.version() {
+            Some(version) => model.with_version(version),
+            None => model,
+        })
+    }
+
+    /// Get the URL of the prediction's page on replicate.com
+    pub fn web_url(&self) -> Option<&str> {
+        self.urls.as_ref()?.web.as_deref()
+    }
+
+    /// Get the URL to stream the prediction output
+    pub fn stream_url(&self) -> Option<&str> {
+        self.urls.as_ref()?.stream.as_deref()
+    }
+
+    /// Check if the output can be streamed, i.e. the API returned a stream URL
+    ///
+    /// Only models that support streaming get one, so check this before
+    /// calling [`PredictionsApi::stream`](crate::api::PredictionsApi::stream).
+    pub fn supports_streaming(&self) -> bool {
+        self.stream_url().is_some()
+    }
+
+    /// Get the URL to cancel the prediction
+    pub fn cancel_url(&self) -> Option<&str> {
+        self.urls.as_ref()?.cancel.as_deref()
+    }
+
+    /// Get a persistable handle to this prediction
+    pub fn handle(&self) -> PredictionHandle {
+        PredictionHandle::from(self)
+    }
+
+    /// Check if the input and output are still available
+    ///
+    /// Returns `false` once Replicate has purged the prediction's data.
+    pub fn has_data(&self) -> bool {
+        self.data_removed != Some(true)
+    }
+
+    /// Fail with [`Error::DataRemoved`](crate::Error::DataRemoved) if the data was purged
+    fn ensure_data(&self) -> crate::Result<()> {
+        match self.has_data() {
+            true => Ok(()),
+            false => Err(crate::Error::data_removed(&self.id)),
+        }
+    }
+
+    /// Concatenate the text tokens present in the output, whatever the status
+    ///
+    /// Language models fill `output` with tokens while still processing, so
+    /// this gives the text generated so far. A string output is returned as
+    /// is; non-string items in an array are skipped. Returns `None` for other
+    /// output shapes or when there is no output yet.
+    pub fn partial_output_text(&self) -> Option<String> {
+        let parsed;
+        let output = match (&self.output, &self.output_raw) {
+            (Some(output), _) => output,
+            (None, Some(raw)) => {
+                parsed = serde_json::from_str::<Value>(raw.get()).ok()?;
+                &parsed
+            }
+            (None, None) => return None,
+        };
+
+        match output {
+            Value::String(text) => Some(text.clone()),
+            Value::Array(items) => Some(items.iter().filter_map(Value::as_str).collect()),
+            _ => None,
+        }
+    }
+
+    /// Text added to the output since `previous`, for pseudo-streaming by polling
+    ///
+    /// If the new text no longer extends the previous one, e.g. because the
+    /// output switched from tokens to a single string, the whole new text is
+    /// returned. Returns `None` when nothing was added.
+    pub fn output_delta_since(&self, previous: Option<&Prediction>) -> Option<String> {
+        let current = self.partial_output_text()?;
+        let previous = previous
+            .and_then(Prediction::partial_output_text)
+            .unwrap_or_default();
+        let delta = match current.strip_prefix(previous.as_str()) {
+            Some(added) => added.to_string(),
+            None => current,
+        };
+        (!delta.is_empty()).then_some(delta)
+    }
+
+    /// Classify the output into one of the common shapes
+    ///
+    /// Uses the default [`OutputClassifier`] heuristics; see its documentation
+    /// for the rules, and [`classified_output_with`](Self::classified_output_with)
+    /// to override them.
+    pub fn classified_output(&self) -> Option<ModelOutput> {
+        self.classified_output_with(&OutputClassifier::default())
+    }
+
+    /// Classify the output using custom heuristics
+    pub fn classified_output_with(&self, classifier: &OutputClassifier) -> Option<ModelOutput> {
+        self.output
+            .as_ref()
+            .map(|output| classifier.classify(output))
+    }
+
+    /// Select part of the output of a succeeded prediction
+    ///
+    /// Fails with [`Error::InvalidInput`](crate::Error::InvalidInput) if the
+    /// prediction has not succeeded or the selector matches nothing. Works in
+    /// raw output mode too.
+    pub fn select_output(&self, selector: &OutputSelector) -> crate::Result<Value> {
+        if !self.is_successful() {
+            return Err(crate::Error::invalid_input(format!(
+                "Prediction {} has not succeeded (status: {:?})",
+                self.id, self.status
+            )));
+        }
+
+        let output: Option<Value> = self.output_as()?;
+        output
+            .as_ref()
+            .and_then(|output| selector.select(output))
+            .ok_or_else(|| {
+                crate::Error::invalid_input(format!(
+                    "Output of prediction {} has nothing matching {:?}",
+                    self.id, selector
+                ))
+            })
+    }
+
+    /// Decode every `data:` URL found in the output into its media type and bytes
+    ///
+    /// The output is searched recursively, so data URLs nested in arrays or
+    /// objects are found too. Strings that look like data URLs but fail to
+    /// decode are skipped. In raw output mode, call
+    /// [`materialize_output`](Self::materialize_output) first.
+    pub fn decode_output_data_urls(&self) -> Vec<(String, Bytes)> {
+        fn collect(value: &Value, out: &mut Vec<(String, Bytes)>) {
+            match value {
+                Value::String(s) if is_data_url(s) => {
+                    if let Ok(decoded) = decode_data_url(s) {
+                        out.push(decoded);
+                    }
+                }
+                Value::Array(items) => items.iter().for_each(|v| collect(v, out)),
+                Value::Object(map) => map.values().for_each(|v| collect(v, out)),
+                _ => {}
+            }
+        }
+
+        let mut decoded = Vec::new();
+        if let Some(output) = &self.output {
+            collect(output, &mut decoded);
+        }
+        decoded
+    }
+
+    /// Iterate over the items of the output
+    ///
+    /// Yields each element of an array output, flattening nested arrays, a
+    /// scalar or object output as its only item, and nothing for a missing or
+    /// `null` output. This saves matching on "a string or an array of
+    /// strings" for models whose `num_outputs` decides the shape. In raw
+    /// output mode, call [`materialize_output`](Self::materialize_output)
+    /// first.
+    pub fn outputs_iter(&self) -> impl Iterator<Item = &Value> {
+        OutputItems::new(self.output.as_ref())
+    }
+
+    /// Count the items [`outputs_iter`](Self::outputs_iter) yields
+    pub fn outputs_len(&self) -> usize {
+        self.outputs_iter().count()
+    }
+
+    /// Get the files in the output
+    ///
+    /// Each item of the output, as iterated by
+    /// [`outputs_iter`](Self::outputs_iter), is classified with the default
+    /// [`OutputClassifier`]; file URLs yield files, and so do file objects
+    /// like `{"url": ..., "content_type": ..., "size": ...}`, with their
+    /// details filled in. Other items are skipped. Works in raw output mode
+    /// too. Fails like [`output_as`](Self::output_as) if the data was purged
+    /// or the record is partial.
+    pub fn file_outputs(&self) -> crate::Result<Vec<FileOutput>> {
+        let output: Option<Value> = self.output_as()?;
+        let classifier = OutputClassifier::default();
+        Ok(OutputItems::new(output.as_ref())
+            .flat_map(|item| classifier.classify(item).into_file_outputs())
+            .collect())
+    }
+
+    /// Decode the output using the output schema of the version that ran it
+    ///
+    /// The schema decides the shape instead of guessing from the value: a
+    /// `uri` string or an array of them yields [`TypedOutput::Files`], a
+    /// string or a concatenated iterator of strings yields
+    /// [`TypedOutput::Text`], and anything else, including output when the
+    /// version has no schema, yields [`TypedOutput::Json`]. A missing output
+    /// is `Json(Value::Null)`. Fails like [`output_as`](Self::output_as).
+    pub fn output_typed_via_schema(&self, version: &ModelVersion) -> crate::Result<TypedOutput> {
+        let output: Option<Value> = self.output_as()?;
+        Ok(TypedOutput::decode(
+            output.unwrap_or(Value::Null),
+            version.openapi_schema.as_ref(),
+        ))
+    }
+
+    /// Get the files referenced by the input
+    ///
+    /// Every string in the input that is an `http`, `https` or `data:` URL is
+    /// returned, including those nested in arrays or objects, so the source
+    /// assets of a prediction can be downloaded again. Inputs are scanned in
+    /// sorted key order; other values are skipped. Returns nothing when the
+    /// prediction has no input or its data was purged.
+    pub fn input_file_urls(&self) -> Vec<FileOutput> {
+        fn collect(value: &Value, out: &mut Vec<FileOutput>) {
+            match value {
+                Value::String(s) if is_file_url(s) => out.push(FileOutput::new(s.as_str())),
+                Value::Array(items) => items.iter().for_each(|v| collect(v, out)),
+                Value::Object(map) => map.values().for_each(|v| collect(v, out)),
+                _ => {}
+            }
+        }
+
+        let mut inputs: Vec<_> = self.input.iter().flatten().collect();
+        inputs.sort_by_key(|(key, _)| *key);
+        let mut files = Vec::new();
+        for (_, value) in inputs {
+            collect(value, &mut files);
+        }
+        files
+    }
+
+    /// Download every file in the output into a directory
+    ///
+    /// Files from [`file_outputs`](Self::file_outputs) are named after their
+    /// URL path, or `output-{index}` when it has no usable name, with a
+    /// numeric suffix added when a name is already taken. The directory is
+    /// created if needed, and existing files are never overwritten. Files
+    /// hosted on the API are fetched with the credentials of `http`, e.g.
+    /// [`Client::http_client`](crate::Client::http_client). Returns the
+    /// written paths in output order.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use replicate_client::Client;
+    /// # use std::path::Path;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = Client::new("your-api-token")?;
+    /// let prediction = client
+    ///     .create_prediction("black-forest-labs/flux-schnell")
+    ///     .input("prompt", "a lighthouse at dusk")
+    ///     .send_and_wait()
+    ///     .await?;
+    /// let paths = prediction
+    ///     .save_outputs_to_dir(Path::new("outputs"), client.http_client())
+    ///     .await?;
+    /// println!("Saved {:?}", paths);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn save_outputs_to_dir(
+        &self,
+        dir: &Path,
+        http: &HttpClient,
+    ) -> crate::Result<Vec<PathBuf>> {
+        /// Insert `-{suffix}` before the extension of a file name.
+        fn with_suffix(name: &str, suffix: usize) -> String {
+            let path = Path::new(name);
+            match (path.file_stem(), path.extension()) {
+                (Some(stem), Some(extension)) => format!(
+                    "{}-{}.{}",
+                    stem.to_string_lossy(),
+                    suffix,
+                    extension.to_string_lossy()
+                ),
+                _ => format!("{}-{}", name, suffix),
+            }
+        }
+
+        let files = self.file_outputs()?;
+        tokio::fs::create_dir_all(dir).await?;
+
+        let mut paths = Vec::with_capacity(files.len());
+        for (index, file) in files.iter().enumerate() {
+            let name = file.local_file_name(index);
+            let mut path = dir.join(&name);
+            let mut suffix = 1;
+            // Creating the file claims the name, so concurrent saves into the
+            // same directory never write to the same file
+            let output = loop {
+                match tokio::fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&path)
+                    .await
+                {
+                    Ok(output) => break output,
+                    Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                        path = dir.join(with_suffix(&name, suffix));
+                        suffix += 1;
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            };
+            file.save_to_file_via(http, output).await?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    /// Download every file in the output into a directory and write a manifest
+    ///
+    /// Saves the files like [`save_outputs_to_dir`](Self::save_outputs_to_dir),
+    /// then writes the [`artifact_manifest`](Self::artifact_manifest), with
+    /// the local paths filled in, to `{id}.manifest.json` in the same
+    /// directory, so the manifests of a batch saved together do not clash.
+    /// Returns the manifest.
+    pub async fn save_outputs_with_manifest(
+        &self,
+        dir: &Path,
+        http: &HttpClient,
+    ) -> crate::Result<Manifest> {
+        let paths = self.save_outputs_to_dir(dir, http).await?;
+        let artifacts = self
+            .file_outputs()?
+            .iter()
+            .zip(paths)
+            .map(|(file, path)| ManifestArtifact {
+                path: Some(path),
+                ..ManifestArtifact::from(file)
+            })
+            .collect();
+        let manifest = Manifest::for_prediction(self, artifacts);
+        manifest
+            .write_json(dir.join(format!("{}.manifest.json", self.id)))
+            .await?;
+        Ok(manifest)
+    }
+
+    /// Describe the output files and their provenance for data pipelines
+    ///
+    /// Lists the files of [`file_outputs`](Self::file_outputs) with their
+    /// content type, as reported or inferred from the URL, alongside the
+    /// prediction ID, model, version, status, timestamps and metrics. A
+    /// prediction whose output is unavailable, e.g. purged or partial, is
+    /// listed without artifacts.
+    pub fn artifact_manifest(&self) -> Manifest {
+        let artifacts = self
+            .file_outputs()
+            .unwrap_or_default()
+            .iter()
+            .map(ManifestArtifact::from)
+            .collect();
+        Manifest::for_prediction(self, artifacts)
+    }
+
+    /// Get the raw output JSON, if the prediction was fetched in raw output mode
+    pub fn raw_output(&self) -> Option<&RawValue> {
+        self.output_raw.as_deref()
+    }
+
+    /// Deserialize the output into a concrete type
+    ///
+    /// Works in both modes; in raw output mode the output is parsed straight
+    /// from the raw JSON without building an intermediate `Value` tree.
+    /// Failures are reported as [`Error::Deserialize`](crate::Error::Deserialize)
+    /// with the JSON path of the offending field, and purged predictions as
+    /// [`Error::DataRemoved`](crate::Error::DataRemoved). A
+    /// [partial](Self::is_partial) record that succeeded without an `output`
+    /// key fails with [`Error::PartialRecord`](crate::Error::PartialRecord).
+    pub fn output_as<T: DeserializeOwned>(&self) -> crate::Result<Option<T>> {
+        self.ensure_data()?;
+        if self.is_partial && self.output_omitted && self.is_successful() {
+            return Err(crate::Error::partial_record(&self.id));
+        }
+        if let Some(raw) = &self.output_raw {
+            let mut deserializer = serde_json::Deserializer::from_str(raw.get());
+            return deserialize_with_path(&mut deserializer).map(Some);
+        }
+        match &self.output {
+            Some(value) => deserialize_with_path(value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Deserialize the input into a concrete type
+    ///
+    /// Useful for recovering the typed input a prediction was created with,
+    /// e.g. to re-run it with tweaks. Failures report the JSON path like
+    /// [`output_as`](Self::output_as); purged predictions fail with
+    /// [`Error::DataRemoved`](crate::Error::DataRemoved) and
+    /// [partial](Self::is_partial) records without input with
+    /// [`Error::PartialRecord`](crate::Error::PartialRecord).
+    pub fn input_as<T: DeserializeOwned>(&self) -> crate::Result<Option<T>> {
+        self.ensure_data()?;
+        match &self.input {
+            Some(input) => {
+                let value = Value::Object(input.clone().into_iter().collect());
+                deserialize_with_path(&value).map(Some)
+            }
+            None if self.is_partial => Err(crate::Error::partial_record(&self.id)),
+            None => Ok(None),
+        }
+    }
+
+    /// Look up a value in the output by RFC 6901 JSON pointer
+    ///
+    /// E.g. `/segments/0/text`; `~1` and `~0` escape `/` and `~` in keys.
+    /// Returns `None` for pointers that are malformed or do not resolve. In
+    /// raw output mode call [`materialize_output`](Self::materialize_output)
+    /// first, or use [`output_pointer_as`](Self::output_pointer_as).
+    pub fn output_pointer(&self, pointer: &str) -> Option<&Value> {
+        self.output.as_ref()?.pointer(pointer)
+    }
+
+    /// Deserialize the output value at a JSON pointer into a concrete type
+    ///
+    /// Returns `Ok(None)` when the pointer does not resolve. Type mismatches
+    /// fail with [`Error::Deserialize`](crate::Error::Deserialize), the path
+    /// being relative to the pointed-to value. Works in raw output mode too.
+    pub fn output_pointer_as<T: DeserializeOwned>(
+        &self,
+        pointer: &str,
+    ) -> crate::Result<Option<T>> {
+        self.ensure_data()?;
+        if let Some(raw) = &self.output_raw {
+            let output: Value = serde_json::from_str(raw.get())?;
+            return output
+                .pointer(pointer)
+                .map(deserialize_with_path)
+                .transpose();
+        }
+        self.output_pointer(pointer)
+            .map(deserialize_with_path)
+            .transpose()
+    }
+
+    /// Look up a value in the input by JSON pointer, e.g. `/prompt`
+    ///
+    /// The first segment names an input field, so the empty pointer (the
+    /// whole input) yields `None`, as do malformed or unresolved pointers.
+    pub fn input_pointer(&self, pointer: &str) -> Option<&Value> {
+        map_pointer(self.input.as_ref()?, pointer)
+    }
+
+    /// Deserialize the input value at a JSON pointer into a concrete type
+    ///
+    /// Behaves like [`output_pointer_as`](Self::output_pointer_as).
+    pub fn input_pointer_as<T: DeserializeOwned>(&self, pointer: &str) -> crate::Result<Option<T>> {
+        self.ensure_data()?;
+        self.input_pointer(pointer)
+            .map(deserialize_with_path)
+            .transpose()
+    }
+
+    /// Look up a value in the metrics by JSON pointer, e.g. `/predict_time`
+    ///
+    /// Follows the same rules as [`input_pointer`](Self::input_pointer).
+    pub fn metrics_pointer(&self, pointer: &str) -> Option<&Value> {
+        map_pointer(self.metrics.as_ref()?, pointer)
+    }
+
+    /// Deserialize the metrics value at a JSON pointer into a concrete type
+    ///
+    /// Behaves like [`output_pointer_as`](Self::output_pointer_as).
+    pub fn metrics_pointer_as<T: DeserializeOwned>(
+        &self,
+        pointer: &str,
+    ) -> crate::Result<Option<T>> {
+        self.metrics_pointer(pointer)
+            .map(deserialize_with_path)
+            .transpose()
+    }
+
+    /// Get how long the prediction waited to start, from `created_at` to `started_at`
+    ///
+    /// Returns `None` until both timestamps are set and parseable.
+    pub fn queue_time(&self) -> Option<Duration> {
+        time_between(self.created_at.as_deref(), self.started_at.as_deref())
+    }
+
+    /// Get how long the prediction ran, from `started_at` to `completed_at`
+    ///
+    /// Returns `None` until both timestamps are set and parseable.
+    pub fn run_time(&self) -> Option<Duration> {
+        time_between(self.started_at.as_deref(), self.completed_at.as_deref())
+    }
+
+    /// Get how long the prediction took overall, from `created_at` to `completed_at`
+    ///
+    /// Returns `None` until both timestamps are set and parseable.
+    pub fn total_time(&self) -> Option<Duration> {
+        time_between(self.created_at.as_deref(), self.completed_at.as_deref())
+    }
+
+    /// Get how long a running prediction has been running, from `started_at` to now
+    ///
+    /// Returns `None` until the prediction has started, once it is terminal
+    /// (see [`run_time`](Self::run_time)) and when `started_at` is unparseable.
+    /// A `started_at` ahead of the local clock counts as just started.
+    pub fn elapsed_since_start(&self) -> Option<Duration> {
+        if !self.status.is_running() {
+            return None;
+        }
+        let started_at = parse_timestamp(self.started_at.as_deref()?)?;
+        Some(
+            SystemTime::now()
+                .duration_since(started_at)
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Get when the prediction entered its current status, if known
+    ///
+    /// That is `created_at` while starting, `started_at` while processing and
+    /// `completed_at` once terminal.
+    pub fn status_since(&self) -> Option<SystemTime> {
+        let timestamp = match self.status {
+            PredictionStatus::Starting => self.created_at.as_deref(),
+            PredictionStatus::Processing => self.started_at.as_deref(),
+            PredictionStatus::Succeeded | PredictionStatus::Failed | PredictionStatus::Canceled => {
+                self.completed_at.as_deref()
+            }
+        };
+        parse_timestamp(timestamp?)
+    }
+
+    /// Estimate what this prediction cost with the given prices
+    ///
+    /// Returns `None` when the metrics or prices needed are missing; see
+    /// [`PriceTable`](crate::PriceTable) for how the estimate is made.
+    pub fn estimated_cost(&self, prices: &crate::cost::PriceTable) -> Option<f64> {
+        prices.estimate(self)
+    }
+
+    /// Rebuild a request that re-runs this prediction with the same version and input
+    ///
+    /// Inputs are added in sorted key order. A prediction without input yields
+    /// a request with no input. Returns `None` for a prediction without a
+    /// version, e.g. one created on a deployment or an official model, as the
+    /// API rejects a versionless request; re-run those on
+    /// [`model_ref`](Self::model_ref) instead.
+    pub fn to_request(&self) -> Option<CreatePredictionRequest> {
+        let version = self.version()?;
+        let mut inputs: Vec<_> = self.input.iter().flatten().collect();
+        inputs.sort_by_key(|(key, _)| *key);
+        Some(inputs.into_iter().fold(
+            CreatePredictionRequest::new(version),
+            |request, (key, value)| request.with_input(key.clone(), value.clone()),
+        ))
+    }
+
+    /// Convert into a [`TypedPrediction`], deserializing input and output once
+    pub fn into_typed<I, O>(self) -> crate::Result<TypedPrediction<I, O>>
+    where
+        I: DeserializeOwned,
+        O: DeserializeOwned,
+    {
+        let input = self
+            .input_as()?
+            .ok_or_else(|| crate::Error::invalid_input("Prediction has no input"))?;
+        let output = self.output_as()?;
+        Ok(TypedPrediction {
+            prediction: self,
+            input,
+            output,
+        })
+    }
+
+    /// Parse the raw output into `output`, leaving `output_raw` empty
+    pub fn materialize_output(&mut self) -> crate::Result<()> {
+        if let Some(raw) = self.output_raw.take() {
+            self.output = Some(serde_json::from_str(raw.get())?);
+        }
+        Ok(())
+    }
+
+    /// Build a prediction from top-level fields with the output kept as raw JSON
+    pub(crate) fn from_raw_fields(
+        mut fields: HashMap<String, Box<RawValue>>,
+    ) -> crate::Result<Self> {
+        let output = fields.remove("output");
+        let output_omitted = output.is_none();
+        let output_raw = output.filter(|raw| raw.get().trim() != "null");
+
+        // The remaining fields are small; parse them through the regular model
+        let mut rest = serde_json::Map::with_capacity(fields.len());
+        for (key, raw) in fields {
+            rest.insert(key, serde_json::from_str(raw.get())?);
+        }
+
+        let mut prediction: Self = serde_json::from_value(Value::Object(rest))?;
+        prediction.output_raw = output_raw;
+        prediction.output_omitted = output_omitted;
+        Ok(prediction)
+    }
+}
+
+/// A prediction with its input and output deserialized into concrete types.
+#[derive(Debug, Clone)]
+pub struct TypedPrediction<I, O> {
+    prediction: Prediction,
+    input: I,
+    output: Option<O>,
+}
+
+impl<I, O> TypedPrediction<I, O> {
+    /// Get the typed input
+    pub fn input(&self) -> &I {
+        &self.input
+    }
+
+    /// Get the typed output, if the prediction produced one
+    pub fn output(&self) -> Option<&O> {
+        self.output.as_ref()
+    }
+
+    /// Get the underlying prediction
+    pub fn prediction(&self) -> &Prediction {
+        &self.prediction
+    }
+
+    /// Split into the underlying prediction, input and output
+    pub fn into_parts(self) -> (Prediction, I, Option<O>) {
+        (self.prediction, self.input, self.output)
+    }
+}
+
+/// Depth-first iterator over the items of an output, see
+/// [`Prediction::outputs_iter`].
+struct OutputItems<'a> {
+    stack: Vec<std::slice::Iter<'a, Value>>,
+    single: Option<&'a Value>,
+}
+
+impl<'a> OutputItems<'a> {
+    fn new(output: Option<&'a Value>) -> Self {
+        match output {
+            Some(Value::Array(items)) => Self {
+                stack: vec![items.iter()],
+                single: None,
+            },
+            Some(Value::Null) | None => Self {
+                stack: Vec::new(),
+                single: None,
+            },
+            Some(value) => Self {
+                stack: Vec::new(),
+                single: Some(value),
+            },
+        }
+    }
+}
+
+impl<'a> Iterator for OutputItems<'a> {
+    type Item = &'a Value;
+
+    fn next(&mut self) -> Option<&'a Value> {
+        if let Some(value) = self.single.take() {
+            return Some(value);
+        }
+        loop {
+            match self.stack.last_mut()?.next() {
+                Some(Value::Array(nested)) => self.stack.push(nested.iter()),
+                Some(item) => return Some(item),
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+/// Resolve a JSON pointer whose first segment is a key of `map`.
+fn map_pointer<'a>(map: &'a HashMap<String, Value>, pointer: &str) -> Option<&'a Value> {
+    let rest = pointer.strip_prefix('/')?;
+    let (key, rest) = match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, ""),
+    };
+    let key = key.replace("~1", "/").replace("~0", "~");
+    map.get(&key)?.pointer(rest)
+}
+
+/// Deserialize a value, reporting the JSON path on failure.
+fn deserialize_with_path<'de, D, T>(deserializer: D) -> crate::Result<T>
+where
+    D: serde::Deserializer<'de, Error = serde_json::Error>,
+    T: DeserializeOwned,
+{
+    serde_path_to_error::deserialize(deserializer).map_err(|e| crate::Error::Deserialize {
+        path: e.path().to_string(),
+        source: e.into_inner(),
+    })
+}
+
+/// Time from one API timestamp to a later one, `None` if either is missing,
+/// unparseable or out of order.
+fn time_between(from: Option<&str>, to: Option<&str>) -> Option<Duration> {
+    parse_timestamp(to?)?
+        .duration_since(parse_timestamp(from?)?)
+        .ok()
 }
 
 /// Request to create a new prediction.
-#[derive(Debug, Clone, Serialize)]
+///
+/// Serializes as the API request body, without file inputs and local
+/// settings; use [`persisted`](Self::persisted) to store a pending request
+/// with them. Deserializes from either form.
+///
+/// Inputs are serialized in the order they were added through
+/// [`with_input`](Self::with_input) and the prediction builder, so the same
+/// calls give byte-identical bodies; inputs inserted into the maps directly
+/// follow in sorted key order.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(from = "RequestFields")]
 pub struct CreatePredictionRequest {
     /// The version ID of the model to run
+    ///
+    /// Not sent when empty, as for deployments, which pick the version themselves.
     pub version: String,
 
     /// Input parameters for the model
     pub input: HashMap<String, Value>,
 
     /// Optional webhook URL for notifications
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub webhook: Option<String>,
 
     /// Optional webhook URL for completion notifications
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub webhook_completed: Option<String>,
 
     /// Events to filter for webhooks
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub webhook_events_filter: Option<Vec<String>>,
 
     /// Enable streaming of output
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
 
     /// File inputs that need to be processed
-    #[serde(skip)]
     pub file_inputs: HashMap<String, FileInput>,
 
     /// File encoding strategy
-    #[serde(skip)]
     pub file_encoding_strategy: FileEncodingStrategy,
+
+    /// Accept zero-byte file inputs, which are rejected by default
+    pub allow_empty_files: bool,
+
+    /// Never upload file inputs through the Files API
+    pub no_auto_upload: bool,
+
+    /// Additional top-level request fields not modelled by this crate
+    pub extra: IndexMap<String, Value>,
+
+    /// Input and file input keys in the order they were added
+    key_order: Vec<String>,
 }
 
 impl CreatePredictionRequest {
@@ -155,12 +1104,26 @@ impl CreatePredictionRequest {
             stream: None,
             file_inputs: HashMap::new(),
             file_encoding_strategy: FileEncodingStrategy::default(),
+            allow_empty_files: false,
+            no_auto_upload: false,
+            extra: IndexMap::new(),
+            key_order: Vec::new(),
         }
     }
 
     /// Add an input parameter
     pub fn with_input(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
-        self.input.insert(key.into(), value.into());
+        self.insert_input(key.into(), value.into());
+        self
+    }
+
+    /// Add input parameters from a map, in sorted key order
+    pub fn with_inputs(mut self, inputs: HashMap<String, Value>) -> Self {
+        let mut inputs: Vec<_> = inputs.into_iter().collect();
+        inputs.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (key, value) in inputs {
+            self.insert_input(key, value);
+        }
         self
     }
 
@@ -175,4 +1138,1025 @@ impl CreatePredictionRequest {
         self.stream = Some(true);
         self
     }
+
+    /// Add a top-level request field that has no dedicated setter
+    ///
+    /// Use this for API fields the crate does not model yet. Keys that clash
+    /// with modelled fields such as `input` are rejected.
+    pub fn with_extra(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<Value>,
+    ) -> crate::Result<Self> {
+        let key = key.into();
+        if RESERVED_REQUEST_FIELDS.contains(&key.as_str()) {
+            return Err(crate::Error::invalid_input(format!(
+                "'{}' has a dedicated setter and cannot be set as an extra field",
+                key
+            )));
+        }
+        self.extra.insert(key, value.into());
+        Ok(self)
+    }
+
+    /// Insert an input, remembering its position.
+    pub(crate) fn insert_input(&mut self, key: String, value: Value) {
+        self.record_key(&key);
+        self.input.insert(key, value);
+    }
+
+    /// Insert a file input, remembering its position.
+    pub(crate) fn insert_file_input(&mut self, key: String, file: FileInput) {
+        self.record_key(&key);
+        self.file_inputs.insert(key, file);
+    }
+
+    fn record_key(&mut self, key: &str) {
+        if !self.key_order.iter().any(|existing| existing == key) {
+            self.key_order.push(key.to_string());
+        }
+    }
+
+    /// Get the entries of `map` in the order they were added.
+    pub(crate) fn ordered<'a, V>(
+        &'a self,
+        map: &'a HashMap<String, V>,
+    ) -> Vec<(&'a String, &'a V)> {
+        let mut entries: Vec<_> = self
+            .key_order
+            .iter()
+            .filter_map(|key| map.get_key_value(key))
+            .collect();
+        let mut rest: Vec<_> = map
+            .iter()
+            .filter(|(key, _)| !self.key_order.contains(key))
+            .collect();
+        rest.sort_by_key(|(key, _)| *key);
+        entries.extend(rest);
+        entries
+    }
+
+    /// Serialize the request with its file inputs and local settings.
+    ///
+    /// Use this to persist a pending request, e.g. in a job queue, and
+    /// deserialize it back into a [`CreatePredictionRequest`] to resume it
+    /// with [`PredictionBuilder::from_request`](crate::api::predictions::PredictionBuilder::from_request).
+    /// File inputs are stored as described on [`FileInput`], with bytes
+    /// inlined as base64.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use replicate_client::FileInput;
+    /// use replicate_client::models::CreatePredictionRequest;
+    ///
+    /// let mut request = CreatePredictionRequest::new("version-id").with_input("scale", 2);
+    /// request
+    ///     .file_inputs
+    ///     .insert("image".to_string(), FileInput::from_bytes(&b"png"[..]));
+    ///
+    /// let stored = serde_json::to_string(&request.persisted()).unwrap();
+    /// let resumed: CreatePredictionRequest = serde_json::from_str(&stored).unwrap();
+    /// assert_eq!(resumed.file_inputs, request.file_inputs);
+    /// ```
+    pub fn persisted(&self) -> PersistedRequest<'_> {
+        PersistedRequest { request: self }
+    }
+}
+
+/// A request without a version, e.g. for a deployment, with the given inputs
+impl From<HashMap<String, Value>> for CreatePredictionRequest {
+    fn from(inputs: HashMap<String, Value>) -> Self {
+        Self::new("").with_inputs(inputs)
+    }
+}
+
+/// Entries of a map serialized in a given order.
+struct OrderedEntries<'a, V>(Vec<(&'a String, &'a V)>);
+
+impl<V: Serialize> Serialize for OrderedEntries<'_, V> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().copied())
+    }
+}
+
+/// The API request body of a [`CreatePredictionRequest`].
+#[derive(Serialize)]
+struct RequestBody<'a> {
+    #[serde(skip_serializing_if = "str::is_empty")]
+    version: &'a str,
+    input: OrderedEntries<'a, Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    webhook: &'a Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    webhook_completed: &'a Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    webhook_events_filter: &'a Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: &'a Option<bool>,
+    #[serde(flatten)]
+    extra: &'a IndexMap<String, Value>,
+}
+
+impl<'a> From<&'a CreatePredictionRequest> for RequestBody<'a> {
+    fn from(request: &'a CreatePredictionRequest) -> Self {
+        Self {
+            version: &request.version,
+            input: OrderedEntries(request.ordered(&request.input)),
+            webhook: &request.webhook,
+            webhook_completed: &request.webhook_completed,
+            webhook_events_filter: &request.webhook_events_filter,
+            stream: &request.stream,
+            extra: &request.extra,
+        }
+    }
+}
+
+impl Serialize for CreatePredictionRequest {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        RequestBody::from(self).serialize(serializer)
+    }
+}
+
+/// Either serialized form of a [`CreatePredictionRequest`], keeping the
+/// order of the inputs.
+#[derive(Deserialize)]
+struct RequestFields {
+    #[serde(default)]
+    version: String,
+    #[serde(default)]
+    input: IndexMap<String, Value>,
+    webhook: Option<String>,
+    webhook_completed: Option<String>,
+    webhook_events_filter: Option<Vec<String>>,
+    stream: Option<bool>,
+    #[serde(default)]
+    file_inputs: IndexMap<String, FileInput>,
+    #[serde(default)]
+    file_encoding_strategy: FileEncodingStrategy,
+    #[serde(default)]
+    allow_empty_files: bool,
+    #[serde(default)]
+    no_auto_upload: bool,
+    #[serde(flatten)]
+    extra: IndexMap<String, Value>,
+}
+
+impl From<RequestFields> for CreatePredictionRequest {
+    fn from(fields: RequestFields) -> Self {
+        let mut request = Self::new(fields.version);
+        for (key, value) in fields.input {
+            request.insert_input(key, value);
+        }
+        for (key, file) in fields.file_inputs {
+            request.insert_file_input(key, file);
+        }
+        request.webhook = fields.webhook;
+        request.webhook_completed = fields.webhook_completed;
+        request.webhook_events_filter = fields.webhook_events_filter;
+        request.stream = fields.stream;
+        request.file_encoding_strategy = fields.file_encoding_strategy;
+        request.allow_empty_files = fields.allow_empty_files;
+        request.no_auto_upload = fields.no_auto_upload;
+        request.extra = fields.extra;
+        request
+    }
+}
+
+/// A [`CreatePredictionRequest`] serialized with its file inputs and local
+/// settings, from [`CreatePredictionRequest::persisted`].
+#[derive(Debug, Clone, Copy)]
+pub struct PersistedRequest<'a> {
+    request: &'a CreatePredictionRequest,
+}
+
+impl Serialize for PersistedRequest<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Fields<'a> {
+            #[serde(flatten)]
+            request: RequestBody<'a>,
+            #[serde(skip_serializing_if = "is_empty")]
+            file_inputs: OrderedEntries<'a, FileInput>,
+            file_encoding_strategy: &'a FileEncodingStrategy,
+            #[serde(skip_serializing_if = "std::ops::Not::not")]
+            allow_empty_files: bool,
+            #[serde(skip_serializing_if = "std::ops::Not::not")]
+            no_auto_upload: bool,
+        }
+
+        fn is_empty(entries: &OrderedEntries<'_, FileInput>) -> bool {
+            entries.0.is_empty()
+        }
+
+        Fields {
+            request: RequestBody::from(self.request),
+            file_inputs: OrderedEntries(self.request.ordered(&self.request.file_inputs)),
+            file_encoding_strategy: &self.request.file_encoding_strategy,
+            allow_empty_files: self.request.allow_empty_files,
+            no_auto_upload: self.request.no_auto_upload,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Request fields modelled by [`CreatePredictionRequest`].
+const RESERVED_REQUEST_FIELDS: &[&str] = &[
+    "version",
+    "input",
+    "webhook",
+    "webhook_completed",
+    "webhook_events_filter",
+    "stream",
+    "file_inputs",
+    "file_encoding_strategy",
+    "allow_empty_files",
+    "no_auto_upload",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Segment {
+        text: String,
+        start: f64,
+    }
+
+    #[test]
+    fn test_output_as_reports_path() {
+        let prediction = prediction_with_output(json!({
+            "segments": [{"text": "hi", "start": 0.0}, {"text": "there", "start": "late"}],
+        }));
+
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Transcript {
+            segments: Vec<Segment>,
+        }
+
+        match prediction.output_as::<Transcript>() {
+            Err(crate::Error::Deserialize { path, .. }) => assert_eq!(path, "segments[1].start"),
+            other => panic!("Expected a deserialize error, got {:?}", other),
+        }
+    }
+
+    fn prediction_with_output(output: Value) -> Prediction {
+        serde_json::from_value(json!({
+            "id": "p1",
+            "model": "owner/model",
+            "version": "v1",
+            "status": "succeeded",
+            "output": output,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_file_outputs_from_objects() {
+        let prediction = prediction_with_output(json!([[
+            {"url": "https://a/clip.mp4", "content_type": "video/mp4", "size": 2048},
+            {"url": "https://a/thumb.jpg", "filename": "thumb.jpg"},
+        ]]));
+        let files = prediction.file_outputs().unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].content_type.as_deref(), Some("video/mp4"));
+        assert_eq!(files[0].size, Some(2048));
+        assert_eq!(files[1].filename.as_deref(), Some("thumb.jpg"));
+    }
+
+    #[test]
+    fn test_output_pointer() {
+        let mut prediction = prediction_with_output(json!({
+            "segments": [{"text": "hi", "start": 0.0}, {"text": "there", "start": "late"}],
+            "a/b": {"m~n": 1},
+        }));
+
+        assert_eq!(
+            prediction.output_pointer("/segments/1/text"),
+            Some(&json!("there"))
+        );
+        assert_eq!(prediction.output_pointer("/a~1b/m~0n"), Some(&json!(1)));
+        assert_eq!(prediction.output_pointer("segments"), None);
+        assert_eq!(prediction.output_pointer("/segments/2"), None);
+        assert_eq!(prediction.output_pointer("/segments/01"), None);
+
+        let segment: Option<Segment> = prediction.output_pointer_as("/segments/0").unwrap();
+        assert_eq!(
+            segment,
+            Some(Segment {
+                text: "hi".into(),
+                start: 0.0
+            })
+        );
+        assert!(
+            prediction
+                .output_pointer_as::<Segment>("/missing")
+                .unwrap()
+                .is_none()
+        );
+        match prediction.output_pointer_as::<Segment>("/segments/1") {
+            Err(crate::Error::Deserialize { path, .. }) => assert_eq!(path, "start"),
+            other => panic!("Expected a deserialize error, got {:?}", other),
+        }
+
+        // Raw output mode only supports the typed lookup
+        let raw = serde_json::to_string(prediction.output.as_ref().unwrap()).unwrap();
+        prediction.output = None;
+        prediction.output_raw = Some(RawValue::from_string(raw).unwrap());
+        assert_eq!(prediction.output_pointer("/segments/0/text"), None);
+        let text: Option<String> = prediction.output_pointer_as("/segments/0/text").unwrap();
+        assert_eq!(text.as_deref(), Some("hi"));
+    }
+
+    #[test]
+    fn test_queue_run_and_total_time() {
+        let mut prediction = prediction_with_output(Value::Null);
+        prediction.created_at = Some("2024-05-01T00:00:00.000Z".to_string());
+        prediction.started_at = Some("2024-05-01T00:00:12.500Z".to_string());
+        prediction.completed_at = Some("2024-05-01T00:01:00Z".to_string());
+        assert_eq!(prediction.queue_time(), Some(Duration::from_millis(12_500)));
+        assert_eq!(prediction.run_time(), Some(Duration::from_millis(47_500)));
+        assert_eq!(prediction.total_time(), Some(Duration::from_secs(60)));
+
+        // Still queued: only the times with both ends are missing
+        prediction.started_at = None;
+        prediction.completed_at = None;
+        assert_eq!(prediction.queue_time(), None);
+        assert_eq!(prediction.run_time(), None);
+        assert_eq!(prediction.total_time(), None);
+
+        prediction.started_at = Some("not a time".to_string());
+        prediction.completed_at = Some("2023-12-31T00:00:00Z".to_string());
+        assert_eq!(prediction.queue_time(), None);
+        assert_eq!(prediction.total_time(), None);
+    }
+
+    #[test]
+    fn test_elapsed_since_start() {
+        let mut prediction = prediction_with_output(Value::Null);
+        prediction.status = PredictionStatus::Starting;
+        assert_eq!(prediction.elapsed_since_start(), None);
+
+        prediction.status = PredictionStatus::Processing;
+        prediction.started_at = Some("2024-05-01T00:00:00Z".to_string());
+        assert!(prediction.elapsed_since_start().unwrap() > Duration::from_secs(86400));
+
+        // Clock skew: a start in the future has only just happened
+        prediction.started_at = Some("2999-01-01T00:00:00Z".to_string());
+        assert_eq!(prediction.elapsed_since_start(), Some(Duration::ZERO));
+
+        prediction.started_at = Some("not a time".to_string());
+        assert_eq!(prediction.elapsed_since_start(), None);
+
+        prediction.started_at = Some("2024-05-01T00:00:00Z".to_string());
+        prediction.status = PredictionStatus::Succeeded;
+        assert_eq!(prediction.elapsed_since_start(), None);
+    }
+
+    #[test]
+    fn test_input_and_metrics_pointer() {
+        let prediction: Prediction = serde_json::from_value(json!({
+            "id": "p1",
+            "model": "owner/model",
+            "version": "v1",
+            "status": "succeeded",
+            "input": {"prompt": "hi", "sizes": [512, 768], "a/b": {"c": true}},
+            "metrics": {"predict_time": 1.5, "tokens": {"input": 12}},
+        }))
+        .unwrap();
+
+        assert_eq!(prediction.input_pointer("/prompt"), Some(&json!("hi")));
+        assert_eq!(prediction.input_pointer("/sizes/1"), Some(&json!(768)));
+        assert_eq!(prediction.input_pointer("/a~1b/c"), Some(&json!(true)));
+        assert_eq!(prediction.input_pointer(""), None);
+        assert_eq!(prediction.input_pointer("prompt"), None);
+        assert_eq!(prediction.input_pointer("/prompt/0"), None);
+        assert_eq!(
+            prediction.input_pointer_as::<Vec<u32>>("/sizes").unwrap(),
+            Some(vec![512, 768])
+        );
+        assert!(matches!(
+            prediction.input_pointer_as::<u32>("/prompt"),
+            Err(crate::Error::Deserialize { .. })
+        ));
+
+        assert_eq!(
+            prediction.metrics_pointer("/tokens/input"),
+            Some(&json!(12))
+        );
+        assert_eq!(
+            prediction
+                .metrics_pointer_as::<f64>("/predict_time")
+                .unwrap(),
+            Some(1.5)
+        );
+        assert!(
+            prediction
+                .metrics_pointer_as::<f64>("/missing")
+                .unwrap()
+                .is_none()
+        );
+        assert!(prediction.metrics_pointer_as::<String>("/tokens").is_err());
+
+        let purged: Prediction = serde_json::from_str(PURGED_PREDICTION).unwrap();
+        assert!(matches!(
+            purged.input_pointer_as::<String>("/prompt"),
+            Err(crate::Error::DataRemoved { .. })
+        ));
+    }
+
+    #[test]
+    fn test_classified_output() {
+        let prediction = prediction_with_output(json!(["Once", " upon", " a", " time"]));
+        let classified = prediction.classified_output().unwrap();
+        assert_eq!(
+            classified.concat_text().as_deref(),
+            Some("Once upon a time")
+        );
+
+        let pending = prediction_with_output(Value::Null);
+        assert!(pending.classified_output().is_none());
+    }
+
+    #[test]
+    fn test_output_typed_via_schema() {
+        let version = |output: Value| -> ModelVersion {
+            serde_json::from_value(json!({
+                "id": "v1",
+                "created_at": "2024-01-01T00:00:00Z",
+                "openapi_schema": {"components": {"schemas": {
+                    "Output": output,
+                    "Segment": {"type": "object"},
+                }}},
+            }))
+            .unwrap()
+        };
+        let images = prediction_with_output(json!(["https://a/0.png", "https://a/1.png"]));
+        let chunks = prediction_with_output(json!(["Once", " upon", " a time"]));
+
+        let files = version(json!({"type": "array", "items": {"type": "string", "format": "uri"}}));
+        assert_eq!(
+            images.output_typed_via_schema(&files).unwrap(),
+            TypedOutput::Files(vec![
+                FileOutput::new("https://a/0.png"),
+                FileOutput::new("https://a/1.png"),
+            ])
+        );
+        let iterator = version(json!({
+            "type": "array",
+            "items": {"type": "string"},
+            "x-cog-array-type": "iterator",
+            "x-cog-array-display": "concatenate",
+        }));
+        assert_eq!(
+            chunks.output_typed_via_schema(&iterator).unwrap(),
+            TypedOutput::Text("Once upon a time".into())
+        );
+        // The schema wins over the shape of the value
+        let text = version(json!({"type": "string"}));
+        assert_eq!(
+            prediction_with_output(json!("https://a/0.png"))
+                .output_typed_via_schema(&text)
+                .unwrap(),
+            TypedOutput::Text("https://a/0.png".into())
+        );
+        let segments =
+            version(json!({"type": "array", "items": {"$ref": "#/components/schemas/Segment"}}));
+        assert_eq!(
+            images.output_typed_via_schema(&segments).unwrap(),
+            TypedOutput::Json(json!(["https://a/0.png", "https://a/1.png"]))
+        );
+
+        let mut no_schema = version(Value::Null);
+        no_schema.openapi_schema = None;
+        assert_eq!(
+            chunks.output_typed_via_schema(&no_schema).unwrap(),
+            TypedOutput::Json(json!(["Once", " upon", " a time"]))
+        );
+    }
+
+    #[test]
+    fn test_outputs_iter() {
+        let items = |output: Value| {
+            let prediction = prediction_with_output(output);
+            assert_eq!(prediction.outputs_len(), prediction.outputs_iter().count());
+            prediction.outputs_iter().cloned().collect::<Vec<_>>()
+        };
+        assert_eq!(items(json!("https://a/0.png")), [json!("https://a/0.png")]);
+        assert_eq!(items(json!({"text": "hi"})), [json!({"text": "hi"})]);
+        assert_eq!(
+            items(json!(["https://a/0.png", "https://a/1.png"])),
+            [json!("https://a/0.png"), json!("https://a/1.png")]
+        );
+        assert_eq!(
+            items(json!([["a", "b"], [], [["c"]], "d"])),
+            [json!("a"), json!("b"), json!("c"), json!("d")]
+        );
+        assert!(items(Value::Null).is_empty());
+        assert!(items(json!([])).is_empty());
+
+        // Files come from the same items
+        let prediction = prediction_with_output(json!([
+            ["https://a/0.png", "https://a/1.png"],
+            {"url": "https://a/2.png", "size": 3},
+            "not a file",
+        ]));
+        let urls: Vec<_> = prediction
+            .file_outputs()
+            .unwrap()
+            .into_iter()
+            .map(|file| file.url)
+            .collect();
+        assert_eq!(
+            urls,
+            ["https://a/0.png", "https://a/1.png", "https://a/2.png"]
+        );
+    }
+
+    #[test]
+    fn test_decode_output_data_urls() {
+        let prediction = prediction_with_output(json!([
+            "data:image/png;base64,aGVsbG8=",
+            "https://replicate.delivery/out.png",
+            {"mask": "data:image/webp;base64,d29ybGQ="},
+        ]));
+
+        let decoded = prediction.decode_output_data_urls();
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded[0].0, "image/png");
+        assert_eq!(&decoded[0].1[..], b"hello");
+        assert_eq!(decoded[1].0, "image/webp");
+        assert_eq!(&decoded[1].1[..], b"world");
+
+        let hosted = prediction_with_output(json!("https://replicate.delivery/out.png"));
+        assert!(hosted.decode_output_data_urls().is_empty());
+    }
+
+    #[test]
+    fn test_input_file_urls() {
+        let prediction: Prediction = serde_json::from_value(json!({
+            "id": "p1",
+            "model": "owner/model",
+            "version": "v1",
+            "status": "succeeded",
+            "input": {
+                "prompt": "https is not a URL on its own",
+                "mask": "data:image/png;base64,aGVsbG8=",
+                "image": "https://replicate.delivery/in/cat.png",
+                "references": ["http://example.com/a.jpg", 3, {"url": "https://example.com/b.jpg"}],
+                "path": "/tmp/local.png",
+                "steps": 30,
+            },
+        }))
+        .unwrap();
+
+        let urls: Vec<String> = prediction
+            .input_file_urls()
+            .into_iter()
+            .map(|file| file.url)
+            .collect();
+        assert_eq!(
+            urls,
+            [
+                "https://replicate.delivery/in/cat.png",
+                "data:image/png;base64,aGVsbG8=",
+                "http://example.com/a.jpg",
+                "https://example.com/b.jpg",
+            ]
+        );
+
+        let purged: Prediction = serde_json::from_str(PURGED_PREDICTION).unwrap();
+        assert!(purged.input_file_urls().is_empty());
+    }
+
+    #[test]
+    fn test_unknown_fields_round_trip() {
+        let payload = json!({
+            "id": "p1",
+            "model": "owner/model",
+            "version": "v1",
+            "status": "succeeded",
+            "output": "done",
+            "source": "web",
+            "deployment": {"owner": "acme", "name": "prod"},
+        });
+
+        let prediction: Prediction = serde_json::from_value(payload.clone()).unwrap();
+        assert_eq!(prediction.extra["source"], json!("web"));
+        assert_eq!(prediction.extra["deployment"]["name"], json!("prod"));
+        assert!(!prediction.extra.contains_key("status"));
+
+        let serialized = serde_json::to_value(&prediction).unwrap();
+        assert_eq!(serialized["source"], json!("web"));
+        assert_eq!(serialized["deployment"], payload["deployment"]);
+        assert_eq!(serialized["output"], json!("done"));
+    }
+
+    #[test]
+    fn test_unknown_fields_in_raw_mode() {
+        let fields: HashMap<String, Box<RawValue>> = serde_json::from_str(
+            r#"{"id": "p1", "model": "o/m", "version": "v1", "status": "succeeded", "output": [1], "source": "api"}"#,
+        )
+        .unwrap();
+
+        let prediction = Prediction::from_raw_fields(fields).unwrap();
+        assert_eq!(prediction.extra["source"], json!("api"));
+        assert!(!prediction.extra.contains_key("output"));
+    }
+
+    /// A prediction as returned by the API after its data was purged.
+    const PURGED_PREDICTION: &str = r#"{
+        "id": "old-1",
+        "model": "owner/model",
+        "version": "v1",
+        "status": "succeeded",
+        "input": null,
+        "output": null,
+        "logs": "",
+        "error": null,
+        "created_at": "2023-01-01T00:00:00.000Z",
+        "completed_at": "2023-01-01T00:00:05.000Z",
+        "data_removed": true
+    }"#;
+
+    #[test]
+    fn test_purged_prediction() {
+        let prediction: Prediction = serde_json::from_str(PURGED_PREDICTION).unwrap();
+        assert_eq!(prediction.data_removed, Some(true));
+        assert!(!prediction.has_data());
+        assert!(prediction.is_successful());
+        assert!(!prediction.extra.contains_key("data_removed"));
+
+        assert!(matches!(
+            prediction.output_as::<Vec<String>>(),
+            Err(crate::Error::DataRemoved { prediction_id }) if prediction_id == "old-1"
+        ));
+        assert!(matches!(
+            prediction.input_as::<Value>(),
+            Err(crate::Error::DataRemoved { .. })
+        ));
+        assert!(matches!(
+            prediction.file_outputs(),
+            Err(crate::Error::DataRemoved { .. })
+        ));
+
+        let serialized = serde_json::to_value(&prediction).unwrap();
+        assert_eq!(serialized["data_removed"], json!(true));
+
+        // Raw output mode keeps the flag too
+        let fields: HashMap<String, Box<RawValue>> =
+            serde_json::from_str(PURGED_PREDICTION).unwrap();
+        let raw = Prediction::from_raw_fields(fields).unwrap();
+        assert!(!raw.has_data());
+        assert!(raw.output_as::<Value>().is_err());
+    }
+
+    #[test]
+    fn test_data_present() {
+        let mut prediction = prediction_with_output(json!([
+            "https://replicate.delivery/a.png",
+            "https://replicate.delivery/b.png"
+        ]));
+        assert_eq!(prediction.data_removed, None);
+        assert!(prediction.has_data());
+        assert_eq!(prediction.file_outputs().unwrap().len(), 2);
+
+        prediction.data_removed = Some(false);
+        assert!(prediction.has_data());
+        assert!(prediction.output_as::<Vec<String>>().unwrap().is_some());
+        assert_eq!(
+            serde_json::to_value(&prediction).unwrap()["data_removed"],
+            json!(false)
+        );
+    }
+
+    #[test]
+    fn test_minimal_urls() {
+        let base = json!({
+            "id": "p1",
+            "model": "owner/model",
+            "version": "v1",
+            "status": "succeeded",
+        });
+        let with_urls = |urls: Value| -> Prediction {
+            let mut payload = base.clone();
+            payload["urls"] = urls;
+            serde_json::from_value(payload).unwrap()
+        };
+
+        let empty = with_urls(json!({}));
+        assert!(empty.urls.is_some());
+        assert_eq!(empty.cancel_url(), None);
+        assert_eq!(empty.stream_url(), None);
+        assert!(!empty.supports_streaming());
+
+        let webhook = with_urls(json!({
+            "get": "https://api.replicate.com/v1/predictions/p1",
+            "web": "https://replicate.com/p/p1",
+        }));
+        assert_eq!(webhook.web_url(), Some("https://replicate.com/p/p1"));
+        assert_eq!(webhook.cancel_url(), None);
+
+        let extra = with_urls(json!({
+            "get": "https://api.replicate.com/v1/predictions/p1",
+            "cancel": "https://api.replicate.com/v1/predictions/p1/cancel",
+            "stream": "https://stream.replicate.com/v1/files/abc",
+            "logs": "https://api.replicate.com/v1/predictions/p1/logs",
+        }));
+        assert_eq!(
+            extra.cancel_url(),
+            Some("https://api.replicate.com/v1/predictions/p1/cancel")
+        );
+        assert_eq!(
+            extra.stream_url(),
+            Some("https://stream.replicate.com/v1/files/abc")
+        );
+        assert!(extra.supports_streaming());
+        let urls = extra.urls.as_ref().unwrap();
+        assert_eq!(
+            urls.other["logs"],
+            json!("https://api.replicate.com/v1/predictions/p1/logs")
+        );
+        assert_eq!(
+            serde_json::to_value(urls).unwrap()["logs"],
+            urls.other["logs"]
+        );
+
+        let missing: Prediction = serde_json::from_value(base).unwrap();
+        assert_eq!(missing.web_url(), None);
+    }
+
+    #[test]
+    fn test_to_request() {
+        let prediction: Prediction = serde_json::from_value(json!({
+            "id": "p1",
+            "model": "stability-ai/sdxl",
+            "version": "v1",
+            "status": "succeeded",
+            "input": {"width": 1024, "prompt": "A lighthouse"},
+        }))
+        .unwrap();
+
+        let request = prediction
+            .to_request()
+            .unwrap()
+            .with_input("prompt", "A castle");
+        assert_eq!(request.version, "v1");
+        assert_eq!(
+            serde_json::to_string(&request).unwrap(),
+            r#"{"version":"v1","input":{"prompt":"A castle","width":1024}}"#
+        );
+
+        let mut without_input = prediction.clone();
+        without_input.input = None;
+        let request = without_input.to_request().unwrap();
+        assert!(request.input.is_empty());
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({"version": "v1", "input": {}})
+        );
+    }
+
+    #[test]
+    fn test_deployment_predictions_without_version() {
+        // As returned when creating on a deployment: no version, or a null one
+        let created: Prediction = serde_json::from_value(json!({
+            "id": "p1",
+            "model": "acme/flux-prod",
+            "status": "starting",
+            "input": {"prompt": "A lighthouse"},
+            "logs": "",
+            "urls": {"get": "https://api.replicate.com/v1/predictions/p1"},
+        }))
+        .unwrap();
+        assert_eq!(created.version, None);
+        assert_eq!(created.version(), None);
+        assert_eq!(
+            created.model_ref(),
+            Some(ModelRef::new("acme", "flux-prod"))
+        );
+        assert!(created.to_request().is_none());
+
+        let fetched: Prediction = serde_json::from_value(json!({
+            "id": "p1",
+            "model": "acme/flux-prod",
+            "version": null,
+            "status": "succeeded",
+        }))
+        .unwrap();
+        assert_eq!(fetched.version(), None);
+
+        let pinned: Prediction = serde_json::from_value(json!({
+            "id": "p2",
+            "model": "stability-ai/sdxl",
+            "version": "v1",
+            "status": "succeeded",
+        }))
+        .unwrap();
+        assert_eq!(pinned.version(), Some("v1"));
+        assert_eq!(
+            pinned.model_ref().unwrap().to_string(),
+            "stability-ai/sdxl:v1"
+        );
+
+        let mut unknown_model = pinned.clone();
+        unknown_model.model = String::new();
+        assert_eq!(unknown_model.model_ref(), None);
+    }
+
+    #[test]
+    fn test_request_extra_fields() {
+        let request = CreatePredictionRequest::new("v1")
+            .with_input("prompt", "hi")
+            .with_extra("metadata", json!({"audit_id": "a-42"}))
+            .unwrap()
+            .with_extra("priority", 1)
+            .unwrap();
+        assert_eq!(
+            serde_json::to_string(&request).unwrap(),
+            r#"{"version":"v1","input":{"prompt":"hi"},"metadata":{"audit_id":"a-42"},"priority":1}"#
+        );
+
+        let clash = CreatePredictionRequest::new("v1").with_extra("input", json!({}));
+        assert!(matches!(clash, Err(crate::Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_partial_output_and_delta() {
+        let first = prediction_with_output(json!(["Once"]));
+        let second = prediction_with_output(json!(["Once", " upon", 3]));
+        let switched = prediction_with_output(json!("Once upon a time"));
+        let rewritten = prediction_with_output(json!(["A new start"]));
+        let structured = prediction_with_output(json!({"text": "hi"}));
+
+        assert_eq!(second.partial_output_text().as_deref(), Some("Once upon"));
+        assert_eq!(structured.partial_output_text(), None);
+
+        assert_eq!(first.output_delta_since(None).as_deref(), Some("Once"));
+        assert_eq!(
+            second.output_delta_since(Some(&first)).as_deref(),
+            Some(" upon")
+        );
+        assert_eq!(second.output_delta_since(Some(&second)), None);
+        assert_eq!(
+            switched.output_delta_since(Some(&second)).as_deref(),
+            Some(" a time")
+        );
+        assert_eq!(
+            rewritten.output_delta_since(Some(&switched)).as_deref(),
+            Some("A new start")
+        );
+        assert_eq!(structured.output_delta_since(Some(&first)), None);
+    }
+
+    #[tokio::test]
+    async fn test_save_outputs_to_dir() {
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        for (route, body) in [("/a/out.png", "first"), ("/b/out.png", "second")] {
+            Mock::given(method("GET"))
+                .and(path(route))
+                .and(header("authorization", "Token test-token"))
+                .respond_with(ResponseTemplate::new(200).set_body_string(body))
+                .mount(&server)
+                .await;
+        }
+
+        let http = HttpClient::with_base_url("test-token", server.uri()).unwrap();
+        let prediction = prediction_with_output(json!([
+            format!("{}/a/out.png", server.uri()),
+            format!("{}/b/out.png", server.uri()),
+            "data:image/webp;base64,aGVsbG8=",
+        ]));
+
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("nested/outputs");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("out-1.png"), b"existing").unwrap();
+
+        let paths = prediction.save_outputs_to_dir(&dir, &http).await.unwrap();
+        assert_eq!(
+            paths,
+            [
+                dir.join("out.png"),
+                dir.join("out-2.png"),
+                dir.join("output-2.webp")
+            ]
+        );
+        assert_eq!(std::fs::read(&paths[0]).unwrap(), b"first");
+        assert_eq!(std::fs::read(&paths[1]).unwrap(), b"second");
+        assert_eq!(std::fs::read(&paths[2]).unwrap(), b"hello");
+        assert_eq!(std::fs::read(dir.join("out-1.png")).unwrap(), b"existing");
+    }
+
+    #[tokio::test]
+    async fn test_save_outputs_creates_dir() {
+        let http = HttpClient::new("test-token").unwrap();
+        let prediction = prediction_with_output(json!("data:image/png;base64,aGk="));
+
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("created");
+        let paths = prediction.save_outputs_to_dir(&dir, &http).await.unwrap();
+        assert_eq!(paths, [dir.join("output-0.png")]);
+
+        let text = prediction_with_output(json!("just text"));
+        assert!(
+            text.save_outputs_to_dir(&dir, &http)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_save_outputs_with_manifest() {
+        let http = HttpClient::new("test-token").unwrap();
+        let mut prediction = prediction_with_output(json!([
+            "data:image/png;base64,aGk=",
+            "data:audio/wav;base64,aGk=",
+        ]));
+        prediction.id = "p1".to_string();
+
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = prediction
+            .save_outputs_with_manifest(dir.path(), &http)
+            .await
+            .unwrap();
+        let paths: Vec<_> = manifest
+            .artifacts()
+            .map(|artifact| artifact.path.clone().unwrap())
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                dir.path().join("output-0.png"),
+                dir.path().join("output-1.wav")
+            ]
+        );
+        let content_types: Vec<_> = manifest
+            .artifacts()
+            .map(|artifact| artifact.content_type.as_deref().unwrap())
+            .collect();
+        assert_eq!(content_types, ["image/png", "audio/wav"]);
+
+        let written: Manifest =
+            serde_json::from_slice(&std::fs::read(dir.path().join("p1.manifest.json")).unwrap())
+                .unwrap();
+        assert_eq!(written, manifest);
+    }
+
+    /// A prediction as returned by `GET /v1/predictions`.
+    const LISTED_PREDICTION: &str = r#"{
+        "id": "listed-1",
+        "model": "owner/model",
+        "version": "v1",
+        "status": "succeeded",
+        "source": "api",
+        "urls": {"get": "https://api.replicate.com/v1/predictions/listed-1"},
+        "created_at": "2024-05-01T00:00:00.000Z",
+        "completed_at": "2024-05-01T00:00:03.000Z"
+    }"#;
+
+    #[test]
+    fn test_partial_record_detection() {
+        let prediction: Prediction = serde_json::from_str(LISTED_PREDICTION).unwrap();
+        assert!(prediction.is_partial);
+        assert!(prediction.output_omitted);
+        assert!(matches!(
+            prediction.output_as::<Value>(),
+            Err(crate::Error::PartialRecord { prediction_id }) if prediction_id == "listed-1"
+        ));
+        assert!(matches!(
+            prediction.file_outputs(),
+            Err(crate::Error::PartialRecord { .. })
+        ));
+        assert!(matches!(
+            prediction.input_as::<Value>(),
+            Err(crate::Error::PartialRecord { .. })
+        ));
+        let purged: Prediction = serde_json::from_str(PURGED_PREDICTION).unwrap();
+        assert!(!purged.is_partial);
+
+        // A listed record with a null output has no output, not a missing one
+        let mut listed: Value = serde_json::from_str(LISTED_PREDICTION).unwrap();
+        listed["output"] = Value::Null;
+        let prediction: Prediction = serde_json::from_value(listed.clone()).unwrap();
+        assert!(prediction.is_partial && !prediction.output_omitted);
+        assert_eq!(prediction.output_as::<Value>().unwrap(), None);
+
+        listed["output"] = json!(["https://example.com/cat.png"]);
+        listed["input"] = json!({"prompt": "a cat"});
+        listed["logs"] = Value::Null;
+        let prediction: Prediction = serde_json::from_value(listed).unwrap();
+        assert!(!prediction.is_partial);
+        assert_eq!(prediction.logs, None);
+        assert_eq!(prediction.input.unwrap()["prompt"], "a cat");
+        assert_eq!(prediction.output.unwrap()[0], "https://example.com/cat.png");
+        assert!(prediction.extra.contains_key("source"));
+    }
 }
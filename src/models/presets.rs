@@ -0,0 +1,465 @@
+//! Typed inputs for popular model families.
+//!
+//! Each preset knows the input names of a model family, so callers don't have
+//! to look them up. Only the essential inputs are required; everything else is
+//! omitted unless set, leaving the model's defaults in place. Presets carry no
+//! model versions: pass any compatible model reference to
+//! [`Client::create_prediction`](crate::Client::create_prediction) and add the
+//! preset with [`PredictionBuilder::inputs_from`] or
+//! [`PredictionBuilder::inputs`] via [`to_inputs`](SdxlPrediction::to_inputs).
+//!
+//! ```no_run
+//! # use replicate_client::Client;
+//! use replicate_client::models::presets::SdxlPrediction;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = Client::new("your-api-token")?;
+//!
+//! let input = SdxlPrediction::new("an astronaut riding a horse")
+//!     .negative_prompt("blurry")
+//!     .size(1024, 768);
+//! let prediction = client
+//!     .create_prediction("stability-ai/sdxl:version-id")
+//!     .inputs_from(&input)?
+//!     .send()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`PredictionBuilder::inputs_from`]: crate::api::predictions::PredictionBuilder::inputs_from
+//! [`PredictionBuilder::inputs`]: crate::api::predictions::PredictionBuilder::inputs
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Serialize a preset into a map of model inputs.
+fn to_inputs<T: Serialize>(preset: &T) -> HashMap<String, Value> {
+    match serde_json::to_value(preset) {
+        Ok(Value::Object(fields)) => fields.into_iter().collect(),
+        _ => HashMap::new(),
+    }
+}
+
+/// Inputs for Stable Diffusion XL and compatible text-to-image models.
+///
+/// Targets [stability-ai/sdxl](https://replicate.com/stability-ai/sdxl) and
+/// its fine-tunes.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SdxlPrediction {
+    /// Description of the image to generate
+    pub prompt: String,
+    /// Things the image should not contain
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub negative_prompt: Option<String>,
+    /// Image width in pixels
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    /// Image height in pixels
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    /// Number of images to generate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_outputs: Option<u32>,
+    /// Number of denoising steps
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_inference_steps: Option<u32>,
+    /// Scale for classifier-free guidance
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guidance_scale: Option<f64>,
+    /// Random seed, for reproducible images
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+impl SdxlPrediction {
+    /// Create inputs with only a prompt.
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            negative_prompt: None,
+            width: None,
+            height: None,
+            num_outputs: None,
+            num_inference_steps: None,
+            guidance_scale: None,
+            seed: None,
+        }
+    }
+
+    /// Set things the image should not contain.
+    pub fn negative_prompt(mut self, negative_prompt: impl Into<String>) -> Self {
+        self.negative_prompt = Some(negative_prompt.into());
+        self
+    }
+
+    /// Set the image width and height in pixels.
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.width = Some(width);
+        self.height = Some(height);
+        self
+    }
+
+    /// Set the number of images to generate.
+    pub fn num_outputs(mut self, num_outputs: u32) -> Self {
+        self.num_outputs = Some(num_outputs);
+        self
+    }
+
+    /// Set the number of denoising steps.
+    pub fn num_inference_steps(mut self, steps: u32) -> Self {
+        self.num_inference_steps = Some(steps);
+        self
+    }
+
+    /// Set the scale for classifier-free guidance.
+    pub fn guidance_scale(mut self, scale: f64) -> Self {
+        self.guidance_scale = Some(scale);
+        self
+    }
+
+    /// Set the random seed.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Get the inputs as a map from input name to value.
+    pub fn to_inputs(&self) -> HashMap<String, Value> {
+        to_inputs(self)
+    }
+}
+
+/// Inputs for FLUX text-to-image models.
+///
+/// Targets [black-forest-labs/flux-schnell](https://replicate.com/black-forest-labs/flux-schnell)
+/// and [black-forest-labs/flux-dev](https://replicate.com/black-forest-labs/flux-dev).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FluxPrediction {
+    /// Description of the image to generate
+    pub prompt: String,
+    /// Aspect ratio such as `16:9`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aspect_ratio: Option<String>,
+    /// Number of images to generate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_outputs: Option<u32>,
+    /// Image format such as `webp` or `png`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_format: Option<String>,
+    /// Random seed, for reproducible images
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+impl FluxPrediction {
+    /// Create inputs with only a prompt.
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            aspect_ratio: None,
+            num_outputs: None,
+            output_format: None,
+            seed: None,
+        }
+    }
+
+    /// Set the aspect ratio, e.g. `16:9`.
+    pub fn aspect_ratio(mut self, aspect_ratio: impl Into<String>) -> Self {
+        self.aspect_ratio = Some(aspect_ratio.into());
+        self
+    }
+
+    /// Set the number of images to generate.
+    pub fn num_outputs(mut self, num_outputs: u32) -> Self {
+        self.num_outputs = Some(num_outputs);
+        self
+    }
+
+    /// Set the image format, e.g. `png`.
+    pub fn output_format(mut self, output_format: impl Into<String>) -> Self {
+        self.output_format = Some(output_format.into());
+        self
+    }
+
+    /// Set the random seed.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Get the inputs as a map from input name to value.
+    pub fn to_inputs(&self) -> HashMap<String, Value> {
+        to_inputs(self)
+    }
+}
+
+/// Author of a [`ChatMessage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatRole {
+    /// Instructions for the model
+    System,
+    /// A message from the user
+    User,
+    /// A previous reply of the model
+    Assistant,
+}
+
+/// One message of a chat conversation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatMessage {
+    /// Author of the message
+    pub role: ChatRole,
+    /// Text of the message
+    pub content: String,
+}
+
+impl ChatMessage {
+    /// Create a system message.
+    pub fn system(content: impl Into<String>) -> Self {
+        Self {
+            role: ChatRole::System,
+            content: content.into(),
+        }
+    }
+
+    /// Create a user message.
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: ChatRole::User,
+            content: content.into(),
+        }
+    }
+
+    /// Create an assistant message.
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self {
+            role: ChatRole::Assistant,
+            content: content.into(),
+        }
+    }
+}
+
+/// Inputs for LLaMA-style chat models.
+///
+/// Targets [meta/meta-llama-3-8b-instruct](https://replicate.com/meta/meta-llama-3-8b-instruct),
+/// [meta/meta-llama-3-70b-instruct](https://replicate.com/meta/meta-llama-3-70b-instruct)
+/// and other models taking `prompt` and `system_prompt`. These models take a
+/// single prompt rather than a message list, so the conversation is flattened:
+/// system messages are joined into `system_prompt`, a lone user message
+/// becomes the prompt as is, and longer conversations become a transcript of
+/// `User:` and `Assistant:` turns.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChatPrediction {
+    /// The conversation flattened into a single prompt
+    pub prompt: String,
+    /// Instructions for the model
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    /// Maximum number of tokens to generate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_new_tokens: Option<u32>,
+    /// Sampling temperature
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    /// Nucleus sampling probability
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+}
+
+impl ChatPrediction {
+    /// Create inputs from a conversation.
+    pub fn new(messages: impl IntoIterator<Item = ChatMessage>) -> Self {
+        let (system, turns): (Vec<_>, Vec<_>) = messages
+            .into_iter()
+            .partition(|message| message.role == ChatRole::System);
+
+        let system_prompt = (!system.is_empty()).then(|| {
+            system
+                .into_iter()
+                .map(|message| message.content)
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        });
+        let prompt = match turns.as_slice() {
+            [only] if only.role == ChatRole::User => only.content.clone(),
+            _ => turns
+                .iter()
+                .map(|message| {
+                    let speaker = match message.role {
+                        ChatRole::Assistant => "Assistant",
+                        _ => "User",
+                    };
+                    format!("{}: {}", speaker, message.content)
+                })
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+        };
+
+        Self {
+            prompt,
+            system_prompt,
+            max_new_tokens: None,
+            temperature: None,
+            top_p: None,
+        }
+    }
+
+    /// Set the maximum number of tokens to generate.
+    pub fn max_new_tokens(mut self, max_new_tokens: u32) -> Self {
+        self.max_new_tokens = Some(max_new_tokens);
+        self
+    }
+
+    /// Set the sampling temperature.
+    pub fn temperature(mut self, temperature: f64) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// Set the nucleus sampling probability.
+    pub fn top_p(mut self, top_p: f64) -> Self {
+        self.top_p = Some(top_p);
+        self
+    }
+
+    /// Get the inputs as a map from input name to value.
+    pub fn to_inputs(&self) -> HashMap<String, Value> {
+        to_inputs(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sdxl_serialization() {
+        let minimal = SdxlPrediction::new("a red fox");
+        assert_eq!(
+            serde_json::to_value(&minimal).unwrap(),
+            json!({"prompt": "a red fox"})
+        );
+
+        let full = minimal
+            .negative_prompt("blurry")
+            .size(1024, 768)
+            .num_outputs(2)
+            .num_inference_steps(30)
+            .guidance_scale(7.5)
+            .seed(42);
+        assert_eq!(
+            serde_json::to_value(&full).unwrap(),
+            json!({
+                "prompt": "a red fox",
+                "negative_prompt": "blurry",
+                "width": 1024,
+                "height": 768,
+                "num_outputs": 2,
+                "num_inference_steps": 30,
+                "guidance_scale": 7.5,
+                "seed": 42,
+            })
+        );
+        assert_eq!(full.to_inputs()["width"], json!(1024));
+        assert_eq!(full.to_inputs().len(), 8);
+    }
+
+    #[test]
+    fn test_flux_serialization() {
+        assert_eq!(
+            FluxPrediction::new("a lighthouse").to_inputs(),
+            HashMap::from([("prompt".to_string(), json!("a lighthouse"))])
+        );
+
+        let full = FluxPrediction::new("a lighthouse")
+            .aspect_ratio("16:9")
+            .num_outputs(4)
+            .output_format("png")
+            .seed(7);
+        assert_eq!(
+            serde_json::to_value(&full).unwrap(),
+            json!({
+                "prompt": "a lighthouse",
+                "aspect_ratio": "16:9",
+                "num_outputs": 4,
+                "output_format": "png",
+                "seed": 7,
+            })
+        );
+    }
+
+    #[test]
+    fn test_chat_serialization() {
+        let single = ChatPrediction::new([ChatMessage::user("Hello!")]);
+        assert_eq!(
+            serde_json::to_value(&single).unwrap(),
+            json!({"prompt": "Hello!"})
+        );
+
+        let conversation = ChatPrediction::new([
+            ChatMessage::system("Be brief."),
+            ChatMessage::user("What is Rust?"),
+            ChatMessage::assistant("A systems language."),
+            ChatMessage::system("Answer in English."),
+            ChatMessage::user("Who made it?"),
+        ])
+        .max_new_tokens(128)
+        .temperature(0.2)
+        .top_p(0.9);
+        assert_eq!(
+            serde_json::to_value(&conversation).unwrap(),
+            json!({
+                "prompt": "User: What is Rust?\n\nAssistant: A systems language.\n\nUser: Who made it?",
+                "system_prompt": "Be brief.\n\nAnswer in English.",
+                "max_new_tokens": 128,
+                "temperature": 0.2,
+                "top_p": 0.9,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_presets_plug_into_builder() {
+        use crate::Client;
+        use wiremock::matchers::{body_json, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/predictions"))
+            .and(body_json(json!({
+                "version": "v1",
+                "input": {"prompt": "Hi", "system_prompt": "Be brief.", "max_new_tokens": 64},
+            })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "id": "p1",
+                "model": "meta/meta-llama-3-8b-instruct",
+                "version": "v1",
+                "status": "starting",
+            })))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let client = Client::with_base_url("test-token", server.uri()).unwrap();
+        let preset =
+            ChatPrediction::new([ChatMessage::system("Be brief."), ChatMessage::user("Hi")])
+                .max_new_tokens(64);
+        client
+            .create_prediction("v1")
+            .inputs_from(&preset)
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+        client
+            .create_prediction("v1")
+            .inputs(preset.to_inputs())
+            .send()
+            .await
+            .unwrap();
+    }
+}
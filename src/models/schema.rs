@@ -0,0 +1,434 @@
+//! Model input schemas and schema-validated inputs.
+
+use indexmap::IndexMap;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+use crate::error::{Error, Result};
+
+/// Schema of a single model input.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PropertySchema {
+    /// JSON schema type (`string`, `integer`, `number`, `boolean`, `array`, `object`)
+    pub kind: Option<String>,
+    /// Format hint such as `uri`
+    pub format: Option<String>,
+    /// Allowed values, if the input is an enumeration
+    pub allowed: Option<Vec<Value>>,
+    /// Inclusive lower bound for numeric inputs
+    pub minimum: Option<f64>,
+    /// Inclusive upper bound for numeric inputs
+    pub maximum: Option<f64>,
+    /// Value used by the model when the input is not set
+    pub default: Option<Value>,
+    /// Human-readable description
+    pub description: Option<String>,
+}
+
+impl PropertySchema {
+    /// Check a value against this schema.
+    pub fn validate(&self, name: &str, value: &Value) -> Result<()> {
+        if let Some(kind) = &self.kind
+            && !matches_kind(kind, value)
+        {
+            return Err(Error::invalid_input(format!(
+                "Input '{}' must be of type {}, got {}",
+                name, kind, value
+            )));
+        }
+
+        if let Some(allowed) = &self.allowed
+            && !allowed
+                .iter()
+                .any(|candidate| values_equal(candidate, value))
+        {
+            let allowed: Vec<String> = allowed.iter().map(Value::to_string).collect();
+            return Err(Error::invalid_input(format!(
+                "Input '{}' must be one of {}, got {}",
+                name,
+                allowed.join(", "),
+                value
+            )));
+        }
+
+        if let Some(number) = value.as_f64() {
+            if let Some(minimum) = self.minimum
+                && number < minimum
+            {
+                return Err(Error::invalid_input(format!(
+                    "Input '{}' must be at least {}, got {}",
+                    name, minimum, value
+                )));
+            }
+            if let Some(maximum) = self.maximum
+                && number > maximum
+            {
+                return Err(Error::invalid_input(format!(
+                    "Input '{}' must be at most {}, got {}",
+                    name, maximum, value
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Build a property schema from its JSON form, resolving `allOf` references.
+    fn from_json(property: &Map<String, Value>, components: Option<&Map<String, Value>>) -> Self {
+        let mut merged = Map::new();
+        let parts = property.get("allOf").and_then(Value::as_array);
+        for part in parts.into_iter().flatten().filter_map(Value::as_object) {
+            if let Some(resolved) = resolve(part, components) {
+                merged.extend(resolved.clone());
+            }
+        }
+        if property.contains_key("$ref")
+            && let Some(resolved) = resolve(property, components)
+        {
+            merged.extend(resolved.clone());
+        }
+        // Keys on the property itself take precedence over referenced schemas
+        merged.extend(property.clone());
+
+        Self {
+            kind: merged
+                .get("type")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            format: merged
+                .get("format")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            allowed: merged.get("enum").and_then(Value::as_array).cloned(),
+            minimum: merged.get("minimum").and_then(Value::as_f64),
+            maximum: merged.get("maximum").and_then(Value::as_f64),
+            default: merged.get("default").cloned(),
+            description: merged
+                .get("description")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+        }
+    }
+}
+
+/// Input schema of a model version.
+///
+/// Properties are kept in the model's preferred order (`x-order`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InputSchema {
+    properties: IndexMap<String, PropertySchema>,
+    required: Vec<String>,
+}
+
+impl InputSchema {
+    /// Extract the input schema from a model version's OpenAPI schema.
+    pub fn from_openapi(openapi: &Value) -> Result<Self> {
+        let components = openapi
+            .pointer("/components/schemas")
+            .and_then(Value::as_object);
+        let input = components
+            .and_then(|schemas| schemas.get("Input"))
+            .and_then(Value::as_object)
+            .ok_or_else(|| Error::invalid_input("OpenAPI schema has no Input component"))?;
+
+        let mut properties: Vec<(i64, String, PropertySchema)> = input
+            .get("properties")
+            .and_then(Value::as_object)
+            .map(|properties| {
+                properties
+                    .iter()
+                    .filter_map(|(name, property)| {
+                        let property = property.as_object()?;
+                        let order = property
+                            .get("x-order")
+                            .and_then(Value::as_i64)
+                            .unwrap_or(i64::MAX);
+                        Some((
+                            order,
+                            name.clone(),
+                            PropertySchema::from_json(property, components),
+                        ))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        properties.sort_by(|a, b| (a.0, &a.1).cmp(&(b.0, &b.1)));
+
+        let required = input
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|required| {
+                required
+                    .iter()
+                    .filter_map(|name| name.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            properties: properties
+                .into_iter()
+                .map(|(_, name, property)| (name, property))
+                .collect(),
+            required,
+        })
+    }
+
+    /// Get all input properties in order.
+    pub fn properties(&self) -> &IndexMap<String, PropertySchema> {
+        &self.properties
+    }
+
+    /// Get the schema of a single input.
+    pub fn property(&self, name: &str) -> Option<&PropertySchema> {
+        self.properties.get(name)
+    }
+
+    /// Names of the required inputs.
+    pub fn required(&self) -> &[String] {
+        &self.required
+    }
+
+    /// Check if an input is required.
+    pub fn is_required(&self, name: &str) -> bool {
+        self.required.iter().any(|required| required == name)
+    }
+}
+
+/// Inputs built against a model's [`InputSchema`], validated as they are set.
+///
+/// # Examples
+///
+/// ```
+/// use replicate_client::{DynamicInput, InputSchema};
+/// use serde_json::json;
+///
+/// # fn main() -> replicate_client::Result<()> {
+/// let schema = InputSchema::from_openapi(&json!({
+///     "components": {"schemas": {"Input": {
+///         "required": ["prompt"],
+///         "properties": {
+///             "prompt": {"type": "string"},
+///             "steps": {"type": "integer", "minimum": 1, "maximum": 500, "default": 50}
+///         }
+///     }}}
+/// }))?;
+///
+/// let mut input = DynamicInput::for_schema(&schema);
+/// input.set("prompt", "hi")?;
+/// assert!(input.set("steps", 1000).is_err());
+///
+/// let values = input.finish()?;
+/// assert_eq!(values["steps"], 50);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DynamicInput {
+    schema: InputSchema,
+    values: IndexMap<String, Value>,
+}
+
+impl DynamicInput {
+    /// Start building inputs for a schema.
+    pub fn for_schema(schema: &InputSchema) -> Self {
+        Self {
+            schema: schema.clone(),
+            values: IndexMap::new(),
+        }
+    }
+
+    /// Get the schema the inputs are validated against.
+    pub fn schema(&self) -> &InputSchema {
+        &self.schema
+    }
+
+    /// Set an input, validating it against the schema.
+    ///
+    /// Unknown inputs, type mismatches, values outside an enumeration and
+    /// out-of-range numbers are rejected with [`Error::InvalidInput`].
+    pub fn set(&mut self, key: &str, value: impl Into<Value>) -> Result<&mut Self> {
+        let property = self
+            .schema
+            .property(key)
+            .ok_or_else(|| Error::invalid_input(format!("Unknown input '{}'", key)))?;
+        let value = value.into();
+        property.validate(key, &value)?;
+        self.values.insert(key.to_string(), value);
+        Ok(self)
+    }
+
+    /// Get the value set for an input.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.values.get(key)
+    }
+
+    /// Names of required inputs that have not been set.
+    pub fn missing_required(&self) -> Vec<&str> {
+        self.schema
+            .required()
+            .iter()
+            .filter(|name| !self.values.contains_key(name.as_str()))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Finish building and return the inputs, with schema defaults filled in
+    /// for unset optional inputs.
+    ///
+    /// Fails if any required input is missing.
+    pub fn finish(self) -> Result<HashMap<String, Value>> {
+        Ok(self.into_ordered()?.into_iter().collect())
+    }
+
+    /// Like [`finish`](Self::finish), keeping the schema's input order.
+    pub(crate) fn into_ordered(self) -> Result<IndexMap<String, Value>> {
+        let missing = self.missing_required();
+        if !missing.is_empty() {
+            return Err(Error::invalid_input(format!(
+                "Missing required inputs: {}",
+                missing.join(", ")
+            )));
+        }
+
+        let mut values = self.values;
+        let mut ordered = IndexMap::with_capacity(self.schema.properties.len());
+        for (name, property) in self.schema.properties {
+            if let Some(value) = values.shift_remove(&name) {
+                ordered.insert(name, value);
+            } else if let Some(default) = property.default {
+                ordered.insert(name, default);
+            }
+        }
+        Ok(ordered)
+    }
+}
+
+/// Resolve a schema fragment that may be a local `$ref`.
+fn resolve<'a>(
+    fragment: &'a Map<String, Value>,
+    components: Option<&'a Map<String, Value>>,
+) -> Option<&'a Map<String, Value>> {
+    match fragment.get("$ref").and_then(Value::as_str) {
+        Some(reference) => components?
+            .get(reference.strip_prefix("#/components/schemas/")?)?
+            .as_object(),
+        None => Some(fragment),
+    }
+}
+
+fn matches_kind(kind: &str, value: &Value) -> bool {
+    match kind {
+        "string" => value.is_string(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => true,
+    }
+}
+
+/// Compare values, treating numbers of different representations as equal.
+fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sdxl_schema() -> InputSchema {
+        let openapi: Value =
+            serde_json::from_str(include_str!("../../tests/fixtures/sdxl_openapi.json")).unwrap();
+        InputSchema::from_openapi(&openapi).unwrap()
+    }
+
+    #[test]
+    fn test_parse_sdxl_schema() {
+        let schema = sdxl_schema();
+        let names: Vec<_> = schema.properties().keys().take(3).collect();
+        assert_eq!(names, ["prompt", "negative_prompt", "image"]);
+        assert!(schema.is_required("prompt"));
+
+        let scheduler = schema.property("scheduler").unwrap();
+        assert_eq!(scheduler.kind.as_deref(), Some("string"));
+        assert_eq!(scheduler.default, Some(json!("K_EULER")));
+        assert_eq!(scheduler.allowed.as_ref().map(Vec::len), Some(7));
+
+        let steps = schema.property("num_inference_steps").unwrap();
+        assert_eq!((steps.minimum, steps.maximum), (Some(1.0), Some(500.0)));
+    }
+
+    #[test]
+    fn test_set_validates_against_schema() {
+        let schema = sdxl_schema();
+        let mut input = DynamicInput::for_schema(&schema);
+
+        input
+            .set("prompt", "hi")
+            .unwrap()
+            .set("num_inference_steps", 30)
+            .unwrap();
+        input.set("scheduler", "DDIM").unwrap();
+        input.set("guidance_scale", 7).unwrap();
+
+        let cases = [
+            ("num_inference_steps", json!("30"), "type integer"),
+            ("num_inference_steps", json!(30.5), "type integer"),
+            ("apply_watermark", json!("yes"), "type boolean"),
+            ("scheduler", json!("EULER"), "must be one of"),
+            ("refine", json!("none"), "must be one of"),
+            ("num_outputs", json!(5), "at most 4"),
+            ("lora_scale", json!(-0.1), "at least 0"),
+            ("steps", json!(30), "Unknown input"),
+        ];
+        for (key, value, expected) in cases {
+            match input.set(key, value.clone()) {
+                Err(Error::InvalidInput(message)) => assert!(
+                    message.contains(expected),
+                    "{} = {}: unexpected message {:?}",
+                    key,
+                    value,
+                    message
+                ),
+                other => panic!("{} = {} should be rejected, got {:?}", key, value, other),
+            }
+        }
+
+        // Rejected values leave earlier ones untouched
+        assert_eq!(input.get("num_inference_steps"), Some(&json!(30)));
+        assert_eq!(input.get("scheduler"), Some(&json!("DDIM")));
+    }
+
+    #[test]
+    fn test_finish_fills_defaults() {
+        let schema = sdxl_schema();
+        let mut input = DynamicInput::for_schema(&schema);
+        assert_eq!(input.missing_required(), ["prompt"]);
+        assert!(matches!(
+            input.clone().finish(),
+            Err(Error::InvalidInput(_))
+        ));
+
+        input.set("prompt", "A lighthouse").unwrap();
+        input.set("width", 768).unwrap();
+        assert!(input.missing_required().is_empty());
+
+        let values = input.finish().unwrap();
+        assert_eq!(values["prompt"], "A lighthouse");
+        assert_eq!(values["width"], 768);
+        assert_eq!(values["height"], 1024);
+        assert_eq!(values["scheduler"], "K_EULER");
+        assert_eq!(values["refine"], "no_refiner");
+        assert_eq!(values["apply_watermark"], true);
+        assert_eq!(values["negative_prompt"], "");
+        // Optional inputs without defaults stay unset
+        assert!(!values.contains_key("seed"));
+        assert!(!values.contains_key("image"));
+    }
+}
@@ -0,0 +1,67 @@
+//! Server-sent events emitted by streaming predictions.
+
+/// A single event from a prediction's output stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamEvent {
+    /// A chunk of model output
+    Output(String),
+    /// A chunk of prediction logs
+    Logs(String),
+    /// The prediction failed with the given message
+    Error(String),
+    /// The stream has finished; `reason` is set when the prediction did not succeed
+    Done {
+        /// Why the stream ended early (e.g. `canceled`)
+        reason: Option<String>,
+    },
+}
+
+impl StreamEvent {
+    /// Build an event from its SSE event name and data.
+    ///
+    /// Returns `None` for event types this crate does not know about.
+    pub(crate) fn from_sse(event: &str, data: String) -> Option<Self> {
+        match event {
+            "output" => Some(Self::Output(data)),
+            "logs" => Some(Self::Logs(data)),
+            "error" => Some(Self::Error(data)),
+            "done" => {
+                let reason = serde_json::from_str::<serde_json::Value>(&data)
+                    .ok()
+                    .and_then(|value| value.get("reason")?.as_str().map(str::to_string))
+                    .filter(|reason| !reason.is_empty());
+                Some(Self::Done { reason })
+            }
+            _ => None,
+        }
+    }
+
+    /// Check if this is the final event of the stream
+    pub fn is_done(&self) -> bool {
+        matches!(self, Self::Done { .. })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_sse() {
+        assert_eq!(
+            StreamEvent::from_sse("output", "Hello".into()),
+            Some(StreamEvent::Output("Hello".into()))
+        );
+        assert_eq!(
+            StreamEvent::from_sse("done", "{}".into()),
+            Some(StreamEvent::Done { reason: None })
+        );
+        assert_eq!(
+            StreamEvent::from_sse("done", r#"{"reason": "canceled"}"#.into()),
+            Some(StreamEvent::Done {
+                reason: Some("canceled".into())
+            })
+        );
+        assert_eq!(StreamEvent::from_sse("ping", String::new()), None);
+    }
+}
@@ -0,0 +1,267 @@
+//! Prediction inputs with `{placeholder}` variables, for batch runs.
+
+use crate::error::{Error, Result};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Model inputs whose strings may contain `{name}` placeholders.
+///
+/// Rendering replaces every placeholder with the value of the variable of
+/// that name, in strings at any depth of arrays and objects; object keys are
+/// left as they are. Write `{{` and `}}` for literal braces.
+///
+/// A placeholder without a variable fails the render, and so by default does
+/// a variable that no placeholder uses, which usually means a misspelled
+/// column; see [`allow_unused_vars`](Self::allow_unused_vars).
+///
+/// # Examples
+///
+/// ```
+/// use replicate_client::InputTemplate;
+/// use serde_json::json;
+/// use std::collections::HashMap;
+///
+/// let template = InputTemplate::new(HashMap::from([
+///     ("prompt".to_string(), json!("A {style} painting of {subject}")),
+///     ("negative_prompt".to_string(), json!("{{blurry}}")),
+/// ]));
+/// let vars = HashMap::from([
+///     ("style".to_string(), "watercolor".to_string()),
+///     ("subject".to_string(), "a lighthouse".to_string()),
+/// ]);
+/// let inputs = template.render(&vars).unwrap();
+/// assert_eq!(inputs["prompt"], json!("A watercolor painting of a lighthouse"));
+/// assert_eq!(inputs["negative_prompt"], json!("{blurry}"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct InputTemplate {
+    inputs: HashMap<String, Value>,
+    allow_unused_vars: bool,
+}
+
+impl InputTemplate {
+    /// Create a template from model inputs.
+    pub fn new(inputs: HashMap<String, Value>) -> Self {
+        Self {
+            inputs,
+            allow_unused_vars: false,
+        }
+    }
+
+    /// Set whether variables no placeholder uses are ignored instead of
+    /// failing the render, e.g. for extra columns of a CSV file.
+    pub fn allow_unused_vars(mut self, allow: bool) -> Self {
+        self.allow_unused_vars = allow;
+        self
+    }
+
+    /// Get the template inputs.
+    pub fn inputs(&self) -> &HashMap<String, Value> {
+        &self.inputs
+    }
+
+    /// Get the names of the placeholders used by the template.
+    ///
+    /// Fails with [`Error::InvalidInput`] if a string is not a valid template.
+    pub fn placeholders(&self) -> Result<BTreeSet<String>> {
+        let mut names = BTreeSet::new();
+        for (key, value) in &self.inputs {
+            render_value(value, key, &mut |name| {
+                names.insert(name.to_string());
+                Ok(String::new())
+            })?;
+        }
+        Ok(names)
+    }
+
+    /// Render the inputs with the given variables.
+    ///
+    /// Fails with [`Error::InvalidInput`] on a malformed placeholder or an
+    /// unmatched brace, on a placeholder without a variable, and, unless
+    /// allowed, on a variable that no placeholder uses.
+    pub fn render(&self, vars: &HashMap<String, String>) -> Result<HashMap<String, Value>> {
+        let mut keys: Vec<_> = self.inputs.keys().collect();
+        keys.sort();
+
+        let mut used = HashSet::new();
+        let mut rendered = HashMap::with_capacity(self.inputs.len());
+        for key in keys {
+            let value = render_value(&self.inputs[key], key, &mut |name| {
+                let value = vars.get(name).ok_or_else(|| {
+                    Error::invalid_input(format!(
+                        "Missing template variable '{}' in input '{}'",
+                        name, key
+                    ))
+                })?;
+                used.insert(name.to_string());
+                Ok(value.clone())
+            })?;
+            rendered.insert(key.clone(), value);
+        }
+
+        if !self.allow_unused_vars {
+            let mut unused: Vec<_> = vars
+                .keys()
+                .filter(|name| !used.contains(name.as_str()))
+                .map(String::as_str)
+                .collect();
+            if !unused.is_empty() {
+                unused.sort_unstable();
+                return Err(Error::invalid_input(format!(
+                    "Unused template variables: {}",
+                    unused.join(", ")
+                )));
+            }
+        }
+        Ok(rendered)
+    }
+}
+
+/// Substitute the placeholders in the strings of `value`.
+fn render_value(
+    value: &Value,
+    key: &str,
+    lookup: &mut dyn FnMut(&str) -> Result<String>,
+) -> Result<Value> {
+    Ok(match value {
+        Value::String(text) => Value::String(render_str(text, key, lookup)?),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| render_value(item, key, lookup))
+                .collect::<Result<_>>()?,
+        ),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(name, field)| Ok((name.clone(), render_value(field, key, lookup)?)))
+                .collect::<Result<_>>()?,
+        ),
+        other => other.clone(),
+    })
+}
+
+/// Substitute the placeholders in one string, unescaping `{{` and `}}`.
+fn render_str(
+    text: &str,
+    key: &str,
+    lookup: &mut dyn FnMut(&str) -> Result<String>,
+) -> Result<String> {
+    let invalid = |problem: &str| {
+        Error::invalid_input(format!(
+            "Invalid template in input '{}': {} in '{}'",
+            key, problem, text
+        ))
+    };
+
+    let mut rendered = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(index) = rest.find(['{', '}']) {
+        rendered.push_str(&rest[..index]);
+        let brace = &rest[index..index + 1];
+        let after = &rest[index + 1..];
+        if let Some(remainder) = after.strip_prefix(brace) {
+            rendered.push_str(brace);
+            rest = remainder;
+        } else if brace == "}" {
+            return Err(invalid("unmatched '}'"));
+        } else {
+            let end = after.find('}').ok_or_else(|| invalid("unclosed '{'"))?;
+            let name = &after[..end];
+            if name.is_empty() || name.contains('{') {
+                return Err(invalid(&format!("invalid placeholder '{{{}}}'", name)));
+            }
+            rendered.push_str(&lookup(name)?);
+            rest = &after[end + 1..];
+        }
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_render_nested_inputs() {
+        let template = InputTemplate::new(HashMap::from([
+            ("prompt".to_string(), json!("{subject} at {time}")),
+            (
+                "messages".to_string(),
+                json!([{"role": "user", "content": ["Describe {subject}", 3]}]),
+            ),
+            ("steps".to_string(), json!(30)),
+        ]));
+        assert_eq!(
+            template.placeholders().unwrap(),
+            BTreeSet::from(["subject".to_string(), "time".to_string()])
+        );
+
+        let inputs = template
+            .render(&vars(&[("subject", "a fox"), ("time", "dawn")]))
+            .unwrap();
+        assert_eq!(inputs["prompt"], json!("a fox at dawn"));
+        assert_eq!(
+            inputs["messages"],
+            json!([{"role": "user", "content": ["Describe a fox", 3]}])
+        );
+        assert_eq!(inputs["steps"], json!(30));
+    }
+
+    #[test]
+    fn test_missing_and_unused_vars() {
+        let template =
+            InputTemplate::new(HashMap::from([("prompt".to_string(), json!("{a} {b}"))]));
+        match template.render(&vars(&[("a", "1")])) {
+            Err(Error::InvalidInput(message)) => {
+                assert!(
+                    message.contains("'b'") && message.contains("'prompt'"),
+                    "{}",
+                    message
+                )
+            }
+            other => panic!("Expected a missing variable, got {:?}", other),
+        }
+
+        let extra = vars(&[("a", "1"), ("b", "2"), ("z", "x"), ("c", "y")]);
+        match template.render(&extra) {
+            Err(Error::InvalidInput(message)) => {
+                assert!(message.ends_with(": c, z"), "{}", message)
+            }
+            other => panic!("Expected unused variables, got {:?}", other),
+        }
+        let inputs = template.allow_unused_vars(true).render(&extra).unwrap();
+        assert_eq!(inputs["prompt"], json!("1 2"));
+    }
+
+    #[test]
+    fn test_escaped_and_malformed_braces() {
+        let template = InputTemplate::new(HashMap::from([(
+            "prompt".to_string(),
+            json!("{{\"name\": \"{name}\"}} {{{{}}"),
+        )]));
+        let inputs = template.render(&vars(&[("name", "{x}")])).unwrap();
+        assert_eq!(inputs["prompt"], json!("{\"name\": \"{x}\"} {{}"));
+
+        for text in ["{", "}", "{}", "a {b", "{a{b}", "x } y"] {
+            let template = InputTemplate::new(HashMap::from([("prompt".to_string(), json!(text))]));
+            assert!(
+                matches!(
+                    template.render(&vars(&[("a", "1"), ("b", "2")])),
+                    Err(Error::InvalidInput(message)) if message.contains("Invalid template")
+                ),
+                "{}",
+                text
+            );
+        }
+    }
+}
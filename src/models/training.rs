@@ -0,0 +1,240 @@
+//! Training-related types and structures.
+
+use crate::error::{Error, Result};
+use crate::models::file::{FileEncodingStrategy, FileInput};
+use crate::models::id::TrainingId;
+use crate::models::prediction::{PredictionStatus, PredictionUrls};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// A training job that fine-tunes a model on Replicate.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Training {
+    /// The unique ID of the training
+    pub id: String,
+
+    /// The base model being trained (format: owner/name)
+    pub model: String,
+
+    /// The version ID of the base model
+    pub version: String,
+
+    /// The current status of the training
+    pub status: PredictionStatus,
+
+    /// The input parameters for the training
+    pub input: Option<HashMap<String, Value>>,
+
+    /// The output of the training, including the new version (if completed)
+    pub output: Option<Value>,
+
+    /// Logs from the training execution
+    pub logs: Option<String>,
+
+    /// Error message if the training failed
+    pub error: Option<String>,
+
+    /// Metrics about the training performance
+    pub metrics: Option<HashMap<String, Value>>,
+
+    /// When the training was created
+    pub created_at: Option<String>,
+
+    /// When the training started processing
+    pub started_at: Option<String>,
+
+    /// When the training completed
+    pub completed_at: Option<String>,
+
+    /// URLs associated with the training
+    pub urls: Option<PredictionUrls>,
+
+    /// Fields not modelled by this crate, kept as returned by the API
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+impl From<&Training> for TrainingId {
+    fn from(training: &Training) -> Self {
+        Self::new(training.id.as_str())
+    }
+}
+
+impl Training {
+    /// Check if the training is complete
+    pub fn is_complete(&self) -> bool {
+        self.status.is_terminal()
+    }
+
+    /// Check if the training succeeded
+    pub fn is_successful(&self) -> bool {
+        self.status == PredictionStatus::Succeeded
+    }
+
+    /// Check if the training failed
+    pub fn is_failed(&self) -> bool {
+        self.status == PredictionStatus::Failed
+    }
+}
+
+/// Request to create a new training.
+#[derive(Debug, Clone, Serialize)]
+pub struct CreateTrainingRequest {
+    /// The model version to train, as `owner/name:version`
+    #[serde(skip)]
+    pub version: String,
+
+    /// The destination model for the trained version, as `owner/name`
+    pub destination: String,
+
+    /// Input parameters for the trainer, serialized in insertion order
+    pub input: IndexMap<String, Value>,
+
+    /// Optional webhook URL for notifications
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<String>,
+
+    /// Events to filter for webhooks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhook_events_filter: Option<Vec<String>>,
+
+    /// File inputs that need to be processed
+    #[serde(skip)]
+    pub file_inputs: IndexMap<String, FileInput>,
+
+    /// File encoding strategy
+    #[serde(skip)]
+    pub file_encoding_strategy: FileEncodingStrategy,
+}
+
+impl CreateTrainingRequest {
+    /// Create a new training request for a model version
+    pub fn new(version: impl Into<String>) -> Self {
+        Self {
+            version: version.into(),
+            destination: String::new(),
+            input: IndexMap::new(),
+            webhook: None,
+            webhook_events_filter: None,
+            file_inputs: IndexMap::new(),
+            file_encoding_strategy: FileEncodingStrategy::default(),
+        }
+    }
+
+    /// Set the destination model
+    pub fn with_destination(mut self, owner: &str, name: &str) -> Self {
+        self.destination = format!("{}/{}", owner, name);
+        self
+    }
+
+    /// Add an input parameter
+    pub fn with_input(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.input.insert(key.into(), value.into());
+        self
+    }
+
+    /// Set the webhook URL
+    pub fn with_webhook(mut self, webhook: impl Into<String>) -> Self {
+        self.webhook = Some(webhook.into());
+        self
+    }
+
+    /// Split the version into `(owner, name, version_id)`
+    pub fn version_parts(&self) -> Result<(&str, &str, &str)> {
+        let (model, version_id) = self.version.split_once(':').ok_or_else(|| {
+            Error::invalid_input(format!(
+                "Training version must be owner/name:version, got '{}'",
+                self.version
+            ))
+        })?;
+        let (owner, name) = split_model_identifier(model)?;
+        if version_id.is_empty() {
+            return Err(Error::invalid_input("Training version ID cannot be empty"));
+        }
+        Ok((owner, name, version_id))
+    }
+
+    /// Validate the request before sending it
+    pub fn validate(&self) -> Result<()> {
+        self.version_parts()?;
+        if self.destination.is_empty() {
+            return Err(Error::invalid_input("Training destination is required"));
+        }
+        split_model_identifier(&self.destination)?;
+        Ok(())
+    }
+}
+
+/// Split and validate an `owner/name` model identifier.
+pub(crate) fn split_model_identifier(identifier: &str) -> Result<(&str, &str)> {
+    let valid_part = |part: &str| {
+        !part.is_empty()
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    };
+
+    match identifier.split_once('/') {
+        Some((owner, name)) if valid_part(owner) && valid_part(name) => Ok((owner, name)),
+        _ => Err(Error::invalid_input(format!(
+            "Model must be in the form owner/name, got '{}'",
+            identifier
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_parts() {
+        let request = CreateTrainingRequest::new("ostris/flux-dev-lora-trainer:abc123");
+        assert_eq!(
+            request.version_parts().unwrap(),
+            ("ostris", "flux-dev-lora-trainer", "abc123")
+        );
+
+        assert!(
+            CreateTrainingRequest::new("ostris/trainer")
+                .version_parts()
+                .is_err()
+        );
+        assert!(
+            CreateTrainingRequest::new("trainer:abc")
+                .version_parts()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_destination_validation() {
+        let request = CreateTrainingRequest::new("owner/trainer:v1");
+        assert!(request.validate().is_err());
+
+        assert!(
+            request
+                .clone()
+                .with_destination("me", "my-lora")
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            request
+                .clone()
+                .with_destination("me", "")
+                .validate()
+                .is_err()
+        );
+        assert!(
+            request
+                .clone()
+                .with_destination("me", "a/b")
+                .validate()
+                .is_err()
+        );
+        assert!(request.with_destination("m e", "lora").validate().is_err());
+    }
+}
@@ -0,0 +1,239 @@
+//! Callbacks for prediction lifecycle events.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::{Arc, Mutex};
+
+use crate::models::prediction::{Prediction, PredictionStatus};
+
+/// Receives prediction lifecycle events, for logging or metrics without `tracing`.
+///
+/// Install one with [`ClientBuilder::observer`](crate::ClientBuilder::observer).
+/// Every method has an empty default, so implement only the events of interest.
+///
+/// - [`on_created`](Self::on_created) is called when a create request succeeds.
+/// - [`on_status_change`](Self::on_status_change) and
+///   [`on_terminal`](Self::on_terminal) are called from the polling waits,
+///   such as `send_and_wait` and
+///   [`wait_for_completion`](crate::api::PredictionsApi::wait_for_completion),
+///   but not from the shared [`PredictionWatcher`](crate::PredictionWatcher).
+///   Status changes are reported for predictions created through the same
+///   client; waits on other predictions only report when they finish. Only
+///   the last 1024 unfinished predictions are tracked, so one created long
+///   before it is waited on may miss its first status change.
+/// - [`on_retry`](Self::on_retry) is called before every retry of a request.
+///
+/// Callbacks run inline on the task making the request, so they should
+/// return quickly. A panicking callback is caught and logged, and never
+/// fails the client call.
+pub trait LifecycleObserver: Send + Sync {
+    /// A prediction was created.
+    fn on_created(&self, _prediction: &Prediction) {}
+
+    /// A poll found a prediction in a different status than before.
+    fn on_status_change(
+        &self,
+        _prediction: &Prediction,
+        _old: &PredictionStatus,
+        _new: &PredictionStatus,
+    ) {
+    }
+
+    /// A poll found a prediction succeeded, failed or canceled.
+    fn on_terminal(&self, _prediction: &Prediction) {}
+
+    /// A request to `path` is sent again; `attempt` is 1 for the first retry.
+    fn on_retry(&self, _path: &str, _attempt: u32) {}
+}
+
+/// Observer logging lifecycle events through `tracing`.
+///
+/// Creates and terminal states are logged at `info`, status changes at
+/// `debug` and retries at `warn`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogObserver;
+
+impl LifecycleObserver for LogObserver {
+    fn on_created(&self, prediction: &Prediction) {
+        tracing::info!(
+            "Prediction {} created for {} ({:?})",
+            prediction.id,
+            prediction.model,
+            prediction.status
+        );
+    }
+
+    fn on_status_change(
+        &self,
+        prediction: &Prediction,
+        old: &PredictionStatus,
+        new: &PredictionStatus,
+    ) {
+        tracing::debug!(
+            "Prediction {} went from {:?} to {:?}",
+            prediction.id,
+            old,
+            new
+        );
+    }
+
+    fn on_terminal(&self, prediction: &Prediction) {
+        let predict_time = prediction
+            .metrics_pointer("/predict_time")
+            .and_then(|value| value.as_f64());
+        match predict_time {
+            Some(seconds) => tracing::info!(
+                "Prediction {} {:?} after {:.2}s of prediction time",
+                prediction.id,
+                prediction.status,
+                seconds
+            ),
+            None => tracing::info!("Prediction {} {:?}", prediction.id, prediction.status),
+        }
+    }
+
+    fn on_retry(&self, path: &str, attempt: u32) {
+        tracing::warn!("Retrying request to {} (attempt {})", path, attempt);
+    }
+}
+
+/// Number of unfinished predictions whose last status an observer keeps.
+const MAX_TRACKED_PREDICTIONS: usize = 1024;
+
+/// Installed observer with the last status seen of each prediction it follows.
+///
+/// Clones share the observer and the statuses.
+#[derive(Clone)]
+pub(crate) struct ObserverHandle {
+    observer: Arc<dyn LifecycleObserver>,
+    /// Last status of predictions created but not yet seen to finish.
+    statuses: Arc<Mutex<StatusTracker>>,
+}
+
+/// Last statuses of unfinished predictions, dropping the oldest when full.
+#[derive(Default)]
+struct StatusTracker {
+    statuses: HashMap<String, PredictionStatus>,
+    /// IDs in the order they were first tracked; may still hold IDs that
+    /// were removed since, which are skipped on eviction.
+    order: VecDeque<String>,
+}
+
+impl StatusTracker {
+    /// Record the status of a prediction, returning the previous one.
+    fn insert(&mut self, id: &str, status: PredictionStatus) -> Option<PredictionStatus> {
+        if let Some(old) = self.statuses.get_mut(id) {
+            return Some(std::mem::replace(old, status));
+        }
+        self.statuses.insert(id.to_string(), status);
+        self.order.push_back(id.to_string());
+        while self.statuses.len() > MAX_TRACKED_PREDICTIONS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.statuses.remove(&oldest);
+            }
+        }
+        if self.order.len() > 2 * MAX_TRACKED_PREDICTIONS {
+            let statuses = &self.statuses;
+            self.order.retain(|id| statuses.contains_key(id));
+        }
+        None
+    }
+
+    /// Stop tracking a prediction, returning its last status.
+    fn remove(&mut self, id: &str) -> Option<PredictionStatus> {
+        self.statuses.remove(id)
+    }
+}
+
+impl ObserverHandle {
+    pub(crate) fn new(observer: Arc<dyn LifecycleObserver>) -> Self {
+        Self {
+            observer,
+            statuses: Arc::new(Mutex::new(StatusTracker::default())),
+        }
+    }
+
+    /// Report a created prediction.
+    pub(crate) fn created(&self, prediction: &Prediction) {
+        if !prediction.status.is_terminal() {
+            self.statuses
+                .lock()
+                .unwrap()
+                .insert(&prediction.id, prediction.status.clone());
+        }
+        self.notify(|observer| observer.on_created(prediction));
+    }
+
+    /// Report the state of a prediction found by a poll.
+    pub(crate) fn polled(&self, prediction: &Prediction) {
+        let new = &prediction.status;
+        let old = {
+            let mut statuses = self.statuses.lock().unwrap();
+            if new.is_terminal() {
+                statuses.remove(&prediction.id)
+            } else {
+                statuses.insert(&prediction.id, new.clone())
+            }
+        };
+        if let Some(old) = old
+            && old != *new
+        {
+            self.notify(|observer| observer.on_status_change(prediction, &old, new));
+        }
+        if new.is_terminal() {
+            self.notify(|observer| observer.on_terminal(prediction));
+        }
+    }
+
+    /// Report a retried request.
+    pub(crate) fn retried(&self, path: &str, attempt: u32) {
+        self.notify(|observer| observer.on_retry(path, attempt));
+    }
+
+    /// Run a callback, logging instead of propagating a panic.
+    fn notify(&self, callback: impl FnOnce(&dyn LifecycleObserver)) {
+        let observer = self.observer.as_ref();
+        if catch_unwind(AssertUnwindSafe(|| callback(observer))).is_err() {
+            tracing::warn!("Lifecycle observer panicked; the event was dropped");
+        }
+    }
+}
+
+impl fmt::Debug for ObserverHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ObserverHandle").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_tracker_is_bounded() {
+        let mut tracker = StatusTracker::default();
+        for i in 0..MAX_TRACKED_PREDICTIONS + 10 {
+            tracker.insert(&format!("p{}", i), PredictionStatus::Starting);
+        }
+        assert_eq!(tracker.statuses.len(), MAX_TRACKED_PREDICTIONS);
+        assert!(!tracker.statuses.contains_key("p9"));
+        assert!(tracker.statuses.contains_key("p10"));
+
+        assert_eq!(
+            tracker.insert("p10", PredictionStatus::Processing),
+            Some(PredictionStatus::Starting)
+        );
+
+        // Finished predictions leave no trace
+        let mut tracker = StatusTracker::default();
+        tracker.insert("kept", PredictionStatus::Starting);
+        for i in 0..4 * MAX_TRACKED_PREDICTIONS {
+            let id = format!("done{}", i);
+            tracker.insert(&id, PredictionStatus::Starting);
+            assert_eq!(tracker.remove(&id), Some(PredictionStatus::Starting));
+        }
+        assert!(tracker.order.len() <= 2 * MAX_TRACKED_PREDICTIONS);
+        assert_eq!(tracker.remove("kept"), Some(PredictionStatus::Starting));
+    }
+}
@@ -0,0 +1,257 @@
+//! Clients for many API tokens sharing one connection pool.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use indexmap::IndexMap;
+
+use crate::client::Client;
+use crate::error::Result;
+use crate::http::{HttpClient, HttpConfig, ShutdownReport};
+
+/// Default number of tokens whose clients a [`ClientPool`] keeps.
+pub const DEFAULT_POOL_CAPACITY: usize = 64;
+
+/// Hands out [`Client`]s bound to different API tokens over one connection pool.
+///
+/// Building a [`Client`] per tenant gives each its own connections; pooled
+/// clients share them, and are otherwise isolated: each has its own caches,
+/// stats and shutdown state, and [`Client::reconfigure`] on one retunes only
+/// that tenant, moving it to connections of its own. Clients for recently
+/// used tokens are kept, evicting the least recently used one when full.
+/// Clones share the pool.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use replicate_client::{ClientPool, HttpConfig};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let pool = ClientPool::new(HttpConfig::default())?;
+/// let tenant = pool.client_for("tenant-token")?;
+/// let prediction = tenant.predictions().get("gm3qorzdhgbfurvjtvhg6dckhu").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ClientPool {
+    http: HttpClient,
+    capacity: usize,
+    clients: Arc<Mutex<IndexMap<String, Client>>>,
+}
+
+impl ClientPool {
+    /// Create a pool with the given HTTP configuration.
+    pub fn new(http_config: HttpConfig) -> Result<Self> {
+        Self::build(http_config, None)
+    }
+
+    /// Create a pool for a custom API base URL.
+    pub fn with_base_url(http_config: HttpConfig, base_url: impl Into<String>) -> Result<Self> {
+        Self::build(http_config, Some(&base_url.into()))
+    }
+
+    fn build(http_config: HttpConfig, base_url: Option<&str>) -> Result<Self> {
+        Ok(Self {
+            http: HttpClient::without_token(http_config, base_url)?,
+            capacity: DEFAULT_POOL_CAPACITY,
+            clients: Arc::new(Mutex::new(IndexMap::new())),
+        })
+    }
+
+    /// Set how many tokens' clients are kept. A capacity of zero keeps none.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Get a client that authenticates with `api_token`.
+    ///
+    /// Returns the kept client for a recently used token, so its caches are
+    /// reused; otherwise builds one, which is cheap as it opens no
+    /// connections of its own.
+    pub fn client_for(&self, api_token: impl AsRef<str>) -> Result<Client> {
+        let api_token = api_token.as_ref();
+        let mut clients = self.clients.lock().unwrap();
+        if let Some(index) = clients.get_index_of(api_token) {
+            let last = clients.len() - 1;
+            clients.move_index(index, last);
+            return Ok(clients[last].clone());
+        }
+
+        let client = Client::from_http_client(self.http.for_tenant(api_token)?);
+        if self.capacity > 0 {
+            while clients.len() >= self.capacity {
+                clients.shift_remove_index(0);
+            }
+            clients.insert(api_token.to_string(), client.clone());
+        }
+        Ok(client)
+    }
+
+    /// Get the number of tokens whose clients are kept.
+    pub fn len(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+
+    /// Check if no clients are kept.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the HTTP configuration shared by all pooled clients.
+    pub fn http_config(&self) -> HttpConfig {
+        self.http.current_http_config()
+    }
+
+    /// Shut down every client the pool handed out, like [`Client::shutdown`].
+    ///
+    /// Waits for the in-flight operations of all of them, including clients
+    /// no longer kept. Shutting down a single pooled client leaves the
+    /// others running.
+    pub async fn shutdown(&self, grace: Duration) -> ShutdownReport {
+        self.http.shutdown(grace).await
+    }
+}
+
+/// Leaves out the kept tokens.
+impl std::fmt::Debug for ClientPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientPool")
+            .field("base_url", &self.http.base_url().as_str())
+            .field("capacity", &self.capacity)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Keep-alive HTTP server recording accepted connections and the
+    /// `Authorization` header of each request.
+    async fn start_recording_server() -> (String, Arc<AtomicUsize>, Arc<Mutex<Vec<String>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let tokens = Arc::new(Mutex::new(Vec::new()));
+        let (counter, seen) = (connections.clone(), tokens.clone());
+
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+                let seen = seen.clone();
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 1024];
+                    loop {
+                        let n = match socket.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => n,
+                        };
+                        buf.extend_from_slice(&chunk[..n]);
+                        while let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                            let head = String::from_utf8_lossy(&buf[..end]).to_string();
+                            buf.drain(..end + 4);
+                            if let Some(value) = head.lines().find_map(|line| {
+                                line.strip_prefix("authorization: ")
+                                    .or_else(|| line.strip_prefix("Authorization: "))
+                            }) {
+                                seen.lock().unwrap().push(value.to_string());
+                            }
+                            let response = "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n{}";
+                            if socket.write_all(response.as_bytes()).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                });
+            }
+        });
+
+        (format!("http://{}", addr), connections, tokens)
+    }
+
+    #[tokio::test]
+    async fn test_pooled_clients_share_connections() {
+        let (base_url, connections, tokens) = start_recording_server().await;
+        let pool = ClientPool::with_base_url(HttpConfig::default(), base_url).unwrap();
+
+        let alice = pool.client_for("token-a").unwrap();
+        let bob = pool.client_for("token-b").unwrap();
+        alice.http_client().get("/v1/predictions").await.unwrap();
+        bob.http_client().get("/v1/predictions").await.unwrap();
+        pool.client_for("token-a")
+            .unwrap()
+            .http_client()
+            .get("/v1/files")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *tokens.lock().unwrap(),
+            ["Token token-a", "Token token-b", "Token token-a"]
+        );
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+        assert_eq!(pool.len(), 2);
+        assert!(!format!("{:?}", pool).contains("token-a"));
+    }
+
+    #[tokio::test]
+    async fn test_pooled_clients_are_isolated() {
+        let (base_url, connections, _) = start_recording_server().await;
+        let pool = ClientPool::with_base_url(HttpConfig::default(), base_url).unwrap();
+        let alice = pool.client_for("token-a").unwrap();
+        let bob = pool.client_for("token-b").unwrap();
+
+        alice
+            .reconfigure(|config| config.retry.max_retries = 0)
+            .unwrap();
+        assert_eq!(alice.current_http_config().retry.max_retries, 0);
+        assert_eq!(bob.current_http_config().retry.max_retries, 3);
+        assert_eq!(pool.http_config().retry.max_retries, 3);
+
+        bob.http_client().get("/v1/predictions").await.unwrap();
+        assert_eq!(bob.http_client().stats().requests, 1);
+        assert_eq!(alice.http_client().stats().requests, 0);
+
+        alice.shutdown(Duration::ZERO).await;
+        assert!(matches!(
+            alice.http_client().get("/v1/predictions").await,
+            Err(Error::ClientClosed)
+        ));
+        bob.http_client().get("/v1/files").await.unwrap();
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+        pool.shutdown(Duration::ZERO).await;
+        assert!(bob.http_client().is_closed());
+        assert!(matches!(
+            pool.client_for("token-c")
+                .unwrap()
+                .http_client()
+                .get("/v1/files")
+                .await,
+            Err(Error::ClientClosed)
+        ));
+    }
+
+    #[test]
+    fn test_pool_evicts_least_recently_used() {
+        let pool = ClientPool::new(HttpConfig::default())
+            .unwrap()
+            .with_capacity(2);
+        pool.client_for("a").unwrap();
+        pool.client_for("b").unwrap();
+        pool.client_for("a").unwrap();
+        pool.client_for("c").unwrap();
+
+        let kept: Vec<_> = pool.clients.lock().unwrap().keys().cloned().collect();
+        assert_eq!(kept, ["a", "c"]);
+        assert!(matches!(pool.client_for(""), Err(Error::Auth(_))));
+    }
+}
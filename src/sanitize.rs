@@ -0,0 +1,292 @@
+//! Checks that keep secrets out of prediction inputs.
+
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::error::{Error, Result};
+
+/// Key pattern matching common names of credential fields, in snake, kebab
+/// or camel case (`hf_token`, `client-secret`, `apiKey`) but not names like
+/// `max_new_tokens`.
+pub const DEFAULT_KEY_PATTERN: &str = r"(?i:(^|[_-])(token|secret|password|passwd|api[_-]?key|private[_-]?key|credentials?)$|^(authorization|cookie)$)|[a-z](Token|Secret|Password|ApiKey)$";
+
+/// Value pattern matching Replicate API tokens.
+pub const REPLICATE_TOKEN_PATTERN: &str = r"r8_[A-Za-z0-9]{20,}";
+
+/// Value pattern matching `Bearer` credentials, e.g. a copied `Authorization` header.
+pub const BEARER_TOKEN_PATTERN: &str = r"(?i)\bbearer\s+[A-Za-z0-9\-._~+/]{16,}=*";
+
+/// Text that replaces values removed by [`SanitizePolicy::Redact`].
+pub const REDACTED: &str = "[REDACTED]";
+
+/// What an [`InputSanitizer`] does with inputs that look like secrets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SanitizePolicy {
+    /// Fail the create with [`Error::InvalidInput`] naming the offending inputs
+    #[default]
+    Reject,
+    /// Replace the offending values with [`REDACTED`] and send the prediction
+    Redact,
+}
+
+/// Opt-in check that stops secrets from being sent as prediction inputs.
+///
+/// Inputs are visible on the Replicate dashboard, so an API key or an
+/// `Authorization` header passed by mistake leaks to everyone with access to
+/// the account. An input is flagged when its key, at any nesting level,
+/// matches a key pattern or when a string value matches a value pattern.
+/// Flagged inputs are reported by path, e.g. `headers.Authorization` or
+/// `messages[1].content`, and their values are never included in errors or
+/// logs.
+///
+/// Install it with [`ClientBuilder::input_sanitizer`](crate::ClientBuilder::input_sanitizer);
+/// it runs on every prediction create, after file inputs are processed.
+///
+/// # Examples
+///
+/// ```
+/// use replicate_client::{InputSanitizer, SanitizePolicy};
+///
+/// let sanitizer = InputSanitizer::with_defaults(SanitizePolicy::Reject)
+///     .deny_value(r"sk-[A-Za-z0-9]{32,}")
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct InputSanitizer {
+    policy: SanitizePolicy,
+    key_patterns: Vec<Regex>,
+    value_patterns: Vec<Regex>,
+}
+
+impl InputSanitizer {
+    /// Create a sanitizer without any patterns.
+    pub fn new(policy: SanitizePolicy) -> Self {
+        Self {
+            policy,
+            ..Self::default()
+        }
+    }
+
+    /// Create a sanitizer flagging common credential keys, Replicate API
+    /// tokens and bearer tokens.
+    pub fn with_defaults(policy: SanitizePolicy) -> Self {
+        let pattern = |pattern| Regex::new(pattern).expect("default patterns are valid");
+        Self {
+            policy,
+            key_patterns: vec![pattern(DEFAULT_KEY_PATTERN)],
+            value_patterns: vec![
+                pattern(REPLICATE_TOKEN_PATTERN),
+                pattern(BEARER_TOKEN_PATTERN),
+            ],
+        }
+    }
+
+    /// Flag inputs whose key matches `pattern`.
+    pub fn deny_key(mut self, pattern: &str) -> Result<Self> {
+        self.key_patterns.push(compile(pattern)?);
+        Ok(self)
+    }
+
+    /// Flag string inputs whose value matches `pattern`.
+    pub fn deny_value(mut self, pattern: &str) -> Result<Self> {
+        self.value_patterns.push(compile(pattern)?);
+        Ok(self)
+    }
+
+    /// Get the policy applied to flagged inputs.
+    pub fn policy(&self) -> SanitizePolicy {
+        self.policy
+    }
+
+    /// Find the paths of inputs that look like secrets, in input key order.
+    pub fn find_secrets(&self, input: &HashMap<String, Value>) -> Vec<String> {
+        let mut inputs: Vec<_> = input.iter().collect();
+        inputs.sort_by_key(|(key, _)| *key);
+        let mut found = Vec::new();
+        for (key, value) in inputs {
+            self.visit_field(key, key.to_string(), value, &mut found);
+        }
+        found
+    }
+
+    /// Apply the policy to an input.
+    ///
+    /// Returns the paths of redacted inputs, or fails with
+    /// [`Error::InvalidInput`] under [`SanitizePolicy::Reject`].
+    pub fn sanitize(&self, input: &mut HashMap<String, Value>) -> Result<Vec<String>> {
+        let found = self.find_secrets(input);
+        if found.is_empty() {
+            return Ok(found);
+        }
+        match self.policy {
+            SanitizePolicy::Reject => Err(Error::invalid_input(format!(
+                "Prediction input looks like it contains secrets at: {}",
+                found.join(", ")
+            ))),
+            SanitizePolicy::Redact => {
+                for (key, value) in input.iter_mut() {
+                    self.redact_field(key, value);
+                }
+                tracing::warn!("Redacted possible secrets in inputs: {}", found.join(", "));
+                Ok(found)
+            }
+        }
+    }
+
+    fn is_denied_key(&self, key: &str) -> bool {
+        self.key_patterns
+            .iter()
+            .any(|pattern| pattern.is_match(key))
+    }
+
+    fn is_denied_value(&self, value: &str) -> bool {
+        self.value_patterns
+            .iter()
+            .any(|pattern| pattern.is_match(value))
+    }
+
+    fn visit_field(&self, key: &str, path: String, value: &Value, found: &mut Vec<String>) {
+        if self.is_denied_key(key) && !value.is_null() {
+            found.push(path);
+        } else {
+            self.visit(path, value, found);
+        }
+    }
+
+    fn visit(&self, path: String, value: &Value, found: &mut Vec<String>) {
+        match value {
+            Value::String(text) if self.is_denied_value(text) => found.push(path),
+            Value::Array(items) => {
+                for (index, item) in items.iter().enumerate() {
+                    self.visit(format!("{}[{}]", path, index), item, found);
+                }
+            }
+            Value::Object(fields) => {
+                for (key, value) in fields {
+                    self.visit_field(key, format!("{}.{}", path, key), value, found);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn redact_field(&self, key: &str, value: &mut Value) {
+        if self.is_denied_key(key) && !value.is_null() {
+            *value = Value::String(REDACTED.to_string());
+        } else {
+            self.redact(value);
+        }
+    }
+
+    fn redact(&self, value: &mut Value) {
+        match value {
+            Value::String(text) if self.is_denied_value(text) => {
+                *value = Value::String(REDACTED.to_string());
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.redact(item)),
+            Value::Object(fields) => {
+                for (key, value) in fields.iter_mut() {
+                    self.redact_field(key, value);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Compile a caller-supplied pattern.
+fn compile(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern)
+        .map_err(|e| Error::invalid_input(format!("Invalid pattern '{}': {}", pattern, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn input(value: Value) -> HashMap<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_key_based_detection() {
+        let sanitizer = InputSanitizer::with_defaults(SanitizePolicy::Reject);
+        let input = input(json!({
+            "prompt": "a cat",
+            "api_key": "abc",
+            "hf_token": "abc",
+            "password": null,
+            "accessToken": "abc",
+            "Authorization": "abc",
+            "max_new_tokens": 256,
+            "secretary": "abc",
+        }));
+        assert_eq!(
+            sanitizer.find_secrets(&input),
+            vec!["Authorization", "accessToken", "api_key", "hf_token"]
+        );
+        let custom = InputSanitizer::new(SanitizePolicy::Reject)
+            .deny_key(r"(?i)^api_key$")
+            .unwrap();
+        assert_eq!(custom.find_secrets(&input), vec!["api_key"]);
+        assert!(
+            InputSanitizer::new(SanitizePolicy::Reject)
+                .deny_key("(")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_value_based_detection() {
+        let sanitizer = InputSanitizer::with_defaults(SanitizePolicy::Reject);
+        let input = input(json!({
+            "prompt": "use r8_abcdefghijklmnopqrstuvwxyz012345 please",
+            "note": "r8_short",
+            "header": "Bearer eyJhbGciOiJIUzI1NiJ9.payload",
+        }));
+        assert_eq!(sanitizer.find_secrets(&input), vec!["header", "prompt"]);
+    }
+
+    #[test]
+    fn test_nested_objects() {
+        let sanitizer = InputSanitizer::with_defaults(SanitizePolicy::Redact);
+        let mut input = input(json!({
+            "request": {
+                "headers": {"Authorization": "Token x", "Accept": "*/*"},
+                "urls": ["https://example.com", "https://example.com/?t=r8_abcdefghijklmnopqrstuvwxyz"],
+            },
+            "messages": [{"role": "user", "content": "hello"}],
+        }));
+        let redacted = sanitizer.sanitize(&mut input).unwrap();
+        assert_eq!(
+            redacted,
+            vec!["request.headers.Authorization", "request.urls[1]"]
+        );
+        assert_eq!(
+            Value::Object(input.into_iter().collect()),
+            json!({
+                "request": {
+                    "headers": {"Authorization": REDACTED, "Accept": "*/*"},
+                    "urls": ["https://example.com", REDACTED],
+                },
+                "messages": [{"role": "user", "content": "hello"}],
+            })
+        );
+    }
+
+    #[test]
+    fn test_reject_lists_paths_without_values() {
+        let sanitizer = InputSanitizer::with_defaults(SanitizePolicy::Reject);
+        let mut input = input(json!({"config": {"secret": "hunter2"}}));
+        match sanitizer.sanitize(&mut input) {
+            Err(Error::InvalidInput(message)) => {
+                assert!(message.contains("config.secret"));
+                assert!(!message.contains("hunter2"));
+            }
+            other => panic!("Expected invalid input, got {:?}", other),
+        }
+        assert_eq!(input["config"]["secret"], "hunter2");
+    }
+}
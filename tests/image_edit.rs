@@ -0,0 +1,118 @@
+//! Image-editing runs against a fake Replicate server.
+
+use replicate_client::{Client, Error, FileInput, ImageEditRequest};
+use serde_json::{Value, json};
+use std::time::Duration;
+use tempfile::tempdir;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+/// Mount a prediction that succeeds immediately with the given output.
+async fn mount_prediction(server: &MockServer, output: Value) {
+    let prediction = |status: &str, output: Value| {
+        json!({
+            "id": "edit-1",
+            "model": "owner/img2img",
+            "version": "v1",
+            "status": status,
+            "output": output,
+        })
+    };
+    Mock::given(method("POST"))
+        .and(path("/v1/predictions"))
+        .and(body_partial_json(
+            json!({"input": {"prompt": "make it a watercolor"}}),
+        ))
+        .respond_with(ResponseTemplate::new(201).set_body_json(prediction("starting", Value::Null)))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/predictions/edit-1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(prediction("succeeded", output)))
+        .mount(server)
+        .await;
+}
+
+fn request(output_dir: &std::path::Path) -> ImageEditRequest {
+    let image = FileInput::from_bytes_with_metadata(
+        b"\x89PNG fake image".as_slice(),
+        Some("cat.png".to_string()),
+        Some("image/png".to_string()),
+    );
+    ImageEditRequest::new(image, "make it a watercolor")
+        .input("strength", 0.6)
+        .output_dir(output_dir)
+        .timeout(Duration::from_secs(10))
+}
+
+#[tokio::test]
+async fn test_run_image_edit_saves_outputs() {
+    let server = MockServer::start().await;
+    mount_prediction(
+        &server,
+        json!([
+            format!("{}/files/out-0.webp", server.uri()),
+            format!("{}/files/out-1.webp", server.uri()),
+        ]),
+    )
+    .await;
+    Mock::given(method("GET"))
+        .and(path("/files/out-0.webp"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(b"first".as_slice()))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/files/out-1.webp"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(b"second".as_slice()))
+        .mount(&server)
+        .await;
+
+    let client = Client::with_base_url("test-token", server.uri()).unwrap();
+    let dir = tempdir().unwrap();
+    let paths = client
+        .run_image_edit("owner/img2img:v1", request(dir.path()))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        paths,
+        [
+            dir.path().join("edit-1-0.webp"),
+            dir.path().join("edit-1-1.webp"),
+        ]
+    );
+    assert_eq!(std::fs::read(&paths[0]).unwrap(), b"first");
+    assert_eq!(std::fs::read(&paths[1]).unwrap(), b"second");
+
+    // The small image is inlined as a data URL next to the other inputs
+    let requests: Vec<Request> = server.received_requests().await.unwrap();
+    let create: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert!(
+        create["input"]["image"]
+            .as_str()
+            .unwrap()
+            .starts_with("data:image/png;base64,")
+    );
+    assert_eq!(create["input"]["strength"], 0.6);
+}
+
+#[tokio::test]
+async fn test_run_image_edit_without_files_is_invalid_input() {
+    let server = MockServer::start().await;
+    mount_prediction(&server, json!("I cannot edit this image")).await;
+
+    let client = Client::with_base_url("test-token", server.uri()).unwrap();
+    let dir = tempdir().unwrap();
+    match client
+        .run_image_edit("owner/img2img:v1", request(dir.path()))
+        .await
+    {
+        Err(Error::InvalidInput(message)) => {
+            assert_eq!(
+                message,
+                "Model owner/img2img:v1 returned no files for prediction edit-1"
+            );
+        }
+        other => panic!("Expected invalid input, got {:?}", other),
+    }
+}
@@ -0,0 +1,104 @@
+//! Memory behaviour of fetching predictions with very large outputs.
+
+use replicate_client::Client;
+use serde_json::{Value, json};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Allocator that tracks current and peak heap usage.
+struct CountingAllocator;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            let current = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(current, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Reset the peak to the current usage and return the baseline.
+fn reset_peak() -> usize {
+    let current = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(current, Ordering::Relaxed);
+    current
+}
+
+/// Build a ~20 MB transcript with word-level timestamps.
+fn synthetic_prediction() -> Vec<u8> {
+    let segments: Vec<Value> = (0..60_000)
+        .map(|i| {
+            json!({
+                "text": format!("segment {} {}", i, "lorem ipsum dolor sit amet ".repeat(8)),
+                "start": i as f64 * 0.5,
+                "end": i as f64 * 0.5 + 0.5,
+                "words": [
+                    {"word": "lorem", "start": 0.0, "end": 0.1},
+                    {"word": "ipsum", "start": 0.1, "end": 0.2},
+                ],
+            })
+        })
+        .collect();
+
+    serde_json::to_vec(&json!({
+        "id": "large",
+        "model": "owner/whisper",
+        "version": "v1",
+        "status": "succeeded",
+        "output": {"segments": segments},
+    }))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_raw_output_reduces_peak_memory() {
+    let body = synthetic_prediction();
+    assert!(body.len() > 20 * 1024 * 1024);
+
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/predictions/large"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(body, "application/json"))
+        .mount(&server)
+        .await;
+
+    let mut client = Client::with_base_url("test-token", server.uri()).unwrap();
+
+    let baseline = reset_peak();
+    let parsed = client.predictions().get("large").await.unwrap();
+    let parsed_peak = PEAK.load(Ordering::Relaxed) - baseline;
+
+    client.set_raw_prediction_output(true);
+    let baseline = reset_peak();
+    let raw = client.predictions().get("large").await.unwrap();
+    let raw_peak = PEAK.load(Ordering::Relaxed) - baseline;
+
+    assert!(raw.output.is_none());
+    assert!(raw.raw_output().is_some());
+    assert_eq!(
+        raw.output_as::<Value>().unwrap(),
+        parsed.output,
+        "raw and parsed modes must produce the same output"
+    );
+    assert!(
+        raw_peak * 2 < parsed_peak,
+        "raw mode peaked at {} bytes, parsed mode at {} bytes",
+        raw_peak,
+        parsed_peak
+    );
+}